@@ -0,0 +1,332 @@
+use serde::{Deserialize, Serialize};
+
+use crate::sql::{tokenize, SqlToken};
+
+const TABLE_KEYWORDS: &[&str] = &[
+    "from", "join", "into", "update", "using", "table", "truncate",
+];
+const TABLE_MODIFIERS: &[&str] = &["if", "not", "exists", "only", "lateral", "table"];
+/// Functions whose arguments use `FROM` without naming a table.
+const FROM_FUNCTIONS: &[&str] = &["extract", "substring", "trim", "position", "overlay"];
+/// Words that end a table reference rather than alias it.
+const CLAUSE_KEYWORDS: &[&str] = &[
+    "where",
+    "join",
+    "inner",
+    "left",
+    "right",
+    "full",
+    "cross",
+    "outer",
+    "natural",
+    "on",
+    "using",
+    "group",
+    "order",
+    "having",
+    "limit",
+    "offset",
+    "union",
+    "except",
+    "intersect",
+    "set",
+    "values",
+    "select",
+    "window",
+    "returning",
+    "for",
+    "fetch",
+    "default",
+    "when",
+    "then",
+    "into",
+    "from",
+    "straight_join",
+    "lateral",
+    "as",
+];
+
+/// Tables and engines a module declares it accesses.
+///
+/// Deserializable so it can be embedded in a module manifest.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DataAccessPolicy {
+    #[serde(default)]
+    pub engines: Vec<String>,
+    #[serde(default)]
+    pub tables: Vec<String>,
+    #[serde(default)]
+    pub mode: DataAccessMode,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataAccessMode {
+    Off,
+    /// Report undeclared access as a connector warning but send the statement.
+    #[default]
+    Audit,
+    /// Reject statements touching undeclared tables or engines.
+    Enforce,
+}
+
+impl DataAccessPolicy {
+    pub fn new(mode: DataAccessMode) -> Self {
+        Self {
+            mode,
+            ..Self::default()
+        }
+    }
+
+    pub fn allow_engine(mut self, engine: impl Into<String>) -> Self {
+        self.engines.push(engine.into());
+        self
+    }
+
+    pub fn allow_table(mut self, table: impl Into<String>) -> Self {
+        self.tables.push(table.into());
+        self
+    }
+
+    /// Lists every way `statement` on `engine` falls outside the declared policy.
+    pub fn violations(&self, engine: Option<&str>, statement: &str) -> Vec<String> {
+        let mut violations = Vec::new();
+        if let Some(engine) = engine {
+            if !self.engines.is_empty()
+                && !self
+                    .engines
+                    .iter()
+                    .any(|declared| declared.eq_ignore_ascii_case(engine))
+            {
+                violations.push(format!("engine '{engine}' not declared"));
+            }
+        }
+        for table in referenced_tables(statement) {
+            if !self.allows_table(&table) {
+                violations.push(format!("table '{table}' not declared"));
+            }
+        }
+        violations
+    }
+
+    /// A qualified reference must match a declared name exactly; an
+    /// unqualified one may also match the table part of a qualified name.
+    fn allows_table(&self, table: &str) -> bool {
+        self.tables.iter().any(|declared| {
+            let declared_table = declared.rsplit('.').next().unwrap_or(declared);
+            declared.eq_ignore_ascii_case(table)
+                || (!table.contains('.') && declared_table.eq_ignore_ascii_case(table))
+        })
+    }
+}
+
+/// Tables `statement` reads or writes: names following `FROM`, `JOIN`,
+/// `INTO`, `UPDATE`, `USING`, `TABLE` and `TRUNCATE`, with their schema
+/// qualifier kept. Comments and literals are ignored, subqueries are
+/// searched too and names defined by `WITH` are left out.
+///
+/// Tables reached indirectly, through views, functions or dynamic SQL, are
+/// not seen.
+pub fn referenced_tables(statement: &str) -> Vec<String> {
+    let tokens = tokenize(statement);
+    let ctes = cte_names(&tokens);
+    let mut tables: Vec<String> = Vec::new();
+    // Whether each open parenthesis holds a function such as `extract`,
+    // whose `FROM` does not name a table.
+    let mut parens: Vec<bool> = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            SqlToken::Symbol('(') => parens.push(
+                i > 0
+                    && tokens[i - 1]
+                        .keyword()
+                        .is_some_and(|word| FROM_FUNCTIONS.contains(&word)),
+            ),
+            SqlToken::Symbol(')') => {
+                parens.pop();
+            }
+            _ => {}
+        }
+        let Some(keyword) = token.keyword() else {
+            continue;
+        };
+        if !TABLE_KEYWORDS.contains(&keyword) || parens.last() == Some(&true) {
+            continue;
+        }
+        let mut at = i + 1;
+        loop {
+            while tokens
+                .get(at)
+                .and_then(SqlToken::keyword)
+                .is_some_and(|word| TABLE_MODIFIERS.contains(&word))
+            {
+                at += 1;
+            }
+            let Some((name, end)) = qualified_name(&tokens, at) else {
+                break;
+            };
+            if !name.contains('.') && ctes.contains(&name) {
+                // A CTE, not a table.
+            } else if !tables.contains(&name) {
+                tables.push(name);
+            }
+            at = skip_alias(&tokens, end);
+            // `FROM a, b` lists several tables.
+            if keyword != "from" || tokens.get(at) != Some(&SqlToken::Symbol(',')) {
+                break;
+            }
+            at += 1;
+        }
+    }
+    tables
+}
+
+/// The dotted name starting at `tokens[at]`, lowercased, and the index
+/// after it.
+fn qualified_name(tokens: &[SqlToken], at: usize) -> Option<(String, usize)> {
+    let mut parts = Vec::new();
+    let mut at = at;
+    loop {
+        match tokens.get(at) {
+            Some(SqlToken::Word { text, quoted }) => {
+                if !quoted && parts.is_empty() && TABLE_KEYWORDS.contains(&text.as_str()) {
+                    return None;
+                }
+                parts.push(text.to_lowercase());
+            }
+            _ => return None,
+        }
+        if tokens.get(at + 1) != Some(&SqlToken::Symbol('.')) {
+            return Some((parts.join("."), at + 1));
+        }
+        at += 2;
+    }
+}
+
+fn skip_alias(tokens: &[SqlToken], at: usize) -> usize {
+    match tokens.get(at) {
+        Some(token) if token.is_keyword("as") => at + 2,
+        Some(SqlToken::Word { quoted: true, .. }) => at + 1,
+        Some(SqlToken::Word { text, .. }) if !CLAUSE_KEYWORDS.contains(&text.as_str()) => at + 1,
+        _ => at,
+    }
+}
+
+/// Names defined by a leading `WITH` clause.
+fn cte_names(tokens: &[SqlToken]) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut at = match tokens.first() {
+        Some(token) if token.is_keyword("with") => 1,
+        _ => return names,
+    };
+    if tokens
+        .get(at)
+        .is_some_and(|token| token.is_keyword("recursive"))
+    {
+        at += 1;
+    }
+    while let Some(SqlToken::Word { text, .. }) = tokens.get(at) {
+        names.push(text.to_lowercase());
+        at += 1;
+        // Optional column list, then `AS [NOT] [MATERIALIZED] ( ... )`.
+        if tokens.get(at) == Some(&SqlToken::Symbol('(')) {
+            at = skip_group(tokens, at);
+        }
+        while tokens
+            .get(at)
+            .and_then(SqlToken::keyword)
+            .is_some_and(|word| matches!(word, "as" | "not" | "materialized"))
+        {
+            at += 1;
+        }
+        if tokens.get(at) != Some(&SqlToken::Symbol('(')) {
+            break;
+        }
+        at = skip_group(tokens, at);
+        if tokens.get(at) != Some(&SqlToken::Symbol(',')) {
+            break;
+        }
+        at += 1;
+    }
+    names
+}
+
+/// Index after the parenthesised group opening at `tokens[at]`.
+fn skip_group(tokens: &[SqlToken], at: usize) -> usize {
+    let mut depth = 0usize;
+    for (i, token) in tokens.iter().enumerate().skip(at) {
+        match token {
+            SqlToken::Symbol('(') => depth += 1,
+            SqlToken::Symbol(')') => {
+                depth -= 1;
+                if depth == 0 {
+                    return i + 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    tokens.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_tables_in_subqueries() {
+        assert_eq!(
+            referenced_tables("SELECT * FROM users WHERE id IN (SELECT id FROM secret)"),
+            ["users", "secret"]
+        );
+    }
+
+    #[test]
+    fn ignores_comments_and_literals() {
+        assert_eq!(referenced_tables("SELECT * FROM/**/secret"), ["secret"]);
+        assert_eq!(
+            referenced_tables("SELECT 'from fake' FROM -- from other\n t"),
+            ["t"]
+        );
+    }
+
+    #[test]
+    fn keeps_schema_qualifier() {
+        assert_eq!(
+            referenced_tables("SELECT * FROM other.users u JOIN \"Public\".\"Orders\" o ON true"),
+            ["other.users", "public.orders"]
+        );
+        let policy = DataAccessPolicy::new(DataAccessMode::Enforce).allow_table("users");
+        assert!(policy.violations(None, "SELECT * FROM users").is_empty());
+        assert_eq!(
+            policy.violations(None, "SELECT * FROM other.users"),
+            ["table 'other.users' not declared"]
+        );
+        let qualified = DataAccessPolicy::new(DataAccessMode::Enforce).allow_table("app.users");
+        assert!(qualified.violations(None, "SELECT * FROM users").is_empty());
+    }
+
+    #[test]
+    fn finds_merge_and_list_targets() {
+        assert_eq!(
+            referenced_tables("MERGE INTO target t USING source s ON t.id = s.id"),
+            ["target", "source"]
+        );
+        assert_eq!(
+            referenced_tables("SELECT * FROM a, b AS x, c WHERE a.id = x.id"),
+            ["a", "b", "c"]
+        );
+        assert_eq!(referenced_tables("TRUNCATE TABLE logs"), ["logs"]);
+    }
+
+    #[test]
+    fn leaves_out_ctes_and_function_arguments() {
+        assert_eq!(
+            referenced_tables(
+                "WITH recent (id) AS (SELECT id FROM orders), x AS (SELECT 1) \
+                 SELECT extract(year FROM ts) FROM recent JOIN x USING (id)"
+            ),
+            ["orders"]
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use crate::error::ModuleKitError;
+use crate::service::ModuleServiceDescriptor;
+use crate::verifier::VerifiedClaims;
+
+/// Fails with `ModuleKitError::Forbidden` unless `claims` carries every scope.
+pub fn require_scopes(claims: &VerifiedClaims, scopes: &[&str]) -> Result<(), ModuleKitError> {
+    let missing: Vec<String> = scopes
+        .iter()
+        .filter(|scope| !claims.has_scope(scope))
+        .map(|scope| scope.to_string())
+        .collect();
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(ModuleKitError::Forbidden {
+            missing_scopes: missing,
+            required_roles: Vec::new(),
+        })
+    }
+}
+
+pub fn require_role(claims: &VerifiedClaims, role: &str) -> Result<(), ModuleKitError> {
+    require_any_role(claims, &[role])
+}
+
+/// Passes when `claims` holds at least one of `roles`.
+pub fn require_any_role(claims: &VerifiedClaims, roles: &[&str]) -> Result<(), ModuleKitError> {
+    if roles.is_empty() || roles.iter().any(|role| claims.has_role(role)) {
+        Ok(())
+    } else {
+        Err(ModuleKitError::Forbidden {
+            missing_scopes: Vec::new(),
+            required_roles: roles.iter().map(|role| role.to_string()).collect(),
+        })
+    }
+}
+
+/// Scope and role requirements for a service, usually taken from its descriptor.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuthzPolicy {
+    pub required_scopes: Vec<String>,
+    /// Callers need at least one of these roles; empty allows any role.
+    pub allowed_roles: Vec<String>,
+}
+
+impl AuthzPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_descriptor(descriptor: &ModuleServiceDescriptor) -> Self {
+        Self {
+            required_scopes: descriptor.required_scopes.clone(),
+            allowed_roles: descriptor.allowed_roles.clone(),
+        }
+    }
+
+    pub fn require_scope(mut self, scope: impl Into<String>) -> Self {
+        self.required_scopes.push(scope.into());
+        self
+    }
+
+    pub fn allow_role(mut self, role: impl Into<String>) -> Self {
+        self.allowed_roles.push(role.into());
+        self
+    }
+
+    /// Reports missing scopes and unmet roles together in one error.
+    pub fn check(&self, claims: &VerifiedClaims) -> Result<(), ModuleKitError> {
+        let missing_scopes: Vec<String> = self
+            .required_scopes
+            .iter()
+            .filter(|scope| !claims.has_scope(scope))
+            .cloned()
+            .collect();
+        let role_ok = self.allowed_roles.is_empty()
+            || self.allowed_roles.iter().any(|role| claims.has_role(role));
+        if missing_scopes.is_empty() && role_ok {
+            return Ok(());
+        }
+        Err(ModuleKitError::Forbidden {
+            missing_scopes,
+            required_roles: if role_ok {
+                Vec::new()
+            } else {
+                self.allowed_roles.clone()
+            },
+        })
+    }
+}
+
+impl From<&ModuleServiceDescriptor> for AuthzPolicy {
+    fn from(descriptor: &ModuleServiceDescriptor) -> Self {
+        Self::from_descriptor(descriptor)
+    }
+}
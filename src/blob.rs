@@ -0,0 +1,416 @@
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::connector_endpoint::{ConnectionHandle, ConnectorEndpoint, CONNECTOR_TIMEOUT};
+use crate::connector_envelope::{read_reply_line, write_line, ConnectorReply, ConnectorRequest};
+use crate::control_plane::ControlPlaneClient;
+use crate::env::{ModuleEnvironment, ENV_BLOB_CONNECTOR_URI};
+use crate::error::ModuleKitError;
+use crate::secrets::SecretString;
+use crate::token_provider::{ScopedTokenCache, ServiceTokenProvider};
+use crate::tokens::ModuleTokenExchangeRequest;
+
+const PRESIGN_PATH: &str = "modules/runtime/blobs/presign";
+/// Bytes per chunk handed from the `get_async` reader thread.
+#[cfg(feature = "tokio")]
+const STREAM_CHUNK_BYTES: usize = 64 * 1024;
+/// Chunks buffered between the `get_async` reader thread and the writer.
+#[cfg(feature = "tokio")]
+const STREAM_BUFFER_CHUNKS: usize = 4;
+
+/// Request line sent to the object store connector. `Put` is followed by
+/// exactly `size` bytes of content.
+pub type BlobConnectorRequest = ConnectorRequest<BlobCommand>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum BlobCommand {
+    Put {
+        key: String,
+        size: u64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        content_type: Option<String>,
+    },
+    Get {
+        key: String,
+    },
+    Delete {
+        key: String,
+    },
+    List {
+        prefix: String,
+    },
+}
+
+impl BlobCommand {
+    /// `Put` and `Delete` need a `blob:write` token.
+    pub fn requires_write_scope(&self) -> bool {
+        matches!(self, BlobCommand::Put { .. } | BlobCommand::Delete { .. })
+    }
+}
+
+/// Response line from the connector. For a found `Get` it is followed by
+/// `object.size` bytes of content.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlobConnectorResponse {
+    pub ok: bool,
+    /// Stored, fetched or deleted object; `None` when the key does not exist.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object: Option<BlobObject>,
+    /// Objects matching a `List` prefix.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub objects: Vec<BlobObject>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ConnectorReply for BlobConnectorResponse {
+    fn is_ok(&self) -> bool {
+        self.ok
+    }
+
+    fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlobObject {
+    pub key: String,
+    pub size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    /// RFC 3339 timestamp of the last write.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum PresignMethod {
+    Get,
+    Put,
+}
+
+#[derive(Serialize)]
+struct PresignRequest<'a> {
+    key: &'a str,
+    method: PresignMethod,
+    expires_in_seconds: u64,
+}
+
+/// URL that grants `method` on one object without a Fenrir token, e.g. for
+/// browser uploads.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresignedBlobUrl {
+    pub url: String,
+    pub method: PresignMethod,
+    pub expires_in_seconds: u64,
+}
+
+/// Client for the platform object store connector.
+///
+/// Reads and listings use the service token; writes and deletes exchange it
+/// for a `blob:write` token, cached until shortly before it expires.
+pub struct BlobConnectorClient {
+    endpoint: ConnectorEndpoint,
+    tokens: Arc<ServiceTokenProvider>,
+    control_plane: Option<ControlPlaneClient>,
+    write_token: ScopedTokenCache,
+}
+
+impl BlobConnectorClient {
+    pub fn from_env() -> Result<Self, ModuleKitError> {
+        Self::from_environment(ModuleEnvironment::from_env()?)
+    }
+
+    /// Fails with `MissingEnv` when `FENRIR_BLOB_CONNECTOR_URI` is unset.
+    pub fn from_environment(env: ModuleEnvironment) -> Result<Self, ModuleKitError> {
+        let tokens = Arc::new(env.token_provider()?);
+        Self::with_token_provider(env, tokens)
+    }
+
+    /// Client for `env.blob_connector`, exchanging `tokens` for `blob:write`
+    /// on writes. `presign` is available when `env` names a control plane.
+    pub fn with_token_provider(
+        env: ModuleEnvironment,
+        tokens: Arc<ServiceTokenProvider>,
+    ) -> Result<Self, ModuleKitError> {
+        let control_plane = match env.control_plane.url {
+            Some(_) => Some(ControlPlaneClient::new(&env.control_plane)?),
+            None => None,
+        };
+        let endpoint = env
+            .blob_connector
+            .ok_or(ModuleKitError::MissingEnv(ENV_BLOB_CONNECTOR_URI))?;
+        let mut client = Self::new(endpoint, tokens);
+        client.control_plane = control_plane;
+        Ok(client)
+    }
+
+    /// Client without a control plane; `presign` fails with
+    /// `ControlPlaneMissing` until one is set.
+    pub fn new(endpoint: ConnectorEndpoint, tokens: Arc<ServiceTokenProvider>) -> Self {
+        Self {
+            endpoint,
+            tokens,
+            control_plane: None,
+            write_token: ScopedTokenCache::new(ModuleTokenExchangeRequest::blob_write),
+        }
+    }
+
+    pub fn with_control_plane(mut self, control_plane: ControlPlaneClient) -> Self {
+        self.control_plane = Some(control_plane);
+        self
+    }
+
+    pub fn endpoint(&self) -> &ConnectorEndpoint {
+        &self.endpoint
+    }
+
+    /// Stores `data` under `key`, replacing any existing object.
+    pub fn put(
+        &self,
+        key: impl Into<String>,
+        data: &[u8],
+        content_type: Option<&str>,
+    ) -> Result<BlobObject, ModuleKitError> {
+        self.put_stream(key, data, data.len() as u64, content_type)
+    }
+
+    /// Streams `size` bytes from `content` into `key` without buffering them.
+    pub fn put_stream(
+        &self,
+        key: impl Into<String>,
+        content: impl Read,
+        size: u64,
+        content_type: Option<&str>,
+    ) -> Result<BlobObject, ModuleKitError> {
+        let key = key.into();
+        let command = BlobCommand::Put {
+            key: key.clone(),
+            size,
+            content_type: content_type.map(str::to_string),
+        };
+        let (mut connection, token) = self.connect(&command)?;
+        write_line(&mut connection, &BlobConnectorRequest::new(token, command))?;
+        let copied = io::copy(&mut content.take(size), &mut connection)?;
+        if copied != size {
+            connection.abort();
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("blob '{key}' content ended after {copied} of {size} bytes"),
+            )
+            .into());
+        }
+        connection.flush()?;
+        let (_, response) = read_reply_line::<BlobConnectorResponse>(connection, "blob")?;
+        response.object.ok_or_else(|| {
+            ModuleKitError::ConnectorRejected(format!("put of '{key}' returned no object"))
+        })
+    }
+
+    /// Writes the object's content to `out`, returning its metadata, or
+    /// `None` when `key` does not exist.
+    pub fn get(
+        &self,
+        key: impl Into<String>,
+        out: &mut impl Write,
+    ) -> Result<Option<BlobObject>, ModuleKitError> {
+        let command = BlobCommand::Get { key: key.into() };
+        let (mut connection, token) = self.connect(&command)?;
+        write_line(&mut connection, &BlobConnectorRequest::new(token, command))?;
+        let (reader, response) = read_reply_line::<BlobConnectorResponse>(connection, "blob")?;
+        let Some(object) = response.object else {
+            return Ok(None);
+        };
+        let copied = io::copy(&mut reader.take(object.size), out)?;
+        if copied != object.size {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!(
+                    "blob '{}' content ended after {copied} of {} bytes",
+                    object.key, object.size
+                ),
+            )
+            .into());
+        }
+        Ok(Some(object))
+    }
+
+    /// `get` for async writers. Connecting and reading the reply line block;
+    /// the content is then read on a dedicated thread and written to `out`
+    /// as it arrives.
+    #[cfg(feature = "tokio")]
+    pub async fn get_async(
+        &self,
+        key: impl Into<String>,
+        out: &mut (impl tokio::io::AsyncWrite + Unpin),
+    ) -> Result<Option<BlobObject>, ModuleKitError> {
+        use tokio::io::AsyncWriteExt;
+
+        let command = BlobCommand::Get { key: key.into() };
+        let (mut connection, token) = self.connect(&command)?;
+        write_line(&mut connection, &BlobConnectorRequest::new(token, command))?;
+        let (reader, response) = read_reply_line::<BlobConnectorResponse>(connection, "blob")?;
+        let Some(object) = response.object else {
+            return Ok(None);
+        };
+        let (tx, mut rx) = tokio::sync::mpsc::channel(STREAM_BUFFER_CHUNKS);
+        let size = object.size;
+        std::thread::spawn(move || {
+            let mut content = reader.take(size);
+            loop {
+                let mut chunk = vec![0; STREAM_CHUNK_BYTES];
+                let item = match content.read(&mut chunk) {
+                    Ok(0) => break,
+                    Ok(read) => {
+                        chunk.truncate(read);
+                        Ok(chunk)
+                    }
+                    Err(err) => Err(err),
+                };
+                let failed = item.is_err();
+                if tx.blocking_send(item).is_err() || failed {
+                    break;
+                }
+            }
+        });
+        let mut copied = 0;
+        while let Some(chunk) = rx.recv().await {
+            let chunk = chunk?;
+            out.write_all(&chunk).await?;
+            copied += chunk.len() as u64;
+        }
+        out.flush().await?;
+        if copied != object.size {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!(
+                    "blob '{}' content ended after {copied} of {} bytes",
+                    object.key, object.size
+                ),
+            )
+            .into());
+        }
+        Ok(Some(object))
+    }
+
+    /// Removes `key`, returning whether it existed.
+    pub fn delete(&self, key: impl Into<String>) -> Result<bool, ModuleKitError> {
+        Ok(self
+            .command(BlobCommand::Delete { key: key.into() })?
+            .object
+            .is_some())
+    }
+
+    /// Objects whose key starts with `prefix`, sorted by key.
+    pub fn list(&self, prefix: impl Into<String>) -> Result<Vec<BlobObject>, ModuleKitError> {
+        let mut objects = self
+            .command(BlobCommand::List {
+                prefix: prefix.into(),
+            })?
+            .objects;
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(objects)
+    }
+
+    /// Asks the control plane for a URL granting `method` on `key` for `ttl`.
+    ///
+    /// `Put` URLs are requested with the `blob:write` token.
+    pub fn presign(
+        &self,
+        key: &str,
+        method: PresignMethod,
+        ttl: Duration,
+    ) -> Result<PresignedBlobUrl, ModuleKitError> {
+        let control_plane = self
+            .control_plane
+            .as_ref()
+            .ok_or(ModuleKitError::ControlPlaneMissing)?;
+        let bearer = match method {
+            PresignMethod::Put => self.write_token.get(&self.tokens)?,
+            PresignMethod::Get => self.tokens.current_token()?,
+        };
+        control_plane.post_json(
+            bearer.expose(),
+            PRESIGN_PATH,
+            &PresignRequest {
+                key,
+                method,
+                expires_in_seconds: ttl.as_secs().max(1),
+            },
+        )
+    }
+
+    fn command(&self, command: BlobCommand) -> Result<BlobConnectorResponse, ModuleKitError> {
+        let (mut connection, token) = self.connect(&command)?;
+        write_line(&mut connection, &BlobConnectorRequest::new(token, command))?;
+        read_reply_line::<BlobConnectorResponse>(connection, "blob").map(|(_, response)| response)
+    }
+
+    fn connect(
+        &self,
+        command: &BlobCommand,
+    ) -> Result<(ConnectionHandle, SecretString), ModuleKitError> {
+        let token = if command.requires_write_scope() {
+            self.write_token.get(&self.tokens)?
+        } else {
+            self.tokens.current_token()?
+        };
+        let connection = self.endpoint.connect()?;
+        connection.set_read_timeout(Some(CONNECTOR_TIMEOUT))?;
+        Ok((connection, token))
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+
+    use super::*;
+    use crate::token_provider::ServiceTokenLease;
+
+    #[test]
+    fn get_async_streams_content_to_an_async_writer() {
+        let content: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let served = content.clone();
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let reply = serde_json::json!({
+                "ok": true,
+                "object": { "key": "report.bin", "size": served.len() },
+            });
+            let mut stream = reader.into_inner();
+            stream.write_all(format!("{reply}\n").as_bytes()).unwrap();
+            stream.write_all(&served).unwrap();
+        });
+        let tokens =
+            ServiceTokenProvider::builder(ServiceTokenLease::new("token", None, None, None))
+                .auto_refresh(false)
+                .build();
+        let client = BlobConnectorClient::new(ConnectorEndpoint::Tcp { addr }, Arc::new(tokens));
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        let mut out = Vec::new();
+        let object = runtime
+            .block_on(client.get_async("report.bin", &mut out))
+            .unwrap()
+            .unwrap();
+        assert_eq!(object.size, content.len() as u64);
+        assert_eq!(out, content);
+    }
+}
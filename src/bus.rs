@@ -0,0 +1,279 @@
+use std::io::{BufRead, BufReader};
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+use crate::connector_endpoint::{ConnectionHandle, ConnectorEndpoint, CONNECTOR_TIMEOUT};
+use crate::connector_envelope::{read_reply_line, write_line, ConnectorReply, ConnectorRequest};
+use crate::env::{ModuleEnvironment, ENV_BUS_CONNECTOR_URI};
+use crate::error::ModuleKitError;
+use crate::secrets::SecretString;
+use crate::token_provider::{ScopedTokenCache, ServiceTokenProvider};
+use crate::tokens::ModuleTokenExchangeRequest;
+
+/// First frame on a bus connection. Frames are JSON objects terminated by a
+/// newline.
+pub type BusConnectorRequest = ConnectorRequest<BusCommand>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum BusCommand {
+    /// Requires a `bus:publish` token.
+    Publish { topic: String, payload: JsonValue },
+    /// Keeps the connection open and streams `BusMessage` frames after the
+    /// acknowledgement. Requires a `bus:consume` token.
+    Subscribe { topic: String },
+}
+
+/// Reply to a request frame; also sent mid-stream when the connector ends a
+/// subscription.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BusConnectorResponse {
+    pub ok: bool,
+    /// Identifier the bus assigned to a published message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ConnectorReply for BusConnectorResponse {
+    fn is_ok(&self) -> bool {
+        self.ok
+    }
+
+    fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BusMessage {
+    pub id: String,
+    pub topic: String,
+    pub payload: JsonValue,
+}
+
+impl BusMessage {
+    pub fn payload_as<T: DeserializeOwned>(&self) -> Result<T, ModuleKitError> {
+        Ok(serde_json::from_value(self.payload.clone())?)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BusFrame {
+    Message(BusMessage),
+    Status(BusConnectorResponse),
+}
+
+/// Client for the platform message bus connector.
+///
+/// Publishing and consuming each exchange the service token for their own
+/// scoped token (`bus:publish`, `bus:consume`), cached until shortly before
+/// it expires.
+pub struct BusConnectorClient {
+    endpoint: ConnectorEndpoint,
+    tokens: Arc<ServiceTokenProvider>,
+    publish_token: ScopedTokenCache,
+    consume_token: ScopedTokenCache,
+}
+
+impl BusConnectorClient {
+    pub fn from_env() -> Result<Self, ModuleKitError> {
+        Self::from_environment(ModuleEnvironment::from_env()?)
+    }
+
+    /// Fails with `MissingEnv` when `FENRIR_BUS_CONNECTOR_URI` is unset.
+    pub fn from_environment(env: ModuleEnvironment) -> Result<Self, ModuleKitError> {
+        let tokens = Arc::new(env.token_provider()?);
+        Self::with_token_provider(env, tokens)
+    }
+
+    /// Client for `env.bus_connector`. Publishing and subscribing exchange
+    /// `tokens` for `bus:publish` and `bus:consume` respectively.
+    pub fn with_token_provider(
+        env: ModuleEnvironment,
+        tokens: Arc<ServiceTokenProvider>,
+    ) -> Result<Self, ModuleKitError> {
+        let endpoint = env
+            .bus_connector
+            .ok_or(ModuleKitError::MissingEnv(ENV_BUS_CONNECTOR_URI))?;
+        Ok(Self::new(endpoint, tokens))
+    }
+
+    pub fn new(endpoint: ConnectorEndpoint, tokens: Arc<ServiceTokenProvider>) -> Self {
+        Self {
+            endpoint,
+            tokens,
+            publish_token: ScopedTokenCache::new(ModuleTokenExchangeRequest::bus_publish),
+            consume_token: ScopedTokenCache::new(ModuleTokenExchangeRequest::bus_consume),
+        }
+    }
+
+    pub fn endpoint(&self) -> &ConnectorEndpoint {
+        &self.endpoint
+    }
+
+    /// Publishes `payload` to `topic`, returning the message id when the bus
+    /// assigns one.
+    pub fn publish(
+        &self,
+        topic: impl Into<String>,
+        payload: &impl Serialize,
+    ) -> Result<Option<String>, ModuleKitError> {
+        let command = BusCommand::Publish {
+            topic: topic.into(),
+            payload: serde_json::to_value(payload)?,
+        };
+        let token = self.publish_token.get(&self.tokens)?;
+        let (_, response) = self.open(token, command)?;
+        Ok(response.message_id)
+    }
+
+    /// Subscribes to `topic`; the iterator blocks until the next message and
+    /// ends when the connector closes the connection.
+    pub fn subscribe(&self, topic: impl Into<String>) -> Result<BusSubscription, ModuleKitError> {
+        let command = BusCommand::Subscribe {
+            topic: topic.into(),
+        };
+        let token = self.consume_token.get(&self.tokens)?;
+        let (reader, _) = self.open(token, command)?;
+        reader.get_ref().set_read_timeout(None)?;
+        let control = reader.get_ref().try_clone()?;
+        Ok(BusSubscription {
+            reader,
+            control: Arc::new(control),
+            done: false,
+        })
+    }
+
+    /// Sends the request frame and reads the acknowledgement.
+    fn open(
+        &self,
+        token: SecretString,
+        command: BusCommand,
+    ) -> Result<(BufReader<ConnectionHandle>, BusConnectorResponse), ModuleKitError> {
+        let mut connection = self.endpoint.connect()?;
+        connection.set_read_timeout(Some(CONNECTOR_TIMEOUT))?;
+        write_line(&mut connection, &BusConnectorRequest::new(token, command))?;
+        read_reply_line(connection, "bus")
+    }
+}
+
+/// Messages from one subscription, in delivery order.
+pub struct BusSubscription {
+    reader: BufReader<ConnectionHandle>,
+    control: Arc<ConnectionHandle>,
+    done: bool,
+}
+
+impl BusSubscription {
+    /// Handle that ends the subscription from another thread.
+    pub fn closer(&self) -> BusSubscriptionCloser {
+        BusSubscriptionCloser {
+            control: Arc::clone(&self.control),
+        }
+    }
+
+    fn next_frame(&mut self) -> Option<Result<BusMessage, ModuleKitError>> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            match self.reader.read_line(&mut line) {
+                Ok(0) => return None,
+                Ok(_) if line.trim().is_empty() => continue,
+                Ok(_) => {}
+                Err(err) => return Some(Err(err.into())),
+            }
+            match serde_json::from_str::<BusFrame>(&line) {
+                Ok(BusFrame::Message(message)) => return Some(Ok(message)),
+                // Keep-alive from the connector.
+                Ok(BusFrame::Status(status)) if status.ok => continue,
+                Ok(BusFrame::Status(status)) => return status.into_result().err().map(Err),
+                Err(err) => return Some(Err(err.into())),
+            }
+        }
+    }
+}
+
+impl Iterator for BusSubscription {
+    type Item = Result<BusMessage, ModuleKitError>;
+
+    /// Yields messages until the connection closes; the first error ends
+    /// the subscription.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let item = self.next_frame();
+        if !matches!(item, Some(Ok(_))) {
+            self.done = true;
+        }
+        item
+    }
+}
+
+#[derive(Clone)]
+pub struct BusSubscriptionCloser {
+    control: Arc<ConnectionHandle>,
+}
+
+impl BusSubscriptionCloser {
+    /// Closes the connection; the subscription's iterator then ends.
+    pub fn close(&self) {
+        self.control.abort();
+    }
+}
+
+/// Messages buffered between a `BusStream` reader thread and the stream.
+#[cfg(feature = "tokio")]
+const STREAM_BUFFER_MESSAGES: usize = 16;
+
+/// `BusSubscription` as an async stream; dropping it closes the
+/// subscription.
+#[cfg(feature = "tokio")]
+pub struct BusStream {
+    rx: tokio::sync::mpsc::Receiver<Result<BusMessage, ModuleKitError>>,
+    closer: BusSubscriptionCloser,
+}
+
+#[cfg(feature = "tokio")]
+impl BusConnectorClient {
+    /// `subscribe` as a `Stream`, read by a dedicated thread. Opening the
+    /// subscription blocks; call it from a blocking context.
+    pub fn subscribe_stream(&self, topic: impl Into<String>) -> Result<BusStream, ModuleKitError> {
+        let subscription = self.subscribe(topic)?;
+        let closer = subscription.closer();
+        let (tx, rx) = tokio::sync::mpsc::channel(STREAM_BUFFER_MESSAGES);
+        std::thread::spawn(move || {
+            for item in subscription {
+                if tx.blocking_send(item).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(BusStream { rx, closer })
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl futures_core::Stream for BusStream {
+    type Item = Result<BusMessage, ModuleKitError>;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Drop for BusStream {
+    fn drop(&mut self) {
+        self.closer.close();
+    }
+}
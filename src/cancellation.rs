@@ -0,0 +1,107 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::connector::DbConnectorClient;
+use crate::connector_request::{
+    DbConnectorCommand, DbConnectorIntent, DbTenantPolicy, ExecuteOptions,
+};
+use crate::connector_response::DbConnectorResponse;
+use crate::error::ModuleKitError;
+use crate::tenant_context::TenantContext;
+
+static NEXT_REQUEST: AtomicU64 = AtomicU64::new(0);
+
+/// Id unique across replicas: process id, current time and a counter. Used
+/// for request ids and generated idempotency keys.
+pub(crate) fn unique_request_id() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos() as u64)
+        .unwrap_or_default();
+    let sequence = NEXT_REQUEST.fetch_add(1, Ordering::Relaxed);
+    format!("{:x}-{nanos:x}-{sequence:x}", std::process::id())
+}
+
+/// A request running on a background thread that can be cancelled.
+///
+/// Dropping the handle does not cancel the request; it keeps running and
+/// its result is discarded.
+pub struct QueryHandle {
+    client: Arc<DbConnectorClient>,
+    request_id: String,
+    intent: DbConnectorIntent,
+    engine: Option<String>,
+    cancelled: AtomicBool,
+    thread: thread::JoinHandle<Result<DbConnectorResponse, ModuleKitError>>,
+}
+
+impl QueryHandle {
+    pub fn request_id(&self) -> &str {
+        &self.request_id
+    }
+
+    /// Sends a cancel for this request over a second connection, returning
+    /// whether the connector still knew the request.
+    pub fn cancel(&self) -> Result<bool, ModuleKitError> {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.client
+            .cancel_request(&self.request_id, self.intent, self.engine.as_deref())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Blocks until the request completes. A request that failed after
+    /// `cancel` was called reports `ModuleKitError::QueryCancelled`.
+    pub fn wait(self) -> Result<DbConnectorResponse, ModuleKitError> {
+        let result = match self.thread.join() {
+            Ok(result) => result,
+            Err(panic) => std::panic::resume_unwind(panic),
+        };
+        if !self.cancelled.load(Ordering::SeqCst) {
+            return result;
+        }
+        match result {
+            Ok(response) if response.ok => Ok(response),
+            _ => Err(ModuleKitError::QueryCancelled(self.request_id)),
+        }
+    }
+}
+
+impl DbConnectorClient {
+    /// Like `execute_with`, but runs the request on a background thread
+    /// under a fresh request id and returns at once. The caller's
+    /// thread-local `TenantContext` carries over to that thread.
+    pub fn execute_cancellable(
+        self: &Arc<Self>,
+        command: DbConnectorCommand,
+        intent: DbConnectorIntent,
+        engine: Option<&str>,
+        tenant: Option<DbTenantPolicy>,
+        options: ExecuteOptions,
+    ) -> QueryHandle {
+        let request_id = unique_request_id();
+        let options = options.request_id(request_id.clone());
+        let engine = engine.map(str::to_string);
+        let thread = {
+            let client = Arc::clone(self);
+            let engine = engine.clone();
+            let ambient = TenantContext::current();
+            thread::spawn(move || {
+                let _tenant = ambient.map(TenantContext::enter);
+                client.execute_with(command, intent, engine.as_deref(), tenant, &options)
+            })
+        };
+        QueryHandle {
+            client: Arc::clone(self),
+            request_id,
+            intent,
+            engine,
+            cancelled: AtomicBool::new(false),
+            thread,
+        }
+    }
+}
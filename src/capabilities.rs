@@ -0,0 +1,125 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::ModuleKitError;
+
+/// Parameter limit assumed for engines that report none, matching SQLite
+/// builds before 3.32.
+const FALLBACK_MAX_PARAMS: u32 = 999;
+
+/// SQL features a higher-level helper may depend on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EngineFeature {
+    PreparedParams,
+    Transactions,
+    Returning,
+    Savepoints,
+    ListenNotify,
+    JsonOperators,
+}
+
+impl EngineFeature {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EngineFeature::PreparedParams => "prepared statement parameters",
+            EngineFeature::Transactions => "transactions",
+            EngineFeature::Returning => "RETURNING clause",
+            EngineFeature::Savepoints => "savepoints",
+            EngineFeature::ListenNotify => "LISTEN/NOTIFY",
+            EngineFeature::JsonOperators => "JSON operators",
+        }
+    }
+}
+
+impl fmt::Display for EngineFeature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// What an engine behind the connector supports, as reported by the
+/// connector's `capabilities` handshake.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EngineCapabilities {
+    pub engine: String,
+    /// Connectors that predate this field always supported both.
+    #[serde(default = "reported_true")]
+    pub prepared_params: bool,
+    #[serde(default = "reported_true")]
+    pub transactions: bool,
+    #[serde(default)]
+    pub returning: bool,
+    #[serde(default)]
+    pub savepoints: bool,
+    #[serde(default)]
+    pub listen_notify: bool,
+    #[serde(default)]
+    pub json_operators: bool,
+    /// Most bind parameters one statement may carry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_params: Option<u32>,
+}
+
+impl EngineCapabilities {
+    /// Built-in assumptions for well-known engines, used when the connector
+    /// predates the handshake.
+    pub fn defaults_for(engine: &str) -> Self {
+        let (returning, savepoints, listen_notify, json_operators) =
+            match engine.to_ascii_lowercase().as_str() {
+                "postgres" | "postgresql" => (true, true, true, true),
+                "sqlite" => (true, true, false, true),
+                "mysql" => (false, true, false, true),
+                "mariadb" => (true, true, false, true),
+                _ => (false, false, false, false),
+            };
+        let max_params = match engine.to_ascii_lowercase().as_str() {
+            "postgres" | "postgresql" | "mysql" | "mariadb" => 65_535,
+            "sqlite" => 32_766,
+            "mssql" | "sqlserver" => 2_100,
+            _ => FALLBACK_MAX_PARAMS,
+        };
+        Self {
+            engine: engine.to_string(),
+            prepared_params: true,
+            transactions: true,
+            returning,
+            savepoints,
+            listen_notify,
+            json_operators,
+            max_params: Some(max_params),
+        }
+    }
+
+    /// `max_params`, or a conservative 999 when the engine did not report it.
+    pub fn param_limit(&self) -> usize {
+        self.max_params.unwrap_or(FALLBACK_MAX_PARAMS) as usize
+    }
+
+    pub fn supports(&self, feature: EngineFeature) -> bool {
+        match feature {
+            EngineFeature::PreparedParams => self.prepared_params,
+            EngineFeature::Transactions => self.transactions,
+            EngineFeature::Returning => self.returning,
+            EngineFeature::Savepoints => self.savepoints,
+            EngineFeature::ListenNotify => self.listen_notify,
+            EngineFeature::JsonOperators => self.json_operators,
+        }
+    }
+
+    /// Fails with `ModuleKitError::UnsupportedEngineFeature` when `feature` is missing.
+    pub fn require(&self, feature: EngineFeature) -> Result<(), ModuleKitError> {
+        if self.supports(feature) {
+            Ok(())
+        } else {
+            Err(ModuleKitError::UnsupportedEngineFeature {
+                engine: self.engine.clone(),
+                feature,
+            })
+        }
+    }
+}
+
+fn reported_true() -> bool {
+    true
+}
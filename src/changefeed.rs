@@ -0,0 +1,267 @@
+use std::collections::BTreeMap;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+use crate::connector::DbConnectorClient;
+use crate::connector_request::{
+    DbConnectorCommand, DbConnectorIntent, DbPreparedParam, DbTenantPolicy,
+};
+use crate::error::ModuleKitError;
+use crate::sql::SafeIdent;
+use crate::values::standard_coercion;
+
+const DEFAULT_BATCH_SIZE: u32 = 500;
+const TENANT_PARAM: &str = "tenant";
+
+/// Opaque position in a change feed, handed to clients between syncs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangeCursor(String);
+
+#[derive(Serialize, Deserialize)]
+struct CursorPosition {
+    version: String,
+    key: String,
+}
+
+impl ChangeCursor {
+    pub fn parse(value: impl Into<String>) -> Result<Self, ModuleKitError> {
+        let cursor = Self(value.into());
+        cursor.position()?;
+        Ok(cursor)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    fn encode(position: &CursorPosition) -> Result<Self, ModuleKitError> {
+        Ok(Self(URL_SAFE_NO_PAD.encode(serde_json::to_vec(position)?)))
+    }
+
+    fn position(&self) -> Result<CursorPosition, ModuleKitError> {
+        let invalid = |message: String| ModuleKitError::InvalidCursor(message);
+        let bytes = URL_SAFE_NO_PAD
+            .decode(&self.0)
+            .map_err(|err| invalid(err.to_string()))?;
+        serde_json::from_slice(&bytes).map_err(|err| invalid(err.to_string()))
+    }
+}
+
+/// One row that changed since the cursor.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RowChange {
+    pub key: String,
+    pub version: String,
+    /// Set when the tombstone column is non-null.
+    pub deleted: bool,
+    pub values: BTreeMap<String, String>,
+}
+
+/// Changes returned by one `ChangeFeed::since` call.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChangeSet {
+    pub changes: Vec<RowChange>,
+    /// Cursor to pass to the next call; unchanged when nothing changed.
+    pub cursor: Option<String>,
+    /// More changes are waiting beyond this batch.
+    pub has_more: bool,
+}
+
+/// Incremental sync over a table whose rows carry a monotonically increasing
+/// version, such as an `updated_at` timestamp or a `version` counter.
+///
+/// Changes are ordered by `(version, key)`, so rows sharing a version are
+/// never skipped between batches. Deletes are only visible as soft-deletes
+/// through `tombstone_column`.
+pub struct ChangeFeed<'a> {
+    client: &'a DbConnectorClient,
+    table: SafeIdent,
+    key_column: SafeIdent,
+    version_column: SafeIdent,
+    tombstone_column: Option<SafeIdent>,
+    columns: Vec<SafeIdent>,
+    tenant: Option<(SafeIdent, String)>,
+    engine: Option<String>,
+    batch_size: u32,
+}
+
+impl<'a> ChangeFeed<'a> {
+    pub fn new(client: &'a DbConnectorClient, table: &str) -> Result<Self, ModuleKitError> {
+        Ok(Self {
+            client,
+            table: SafeIdent::new(table)?,
+            key_column: SafeIdent::new("id")?,
+            version_column: SafeIdent::new("updated_at")?,
+            tombstone_column: None,
+            columns: Vec::new(),
+            tenant: None,
+            engine: None,
+            batch_size: DEFAULT_BATCH_SIZE,
+        })
+    }
+
+    pub fn key_column(mut self, column: &str) -> Result<Self, ModuleKitError> {
+        self.key_column = SafeIdent::new(column)?;
+        Ok(self)
+    }
+
+    pub fn version_column(mut self, column: &str) -> Result<Self, ModuleKitError> {
+        self.version_column = SafeIdent::new(column)?;
+        Ok(self)
+    }
+
+    /// Column that is non-null on soft-deleted rows, e.g. `deleted_at`.
+    pub fn tombstone_column(mut self, column: &str) -> Result<Self, ModuleKitError> {
+        self.tombstone_column = Some(SafeIdent::new(column)?);
+        Ok(self)
+    }
+
+    /// Columns returned with each change; all columns when none are given.
+    pub fn columns(mut self, columns: &[&str]) -> Result<Self, ModuleKitError> {
+        self.columns = columns
+            .iter()
+            .map(|column| SafeIdent::new(*column))
+            .collect::<Result<_, _>>()?;
+        Ok(self)
+    }
+
+    /// Restricts the feed to rows whose `column` equals `tenant`.
+    pub fn tenant(
+        mut self,
+        column: &str,
+        tenant: impl Into<String>,
+    ) -> Result<Self, ModuleKitError> {
+        self.tenant = Some((SafeIdent::new(column)?, tenant.into()));
+        Ok(self)
+    }
+
+    pub fn engine(mut self, value: impl Into<String>) -> Self {
+        self.engine = Some(value.into());
+        self
+    }
+
+    pub fn batch_size(mut self, value: u32) -> Self {
+        self.batch_size = value.max(1);
+        self
+    }
+
+    /// Up to `batch_size` changes after `cursor`, or from the start when `None`.
+    pub fn since(&self, cursor: Option<&ChangeCursor>) -> Result<ChangeSet, ModuleKitError> {
+        let mut conditions = Vec::new();
+        let mut params = Vec::new();
+        let mut tenant_policy = None;
+        if let Some((column, tenant)) = &self.tenant {
+            conditions.push(format!("{column} = :{TENANT_PARAM}"));
+            params.push(DbPreparedParam {
+                name: TENANT_PARAM.to_string(),
+                value: JsonValue::String(tenant.clone()),
+            });
+            tenant_policy = Some(DbTenantPolicy::inject(TENANT_PARAM));
+        }
+        if let Some(cursor) = cursor {
+            let position = cursor.position()?;
+            let (version, key) = (&self.version_column, &self.key_column);
+            conditions.push(format!(
+                "({version} > :after_version OR ({version} = :after_version AND {key} > :after_key))"
+            ));
+            params.push(DbPreparedParam {
+                name: "after_version".to_string(),
+                value: cursor_value(&position.version),
+            });
+            params.push(DbPreparedParam {
+                name: "after_key".to_string(),
+                value: cursor_value(&position.key),
+            });
+        }
+        let selected = if self.columns.is_empty() {
+            "*".to_string()
+        } else {
+            let mut columns = self.columns.clone();
+            for required in [&self.key_column, &self.version_column]
+                .into_iter()
+                .chain(self.tombstone_column.as_ref())
+            {
+                if !columns.contains(required) {
+                    columns.push(required.clone());
+                }
+            }
+            columns
+                .iter()
+                .map(SafeIdent::as_str)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let mut statement = format!("SELECT {selected} FROM {}", self.table);
+        if !conditions.is_empty() {
+            statement.push_str(&format!(" WHERE {}", conditions.join(" AND ")));
+        }
+        statement.push_str(&format!(
+            " ORDER BY {}, {} LIMIT {}",
+            self.version_column,
+            self.key_column,
+            self.batch_size as u64 + 1
+        ));
+        let response = self
+            .client
+            .execute(
+                DbConnectorCommand::Prepared { statement, params },
+                DbConnectorIntent::Read,
+                self.engine.as_deref(),
+                tenant_policy,
+            )?
+            .into_result()?;
+        let coercion = match &self.engine {
+            Some(engine) => self.client.cell_coercion(engine),
+            None => standard_coercion().clone(),
+        };
+        let mut changes = Vec::new();
+        for result in response.results.iter().flatten() {
+            for row in result.rows_with(&coercion) {
+                let deleted = match &self.tombstone_column {
+                    Some(column) => row.get::<Option<String>>(column.as_str())?.is_some(),
+                    None => false,
+                };
+                changes.push(RowChange {
+                    key: row.get(self.key_column.as_str())?,
+                    version: row.get(self.version_column.as_str())?,
+                    deleted,
+                    values: row
+                        .columns()
+                        .iter()
+                        .cloned()
+                        .zip(row.values().iter().cloned())
+                        .collect(),
+                });
+            }
+        }
+        let has_more = changes.len() > self.batch_size as usize;
+        changes.truncate(self.batch_size as usize);
+        let cursor = match changes.last() {
+            Some(last) => Some(
+                ChangeCursor::encode(&CursorPosition {
+                    version: last.version.clone(),
+                    key: last.key.clone(),
+                })?
+                .0,
+            ),
+            None => cursor.map(|cursor| cursor.0.clone()),
+        };
+        Ok(ChangeSet {
+            changes,
+            cursor,
+            has_more,
+        })
+    }
+}
+
+/// Integers are bound as numbers so numeric version and key columns compare
+/// numerically; everything else is bound as text.
+pub(crate) fn cursor_value(value: &str) -> JsonValue {
+    match value.parse::<i64>() {
+        Ok(number) => JsonValue::from(number),
+        Err(_) => JsonValue::String(value.to_string()),
+    }
+}
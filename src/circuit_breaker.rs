@@ -0,0 +1,120 @@
+use std::time::{Duration, Instant};
+
+/// Settings for `DbConnectorClient::set_circuit_breaker`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive transport failures that open the circuit.
+    pub failure_threshold: u32,
+    /// How long an open circuit fails requests before probing again.
+    pub open_for: Duration,
+    /// Requests let through while half-open; all must succeed to close.
+    pub half_open_probes: u32,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            open_for: Duration::from_secs(30),
+            half_open_probes: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests flow normally.
+    Closed,
+    /// Requests fail immediately with `ModuleKitError::CircuitOpen`.
+    Open,
+    /// A limited number of probes decide whether to close again.
+    HalfOpen,
+}
+
+enum BreakerState {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    HalfOpen { admitted: u32, succeeded: u32 },
+}
+
+/// Tracks transport failures of one client; see `CircuitBreakerConfig`.
+pub(crate) struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    state: BreakerState,
+}
+
+impl CircuitBreaker {
+    pub(crate) fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            state: BreakerState::Closed { failures: 0 },
+        }
+    }
+
+    pub(crate) fn config(&self) -> &CircuitBreakerConfig {
+        &self.config
+    }
+
+    pub(crate) fn state(&self) -> CircuitState {
+        match self.state {
+            BreakerState::Closed { .. } => CircuitState::Closed,
+            BreakerState::Open { until } if until <= Instant::now() => CircuitState::HalfOpen,
+            BreakerState::Open { .. } => CircuitState::Open,
+            BreakerState::HalfOpen { .. } => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Admits a request, or returns how long until the circuit probes again.
+    pub(crate) fn admit(&mut self) -> Result<(), Duration> {
+        let probes = self.config.half_open_probes.max(1);
+        match &mut self.state {
+            BreakerState::Closed { .. } => Ok(()),
+            BreakerState::Open { until } => {
+                let now = Instant::now();
+                if *until > now {
+                    return Err(*until - now);
+                }
+                self.state = BreakerState::HalfOpen {
+                    admitted: 1,
+                    succeeded: 0,
+                };
+                Ok(())
+            }
+            BreakerState::HalfOpen { admitted, .. } if *admitted < probes => {
+                *admitted += 1;
+                Ok(())
+            }
+            // Probes are still out; callers retry once they report back.
+            BreakerState::HalfOpen { .. } => Err(Duration::ZERO),
+        }
+    }
+
+    /// Records the outcome of an admitted request.
+    pub(crate) fn record(&mut self, success: bool) {
+        let probes = self.config.half_open_probes.max(1);
+        match &mut self.state {
+            BreakerState::Closed { failures } if success => *failures = 0,
+            BreakerState::Closed { failures } => {
+                *failures += 1;
+                if *failures >= self.config.failure_threshold.max(1) {
+                    self.open();
+                }
+            }
+            BreakerState::HalfOpen { succeeded, .. } if success => {
+                *succeeded += 1;
+                if *succeeded >= probes {
+                    self.state = BreakerState::Closed { failures: 0 };
+                }
+            }
+            BreakerState::HalfOpen { .. } => self.open(),
+            // Requests admitted before the circuit opened.
+            BreakerState::Open { .. } => {}
+        }
+    }
+
+    fn open(&mut self) {
+        self.state = BreakerState::Open {
+            until: Instant::now() + self.config.open_for,
+        };
+    }
+}
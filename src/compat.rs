@@ -0,0 +1,80 @@
+use serde_json::{Map, Value as JsonValue};
+
+const LEGACY_OK_FIELDS: &[&str] = &["success", "succeeded"];
+const LEGACY_RESULTS_FIELDS: &[&str] = &["data", "result"];
+const LEGACY_ERROR_FIELDS: &[&str] = &["message", "err", "error_message"];
+const LEGACY_COUNT_FIELDS: &[&str] = &["affected_rows", "rows_affected", "affected"];
+const LEGACY_TAG_FIELDS: &[&str] = &["command", "command_tag"];
+
+/// Rewrites responses from older connector daemons into the current wire shape.
+///
+/// Returns a description of every rewrite so callers can tell which daemons
+/// still need upgrading.
+pub(crate) fn upgrade_response(value: &mut JsonValue) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let Some(object) = value.as_object_mut() else {
+        return warnings;
+    };
+    rename_legacy(object, LEGACY_OK_FIELDS, "ok", &mut warnings);
+    rename_legacy(object, LEGACY_RESULTS_FIELDS, "results", &mut warnings);
+    rename_legacy(object, LEGACY_ERROR_FIELDS, "error", &mut warnings);
+    if !object.contains_key("ok") {
+        let ok = object.get("error").is_none_or(JsonValue::is_null);
+        object.insert("ok".into(), JsonValue::Bool(ok));
+        warnings.push("response missing 'ok'; inferred from 'error'".into());
+    }
+    if let Some(results) = object.get_mut("results") {
+        if results.is_object() {
+            *results = JsonValue::Array(vec![results.take()]);
+            warnings.push("'results' was a single object; wrapped in an array".into());
+        }
+        if let Some(items) = results.as_array_mut() {
+            for item in items.iter_mut() {
+                upgrade_result_view(item, &mut warnings);
+            }
+        }
+    }
+    warnings
+}
+
+fn upgrade_result_view(value: &mut JsonValue, warnings: &mut Vec<String>) {
+    let Some(object) = value.as_object_mut() else {
+        return;
+    };
+    if object.contains_key("type") {
+        return;
+    }
+    rename_legacy(object, LEGACY_COUNT_FIELDS, "count", warnings);
+    rename_legacy(object, LEGACY_TAG_FIELDS, "tag", warnings);
+    let kind = if object.contains_key("columns") || object.contains_key("rows") {
+        object.entry("columns").or_insert_with(|| JsonValue::Array(Vec::new()));
+        object.entry("rows").or_insert_with(|| JsonValue::Array(Vec::new()));
+        "result_set"
+    } else if object.contains_key("count") {
+        "affected_rows"
+    } else if object.contains_key("tag") {
+        "command"
+    } else {
+        return;
+    };
+    object.insert("type".into(), JsonValue::String(kind.into()));
+    warnings.push(format!("result missing 'type' tag; inferred '{kind}'"));
+}
+
+fn rename_legacy(
+    object: &mut Map<String, JsonValue>,
+    legacy: &[&str],
+    current: &str,
+    warnings: &mut Vec<String>,
+) {
+    if object.contains_key(current) {
+        return;
+    }
+    for name in legacy {
+        if let Some(value) = object.remove(*name) {
+            object.insert(current.to_string(), value);
+            warnings.push(format!("legacy field '{name}' mapped to '{current}'"));
+            return;
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::ModuleKitError;
+
+/// Prefix of a compressed payload, followed by the algorithm id. No JSON,
+/// CBOR or MessagePack message starts with it, so uncompressed payloads
+/// need no header.
+const FRAME_MAGIC: &[u8; 3] = b"FNZ";
+const DEFAULT_MIN_BYTES: usize = 8 * 1024;
+const DEFAULT_MAX_DECOMPRESSED_BYTES: usize = 64 * 1024 * 1024;
+
+/// Payload compression, from `FENRIR_DB_CONNECTOR_COMPRESSION`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompressionAlgorithm {
+    /// Needs the `gzip` feature.
+    Gzip,
+    /// Needs the `zstd` feature.
+    Zstd,
+}
+
+impl CompressionAlgorithm {
+    /// Parses `gzip`, `zstd` or `none`, rejecting algorithms this build
+    /// cannot use.
+    pub fn parse(value: &str) -> Result<Option<Self>, String> {
+        let algorithm = match value.trim().to_ascii_lowercase().as_str() {
+            "" | "none" | "off" => return Ok(None),
+            "gzip" => CompressionAlgorithm::Gzip,
+            "zstd" => CompressionAlgorithm::Zstd,
+            other => {
+                return Err(format!(
+                    "unknown compression '{other}' (expected none, gzip or zstd)"
+                ))
+            }
+        };
+        if !algorithm.is_available() {
+            return Err(format!(
+                "compression '{}' needs the `{}` feature",
+                algorithm.as_str(),
+                algorithm.as_str()
+            ));
+        }
+        Ok(Some(algorithm))
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CompressionAlgorithm::Gzip => "gzip",
+            CompressionAlgorithm::Zstd => "zstd",
+        }
+    }
+
+    /// Whether this build was compiled with support for the algorithm.
+    pub fn is_available(&self) -> bool {
+        match self {
+            CompressionAlgorithm::Gzip => cfg!(feature = "gzip"),
+            CompressionAlgorithm::Zstd => cfg!(feature = "zstd"),
+        }
+    }
+
+    /// Highest accepted level; levels start at 1.
+    pub fn max_level(&self) -> u32 {
+        match self {
+            CompressionAlgorithm::Gzip => 9,
+            CompressionAlgorithm::Zstd => 22,
+        }
+    }
+
+    pub fn default_level(&self) -> u32 {
+        match self {
+            CompressionAlgorithm::Gzip => 6,
+            CompressionAlgorithm::Zstd => 3,
+        }
+    }
+
+    fn id(&self) -> u8 {
+        match self {
+            CompressionAlgorithm::Gzip => 1,
+            CompressionAlgorithm::Zstd => 2,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(CompressionAlgorithm::Gzip),
+            2 => Some(CompressionAlgorithm::Zstd),
+            _ => None,
+        }
+    }
+}
+
+/// Settings for `DbConnectorClient::set_compression`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressionConfig {
+    pub algorithm: CompressionAlgorithm,
+    /// `None` uses the algorithm's default level.
+    pub level: Option<u32>,
+    /// Payloads smaller than this are sent uncompressed.
+    pub min_bytes: usize,
+    /// Largest reply accepted once decompressed.
+    pub max_decompressed_bytes: usize,
+}
+
+impl CompressionConfig {
+    pub fn new(algorithm: CompressionAlgorithm) -> Self {
+        Self {
+            algorithm,
+            level: None,
+            min_bytes: DEFAULT_MIN_BYTES,
+            max_decompressed_bytes: DEFAULT_MAX_DECOMPRESSED_BYTES,
+        }
+    }
+
+    /// Clamped to `1..=algorithm.max_level()`.
+    pub fn level(mut self, value: u32) -> Self {
+        self.level = Some(value.clamp(1, self.algorithm.max_level()));
+        self
+    }
+
+    pub fn min_bytes(mut self, value: usize) -> Self {
+        self.min_bytes = value;
+        self
+    }
+
+    pub fn max_decompressed_bytes(mut self, value: usize) -> Self {
+        self.max_decompressed_bytes = value;
+        self
+    }
+
+    /// Compresses `payload` if it reaches `min_bytes`.
+    pub(crate) fn compress(&self, payload: Vec<u8>) -> Result<Vec<u8>, ModuleKitError> {
+        if payload.len() < self.min_bytes {
+            return Ok(payload);
+        }
+        let mut framed = FRAME_MAGIC.to_vec();
+        framed.push(self.algorithm.id());
+        match self.algorithm {
+            #[cfg(feature = "gzip")]
+            CompressionAlgorithm::Gzip => {
+                use std::io::Write;
+                let mut encoder = flate2::write::GzEncoder::new(
+                    framed,
+                    flate2::Compression::new(self.effective_level()),
+                );
+                encoder.write_all(&payload).map_err(codec_error)?;
+                encoder.finish().map_err(codec_error)
+            }
+            #[cfg(feature = "zstd")]
+            CompressionAlgorithm::Zstd => {
+                zstd::stream::copy_encode(
+                    payload.as_slice(),
+                    &mut framed,
+                    self.effective_level() as i32,
+                )
+                .map_err(codec_error)?;
+                Ok(framed)
+            }
+            #[allow(unreachable_patterns)]
+            other => Err(ModuleKitError::WireFormat(format!(
+                "{} compression is not compiled in",
+                other.as_str()
+            ))),
+        }
+    }
+
+    /// `level`, or the algorithm's default when unset.
+    pub fn effective_level(&self) -> u32 {
+        self.level.unwrap_or(self.algorithm.default_level())
+    }
+}
+
+/// Undoes `CompressionConfig::compress`; payloads without the frame header
+/// are returned unchanged. A framed reply must use the `agreed` algorithm and
+/// may not grow past its `max_decompressed_bytes`.
+#[cfg_attr(not(any(feature = "gzip", feature = "zstd")), allow(unused_variables))]
+pub(crate) fn decompress(
+    bytes: Vec<u8>,
+    agreed: Option<&CompressionConfig>,
+) -> Result<Vec<u8>, ModuleKitError> {
+    let Some(rest) = bytes.strip_prefix(FRAME_MAGIC.as_slice()) else {
+        return Ok(bytes);
+    };
+    let (&id, body) = rest
+        .split_first()
+        .ok_or_else(|| ModuleKitError::WireFormat("truncated compression header".into()))?;
+    let algorithm = CompressionAlgorithm::from_id(id)
+        .ok_or_else(|| ModuleKitError::WireFormat(format!("unknown compression id {id}")))?;
+    let config = agreed
+        .filter(|config| config.algorithm == algorithm)
+        .ok_or_else(|| {
+            ModuleKitError::WireFormat(format!(
+                "reply uses {} compression, which was not negotiated",
+                algorithm.as_str()
+            ))
+        })?;
+    let limit = config.max_decompressed_bytes;
+    match algorithm {
+        #[cfg(feature = "gzip")]
+        CompressionAlgorithm::Gzip => read_capped(flate2::read::GzDecoder::new(body), limit),
+        #[cfg(feature = "zstd")]
+        CompressionAlgorithm::Zstd => {
+            let decoder = zstd::stream::read::Decoder::new(body).map_err(codec_error)?;
+            read_capped(decoder, limit)
+        }
+        #[allow(unreachable_patterns)]
+        other => Err(ModuleKitError::WireFormat(format!(
+            "{} compression is not compiled in",
+            other.as_str()
+        ))),
+    }
+}
+
+/// Reads `decoder` to the end, failing once it yields more than `limit` bytes.
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+fn read_capped(decoder: impl std::io::Read, limit: usize) -> Result<Vec<u8>, ModuleKitError> {
+    use std::io::Read;
+    let mut out = Vec::new();
+    decoder
+        .take((limit as u64).saturating_add(1))
+        .read_to_end(&mut out)
+        .map_err(codec_error)?;
+    if out.len() > limit {
+        return Err(ModuleKitError::WireFormat(format!(
+            "decompressed reply exceeds {limit} bytes"
+        )));
+    }
+    Ok(out)
+}
+
+/// Codec failures are protocol errors, not transport failures.
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+fn codec_error(err: std::io::Error) -> ModuleKitError {
+    ModuleKitError::WireFormat(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unframed_payloads_pass_through() {
+        let payload = br#"{"ok":true}"#.to_vec();
+        assert_eq!(decompress(payload.clone(), None).unwrap(), payload);
+    }
+
+    #[test]
+    fn framed_replies_need_a_negotiated_algorithm() {
+        let mut framed = FRAME_MAGIC.to_vec();
+        framed.push(CompressionAlgorithm::Gzip.id());
+        assert!(decompress(framed.clone(), None).is_err());
+        let zstd = CompressionConfig::new(CompressionAlgorithm::Zstd);
+        assert!(decompress(framed, Some(&zstd)).is_err());
+        assert!(decompress(FRAME_MAGIC.to_vec(), None).is_err());
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn gzip_round_trips_within_the_cap() {
+        let config = CompressionConfig::new(CompressionAlgorithm::Gzip).min_bytes(0);
+        let payload = vec![b'x'; 4096];
+        let framed = config.compress(payload.clone()).unwrap();
+        assert!(framed.starts_with(FRAME_MAGIC));
+        assert_eq!(decompress(framed.clone(), Some(&config)).unwrap(), payload);
+
+        let capped = config.max_decompressed_bytes(1024);
+        assert!(decompress(framed, Some(&capped)).is_err());
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_round_trips() {
+        let config = CompressionConfig::new(CompressionAlgorithm::Zstd).min_bytes(0);
+        let payload = vec![b'y'; 4096];
+        let framed = config.compress(payload.clone()).unwrap();
+        assert_eq!(decompress(framed, Some(&config)).unwrap(), payload);
+    }
+}
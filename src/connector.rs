@@ -1,295 +1,1749 @@
-use std::io::{Read, Write};
-use std::net::{Shutdown, TcpStream};
-use std::sync::Mutex;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
-#[cfg(unix)]
-use std::os::unix::net::UnixStream;
-
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 use serde_json::Value as JsonValue;
 
+use crate::access_policy::{DataAccessMode, DataAccessPolicy};
+use crate::capabilities::EngineCapabilities;
+use crate::circuit_breaker::{CircuitBreaker, CircuitBreakerConfig, CircuitState};
+use crate::compat;
+use crate::compression::{decompress, CompressionAlgorithm, CompressionConfig};
+use crate::connector_endpoint::{open_from_pool, ConnectionHandle, ConnectorEndpoint};
+#[cfg(feature = "otel")]
+use crate::connector_request::TraceContextSource;
+use crate::connector_request::{
+    statement_fingerprint, DbBackupScope, DbConnectorCommand, DbConnectorIntent,
+    DbConnectorRequest, DbLocale, DbTenantPolicy, ExecuteOptions, TraceContext,
+};
+use crate::connector_response::{
+    DbBackupJob, DbConnectorRelocation, DbConnectorResponse, DbConnectorWarning,
+    DbConnectorWarningKind, DbResultSet, StringInterner,
+};
+use crate::consistency::{ConsistencyPolicy, ConsistencyState, DbConsistencyHint};
 use crate::env::ModuleEnvironment;
-use crate::error::ModuleKitError;
+use crate::error::{FailureDomain, ModuleKitError};
+use crate::failover::{EndpointHealth, EndpointPool, EndpointRole};
+use crate::metrics::{
+    MetricsRecorder, METRIC_CONNECTOR_LATENCY, METRIC_CONNECTOR_REQUESTS, METRIC_RESULT_CACHE,
+    METRIC_SCOPED_TOKEN_CACHE,
+};
+use crate::result_cache::{ResultCache, ResultCacheConfig, ResultCacheKey};
+use crate::secrets::SecretString;
+use crate::sql::{write_reason, InsertMany, SqlDialect};
+use crate::tenant_context::TenantContext;
+use crate::token_provider::{ScopedTokenCache, ServiceTokenProvider};
 use crate::tokens::ModuleTokenExchangeRequest;
-use crate::token_provider::ServiceTokenProvider;
+use crate::traffic_dump::{TrafficDump, TrafficDumpConfig};
+use crate::values::{CellCoercion, DbParamValue};
+use crate::watchdog::{
+    ConnectorStats, InFlightTracker, QueryWatchdogConfig, SlowQueryEvent, WatchdogHandle,
+};
+use crate::wire_format::{decode_reply, FormatNegotiation, PayloadEncoding, WireFormat};
+use crate::write_usage::WriteUsageMeter;
 
-const CONNECTOR_TIMEOUT: Duration = Duration::from_secs(15);
-const WRITE_TOKEN_SAFETY_SECONDS: u64 = 5;
+/// Linear backoff step between resent writes.
+const WRITE_RETRY_BACKOFF: Duration = Duration::from_millis(100);
 
-#[derive(Debug, Clone)]
-pub enum ConnectorEndpoint {
-    #[cfg(unix)]
-    Ipc {
-        path: String,
-    },
-    Tcp {
-        addr: String,
-    },
-}
-
-impl ConnectorEndpoint {
-    pub fn from_uri(uri: &str) -> Result<Self, ModuleKitError> {
-        if let Some(rest) = uri.strip_prefix("ipc://") {
-            #[cfg(unix)]
-            {
-                if rest.trim().is_empty() {
-                    return Err(ModuleKitError::InvalidConnectorUri(uri.to_string()));
-                }
-                return Ok(Self::Ipc {
-                    path: rest.trim().to_string(),
-                });
-            }
-            #[cfg(not(unix))]
-            {
-                return Err(ModuleKitError::InvalidConnectorUri(
-                    "ipc protocol is not supported on this platform".into(),
-                ));
-            }
-        }
-        if let Some(rest) = uri.strip_prefix("tcp://") {
-            if rest.trim().is_empty() {
-                return Err(ModuleKitError::InvalidConnectorUri(uri.to_string()));
-            }
-            return Ok(Self::Tcp {
-                addr: rest.trim().to_string(),
-            });
-        }
-        Err(ModuleKitError::InvalidConnectorUri(uri.to_string()))
-    }
+/// How long requests skip engine validation after listing engines failed.
+const ENGINE_CATALOG_RETRY: Duration = Duration::from_secs(30);
 
-    fn send(&self, payload: &[u8]) -> Result<Vec<u8>, ModuleKitError> {
-        match self {
-            #[cfg(unix)]
-            ConnectorEndpoint::Ipc { path } => {
-                let mut stream = UnixStream::connect(path)?;
-                stream.set_read_timeout(Some(CONNECTOR_TIMEOUT)).ok();
-                stream.set_write_timeout(Some(CONNECTOR_TIMEOUT)).ok();
-                stream.write_all(payload)?;
-                stream.shutdown(Shutdown::Write).ok();
-                let mut buf = Vec::new();
-                stream.read_to_end(&mut buf)?;
-                Ok(buf)
-            }
-            ConnectorEndpoint::Tcp { addr } => {
-                let mut stream = TcpStream::connect(addr)?;
-                stream.set_read_timeout(Some(CONNECTOR_TIMEOUT)).ok();
-                stream.set_write_timeout(Some(CONNECTOR_TIMEOUT)).ok();
-                stream.write_all(payload)?;
-                stream.shutdown(Shutdown::Write).ok();
-                let mut buf = Vec::new();
-                stream.read_to_end(&mut buf)?;
-                Ok(buf)
-            }
-        }
-    }
+/// Reply to `DbConnectorCommand::Capabilities`.
+#[derive(Debug, Deserialize)]
+struct CapabilitiesResponse {
+    #[serde(default)]
+    ok: bool,
+    #[serde(default)]
+    capabilities: Option<EngineCapabilities>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DbConnectorRequest {
-    pub token: String,
-    #[serde(default)]
-    pub engine: Option<String>,
+/// Reply to `DbConnectorCommand::ListEngines`.
+#[derive(Debug, Deserialize)]
+struct ListEnginesResponse {
     #[serde(default)]
-    pub intent: Option<DbConnectorIntent>,
-    pub command: DbConnectorCommand,
+    ok: bool,
     #[serde(default)]
-    pub tenant: Option<DbTenantPolicy>,
+    engines: Vec<EngineCapabilities>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "command", rename_all = "snake_case")]
-pub enum DbConnectorCommand {
-    Simple {
-        statement: String,
-    },
-    Prepared {
-        statement: String,
-        params: Vec<DbPreparedParam>,
+/// Engines reported by `DbConnectorCommand::ListEngines`.
+enum EngineCatalog {
+    Listed(Vec<EngineCapabilities>),
+    /// The connector answered that it does not know the command; engines
+    /// are not validated.
+    Unsupported,
+    /// Listing failed at `since`; requests skip validation until
+    /// `ENGINE_CATALOG_RETRY` has passed.
+    Unavailable {
+        since: Instant,
     },
 }
 
-impl DbConnectorCommand {
-    pub fn statement(&self) -> &str {
+impl EngineCatalog {
+    fn engines(&self) -> &[EngineCapabilities] {
         match self {
-            DbConnectorCommand::Simple { statement } => statement,
-            DbConnectorCommand::Prepared { statement, .. } => statement,
+            EngineCatalog::Listed(engines) => engines,
+            EngineCatalog::Unsupported | EngineCatalog::Unavailable { .. } => &[],
         }
     }
-}
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DbPreparedParam {
-    pub name: String,
-    pub value: JsonValue,
+    fn check(&self, engine: &str) -> Result<(), ModuleKitError> {
+        let EngineCatalog::Listed(engines) = self else {
+            return Ok(());
+        };
+        if engines
+            .iter()
+            .any(|listed| listed.engine.eq_ignore_ascii_case(engine))
+        {
+            return Ok(());
+        }
+        Err(ModuleKitError::UnknownEngine {
+            engine: engine.to_string(),
+            available: engines.iter().map(|listed| listed.engine.clone()).collect(),
+        })
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DbTenantPolicy {
-    pub param: String,
+/// Reply to `DbConnectorCommand::NegotiateFormat`.
+#[derive(Debug, Deserialize)]
+struct NegotiateFormatResponse {
     #[serde(default)]
-    pub mode: DbTenantBindingMode,
+    ok: bool,
+    #[serde(default)]
+    format: Option<WireFormat>,
+    #[serde(default)]
+    compression: Option<CompressionAlgorithm>,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
-pub enum DbTenantBindingMode {
-    Inject,
-    RequireMatch,
+/// Reply to `DbConnectorCommand::Authenticate`.
+#[derive(Debug, Deserialize)]
+struct AuthenticateResponse {
+    #[serde(default)]
+    ok: bool,
+    #[serde(default)]
+    session_id: Option<SecretString>,
+    #[serde(default)]
+    expires_in_seconds: Option<u64>,
 }
 
-impl Default for DbTenantBindingMode {
-    fn default() -> Self {
-        Self::Inject
-    }
+/// Reply to `DbConnectorCommand::Backup` and `JobStatus`.
+#[derive(Debug, Deserialize)]
+struct BackupResponse {
+    #[serde(default)]
+    ok: bool,
+    #[serde(default)]
+    job: Option<DbBackupJob>,
+    #[serde(default)]
+    error: Option<String>,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
-pub enum DbConnectorIntent {
-    Read,
-    Write,
+type WarningListener = Arc<dyn Fn(&DbConnectorWarning) + Send + Sync>;
+
+/// Host part of a `host:port` address.
+fn tcp_host(addr: &str) -> &str {
+    addr.rsplit_once(':').map_or(addr, |(host, _)| host)
 }
 
-impl Default for DbConnectorIntent {
-    fn default() -> Self {
-        Self::Read
-    }
+pub struct DbConnectorClient {
+    endpoints: Mutex<EndpointPool>,
+    read_endpoints: Option<Mutex<EndpointPool>>,
+    relocation_targets: Vec<ConnectorEndpoint>,
+    tokens: Arc<ServiceTokenProvider>,
+    write_token: ScopedTokenCache,
+    warning_listeners: Mutex<Vec<WarningListener>>,
+    access_policy: Mutex<Option<DataAccessPolicy>>,
+    coercions: Mutex<HashMap<String, CellCoercion>>,
+    result_interner: Mutex<StringInterner>,
+    in_flight: Arc<InFlightTracker>,
+    watchdog: Mutex<Option<WatchdogHandle>>,
+    traffic_dump: Mutex<Option<TrafficDump>>,
+    capabilities: Mutex<HashMap<String, EngineCapabilities>>,
+    engine_catalog: Mutex<Option<EngineCatalog>>,
+    consistency: Mutex<ConsistencyState>,
+    write_usage: Mutex<Option<Arc<WriteUsageMeter>>>,
+    default_locale: Mutex<Option<DbLocale>>,
+    tenant_context: Mutex<Option<TenantContext>>,
+    read_only: AtomicBool,
+    sessions: Mutex<SessionAuth>,
+    metrics: Mutex<Option<Arc<dyn MetricsRecorder>>>,
+    result_cache: Mutex<Option<ResultCache>>,
+    circuit_breaker: Mutex<Option<CircuitBreaker>>,
+    wire_format: Mutex<FormatNegotiation>,
+    #[cfg(feature = "otel")]
+    trace_source: Mutex<Option<TraceContextSource>>,
 }
 
-impl DbConnectorIntent {
-    pub fn requires_write_scope(&self) -> bool {
-        matches!(self, DbConnectorIntent::Write)
+impl DbConnectorClient {
+    pub fn from_env() -> Result<Self, ModuleKitError> {
+        let env = ModuleEnvironment::from_env()?;
+        Self::from_environment(env)
     }
 
-    pub fn detect(statement: &str) -> Self {
-        let keyword = statement
-            .trim_start()
-            .split_whitespace()
-            .next()
-            .map(|word| word.to_ascii_lowercase())
-            .unwrap_or_default();
-        match keyword.as_str() {
-            "select" | "show" | "describe" | "with" | "explain" => DbConnectorIntent::Read,
-            _ => DbConnectorIntent::Write,
-        }
+    pub fn from_environment(env: ModuleEnvironment) -> Result<Self, ModuleKitError> {
+        let tokens = Arc::new(env.token_provider()?);
+        Ok(Self::with_token_provider(env, tokens))
     }
-}
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct DbConnectorResponse {
-    pub ok: bool,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub results: Option<Vec<DbConnectorResultView>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub error: Option<String>,
-}
-
-impl DbConnectorResponse {
-    pub fn ok(results: Vec<DbConnectorResultView>) -> Self {
+    /// Builds a client that shares `tokens` with other control plane consumers.
+    pub fn with_token_provider(env: ModuleEnvironment, tokens: Arc<ServiceTokenProvider>) -> Self {
+        let traffic_dump = env
+            .connector_dump_dir
+            .clone()
+            .map(|dir| TrafficDump::new(TrafficDumpConfig::new(dir)));
         Self {
-            ok: true,
-            results: Some(results),
-            error: None,
+            endpoints: Mutex::new(EndpointPool::new(env.connector, env.connector_fallbacks)),
+            read_endpoints: env
+                .read_connector
+                .map(|endpoint| Mutex::new(EndpointPool::new(endpoint, Vec::new()))),
+            relocation_targets: env.relocation_targets,
+            tokens,
+            write_token: ScopedTokenCache::new(ModuleTokenExchangeRequest::db_write),
+            warning_listeners: Mutex::new(Vec::new()),
+            access_policy: Mutex::new(None),
+            coercions: Mutex::new(HashMap::new()),
+            result_interner: Mutex::new(StringInterner::new()),
+            in_flight: Arc::new(InFlightTracker::default()),
+            watchdog: Mutex::new(None),
+            traffic_dump: Mutex::new(traffic_dump),
+            capabilities: Mutex::new(HashMap::new()),
+            engine_catalog: Mutex::new(None),
+            consistency: Mutex::new(ConsistencyState::new(env.consistency)),
+            write_usage: Mutex::new(None),
+            default_locale: Mutex::new(None),
+            tenant_context: Mutex::new(None),
+            read_only: AtomicBool::new(env.db_read_only),
+            sessions: Mutex::new(SessionAuth::Disabled),
+            metrics: Mutex::new(None),
+            result_cache: Mutex::new(None),
+            circuit_breaker: Mutex::new(None),
+            wire_format: Mutex::new(FormatNegotiation::new(PayloadEncoding {
+                format: env.connector_format,
+                compression: env.connector_compression,
+            })),
+            #[cfg(feature = "otel")]
+            trace_source: Mutex::new(None),
         }
     }
 
-    pub fn err(message: impl Into<String>) -> Self {
-        Self {
-            ok: false,
-            results: None,
-            error: Some(message.into()),
-        }
+    pub fn token_provider(&self) -> &Arc<ServiceTokenProvider> {
+        &self.tokens
     }
-}
 
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(tag = "type", rename_all = "snake_case")]
-pub enum DbConnectorResultView {
-    ResultSet {
-        columns: Vec<String>,
-        rows: Vec<Vec<String>>,
-    },
-    AffectedRows {
-        count: u64,
-    },
-    Command {
-        tag: String,
-    },
-}
-
-pub struct DbConnectorClient {
-    endpoint: ConnectorEndpoint,
-    tokens: ServiceTokenProvider,
-    cached_write_token: Mutex<Option<CachedToken>>,
-}
+    pub fn execute(
+        &self,
+        command: DbConnectorCommand,
+        intent: DbConnectorIntent,
+        engine: Option<&str>,
+        tenant: Option<DbTenantPolicy>,
+    ) -> Result<DbConnectorResponse, ModuleKitError> {
+        self.execute_with(command, intent, engine, tenant, &ExecuteOptions::default())
+    }
 
-impl DbConnectorClient {
-    pub fn from_env() -> Result<Self, ModuleKitError> {
-        let env = ModuleEnvironment::from_env()?;
-        Self::from_environment(env)
+    /// Like `execute`, with per-request `options`.
+    pub fn execute_with(
+        &self,
+        command: DbConnectorCommand,
+        intent: DbConnectorIntent,
+        engine: Option<&str>,
+        tenant: Option<DbTenantPolicy>,
+        options: &ExecuteOptions,
+    ) -> Result<DbConnectorResponse, ModuleKitError> {
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!(
+            "fenrir.db.execute",
+            engine = engine.unwrap_or_default(),
+            intent = ?intent,
+            statement = %statement_fingerprint(command.statement()),
+            duration_ms = tracing::field::Empty,
+            rows = tracing::field::Empty,
+            ok = tracing::field::Empty,
+            failure_domain = tracing::field::Empty,
+        );
+        #[cfg(feature = "tracing")]
+        let _entered = span.enter();
+        let started = Instant::now();
+        let result = self.execute_inner(command, intent, engine, tenant, options);
+        self.record_execute_metrics(intent, started.elapsed(), &result);
+        #[cfg(feature = "tracing")]
+        {
+            span.record("duration_ms", started.elapsed().as_millis() as u64);
+            match &result {
+                Ok(response) => {
+                    span.record("ok", response.ok);
+                    span.record("rows", response.row_count());
+                    if !response.ok {
+                        span.record("failure_domain", FailureDomain::ConnectorEngine.as_str());
+                    }
+                }
+                Err(err) => {
+                    let domain = err.failure_domain();
+                    span.record("ok", false);
+                    span.record("failure_domain", domain.as_str());
+                    tracing::debug!(
+                        error = %err,
+                        failure_domain = %domain,
+                        "connector request failed"
+                    );
+                }
+            }
+        }
+        result
     }
 
-    pub fn from_environment(env: ModuleEnvironment) -> Result<Self, ModuleKitError> {
-        let tokens = env.token_provider()?;
-        Ok(Self {
-            endpoint: env.connector,
-            tokens,
-            cached_write_token: Mutex::new(None),
-        })
+    fn record_execute_metrics(
+        &self,
+        intent: DbConnectorIntent,
+        elapsed: Duration,
+        result: &Result<DbConnectorResponse, ModuleKitError>,
+    ) {
+        let Some(recorder) = self.metrics.lock().unwrap().clone() else {
+            return;
+        };
+        let intent = if intent.requires_write_scope() {
+            "write"
+        } else {
+            "read"
+        };
+        let failure = match result {
+            Ok(response) if response.ok => None,
+            Ok(_) => Some(("rejected", FailureDomain::ConnectorEngine)),
+            Err(err) => Some(("error", err.failure_domain())),
+        };
+        match failure {
+            None => recorder.increment_counter(
+                METRIC_CONNECTOR_REQUESTS,
+                &[("intent", intent), ("outcome", "ok")],
+                1,
+            ),
+            Some((outcome, domain)) => recorder.increment_counter(
+                METRIC_CONNECTOR_REQUESTS,
+                &[
+                    ("intent", intent),
+                    ("outcome", outcome),
+                    ("domain", domain.as_str()),
+                ],
+                1,
+            ),
+        }
+        recorder.record_histogram(
+            METRIC_CONNECTOR_LATENCY,
+            &[("intent", intent)],
+            elapsed.as_secs_f64(),
+        );
     }
 
-    pub fn execute(
+    fn execute_inner(
         &self,
         command: DbConnectorCommand,
         intent: DbConnectorIntent,
         engine: Option<&str>,
         tenant: Option<DbTenantPolicy>,
+        options: &ExecuteOptions,
     ) -> Result<DbConnectorResponse, ModuleKitError> {
+        // Before the cache, the engine catalog and any token or session work,
+        // so a refused write never reaches the connector or the control plane.
+        self.check_read_only(&command, Some(intent))?;
+        if let Some(engine) = engine {
+            self.check_engine(engine)?;
+        }
+        let mut command = command;
+        let ambient = if options.skip_tenant {
+            None
+        } else {
+            self.ambient_tenant()
+        };
+        let tenant = match (tenant, &ambient) {
+            (Some(policy), _) => Some(policy),
+            (None, Some(context)) if context.bind(&mut command) => Some(context.policy()),
+            (None, _) => None,
+        };
+        if let (
+            Some(policy),
+            DbConnectorCommand::Simple { .. } | DbConnectorCommand::Prepared { .. },
+        ) = (&tenant, &command)
+        {
+            policy.validate(command.statement())?;
+            if let Some(context) = &ambient {
+                context.check_claimed(policy, &command)?;
+            }
+        }
+        let access_warnings = self.check_access_policy(engine, command.statement())?;
+        let locale = options
+            .locale
+            .clone()
+            .or_else(|| self.default_locale.lock().unwrap().clone());
+        let cache_key = self.result_cache_key(
+            &command,
+            intent,
+            engine,
+            tenant.as_ref(),
+            locale.as_ref(),
+            options,
+        );
+        if let Some(key) = &cache_key {
+            if let Some(mut response) = self.cached_result(key) {
+                response.warnings.extend(access_warnings);
+                self.notify_warnings(&response.warnings);
+                return Ok(response);
+            }
+        }
+        let cache_generation = self
+            .result_cache
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(ResultCache::generation);
         let token = self.token_for_intent(intent)?;
-        let request = DbConnectorRequest {
-            token,
+        let session = self.session_for(intent, token.expose(), engine);
+        let mut request = DbConnectorRequest {
+            token: if session.is_some() {
+                SecretString::default()
+            } else {
+                token
+            },
+            session,
             engine: engine.map(|e| e.to_string()),
             intent: Some(intent),
             command,
             tenant,
+            consistency: self.consistency.lock().unwrap().hint_for(intent),
+            locale,
+            trace_context: self.trace_context_for(options),
+            request_id: options.request_id.clone(),
+            idempotency_key: options.idempotency_key_for(intent),
+        };
+        let mut response = self.send_retrying(&request, options)?;
+        if request.session.is_some() && response.is_session_expired() {
+            self.sessions.lock().unwrap().forget(intent);
+            request.session = None;
+            request.token = self.token_for_intent(intent)?;
+            response = self.send_retrying(&request, options)?;
+        }
+        if response.ok {
+            let read_after_write = {
+                let mut consistency = self.consistency.lock().unwrap();
+                consistency.observe(intent, response.session_token.as_deref());
+                consistency.policy() != ConsistencyPolicy::None
+            };
+            if read_after_write && intent.requires_write_scope() {
+                self.clear_result_cache();
+            }
+            self.observe_write_usage(&request, &response);
+        }
+        if let (Some(key), true) = (cache_key, response.ok) {
+            if let Some(cache) = self.result_cache.lock().unwrap().as_mut() {
+                // A write cleared the cache while this read was in flight, so
+                // the result may predate it.
+                if Some(cache.generation()) == cache_generation {
+                    cache.insert(key, &response, &options.cache_tags);
+                }
+            }
+        }
+        response.warnings.extend(access_warnings);
+        self.notify_warnings(&response.warnings);
+        Ok(response)
+    }
+
+    /// `send_request`, resending writes that carry an idempotency key up to
+    /// `options.write_retries` times after a transport failure.
+    fn send_retrying(
+        &self,
+        request: &DbConnectorRequest,
+        options: &ExecuteOptions,
+    ) -> Result<DbConnectorResponse, ModuleKitError> {
+        let retries = match request.idempotency_key {
+            Some(_) => options.write_retries,
+            None => 0,
         };
-        let payload = serde_json::to_vec(&request)?;
-        let response_bytes = self.endpoint.send(&payload)?;
-        let response: DbConnectorResponse = serde_json::from_slice(&response_bytes)?;
+        let mut attempt = 0;
+        loop {
+            match self.send_request(request) {
+                Err(ModuleKitError::ConnectorRequestFailed { .. }) if attempt < retries => {
+                    attempt += 1;
+                    std::thread::sleep(WRITE_RETRY_BACKOFF.saturating_mul(attempt));
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Round trip plus decoding, legacy-protocol upgrades and relocation handling.
+    fn send_request(
+        &self,
+        request: &DbConnectorRequest,
+    ) -> Result<DbConnectorResponse, ModuleKitError> {
+        let (response_bytes, role) = self.routed_round_trip(request).map_err(|err| match err {
+            ModuleKitError::ConnectorIo(source) => ModuleKitError::ConnectorRequestFailed {
+                endpoint: self.endpoint().to_string(),
+                statement_fingerprint: statement_fingerprint(request.command.statement()),
+                source,
+            },
+            other => other,
+        })?;
+        let mut value: JsonValue = decode_reply(&response_bytes)?;
+        let compat_notes = compat::upgrade_response(&mut value);
+        let mut response: DbConnectorResponse = serde_json::from_value(value)?;
+        response.warnings.extend(
+            compat_notes
+                .into_iter()
+                .map(|note| DbConnectorWarning::new(DbConnectorWarningKind::LegacyProtocol, note)),
+        );
+        if let Some(relocation) = response.relocate.take() {
+            response.warnings.push(self.relocate(role, &relocation));
+        }
         Ok(response)
     }
 
-    fn token_for_intent(&self, intent: DbConnectorIntent) -> Result<String, ModuleKitError> {
+    /// Authenticates once per token and sends a session id instead of the
+    /// bearer token on later requests.
+    ///
+    /// A session is reopened when the provider rotates the token, when it
+    /// expires, or when the connector reports it unknown. Connectors that do
+    /// not understand the handshake keep receiving bearer tokens.
+    pub fn set_session_auth(&self, enabled: bool) {
+        *self.sessions.lock().unwrap() = if enabled {
+            SessionAuth::Enabled(HashMap::new())
+        } else {
+            SessionAuth::Disabled
+        };
+    }
+
+    /// Session id to send for `intent` with `token`, opening one if needed.
+    fn session_for(
+        &self,
+        intent: DbConnectorIntent,
+        token: &str,
+        engine: Option<&str>,
+    ) -> Option<SecretString> {
+        if let Some(session) = self.sessions.lock().unwrap().current(intent, token)? {
+            return Some(session);
+        }
+        let request = DbConnectorRequest {
+            token: SecretString::new(token.to_string()),
+            session: None,
+            engine: engine.map(str::to_string),
+            intent: Some(intent),
+            command: DbConnectorCommand::Authenticate,
+            tenant: None,
+            consistency: None,
+            locale: None,
+            trace_context: None,
+            request_id: None,
+            idempotency_key: None,
+        };
+        let reply = self
+            .round_trip(&request)
+            .ok()
+            .and_then(|bytes| decode_reply::<AuthenticateResponse>(&bytes).ok());
+        let mut sessions = self.sessions.lock().unwrap();
+        match reply {
+            Some(AuthenticateResponse {
+                ok: true,
+                session_id: Some(id),
+                expires_in_seconds,
+            }) => {
+                let ttl = expires_in_seconds.map(Duration::from_secs);
+                sessions.open(intent, token, id.expose().clone(), ttl);
+                Some(id)
+            }
+            Some(_) => {
+                sessions.mark_unsupported();
+                None
+            }
+            // Transport failures fall back to the bearer token for this request only.
+            None => None,
+        }
+    }
+
+    /// Address requests are currently sent to: the last endpoint that
+    /// accepted a connection.
+    pub fn endpoint(&self) -> ConnectorEndpoint {
+        self.endpoints.lock().unwrap().active().clone()
+    }
+
+    /// Reachability of the primary connector and each fallback, in order.
+    pub fn endpoint_health(&self) -> Vec<EndpointHealth> {
+        self.endpoints.lock().unwrap().health()
+    }
+
+    /// Replica that read-intent requests prefer, if one is configured.
+    pub fn read_endpoint(&self) -> Option<ConnectorEndpoint> {
+        let pool = self.read_endpoints.as_ref()?;
+        let endpoint = pool.lock().unwrap().active().clone();
+        Some(endpoint)
+    }
+
+    pub fn read_endpoint_health(&self) -> Option<EndpointHealth> {
+        let pool = self.read_endpoints.as_ref()?;
+        let health = pool.lock().unwrap().health().into_iter().next();
+        health
+    }
+
+    /// Switches new requests to the advertised connector. Requests already
+    /// running keep their connection to the old one and finish there.
+    ///
+    /// Only TCP connectors on the current connector's host and those listed
+    /// in `FENRIR_DB_CONNECTOR_RELOCATE_URIS` are followed, since later
+    /// requests carry the module's tokens to the new address.
+    fn relocate(
+        &self,
+        role: EndpointRole,
+        relocation: &DbConnectorRelocation,
+    ) -> DbConnectorWarning {
+        let pool = match (role, &self.read_endpoints) {
+            (EndpointRole::ReadReplica, Some(pool)) => pool,
+            _ => &self.endpoints,
+        };
+        let target = ConnectorEndpoint::from_uri(&relocation.uri).and_then(|endpoint| {
+            let mut pool = pool.lock().unwrap();
+            if !self.may_relocate(pool.active(), &endpoint) {
+                return Err(ModuleKitError::InvalidConnectorUri(format!(
+                    "{endpoint} is not an allowed relocation target"
+                )));
+            }
+            pool.relocate(endpoint);
+            Ok(())
+        });
+        match target {
+            Ok(()) => {
+                self.sessions.lock().unwrap().clear();
+                self.wire_format.lock().unwrap().reset();
+                DbConnectorWarning::new(
+                    DbConnectorWarningKind::EndpointMoved,
+                    format!("connector moved to {}", relocation.uri),
+                )
+            }
+            Err(err) => DbConnectorWarning::new(
+                DbConnectorWarningKind::EndpointMoved,
+                format!("ignored connector relocation: {err}"),
+            ),
+        }
+    }
+
+    fn may_relocate(&self, current: &ConnectorEndpoint, target: &ConnectorEndpoint) -> bool {
+        let same_host = match (current, target) {
+            (ConnectorEndpoint::Tcp { addr: from }, ConnectorEndpoint::Tcp { addr: to }) => {
+                tcp_host(from).eq_ignore_ascii_case(tcp_host(to))
+            }
+            _ => false,
+        };
+        let target = target.to_string();
+        same_host
+            || self
+                .relocation_targets
+                .iter()
+                .any(|allowed| allowed.to_string() == target)
+    }
+
+    /// Engines the connector exposes, with their capabilities.
+    ///
+    /// Listed once per client; the entries also answer later
+    /// `engine_capabilities` calls. Empty when the connector answers that it
+    /// does not know the command, in which case requested engines are not
+    /// validated. Any other failure is returned and listed again next call.
+    pub fn engines(&self) -> Result<Vec<EngineCapabilities>, ModuleKitError> {
+        if let Some(catalog) = self.engine_catalog.lock().unwrap().as_ref() {
+            if !matches!(catalog, EngineCatalog::Unavailable { .. }) {
+                return Ok(catalog.engines().to_vec());
+            }
+        }
+        let catalog = match self.list_engines() {
+            Ok(catalog) => catalog,
+            Err(err) => {
+                *self.engine_catalog.lock().unwrap() = Some(EngineCatalog::Unavailable {
+                    since: Instant::now(),
+                });
+                return Err(err);
+            }
+        };
+        let engines = catalog.engines().to_vec();
+        {
+            let mut capabilities = self.capabilities.lock().unwrap();
+            for listed in &engines {
+                capabilities.insert(listed.engine.to_ascii_lowercase(), listed.clone());
+            }
+        }
+        *self.engine_catalog.lock().unwrap() = Some(catalog);
+        Ok(engines)
+    }
+
+    fn list_engines(&self) -> Result<EngineCatalog, ModuleKitError> {
+        let request = DbConnectorRequest {
+            token: self.tokens.current_token()?,
+            engine: None,
+            intent: Some(DbConnectorIntent::Read),
+            command: DbConnectorCommand::ListEngines,
+            session: None,
+            tenant: None,
+            consistency: None,
+            locale: None,
+            trace_context: None,
+            request_id: None,
+            idempotency_key: None,
+        };
+        let bytes = self.round_trip(&request)?;
+        let reply = decode_reply::<ListEnginesResponse>(&bytes)?;
+        if reply.ok {
+            return Ok(EngineCatalog::Listed(reply.engines));
+        }
+        let failure = decode_reply::<DbConnectorResponse>(&bytes)?;
+        if failure.is_unknown_command() {
+            return Ok(EngineCatalog::Unsupported);
+        }
+        Err(failure
+            .into_result()
+            .err()
+            .unwrap_or_else(|| ModuleKitError::ConnectorRejected("engine listing failed".into())))
+    }
+
+    /// Fails with `ModuleKitError::UnknownEngine` unless the connector lists
+    /// `engine`. Call at startup to catch a misconfigured engine before the
+    /// first query; requests validate it as well.
+    pub fn validate_engine(&self, engine: &str) -> Result<(), ModuleKitError> {
+        self.engines()?;
+        self.check_engine(engine)
+    }
+
+    /// Validates against the catalog, listing it first if needed. A failed
+    /// listing lets the request through, which reports its own error, and is
+    /// only retried after `ENGINE_CATALOG_RETRY`.
+    fn check_engine(&self, engine: &str) -> Result<(), ModuleKitError> {
+        let relist = match self.engine_catalog.lock().unwrap().as_ref() {
+            None => true,
+            Some(EngineCatalog::Unavailable { since }) => since.elapsed() >= ENGINE_CATALOG_RETRY,
+            Some(_) => false,
+        };
+        if relist && self.engines().is_err() {
+            return Ok(());
+        }
+        match self.engine_catalog.lock().unwrap().as_ref() {
+            Some(catalog) => catalog.check(engine),
+            None => Ok(()),
+        }
+    }
+
+    /// What `engine` supports, from the connector's capabilities handshake.
+    ///
+    /// Results are cached per engine. Connectors that do not understand the
+    /// handshake fall back to `EngineCapabilities::defaults_for`.
+    pub fn engine_capabilities(&self, engine: &str) -> Result<EngineCapabilities, ModuleKitError> {
+        let key = engine.to_ascii_lowercase();
+        if let Some(cached) = self.capabilities.lock().unwrap().get(&key) {
+            return Ok(cached.clone());
+        }
+        let request = DbConnectorRequest {
+            token: self.tokens.current_token()?,
+            engine: Some(engine.to_string()),
+            intent: Some(DbConnectorIntent::Read),
+            command: DbConnectorCommand::Capabilities,
+            session: None,
+            tenant: None,
+            consistency: None,
+            locale: None,
+            trace_context: None,
+            request_id: None,
+            idempotency_key: None,
+        };
+        let bytes = self.round_trip(&request)?;
+        let capabilities = match decode_reply::<CapabilitiesResponse>(&bytes) {
+            Ok(CapabilitiesResponse {
+                ok: true,
+                capabilities: Some(mut capabilities),
+            }) => {
+                capabilities.engine = engine.to_string();
+                capabilities
+            }
+            _ => EngineCapabilities::defaults_for(engine),
+        };
+        self.capabilities
+            .lock()
+            .unwrap()
+            .insert(key, capabilities.clone());
+        Ok(capabilities)
+    }
+
+    /// Inserts `rows` into `table` with as few prepared statements as
+    /// `engine`'s parameter limit allows, returning the rows affected.
+    ///
+    /// Each statement is its own write request, so a failure leaves the
+    /// chunks before it applied.
+    pub fn insert_many<R, V>(
+        &self,
+        table: &str,
+        columns: &[&str],
+        rows: impl IntoIterator<Item = R>,
+        engine: &str,
+    ) -> Result<u64, ModuleKitError>
+    where
+        R: IntoIterator<Item = V>,
+        V: Into<DbParamValue>,
+    {
+        let insert = InsertMany::new(table, columns.iter().copied()).rows(rows);
+        self.write_chunked(insert, engine)
+    }
+
+    /// Like `insert_many`, but rows clashing on `conflict_keys` update the
+    /// existing row; see `InsertMany::upsert_on` for engine differences.
+    pub fn upsert<R, V>(
+        &self,
+        table: &str,
+        columns: &[&str],
+        conflict_keys: &[&str],
+        rows: impl IntoIterator<Item = R>,
+        engine: &str,
+    ) -> Result<u64, ModuleKitError>
+    where
+        R: IntoIterator<Item = V>,
+        V: Into<DbParamValue>,
+    {
+        let insert = InsertMany::new(table, columns.iter().copied())
+            .rows(rows)
+            .upsert_on(conflict_keys.iter().copied());
+        self.write_chunked(insert, engine)
+    }
+
+    fn write_chunked(&self, insert: InsertMany, engine: &str) -> Result<u64, ModuleKitError> {
+        if insert.is_empty() {
+            return Ok(0);
+        }
+        let max_params = self.engine_capabilities(engine)?.param_limit();
+        let dialect = SqlDialect::for_engine(engine);
+        let mut affected = 0;
+        for chunk in insert.chunks(max_params)? {
+            affected += self
+                .execute(
+                    chunk.build(dialect)?,
+                    DbConnectorIntent::Write,
+                    Some(engine),
+                    None,
+                )?
+                .into_result()?
+                .row_count();
+        }
+        Ok(affected)
+    }
+
+    /// Asks the connector to stop the request sent with `request_id`, over a
+    /// connection of its own. Returns false when the connector no longer
+    /// knows the request, usually because it already finished.
+    ///
+    /// `intent` should match the original request so the cancel reaches the
+    /// same endpoint and carries a token of the same scope.
+    pub fn cancel_request(
+        &self,
+        request_id: &str,
+        intent: DbConnectorIntent,
+        engine: Option<&str>,
+    ) -> Result<bool, ModuleKitError> {
+        let request = DbConnectorRequest {
+            token: self.token_for_intent(intent)?,
+            session: None,
+            engine: engine.map(str::to_string),
+            intent: Some(intent),
+            command: DbConnectorCommand::Cancel {
+                request_id: request_id.to_string(),
+            },
+            tenant: None,
+            consistency: None,
+            locale: None,
+            trace_context: None,
+            request_id: None,
+            idempotency_key: None,
+        };
+        let bytes = self.round_trip(&request)?;
+        Ok(decode_reply::<DbConnectorResponse>(&bytes)?.ok)
+    }
+
+    /// Sends a no-op command and returns the round-trip time, failing if the
+    /// connector or `engine` cannot be reached.
+    pub fn ping(&self, engine: Option<&str>) -> Result<Duration, ModuleKitError> {
+        let request = DbConnectorRequest {
+            token: self.tokens.current_token()?,
+            engine: engine.map(str::to_string),
+            intent: Some(DbConnectorIntent::Read),
+            command: DbConnectorCommand::Ping,
+            session: None,
+            tenant: None,
+            consistency: None,
+            locale: None,
+            trace_context: None,
+            request_id: None,
+            idempotency_key: None,
+        };
+        let started = Instant::now();
+        let bytes = self.round_trip(&request)?;
+        decode_reply::<DbConnectorResponse>(&bytes)?.into_result()?;
+        Ok(started.elapsed())
+    }
+
+    /// Starts a logical backup of `scope` on `engine`, returning the queued job.
+    ///
+    /// Exchanges the service token for a `db:admin` token first, so the
+    /// module needs that scope granted.
+    pub fn request_backup(
+        &self,
+        scope: DbBackupScope,
+        engine: Option<&str>,
+    ) -> Result<DbBackupJob, ModuleKitError> {
+        self.admin_command(DbConnectorCommand::Backup { scope }, engine)
+    }
+
+    /// Current state of backup or restore job `job_id`, for polling until `is_finished`.
+    pub fn restore_status(
+        &self,
+        job_id: &str,
+        engine: Option<&str>,
+    ) -> Result<DbBackupJob, ModuleKitError> {
+        self.admin_command(
+            DbConnectorCommand::JobStatus {
+                job_id: job_id.to_string(),
+            },
+            engine,
+        )
+    }
+
+    fn admin_command(
+        &self,
+        command: DbConnectorCommand,
+        engine: Option<&str>,
+    ) -> Result<DbBackupJob, ModuleKitError> {
+        let token = self
+            .tokens
+            .issue_scoped_token(ModuleTokenExchangeRequest::db_admin())?
+            .token;
+        let request = DbConnectorRequest {
+            token,
+            engine: engine.map(str::to_string),
+            intent: Some(DbConnectorIntent::Write),
+            command,
+            session: None,
+            tenant: None,
+            consistency: None,
+            locale: None,
+            trace_context: None,
+            request_id: None,
+            idempotency_key: None,
+        };
+        let bytes = self.round_trip(&request)?;
+        let response: BackupResponse = decode_reply(&bytes)?;
+        match (response.ok, response.job) {
+            (true, Some(job)) => Ok(job),
+            (true, None) => Err(ModuleKitError::ConnectorRejected(
+                "connector returned no backup job".into(),
+            )),
+            (false, _) => Err(ModuleKitError::ConnectorRejected(
+                response.error.unwrap_or_else(|| "unknown error".into()),
+            )),
+        }
+    }
+
+    /// Sends `request` under the in-flight tracker and traffic dump, returning the raw reply.
+    fn round_trip(&self, request: &DbConnectorRequest) -> Result<Vec<u8>, ModuleKitError> {
+        self.routed_round_trip(request).map(|(bytes, _)| bytes)
+    }
+
+    /// `round_trip`, also reporting which connector answered.
+    fn routed_round_trip(
+        &self,
+        request: &DbConnectorRequest,
+    ) -> Result<(Vec<u8>, EndpointRole), ModuleKitError> {
+        self.check_read_only(&request.command, request.intent)?;
+        let encoding = self.encoding_for(request);
+        let payload = encoding.encode(request)?;
+        self.admit_through_circuit()?;
+        let request_id = self
+            .in_flight
+            .begin(request.engine.clone(), request.command.statement());
+        let started = Instant::now();
+        let mut role = EndpointRole::Primary;
+        let sent = self
+            .open_connection(self.prefers_replica(request))
+            .and_then(|(connection, served_by)| {
+                role = served_by;
+                connection.exchange(&payload, |connection| {
+                    self.in_flight.attach(request_id, connection)
+                })
+            })
+            .and_then(|bytes| decompress(bytes, encoding.compression.as_ref()));
+        if let Some(breaker) = self.circuit_breaker.lock().unwrap().as_mut() {
+            breaker.record(!matches!(sent, Err(ModuleKitError::ConnectorIo(_))));
+        }
+        if let Some(dump) = self.traffic_dump.lock().unwrap().as_mut() {
+            let _ = dump.record(request, &sent, started.elapsed());
+        }
+        // A reply that arrived as the watchdog fired still counts: the
+        // request ran, and reporting it killed would invite a second run.
+        let killed = self.in_flight.finish(request_id);
+        match (sent, killed) {
+            (Err(_), Some(elapsed)) => Err(ModuleKitError::QueryKilled(elapsed)),
+            (sent, _) => sent.map(|bytes| (bytes, role)),
+        }
+    }
+
+    /// Encoding for `request`, negotiating it first if needed.
+    ///
+    /// The handshake reuses the request's token. A transport failure sends
+    /// this request as plain JSON and asks again next time; whatever the
+    /// connector does not acknowledge falls back to JSON without compression.
+    fn encoding_for(&self, request: &DbConnectorRequest) -> PayloadEncoding {
+        if let DbConnectorCommand::NegotiateFormat { .. } = request.command {
+            return PayloadEncoding::default();
+        }
+        let requested = {
+            let negotiation = self.wire_format.lock().unwrap();
+            if let Some(agreed) = negotiation.agreed() {
+                return agreed;
+            }
+            negotiation.requested()
+        };
+        let handshake = DbConnectorRequest {
+            token: request.token.clone(),
+            session: None,
+            engine: request.engine.clone(),
+            intent: None,
+            command: DbConnectorCommand::NegotiateFormat {
+                format: requested.format,
+                compression: requested.compression.map(|config| config.algorithm),
+            },
+            tenant: None,
+            consistency: None,
+            locale: None,
+            trace_context: None,
+            request_id: None,
+            idempotency_key: None,
+        };
+        let bytes = match self.round_trip(&handshake) {
+            Ok(bytes) => bytes,
+            Err(_) => return PayloadEncoding::default(),
+        };
+        let (format, compression) = match decode_reply::<NegotiateFormatResponse>(&bytes) {
+            Ok(NegotiateFormatResponse {
+                ok: true,
+                format,
+                compression,
+            }) => (format, compression),
+            _ => (None, None),
+        };
+        let mut negotiation = self.wire_format.lock().unwrap();
+        negotiation.settle(format, compression);
+        negotiation.agreed().unwrap_or_default()
+    }
+
+    /// Proposes `format` for later requests, falling back to JSON if the
+    /// connector does not acknowledge it. Passing the format already
+    /// requested keeps the outcome of an earlier handshake.
+    pub fn set_wire_format(&self, format: WireFormat) {
+        let mut negotiation = self.wire_format.lock().unwrap();
+        let mut requested = negotiation.requested();
+        if requested.format != format {
+            requested.format = format;
+            *negotiation = FormatNegotiation::new(requested);
+        }
+    }
+
+    /// Format requests are currently sent in; JSON until a handshake for a
+    /// binary format succeeds.
+    pub fn wire_format(&self) -> WireFormat {
+        self.agreed_encoding().format
+    }
+
+    /// Compresses requests of at least `config.min_bytes` once the connector
+    /// acknowledges the algorithm; `None` turns compression off. Replies are
+    /// decompressed whenever the connector compressed them.
+    pub fn set_compression(&self, config: Option<CompressionConfig>) {
+        let mut negotiation = self.wire_format.lock().unwrap();
+        let mut requested = negotiation.requested();
+        if requested.compression != config {
+            requested.compression = config;
+            *negotiation = FormatNegotiation::new(requested);
+        }
+    }
+
+    /// Compression requests are currently sent with; `None` until a
+    /// handshake for it succeeds.
+    pub fn compression(&self) -> Option<CompressionConfig> {
+        self.agreed_encoding().compression
+    }
+
+    fn agreed_encoding(&self) -> PayloadEncoding {
+        self.wire_format
+            .lock()
+            .unwrap()
+            .agreed()
+            .unwrap_or_default()
+    }
+
+    /// Reads go to the read replica unless consistency pins them to the
+    /// primary; everything else, including intent-less commands, does not.
+    fn prefers_replica(&self, request: &DbConnectorRequest) -> bool {
+        self.read_endpoints.is_some()
+            && matches!(request.intent, Some(DbConnectorIntent::Read))
+            && request.consistency != Some(DbConsistencyHint::Primary)
+    }
+
+    /// Connects to the first reachable endpoint, healthy ones first. Only
+    /// connection failures move on to the next endpoint; a request that was
+    /// sent is never repeated elsewhere.
+    ///
+    /// With `prefer_replica`, a healthy read replica is tried before the
+    /// primary endpoints; one that is cooling down is skipped.
+    fn open_connection(
+        &self,
+        prefer_replica: bool,
+    ) -> Result<(ConnectionHandle, EndpointRole), ModuleKitError> {
+        if let (true, Some(replica)) = (prefer_replica, &self.read_endpoints) {
+            if let Ok(connection) = open_from_pool(replica, false) {
+                return Ok((connection, EndpointRole::ReadReplica));
+            }
+        }
+        let connection = open_from_pool(&self.endpoints, true)?;
+        Ok((connection, EndpointRole::Primary))
+    }
+
+    /// Fails fast with `CircuitOpen` while the breaker is open.
+    fn admit_through_circuit(&self) -> Result<(), ModuleKitError> {
+        let admitted = match self.circuit_breaker.lock().unwrap().as_mut() {
+            Some(breaker) => breaker.admit(),
+            None => return Ok(()),
+        };
+        admitted.map_err(|retry_after| ModuleKitError::CircuitOpen {
+            endpoint: self.endpoint().to_string(),
+            retry_after,
+        })
+    }
+
+    /// Starts a background watchdog that reports requests running longer than
+    /// `config.slow_threshold` and, if `config.kill_after` is set, aborts them.
+    ///
+    /// Replaces any watchdog already running.
+    pub fn enable_watchdog(
+        &self,
+        config: QueryWatchdogConfig,
+        on_slow: impl Fn(&SlowQueryEvent) + Send + 'static,
+    ) {
+        let handle = WatchdogHandle::start(Arc::clone(&self.in_flight), config, on_slow);
+        *self.watchdog.lock().unwrap() = Some(handle);
+    }
+
+    pub fn disable_watchdog(&self) {
+        let handle = self.watchdog.lock().unwrap().take();
+        drop(handle);
+    }
+
+    /// Fails requests immediately with `ModuleKitError::CircuitOpen` after
+    /// `config.failure_threshold` consecutive transport failures, instead of
+    /// letting each wait for the connector timeout; `None` turns it off.
+    ///
+    /// Only I/O failures reaching the connector count; engine errors and
+    /// rejections mean the connector is up. Passing the configuration already
+    /// in effect keeps the current state.
+    pub fn set_circuit_breaker(&self, config: Option<CircuitBreakerConfig>) {
+        let mut guard = self.circuit_breaker.lock().unwrap();
+        if guard.as_ref().map(CircuitBreaker::config) == config.as_ref() {
+            return;
+        }
+        *guard = config.map(CircuitBreaker::new);
+    }
+
+    /// `None` when no circuit breaker is configured.
+    pub fn circuit_state(&self) -> Option<CircuitState> {
+        self.circuit_breaker
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(CircuitBreaker::state)
+    }
+
+    /// Starts or stops capturing redacted request/response pairs.
+    ///
+    /// Passing the configuration already in effect keeps the current file, so
+    /// this can be called on every config watcher update.
+    pub fn set_traffic_dump(&self, config: Option<TrafficDumpConfig>) {
+        let mut guard = self.traffic_dump.lock().unwrap();
+        if guard.as_ref().map(TrafficDump::config) == config.as_ref() {
+            return;
+        }
+        *guard = config.map(TrafficDump::new);
+    }
+
+    pub fn traffic_dump(&self) -> Option<TrafficDumpConfig> {
+        self.traffic_dump
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|dump| dump.config().clone())
+    }
+
+    pub fn stats(&self) -> ConnectorStats {
+        self.in_flight.stats()
+    }
+
+    /// Replaces the read-after-write policy, dropping any pinned reads or
+    /// session token tracked so far.
+    pub fn set_consistency_policy(&self, policy: ConsistencyPolicy) {
+        *self.consistency.lock().unwrap() = ConsistencyState::new(policy);
+    }
+
+    pub fn consistency_policy(&self) -> ConsistencyPolicy {
+        self.consistency.lock().unwrap().policy()
+    }
+
+    /// Refuses every write from now on; there is no way to turn it off
+    /// again. Also enabled by `FENRIR_DB_READ_ONLY`.
+    ///
+    /// Write-intent requests fail with `ModuleKitError::ReadOnlyViolation`
+    /// before anything is sent. So do statements declared as reads that do
+    /// not start with a read keyword, contain a write keyword anywhere
+    /// outside literals and comments, or hold several statements; an
+    /// `IntentMismatch` warning is first passed to `on_warning` listeners
+    /// for auditing.
+    pub fn enable_read_only(&self) {
+        self.read_only.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.load(Ordering::SeqCst)
+    }
+
+    fn check_read_only(
+        &self,
+        command: &DbConnectorCommand,
+        intent: Option<DbConnectorIntent>,
+    ) -> Result<(), ModuleKitError> {
+        if !self.is_read_only() {
+            return Ok(());
+        }
+        let statement = command.statement();
+        let fingerprint = statement_fingerprint(statement);
+        if let Some(DbConnectorIntent::Write) = intent {
+            return Err(ModuleKitError::ReadOnlyViolation(format!(
+                "write-intent request for statement {fingerprint}"
+            )));
+        }
+        let Some(reason) = write_reason(statement) else {
+            return Ok(());
+        };
+        let message = format!("statement {fingerprint} declared as a read {reason}");
+        self.notify_warnings(&[DbConnectorWarning::new(
+            DbConnectorWarningKind::IntentMismatch,
+            message.clone(),
+        )]);
+        Err(ModuleKitError::ReadOnlyViolation(message))
+    }
+
+    /// Tenant bound to requests that pass no `DbTenantPolicy` and run
+    /// outside a thread-local `TenantContext`. Its id is added as a prepared
+    /// parameter, so simple statements are sent as prepared ones.
+    pub fn set_tenant_context(&self, context: Option<TenantContext>) {
+        *self.tenant_context.lock().unwrap() = context;
+    }
+
+    pub fn tenant_context(&self) -> Option<TenantContext> {
+        self.tenant_context.lock().unwrap().clone()
+    }
+
+    /// The current thread's `TenantContext`, else the client's.
+    fn ambient_tenant(&self) -> Option<TenantContext> {
+        TenantContext::current().or_else(|| self.tenant_context())
+    }
+
+    /// Locale sent with requests that do not set one in `ExecuteOptions`.
+    pub fn set_default_locale(&self, locale: Option<DbLocale>) {
+        *self.default_locale.lock().unwrap() = locale;
+    }
+
+    /// Attaches the context `source` returns to every request, typically the
+    /// current OpenTelemetry span injected through `TraceContext::from_headers`.
+    #[cfg(feature = "otel")]
+    pub fn set_trace_context_source(&self, source: Option<TraceContextSource>) {
+        *self.trace_source.lock().unwrap() = source;
+    }
+
+    #[cfg(feature = "otel")]
+    fn trace_context_for(&self, options: &ExecuteOptions) -> Option<TraceContext> {
+        if options.skip_trace_context {
+            return None;
+        }
+        let source = self.trace_source.lock().unwrap().clone()?;
+        source()
+    }
+
+    #[cfg(not(feature = "otel"))]
+    fn trace_context_for(&self, _options: &ExecuteOptions) -> Option<TraceContext> {
+        None
+    }
+
+    /// Reports request counts, latency and scoped-token cache hits to `recorder`.
+    pub fn set_metrics_recorder(&self, recorder: Option<Arc<dyn MetricsRecorder>>) {
+        *self.metrics.lock().unwrap() = recorder;
+    }
+
+    /// Serves repeated reads from memory for `config.ttl`, or turns caching
+    /// off with `None`.
+    ///
+    /// Only successful `Read`-intent statements are cached, keyed by the full
+    /// statement, parameters, tenant binding, engine and locale. Under a
+    /// `ConsistencyPolicy` other than `None`, a successful write clears the
+    /// cache so that following reads observe it. Otherwise writes do not
+    /// invalidate entries: tag reads through `ExecuteOptions::cache_tag` and
+    /// call `invalidate_cache` after changing the underlying data. Passing the
+    /// configuration already in effect keeps the cached entries.
+    pub fn set_result_cache(&self, config: Option<ResultCacheConfig>) {
+        let mut guard = self.result_cache.lock().unwrap();
+        if guard.as_ref().map(ResultCache::config) == config.as_ref() {
+            return;
+        }
+        *guard = config.map(ResultCache::new);
+    }
+
+    /// Drops cached responses stored under `tag`, returning how many.
+    pub fn invalidate_cache(&self, tag: &str) -> usize {
+        self.result_cache
+            .lock()
+            .unwrap()
+            .as_mut()
+            .map_or(0, |cache| cache.invalidate(tag))
+    }
+
+    pub fn clear_result_cache(&self) {
+        if let Some(cache) = self.result_cache.lock().unwrap().as_mut() {
+            cache.clear();
+        }
+    }
+
+    /// Counts affected rows and bytes of tenant-bound writes into `meter`.
+    pub fn set_write_usage_meter(&self, meter: Option<Arc<WriteUsageMeter>>) {
+        *self.write_usage.lock().unwrap() = meter;
+    }
+
+    fn observe_write_usage(&self, request: &DbConnectorRequest, response: &DbConnectorResponse) {
+        let intent = request.intent.unwrap_or_default();
+        if !intent.requires_write_scope() {
+            return;
+        }
+        if let (Some(meter), Some(tenant)) =
+            (self.write_usage.lock().unwrap().as_ref(), &request.tenant)
+        {
+            meter.observe(&request.command, tenant, response);
+        }
+    }
+
+    /// Declares the tables and engines this module may touch.
+    pub fn set_access_policy(&self, policy: Option<DataAccessPolicy>) {
+        *self.access_policy.lock().unwrap() = policy;
+    }
+
+    fn check_access_policy(
+        &self,
+        engine: Option<&str>,
+        statement: &str,
+    ) -> Result<Vec<DbConnectorWarning>, ModuleKitError> {
+        let guard = self.access_policy.lock().unwrap();
+        let Some(policy) = guard.as_ref() else {
+            return Ok(Vec::new());
+        };
+        if policy.mode == DataAccessMode::Off {
+            return Ok(Vec::new());
+        }
+        let violations = policy.violations(engine, statement);
+        if policy.mode == DataAccessMode::Enforce && !violations.is_empty() {
+            return Err(ModuleKitError::AccessPolicyViolation(violations.join(", ")));
+        }
+        Ok(violations
+            .into_iter()
+            .map(|message| {
+                DbConnectorWarning::new(DbConnectorWarningKind::UndeclaredAccess, message)
+            })
+            .collect())
+    }
+
+    /// Registers a callback invoked for every warning carried by a response.
+    pub fn on_warning(&self, callback: impl Fn(&DbConnectorWarning) + Send + Sync + 'static) {
+        self.warning_listeners
+            .lock()
+            .unwrap()
+            .push(Arc::new(callback));
+    }
+
+    fn result_cache_key(
+        &self,
+        command: &DbConnectorCommand,
+        intent: DbConnectorIntent,
+        engine: Option<&str>,
+        tenant: Option<&DbTenantPolicy>,
+        locale: Option<&DbLocale>,
+        options: &ExecuteOptions,
+    ) -> Option<ResultCacheKey> {
+        if intent.requires_write_scope()
+            || options.bypass_cache
+            || self.result_cache.lock().unwrap().is_none()
+        {
+            return None;
+        }
+        ResultCacheKey::new(command, engine, tenant, locale)
+    }
+
+    fn cached_result(&self, key: &ResultCacheKey) -> Option<DbConnectorResponse> {
+        let cached = self.result_cache.lock().unwrap().as_mut()?.get(key);
+        if let Some(recorder) = self.metrics.lock().unwrap().as_ref() {
+            let result = if cached.is_some() { "hit" } else { "miss" };
+            recorder.increment_counter(METRIC_RESULT_CACHE, &[("result", result)], 1);
+        }
+        cached
+    }
+
+    fn notify_warnings(&self, warnings: &[DbConnectorWarning]) {
+        if warnings.is_empty() {
+            return;
+        }
+        // Snapshot so listeners may use the client without deadlocking.
+        let listeners = self.warning_listeners.lock().unwrap().clone();
+        for warning in warnings {
+            for listener in listeners.iter() {
+                listener(warning);
+            }
+        }
+    }
+
+    /// Overrides the NULL/boolean conventions used for `engine`, e.g. for custom engines.
+    pub fn set_cell_coercion(&self, engine: impl Into<String>, coercion: CellCoercion) {
+        self.coercions
+            .lock()
+            .unwrap()
+            .insert(engine.into().to_ascii_lowercase(), coercion);
+    }
+
+    pub fn cell_coercion(&self, engine: &str) -> CellCoercion {
+        self.coercions
+            .lock()
+            .unwrap()
+            .get(&engine.to_ascii_lowercase())
+            .cloned()
+            .unwrap_or_else(|| CellCoercion::for_engine(engine))
+    }
+
+    /// `response`'s result sets in compact form, with column names shared
+    /// across every response converted by this client.
+    pub fn result_sets(&self, response: DbConnectorResponse) -> Vec<DbResultSet> {
+        response.into_result_sets(&mut self.result_interner.lock().unwrap())
+    }
+
+    /// Also shares equal cell values in `result_sets`; worth it when results
+    /// repeat a few values, such as status columns.
+    pub fn set_cell_interning(&self, enabled: bool) {
+        let mut interner = self.result_interner.lock().unwrap();
+        *interner = std::mem::take(&mut *interner).intern_cells(enabled);
+    }
+
+    fn token_for_intent(&self, intent: DbConnectorIntent) -> Result<SecretString, ModuleKitError> {
         if intent.requires_write_scope() {
             return self.fetch_write_token();
         }
         self.tokens.current_token()
     }
 
-    fn fetch_write_token(&self) -> Result<String, ModuleKitError> {
-        if let Some(token) = self.cached_write_token.lock().unwrap().as_ref() {
-            if token.expires_at > Instant::now() {
-                return Ok(token.token.clone());
+    fn fetch_write_token(&self) -> Result<SecretString, ModuleKitError> {
+        let cached = self.write_token.cached();
+        if let Some(recorder) = self.metrics.lock().unwrap().as_ref() {
+            let result = if cached.is_some() { "hit" } else { "miss" };
+            recorder.increment_counter(METRIC_SCOPED_TOKEN_CACHE, &[("result", result)], 1);
+        }
+        match cached {
+            Some(token) => Ok(token),
+            None => self.write_token.refresh(&self.tokens),
+        }
+    }
+}
+
+/// Session-authentication state of a `DbConnectorClient`.
+enum SessionAuth {
+    Disabled,
+    /// Open sessions keyed by whether they carry the write-scoped token.
+    Enabled(HashMap<bool, ConnectorSession>),
+    /// The connector rejected the handshake; bearer tokens are used.
+    Unsupported,
+}
+
+struct ConnectorSession {
+    token: SecretString,
+    id: SecretString,
+    expires_at: Option<Instant>,
+}
+
+impl SessionAuth {
+    /// `None` when sessions are off; `Some(None)` when one must be opened.
+    fn current(&self, intent: DbConnectorIntent, token: &str) -> Option<Option<SecretString>> {
+        let SessionAuth::Enabled(sessions) = self else {
+            return None;
+        };
+        let session = sessions
+            .get(&intent.requires_write_scope())
+            .filter(|session| session.token.expose() == token)
+            .filter(|session| session.expires_at.is_none_or(|at| at > Instant::now()));
+        Some(session.map(|session| SecretString::new(session.id.expose().clone())))
+    }
+
+    fn open(&mut self, intent: DbConnectorIntent, token: &str, id: String, ttl: Option<Duration>) {
+        if let SessionAuth::Enabled(sessions) = self {
+            sessions.insert(
+                intent.requires_write_scope(),
+                ConnectorSession {
+                    token: SecretString::new(token.to_string()),
+                    id: SecretString::new(id),
+                    // An out-of-range lifetime is treated as unknown.
+                    expires_at: ttl.and_then(|ttl| Instant::now().checked_add(ttl)),
+                },
+            );
+        }
+    }
+
+    fn forget(&mut self, intent: DbConnectorIntent) {
+        if let SessionAuth::Enabled(sessions) = self {
+            sessions.remove(&intent.requires_write_scope());
+        }
+    }
+
+    fn clear(&mut self) {
+        if let SessionAuth::Enabled(sessions) = self {
+            sessions.clear();
+        }
+    }
+
+    fn mark_unsupported(&mut self) {
+        if matches!(self, SessionAuth::Enabled(_)) {
+            *self = SessionAuth::Unsupported;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+    use crate::control_plane::ControlPlane;
+    use crate::env::ModuleEnvironmentBuilder;
+    use crate::token_provider::ServiceTokenLease;
+    use crate::tokens::ModuleTokenExchangeResponse;
+
+    #[derive(Default)]
+    struct CountingControlPlane {
+        exchanges: AtomicUsize,
+        grant: bool,
+    }
+
+    impl ControlPlane for CountingControlPlane {
+        fn exchange_token(
+            &self,
+            _bearer: &str,
+            _request: ModuleTokenExchangeRequest,
+        ) -> Result<ModuleTokenExchangeResponse, ModuleKitError> {
+            self.exchanges.fetch_add(1, Ordering::SeqCst);
+            if !self.grant {
+                return Err(ModuleKitError::ControlPlaneMissing);
             }
+            Ok(ModuleTokenExchangeResponse {
+                token: "write-token".into(),
+                scopes: Vec::new(),
+                expires_in_seconds: 3600,
+            })
         }
-        let response = self
-            .tokens
-            .issue_scoped_token(ModuleTokenExchangeRequest::db_write())?;
-        let ttl = response
-            .expires_in_seconds
-            .saturating_sub(WRITE_TOKEN_SAFETY_SECONDS);
-        let expires_at = Instant::now() + Duration::from_secs(ttl.max(WRITE_TOKEN_SAFETY_SECONDS));
-        let mut guard = self.cached_write_token.lock().unwrap();
-        *guard = Some(CachedToken {
-            token: response.token.clone(),
-            expires_at,
+    }
+
+    /// Connector that answers every request with a one-cell result set
+    /// holding how many requests it has served.
+    fn counting_connector() -> ConnectorEndpoint {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        std::thread::spawn(move || {
+            for (served, stream) in listener.incoming().enumerate() {
+                let mut stream = stream.unwrap();
+                let mut request = Vec::new();
+                stream.read_to_end(&mut request).unwrap();
+                let reply = serde_json::json!({
+                    "ok": true,
+                    "results": [{
+                        "type": "result_set",
+                        "columns": ["served"],
+                        "rows": [[(served + 1).to_string()]],
+                    }],
+                });
+                stream.write_all(reply.to_string().as_bytes()).unwrap();
+            }
         });
-        Ok(response.token)
+        ConnectorEndpoint::Tcp { addr }
+    }
+
+    /// Connector that answers with `replies` in order, repeating the last,
+    /// and records the requests it served.
+    fn scripted_connector(
+        replies: Vec<JsonValue>,
+    ) -> (ConnectorEndpoint, Arc<Mutex<Vec<JsonValue>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let served = Arc::clone(&requests);
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request = Vec::new();
+                stream.read_to_end(&mut request).unwrap();
+                let mut served = served.lock().unwrap();
+                served.push(serde_json::from_slice(&request).unwrap());
+                let reply = &replies[(served.len() - 1).min(replies.len() - 1)];
+                stream.write_all(reply.to_string().as_bytes()).unwrap();
+            }
+        });
+        (ConnectorEndpoint::Tcp { addr }, requests)
+    }
+
+    fn client(
+        connector: ConnectorEndpoint,
+        control_plane: Arc<CountingControlPlane>,
+        configure: impl FnOnce(ModuleEnvironmentBuilder) -> ModuleEnvironmentBuilder,
+    ) -> DbConnectorClient {
+        let builder = ModuleEnvironment::builder()
+            .module_id("module")
+            .service_id("service")
+            .service_token("token")
+            .connector(connector);
+        let env = configure(builder).build().unwrap();
+        let tokens =
+            ServiceTokenProvider::builder(ServiceTokenLease::new("token", None, None, None))
+                .control_plane(control_plane)
+                .auto_refresh(false)
+                .build();
+        DbConnectorClient::with_token_provider(env, Arc::new(tokens))
+    }
+
+    #[test]
+    fn read_only_refuses_writes_before_exchanging_a_token() {
+        let control_plane = Arc::new(CountingControlPlane::default());
+        let connector = ConnectorEndpoint::Tcp {
+            addr: "127.0.0.1:9".into(),
+        };
+        let client = client(connector, Arc::clone(&control_plane), |env| {
+            env.db_read_only(true)
+        });
+        for (statement, intent) in [
+            ("DELETE FROM t", DbConnectorIntent::Write),
+            ("SELECT 1; DELETE FROM t", DbConnectorIntent::Read),
+        ] {
+            let result = client.execute(
+                DbConnectorCommand::Simple {
+                    statement: statement.into(),
+                },
+                intent,
+                Some("postgres"),
+                None,
+            );
+            assert!(
+                matches!(result, Err(ModuleKitError::ReadOnlyViolation(_))),
+                "{statement}"
+            );
+        }
+        assert_eq!(control_plane.exchanges.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn writes_clear_cached_reads_under_a_consistency_policy() {
+        let control_plane = Arc::new(CountingControlPlane {
+            grant: true,
+            ..CountingControlPlane::default()
+        });
+        let client = client(counting_connector(), control_plane, |env| {
+            env.consistency(ConsistencyPolicy::PinAfterWrite { reads: 1 })
+        });
+        client.set_result_cache(Some(ResultCacheConfig::default()));
+        let execute = |statement: &str, intent| {
+            let command = DbConnectorCommand::Simple {
+                statement: statement.into(),
+            };
+            let response = client.execute(command, intent, None, None).unwrap();
+            response.results.unwrap()[0]
+                .rows()
+                .next()
+                .map(|row| row.get::<i64>("served").unwrap())
+        };
+        let read = || execute("SELECT served FROM t", DbConnectorIntent::Read);
+        assert_eq!(read(), Some(1));
+        assert_eq!(read(), Some(1));
+        execute("UPDATE t SET x = 1", DbConnectorIntent::Write);
+        assert_eq!(read(), Some(3));
     }
-}
 
-struct CachedToken {
-    token: String,
-    expires_at: Instant,
+    #[test]
+    fn failed_engine_listings_are_retried_instead_of_cached_as_unsupported() {
+        let (connector, served) = scripted_connector(vec![
+            serde_json::json!({ "ok": false, "error": "token rejected" }),
+            serde_json::json!({ "ok": true, "engines": [{ "engine": "postgres" }] }),
+        ]);
+        let client = client(connector, Arc::default(), |env| env);
+        assert!(client.engines().is_err());
+        assert_eq!(client.engines().unwrap().len(), 1);
+        assert!(matches!(
+            client.validate_engine("mysql"),
+            Err(ModuleKitError::UnknownEngine { .. })
+        ));
+        assert_eq!(served.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn only_an_unknown_command_reply_disables_engine_validation() {
+        let (connector, served) = scripted_connector(vec![serde_json::json!({
+            "ok": false,
+            "error": "unknown command: list_engines",
+        })]);
+        let client = client(connector, Arc::default(), |env| env);
+        assert!(client.engines().unwrap().is_empty());
+        client.validate_engine("anything").unwrap();
+        assert_eq!(served.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn requests_do_not_relist_engines_right_after_a_failure() {
+        let (connector, served) = scripted_connector(vec![
+            serde_json::json!({ "ok": false, "error": "connector starting" }),
+            serde_json::json!({ "ok": true, "results": [] }),
+        ]);
+        let client = client(connector, Arc::default(), |env| env);
+        for _ in 0..2 {
+            let command = DbConnectorCommand::Simple {
+                statement: "SELECT 1".into(),
+            };
+            client
+                .execute(command, DbConnectorIntent::Read, Some("postgres"), None)
+                .unwrap();
+        }
+        assert_eq!(served.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn without_tenant_leaves_the_client_tenant_context_off() {
+        let (connector, requests) =
+            scripted_connector(vec![serde_json::json!({ "ok": true, "results": [] })]);
+        let client = client(connector, Arc::default(), |env| env);
+        client.set_tenant_context(Some(TenantContext::new("t1")));
+        for options in [
+            ExecuteOptions::new(),
+            ExecuteOptions::new().without_tenant(),
+        ] {
+            let command = DbConnectorCommand::Simple {
+                statement: "SELECT 1".into(),
+            };
+            client
+                .execute_with(command, DbConnectorIntent::Read, None, None, &options)
+                .unwrap();
+        }
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests[0]["tenant"]["param"], "tenant_id");
+        assert_eq!(requests[0]["command"]["params"][0]["value"], "t1");
+        assert!(requests[1]["tenant"].is_null());
+        assert_eq!(requests[1]["command"]["statement"], "SELECT 1");
+        assert_eq!(requests[1]["command"]["command"], "simple");
+    }
 }
@@ -0,0 +1,245 @@
+use std::fmt;
+use std::io::{Read, Write};
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+use std::sync::Mutex;
+use std::time::Duration;
+
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+
+use crate::error::{FailureDomain, ModuleKitError};
+use crate::failover::EndpointPool;
+use crate::runtime::retry_until;
+
+pub(crate) const CONNECTOR_TIMEOUT: Duration = Duration::from_secs(15);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone)]
+pub enum ConnectorEndpoint {
+    #[cfg(unix)]
+    Ipc {
+        path: String,
+    },
+    Tcp {
+        addr: String,
+    },
+}
+
+impl fmt::Display for ConnectorEndpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(unix)]
+            ConnectorEndpoint::Ipc { path } => write!(f, "ipc://{path}"),
+            ConnectorEndpoint::Tcp { addr } => write!(f, "tcp://{addr}"),
+        }
+    }
+}
+
+impl ConnectorEndpoint {
+    pub fn from_uri(uri: &str) -> Result<Self, ModuleKitError> {
+        if let Some(rest) = uri.strip_prefix("ipc://") {
+            #[cfg(unix)]
+            {
+                if rest.trim().is_empty() {
+                    return Err(ModuleKitError::InvalidConnectorUri(uri.to_string()));
+                }
+                return Ok(Self::Ipc {
+                    path: rest.trim().to_string(),
+                });
+            }
+            #[cfg(not(unix))]
+            {
+                return Err(ModuleKitError::InvalidConnectorUri(
+                    "ipc protocol is not supported on this platform".into(),
+                ));
+            }
+        }
+        if let Some(rest) = uri.strip_prefix("tcp://") {
+            if rest.trim().is_empty() {
+                return Err(ModuleKitError::InvalidConnectorUri(uri.to_string()));
+            }
+            return Ok(Self::Tcp {
+                addr: rest.trim().to_string(),
+            });
+        }
+        Err(ModuleKitError::InvalidConnectorUri(uri.to_string()))
+    }
+
+    /// Opens and closes a connection without sending a request.
+    pub fn probe(&self) -> Result<(), ModuleKitError> {
+        match self {
+            #[cfg(unix)]
+            ConnectorEndpoint::Ipc { path } => {
+                UnixStream::connect(path)?;
+            }
+            ConnectorEndpoint::Tcp { addr } => {
+                let mut last_err = None;
+                for socket_addr in addr.to_socket_addrs()? {
+                    match TcpStream::connect_timeout(&socket_addr, PROBE_TIMEOUT) {
+                        Ok(_) => return Ok(()),
+                        Err(err) => last_err = Some(err),
+                    }
+                }
+                return Err(last_err
+                    .unwrap_or_else(|| std::io::ErrorKind::AddrNotAvailable.into())
+                    .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Probes with backoff until the endpoint accepts connections, for
+    /// modules that start before the connector socket exists.
+    pub fn wait_until_reachable(&self, timeout: Duration) -> Result<(), ModuleKitError> {
+        retry_until(timeout, || self.probe()).map_err(|err| ModuleKitError::NotReady {
+            domain: FailureDomain::ConnectorTransport,
+            message: format!("{self}: {err}"),
+        })
+    }
+
+    /// Opens a connection for protocols that exchange several frames over
+    /// it; writes time out after `CONNECTOR_TIMEOUT`, reads do not.
+    pub(crate) fn connect(&self) -> Result<ConnectionHandle, ModuleKitError> {
+        let connection = self.open()?;
+        connection.set_write_timeout(Some(CONNECTOR_TIMEOUT))?;
+        Ok(connection)
+    }
+
+    /// Sends one request; `on_connect` receives a second handle on the open
+    /// connection so another thread can abort it.
+    pub(crate) fn send(
+        &self,
+        payload: &[u8],
+        on_connect: impl FnOnce(ConnectionHandle),
+    ) -> Result<Vec<u8>, ModuleKitError> {
+        self.open()?.exchange(payload, on_connect)
+    }
+
+    /// Connects without setting timeouts. Failing here means nothing was
+    /// sent, so the request may safely go to another endpoint.
+    pub(crate) fn open(&self) -> std::io::Result<ConnectionHandle> {
+        match self {
+            #[cfg(unix)]
+            ConnectorEndpoint::Ipc { path } => UnixStream::connect(path).map(ConnectionHandle::Ipc),
+            ConnectorEndpoint::Tcp { addr } => TcpStream::connect(addr).map(ConnectionHandle::Tcp),
+        }
+    }
+}
+
+pub(crate) enum ConnectionHandle {
+    #[cfg(unix)]
+    Ipc(UnixStream),
+    Tcp(TcpStream),
+}
+
+impl ConnectionHandle {
+    /// Closes both directions, failing the blocked read on the owning thread.
+    pub(crate) fn abort(&self) {
+        let _ = match self {
+            #[cfg(unix)]
+            ConnectionHandle::Ipc(stream) => stream.shutdown(Shutdown::Both),
+            ConnectionHandle::Tcp(stream) => stream.shutdown(Shutdown::Both),
+        };
+    }
+
+    pub(crate) fn try_clone(&self) -> std::io::Result<Self> {
+        match self {
+            #[cfg(unix)]
+            ConnectionHandle::Ipc(stream) => stream.try_clone().map(ConnectionHandle::Ipc),
+            ConnectionHandle::Tcp(stream) => stream.try_clone().map(ConnectionHandle::Tcp),
+        }
+    }
+
+    pub(crate) fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        match self {
+            #[cfg(unix)]
+            ConnectionHandle::Ipc(stream) => stream.set_read_timeout(timeout),
+            ConnectionHandle::Tcp(stream) => stream.set_read_timeout(timeout),
+        }
+    }
+
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        match self {
+            #[cfg(unix)]
+            ConnectionHandle::Ipc(stream) => stream.set_write_timeout(timeout),
+            ConnectionHandle::Tcp(stream) => stream.set_write_timeout(timeout),
+        }
+    }
+
+    fn shutdown_write(&self) {
+        let _ = match self {
+            #[cfg(unix)]
+            ConnectionHandle::Ipc(stream) => stream.shutdown(Shutdown::Write),
+            ConnectionHandle::Tcp(stream) => stream.shutdown(Shutdown::Write),
+        };
+    }
+
+    /// Writes one request, closes the write half and reads the whole reply.
+    pub(crate) fn exchange(
+        mut self,
+        payload: &[u8],
+        on_connect: impl FnOnce(ConnectionHandle),
+    ) -> Result<Vec<u8>, ModuleKitError> {
+        if let Ok(clone) = self.try_clone() {
+            on_connect(clone);
+        }
+        self.set_read_timeout(Some(CONNECTOR_TIMEOUT)).ok();
+        self.set_write_timeout(Some(CONNECTOR_TIMEOUT)).ok();
+        self.write_all(payload)?;
+        self.shutdown_write();
+        let mut buf = Vec::new();
+        self.read_to_end(&mut buf)?;
+        Ok(buf)
+    }
+}
+
+impl Read for ConnectionHandle {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            #[cfg(unix)]
+            ConnectionHandle::Ipc(stream) => stream.read(buf),
+            ConnectionHandle::Tcp(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for ConnectionHandle {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            #[cfg(unix)]
+            ConnectionHandle::Ipc(stream) => stream.write(buf),
+            ConnectionHandle::Tcp(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            #[cfg(unix)]
+            ConnectionHandle::Ipc(stream) => stream.flush(),
+            ConnectionHandle::Tcp(stream) => stream.flush(),
+        }
+    }
+}
+
+/// Connects to the first endpoint of `pool` that accepts, recording the
+/// outcome of each attempt.
+pub(crate) fn open_from_pool(
+    pool: &Mutex<EndpointPool>,
+    include_down: bool,
+) -> std::io::Result<ConnectionHandle> {
+    let candidates = pool.lock().unwrap().candidates(include_down);
+    let mut last_err = None;
+    for (index, endpoint) in candidates {
+        match endpoint.open() {
+            Ok(connection) => {
+                pool.lock().unwrap().mark_up(index);
+                return Ok(connection);
+            }
+            Err(err) => {
+                pool.lock().unwrap().mark_down(index);
+                last_err = Some(err);
+            }
+        }
+    }
+    Err(last_err.unwrap_or_else(|| std::io::ErrorKind::NotConnected.into()))
+}
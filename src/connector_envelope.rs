@@ -0,0 +1,84 @@
+use std::io::{BufRead, BufReader, Write};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::connector_endpoint::ConnectionHandle;
+use crate::connector_request::DbConnectorIntent;
+use crate::error::ModuleKitError;
+use crate::secrets::{Secret, SecretString};
+
+/// Request sent to the KV, bus and blob connectors: a token and one command.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectorRequest<C> {
+    #[serde(default, skip_serializing_if = "Secret::is_empty")]
+    pub token: SecretString,
+    /// Set for connectors that check the command against the token's scopes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub intent: Option<DbConnectorIntent>,
+    pub command: C,
+}
+
+impl<C> ConnectorRequest<C> {
+    pub fn new(token: SecretString, command: C) -> Self {
+        Self {
+            token,
+            intent: None,
+            command,
+        }
+    }
+
+    pub fn intent(mut self, value: DbConnectorIntent) -> Self {
+        self.intent = Some(value);
+        self
+    }
+}
+
+/// Status carried by every KV, bus and blob connector reply.
+pub trait ConnectorReply: Sized {
+    fn is_ok(&self) -> bool;
+
+    /// The connector's explanation of a failed command.
+    fn error(&self) -> Option<&str>;
+
+    /// `self` when the command succeeded, else
+    /// `ModuleKitError::ConnectorRejected` with the connector's error.
+    fn into_result(self) -> Result<Self, ModuleKitError> {
+        if self.is_ok() {
+            return Ok(self);
+        }
+        Err(ModuleKitError::ConnectorRejected(
+            self.error().unwrap_or("unknown error").to_string(),
+        ))
+    }
+}
+
+/// Writes `request` as one JSON line, the framing of the bus and blob
+/// connectors.
+pub(crate) fn write_line(
+    connection: &mut ConnectionHandle,
+    request: &impl Serialize,
+) -> Result<(), ModuleKitError> {
+    let mut line = serde_json::to_vec(request)?;
+    line.push(b'\n');
+    connection.write_all(&line)?;
+    connection.flush()?;
+    Ok(())
+}
+
+/// Reads and checks the reply line. The reader is returned positioned at
+/// whatever the connector streams after it.
+pub(crate) fn read_reply_line<R: ConnectorReply + DeserializeOwned>(
+    connection: ConnectionHandle,
+    connector: &str,
+) -> Result<(BufReader<ConnectionHandle>, R), ModuleKitError> {
+    let mut reader = BufReader::new(connection);
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(ModuleKitError::ConnectorRejected(format!(
+            "{connector} connector closed the connection without a reply"
+        )));
+    }
+    let reply = serde_json::from_str::<R>(&line)?.into_result()?;
+    Ok((reader, reply))
+}
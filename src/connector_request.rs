@@ -0,0 +1,564 @@
+#[cfg(feature = "otel")]
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+use crate::cancellation::unique_request_id;
+use crate::compression::CompressionAlgorithm;
+use crate::consistency::DbConsistencyHint;
+use crate::error::ModuleKitError;
+use crate::secrets::{Secret, SecretString};
+use crate::sql::named_placeholders;
+use crate::values::DbParamValue;
+use crate::wire_format::WireFormat;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DbConnectorRequest {
+    /// Bearer token; left empty when `session` authenticates the request.
+    #[serde(default, skip_serializing_if = "Secret::is_empty")]
+    pub token: SecretString,
+    /// Session opened by a `DbConnectorCommand::Authenticate` handshake.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<SecretString>,
+    #[serde(default)]
+    pub engine: Option<String>,
+    #[serde(default)]
+    pub intent: Option<DbConnectorIntent>,
+    pub command: DbConnectorCommand,
+    #[serde(default)]
+    pub tenant: Option<DbTenantPolicy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub consistency: Option<DbConsistencyHint>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<DbLocale>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_context: Option<TraceContext>,
+    /// Caller-chosen id that a later `Cancel` command refers to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// Lets the connector recognise a resent write and return the first
+    /// result instead of executing it again.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum DbConnectorCommand {
+    Simple {
+        statement: String,
+    },
+    Prepared {
+        statement: String,
+        params: Vec<DbPreparedParam>,
+    },
+    /// Handshake asking the connector what the target engine supports.
+    Capabilities,
+    /// Starts a logical backup job; requires a `db:admin` token.
+    Backup {
+        scope: DbBackupScope,
+    },
+    /// Reports progress of a backup or restore job.
+    JobStatus {
+        job_id: String,
+    },
+    /// Opens a session bound to the request's token.
+    Authenticate,
+    /// No-op round trip used by health checks.
+    Ping,
+    /// Stops the running request sent with `request_id`.
+    Cancel {
+        request_id: String,
+    },
+    /// Lists the engines behind the connector with their capabilities.
+    ListEngines,
+    /// Handshake proposing a binary wire format and payload compression;
+    /// always sent as uncompressed JSON.
+    NegotiateFormat {
+        format: WireFormat,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        compression: Option<CompressionAlgorithm>,
+    },
+}
+
+impl DbConnectorCommand {
+    pub fn statement(&self) -> &str {
+        match self {
+            DbConnectorCommand::Simple { statement } => statement,
+            DbConnectorCommand::Prepared { statement, .. } => statement,
+            DbConnectorCommand::Capabilities
+            | DbConnectorCommand::Backup { .. }
+            | DbConnectorCommand::JobStatus { .. }
+            | DbConnectorCommand::Authenticate
+            | DbConnectorCommand::Ping
+            | DbConnectorCommand::Cancel { .. }
+            | DbConnectorCommand::ListEngines
+            | DbConnectorCommand::NegotiateFormat { .. } => "",
+        }
+    }
+}
+
+/// What a logical backup covers.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DbBackupScope {
+    /// Limits the backup to one tenant's rows; `None` backs up everything.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    /// Tables to include; empty means all tables.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tables: Vec<String>,
+}
+
+impl DbBackupScope {
+    pub fn tenant(tenant: impl Into<String>) -> Self {
+        Self {
+            tenant: Some(tenant.into()),
+            tables: Vec::new(),
+        }
+    }
+
+    pub fn table(mut self, table: impl Into<String>) -> Self {
+        self.tables.push(table.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DbPreparedParam {
+    pub name: String,
+    pub value: JsonValue,
+}
+
+impl DbPreparedParam {
+    pub fn new(name: impl Into<String>, value: impl Into<DbParamValue>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into().into(),
+        }
+    }
+}
+
+/// How the connector scopes a request to one tenant.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DbTenantPolicy {
+    /// Parameter carrying the tenant id; empty in `SchemaSwitch` mode.
+    #[serde(default)]
+    pub param: String,
+    /// Further parameters bound to the tenant, such as a workspace id.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_params: Vec<String>,
+    #[serde(default)]
+    pub mode: DbTenantBindingMode,
+    /// Schema the connector switches to in `SchemaSwitch` mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+}
+
+impl DbTenantPolicy {
+    pub fn inject(param: impl Into<String>) -> Self {
+        Self::with_mode(param.into(), DbTenantBindingMode::Inject)
+    }
+
+    pub fn require_match(param: impl Into<String>) -> Self {
+        Self::with_mode(param.into(), DbTenantBindingMode::RequireMatch)
+    }
+
+    pub fn schema_switch(schema: impl Into<String>) -> Self {
+        Self {
+            schema: Some(schema.into()),
+            ..Self::with_mode(String::new(), DbTenantBindingMode::SchemaSwitch)
+        }
+    }
+
+    fn with_mode(param: String, mode: DbTenantBindingMode) -> Self {
+        Self {
+            param,
+            extra_params: Vec::new(),
+            mode,
+            schema: None,
+        }
+    }
+
+    /// Binds one more parameter to the tenant.
+    pub fn and_param(mut self, param: impl Into<String>) -> Self {
+        self.extra_params.push(param.into());
+        self
+    }
+
+    /// Every tenant parameter, `param` first.
+    pub fn params(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.param.as_str())
+            .chain(self.extra_params.iter().map(String::as_str))
+            .filter(|param| !param.is_empty())
+    }
+
+    /// Checks the policy is complete and, in `RequireMatch` mode, that
+    /// `statement` references every tenant parameter as a placeholder.
+    pub fn validate(&self, statement: &str) -> Result<(), ModuleKitError> {
+        let invalid = |message: String| Err(ModuleKitError::InvalidTenantPolicy(message));
+        if self.mode == DbTenantBindingMode::SchemaSwitch {
+            return match self.schema.as_deref() {
+                Some(schema)
+                    if !schema.is_empty()
+                        && schema
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '_') =>
+                {
+                    Ok(())
+                }
+                Some(schema) => invalid(format!("invalid tenant schema '{schema}'")),
+                None => invalid("schema-switch policy names no schema".into()),
+            };
+        }
+        if self.params().next().is_none() {
+            return invalid("tenant policy names no parameter".into());
+        }
+        if self.mode == DbTenantBindingMode::RequireMatch {
+            let bound = named_placeholders(statement);
+            if let Some(missing) = self.params().find(|param| !bound.contains(param)) {
+                return invalid(format!(
+                    "statement does not bind tenant parameter ':{missing}'"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Session locale the connector applies for one request: collation for
+/// ordering and comparisons, and formatting of dates stringified into cells.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DbLocale {
+    /// BCP 47 language tag such as `de-DE`.
+    pub tag: String,
+    /// Engine collation name; the connector derives one from `tag` when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collation: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_format: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_zone: Option<String>,
+}
+
+impl DbLocale {
+    pub fn new(tag: impl Into<String>) -> Self {
+        Self {
+            tag: tag.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn collation(mut self, value: impl Into<String>) -> Self {
+        self.collation = Some(value.into());
+        self
+    }
+
+    pub fn date_format(mut self, value: impl Into<String>) -> Self {
+        self.date_format = Some(value.into());
+        self
+    }
+
+    pub fn time_zone(mut self, value: impl Into<String>) -> Self {
+        self.time_zone = Some(value.into());
+        self
+    }
+}
+
+/// W3C trace context the connector uses to parent its spans on the caller's.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceContext {
+    pub traceparent: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tracestate: Option<String>,
+}
+
+impl TraceContext {
+    /// `None` unless `traceparent` is a well-formed `traceparent` header value.
+    pub fn parse(traceparent: &str) -> Option<Self> {
+        let parts: Vec<&str> = traceparent.trim().split('-').collect();
+        let [version, trace_id, parent_id, flags] = parts[..] else {
+            return None;
+        };
+        let hex = |part: &str, len: usize| {
+            part.len() == len && part.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+        };
+        let nonzero = |part: &str| part.bytes().any(|b| b != b'0');
+        let valid = hex(version, 2)
+            && version != "ff"
+            && hex(trace_id, 32)
+            && nonzero(trace_id)
+            && hex(parent_id, 16)
+            && nonzero(parent_id)
+            && hex(flags, 2);
+        valid.then(|| Self {
+            traceparent: traceparent.trim().to_string(),
+            tracestate: None,
+        })
+    }
+
+    /// Reads `traceparent` and `tracestate` from propagation headers, such as
+    /// the carrier an OpenTelemetry propagator injected into.
+    pub fn from_headers<'h>(headers: impl IntoIterator<Item = (&'h str, &'h str)>) -> Option<Self> {
+        let mut traceparent = None;
+        let mut tracestate = None;
+        for (name, value) in headers {
+            if name.eq_ignore_ascii_case("traceparent") {
+                traceparent = Some(value);
+            } else if name.eq_ignore_ascii_case("tracestate") && !value.trim().is_empty() {
+                tracestate = Some(value.trim().to_string());
+            }
+        }
+        let mut context = Self::parse(traceparent?)?;
+        context.tracestate = tracestate;
+        Some(context)
+    }
+
+    pub fn tracestate(mut self, value: impl Into<String>) -> Self {
+        self.tracestate = Some(value.into());
+        self
+    }
+
+    pub fn trace_id(&self) -> &str {
+        &self.traceparent[3..35]
+    }
+}
+
+/// Returns the trace context active on the calling thread, if any.
+#[cfg(feature = "otel")]
+pub type TraceContextSource = Arc<dyn Fn() -> Option<TraceContext> + Send + Sync>;
+
+/// Per-call settings for `DbConnectorClient::execute_with`.
+#[derive(Debug, Clone, Default)]
+pub struct ExecuteOptions {
+    /// Overrides the client's default locale for this request.
+    pub locale: Option<DbLocale>,
+    /// Leaves `trace_context` off this request even when a source is installed.
+    #[cfg(feature = "otel")]
+    pub skip_trace_context: bool,
+    /// Sends the request even when the result cache holds a response for it.
+    pub bypass_cache: bool,
+    /// Tags a cached response is stored under, for `invalidate_cache`.
+    pub cache_tags: Vec<String>,
+    /// Sent as `DbConnectorRequest::request_id` so the request can be cancelled.
+    pub request_id: Option<String>,
+    /// Key for a write; reuse it when retrying the same logical write.
+    pub idempotency_key: Option<String>,
+    /// Times a write is resent after a transport failure. Retried writes get
+    /// a generated idempotency key when none is set.
+    pub write_retries: u32,
+    /// Leaves the ambient or client `TenantContext` off this request. An
+    /// explicit `DbTenantPolicy` passed to `execute_with` still applies.
+    pub skip_tenant: bool,
+}
+
+impl ExecuteOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn locale(mut self, value: DbLocale) -> Self {
+        self.locale = Some(value);
+        self
+    }
+
+    #[cfg(feature = "otel")]
+    pub fn without_trace_context(mut self) -> Self {
+        self.skip_trace_context = true;
+        self
+    }
+
+    /// For statements on tables that are not tenant-scoped, such as the
+    /// kit's own bookkeeping tables.
+    pub fn without_tenant(mut self) -> Self {
+        self.skip_tenant = true;
+        self
+    }
+
+    pub fn bypass_cache(mut self) -> Self {
+        self.bypass_cache = true;
+        self
+    }
+
+    pub fn cache_tag(mut self, tag: impl Into<String>) -> Self {
+        self.cache_tags.push(tag.into());
+        self
+    }
+
+    pub fn request_id(mut self, value: impl Into<String>) -> Self {
+        self.request_id = Some(value.into());
+        self
+    }
+
+    pub fn idempotency_key(mut self, value: impl Into<String>) -> Self {
+        self.idempotency_key = Some(value.into());
+        self
+    }
+
+    /// Resends a write up to `retries` times after an ambiguous transport
+    /// failure. Only safe against connectors that deduplicate by
+    /// idempotency key.
+    pub fn retry_writes(mut self, retries: u32) -> Self {
+        self.write_retries = retries;
+        self
+    }
+
+    /// Key sent with a request of `intent`: the caller's, or a generated one
+    /// for writes that may be retried. Reads never carry one.
+    pub(crate) fn idempotency_key_for(&self, intent: DbConnectorIntent) -> Option<String> {
+        if !intent.requires_write_scope() {
+            return None;
+        }
+        match &self.idempotency_key {
+            Some(key) => Some(key.clone()),
+            None if self.write_retries > 0 => Some(unique_request_id()),
+            None => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DbTenantBindingMode {
+    /// The connector adds the tenant condition to the statement.
+    #[default]
+    Inject,
+    /// The statement binds the tenant parameters itself; the connector
+    /// checks they match the caller's tenant.
+    RequireMatch,
+    /// The connector runs the statement in the tenant's schema.
+    SchemaSwitch,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DbConnectorIntent {
+    #[default]
+    Read,
+    Write,
+}
+
+impl DbConnectorIntent {
+    pub fn requires_write_scope(&self) -> bool {
+        matches!(self, DbConnectorIntent::Write)
+    }
+
+    pub fn detect(statement: &str) -> Self {
+        let keyword = statement
+            .split_whitespace()
+            .next()
+            .map(|word| word.to_ascii_lowercase())
+            .unwrap_or_default();
+        match keyword.as_str() {
+            "select" | "show" | "describe" | "with" | "explain" => DbConnectorIntent::Read,
+            _ => DbConnectorIntent::Write,
+        }
+    }
+}
+
+/// Stable, literal-free identifier of `statement` for logs and errors: the
+/// leading keyword plus a hash of the text with literals replaced by `?`.
+///
+/// Statements differing only in string or numeric literals share a fingerprint.
+pub fn statement_fingerprint(statement: &str) -> String {
+    let mut normalized = String::with_capacity(statement.len());
+    let mut chars = statement.chars().peekable();
+    let mut previous_word = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                while let Some(next) = chars.next() {
+                    if next == '\'' {
+                        if chars.peek() == Some(&'\'') {
+                            chars.next();
+                            continue;
+                        }
+                        break;
+                    }
+                }
+                normalized.push('?');
+                previous_word = false;
+            }
+            c if c.is_ascii_digit() && !previous_word => {
+                while chars
+                    .peek()
+                    .is_some_and(|next| next.is_ascii_digit() || *next == '.')
+                {
+                    chars.next();
+                }
+                normalized.push('?');
+            }
+            c if c.is_whitespace() => {
+                if !normalized.ends_with(' ') && !normalized.is_empty() {
+                    normalized.push(' ');
+                }
+                previous_word = false;
+            }
+            c => {
+                normalized.push(c.to_ascii_lowercase());
+                previous_word = c.is_alphanumeric() || c == '_';
+            }
+        }
+    }
+    // FNV-1a keeps fingerprints stable across processes and Rust versions.
+    let hash = normalized
+        .trim_end()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+        });
+    let keyword = normalized.split(' ').next().unwrap_or_default();
+    format!("{keyword}#{hash:016x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intent_detection_ignores_leading_whitespace() {
+        for statement in [
+            "SELECT 1",
+            "  select 1",
+            "\n\tWITH t AS (SELECT 1) SELECT * FROM t",
+        ] {
+            assert!(matches!(
+                DbConnectorIntent::detect(statement),
+                DbConnectorIntent::Read
+            ));
+        }
+        for statement in ["  INSERT INTO t VALUES (1)", "\nDELETE FROM t", ""] {
+            assert!(matches!(
+                DbConnectorIntent::detect(statement),
+                DbConnectorIntent::Write
+            ));
+        }
+    }
+
+    #[test]
+    fn intent_and_binding_mode_defaults() {
+        assert!(matches!(
+            DbConnectorIntent::default(),
+            DbConnectorIntent::Read
+        ));
+        assert_eq!(DbTenantBindingMode::default(), DbTenantBindingMode::Inject);
+    }
+
+    #[test]
+    fn statement_fingerprint_ignores_literals_and_spacing() {
+        let fingerprint = statement_fingerprint("SELECT * FROM t WHERE id = 42 AND name = 'it''s'");
+        assert!(fingerprint.starts_with("select#"));
+        assert_eq!(
+            fingerprint,
+            statement_fingerprint("select *  from t\n where id = 7 and name = 'bob'")
+        );
+        assert_ne!(
+            fingerprint,
+            statement_fingerprint("SELECT * FROM t2 WHERE id = 42")
+        );
+        assert_ne!(
+            statement_fingerprint("SELECT c1 FROM t"),
+            statement_fingerprint("SELECT c2 FROM t")
+        );
+    }
+}
@@ -0,0 +1,492 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::ModuleKitError;
+use crate::values::{standard_coercion, CellCoercion, DbRow};
+
+/// Strings a `StringInterner` keeps before it stops sharing new ones.
+const INTERNER_MAX_ENTRIES: usize = 4096;
+
+/// `DbConnectorErrorInfo::code` sent when a request names an unknown or expired session.
+const SESSION_EXPIRED_CODE: &str = "session_expired";
+
+/// `DbConnectorErrorInfo::code` sent for a command the connector does not know.
+const UNKNOWN_COMMAND_CODE: &str = "unknown_command";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DbBackupState {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+/// Backup job as reported by the connector.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DbBackupJob {
+    pub job_id: String,
+    pub state: DbBackupState,
+    /// Where the finished backup was written, once completed.
+    #[serde(default)]
+    pub location: Option<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl DbBackupJob {
+    pub fn is_finished(&self) -> bool {
+        matches!(self.state, DbBackupState::Completed | DbBackupState::Failed)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DbConnectorResponse {
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub results: Option<Vec<DbConnectorResultView>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Machine-readable form of `error`, sent by newer connectors.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_info: Option<DbConnectorErrorInfo>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<DbConnectorWarning>,
+    /// Replication position after a write, for `ConsistencyPolicy::SessionToken`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_token: Option<String>,
+    /// Footer sent by a connector being replaced, naming its successor.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relocate: Option<DbConnectorRelocation>,
+}
+
+/// Broad class of a connector failure.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DbErrorCategory {
+    UniqueViolation,
+    ForeignKeyViolation,
+    ConstraintViolation,
+    PermissionDenied,
+    UndefinedObject,
+    Syntax,
+    SerializationFailure,
+    Deadlock,
+    Timeout,
+    Connection,
+    #[default]
+    #[serde(other)]
+    Other,
+}
+
+impl DbErrorCategory {
+    /// Category implied by an SQLSTATE code, for connectors that send only the code.
+    pub fn from_sqlstate(sqlstate: &str) -> Self {
+        match sqlstate {
+            "23505" => DbErrorCategory::UniqueViolation,
+            "23503" => DbErrorCategory::ForeignKeyViolation,
+            "42501" => DbErrorCategory::PermissionDenied,
+            "40001" => DbErrorCategory::SerializationFailure,
+            "40P01" => DbErrorCategory::Deadlock,
+            "57014" => DbErrorCategory::Timeout,
+            "42P01" | "42703" | "42883" => DbErrorCategory::UndefinedObject,
+            "42601" => DbErrorCategory::Syntax,
+            code if code.starts_with("23") => DbErrorCategory::ConstraintViolation,
+            code if code.starts_with("08") => DbErrorCategory::Connection,
+            _ => DbErrorCategory::Other,
+        }
+    }
+
+    fn retryable(&self) -> bool {
+        matches!(
+            self,
+            DbErrorCategory::SerializationFailure
+                | DbErrorCategory::Deadlock
+                | DbErrorCategory::Connection
+        )
+    }
+}
+
+/// Structured error object in a connector response.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DbConnectorErrorInfo {
+    /// Connector-specific error code.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sqlstate: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<DbErrorCategory>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retryable: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Typed connector failure carried by `ModuleKitError::ConnectorFailed`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbConnectorError {
+    pub message: String,
+    pub code: Option<String>,
+    pub sqlstate: Option<String>,
+    pub category: DbErrorCategory,
+    /// Whether repeating the request unchanged may succeed.
+    pub retryable: bool,
+    pub detail: Option<String>,
+}
+
+impl DbConnectorError {
+    /// Fills in a missing category from the SQLSTATE and a missing retryable
+    /// flag from the category.
+    pub fn new(message: impl Into<String>, info: DbConnectorErrorInfo) -> Self {
+        let category = info.category.unwrap_or_else(|| {
+            info.sqlstate
+                .as_deref()
+                .map(DbErrorCategory::from_sqlstate)
+                .unwrap_or_default()
+        });
+        Self {
+            message: message.into(),
+            code: info.code,
+            sqlstate: info.sqlstate,
+            category,
+            retryable: info.retryable.unwrap_or_else(|| category.retryable()),
+            detail: info.detail,
+        }
+    }
+
+    pub fn is_unique_violation(&self) -> bool {
+        self.category == DbErrorCategory::UniqueViolation
+    }
+
+    pub fn is_permission_denied(&self) -> bool {
+        self.category == DbErrorCategory::PermissionDenied
+    }
+}
+
+impl fmt::Display for DbConnectorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)?;
+        if let Some(sqlstate) = &self.sqlstate {
+            write!(f, " (sqlstate {sqlstate})")?;
+        }
+        if let Some(detail) = &self.detail {
+            write!(f, ": {detail}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Advertised address of the connector taking over during a blue/green cutover.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DbConnectorRelocation {
+    /// Connector URI in `ipc://` or `tcp://` form.
+    pub uri: String,
+}
+
+impl DbConnectorResponse {
+    pub fn ok(results: Vec<DbConnectorResultView>) -> Self {
+        Self {
+            ok: true,
+            results: Some(results),
+            error: None,
+            error_info: None,
+            warnings: Vec::new(),
+            session_token: None,
+            relocate: None,
+        }
+    }
+
+    pub fn err(message: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            results: None,
+            error: Some(message.into()),
+            error_info: None,
+            warnings: Vec::new(),
+            session_token: None,
+            relocate: None,
+        }
+    }
+
+    /// Converts a response with `ok == false` into `ModuleKitError::ConnectorFailed`
+    /// when the connector sent `error_info`, or `ConnectorRejected` otherwise.
+    pub fn into_result(self) -> Result<Self, ModuleKitError> {
+        if self.ok {
+            return Ok(self);
+        }
+        let message = self.error.unwrap_or_else(|| "unknown error".into());
+        match self.error_info {
+            Some(info) => Err(ModuleKitError::ConnectorFailed(DbConnectorError::new(
+                message, info,
+            ))),
+            None => Err(ModuleKitError::ConnectorRejected(message)),
+        }
+    }
+
+    /// The result sets in compact `DbResultSet` form; other results are
+    /// dropped. Column names, and cells too if `interner` interns them, are
+    /// shared through `interner`.
+    pub fn into_result_sets(self, interner: &mut StringInterner) -> Vec<DbResultSet> {
+        self.results
+            .into_iter()
+            .flatten()
+            .filter_map(|result| match result {
+                DbConnectorResultView::ResultSet { columns, rows } => {
+                    Some(DbResultSet::new(columns, rows, interner))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Rows returned plus rows affected across all results.
+    pub fn row_count(&self) -> u64 {
+        self.results
+            .iter()
+            .flatten()
+            .map(|result| match result {
+                DbConnectorResultView::ResultSet { rows, .. } => rows.len() as u64,
+                DbConnectorResultView::AffectedRows { count } => *count,
+                DbConnectorResultView::Command { .. } => 0,
+            })
+            .sum()
+    }
+
+    pub(crate) fn is_session_expired(&self) -> bool {
+        !self.ok
+            && self
+                .error_info
+                .as_ref()
+                .and_then(|info| info.code.as_deref())
+                == Some(SESSION_EXPIRED_CODE)
+    }
+
+    /// Whether the connector refused the command itself as one it does not
+    /// implement, by error code or by the message older connectors send.
+    pub(crate) fn is_unknown_command(&self) -> bool {
+        if self.ok {
+            return false;
+        }
+        let code = self
+            .error_info
+            .as_ref()
+            .and_then(|info| info.code.as_deref());
+        code == Some(UNKNOWN_COMMAND_CODE)
+            || self
+                .error
+                .as_deref()
+                .is_some_and(|message| message.to_ascii_lowercase().starts_with("unknown command"))
+    }
+}
+
+/// Non-fatal condition reported alongside a connector response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DbConnectorWarning {
+    pub kind: DbConnectorWarningKind,
+    pub message: String,
+}
+
+impl DbConnectorWarning {
+    pub fn new(kind: DbConnectorWarningKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DbConnectorWarningKind {
+    DeprecatedSyntax,
+    TruncatedResult,
+    ImplicitConversion,
+    /// The response was produced by an older connector and rewritten client-side.
+    LegacyProtocol,
+    /// The statement touched tables or engines outside the module's access policy.
+    UndeclaredAccess,
+    /// The connector announced a new address; later requests dial it.
+    EndpointMoved,
+    /// A statement's leading keyword contradicts its declared intent.
+    IntentMismatch,
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DbConnectorResultView {
+    ResultSet {
+        columns: Vec<String>,
+        rows: Vec<Vec<String>>,
+    },
+    AffectedRows {
+        count: u64,
+    },
+    Command {
+        tag: String,
+    },
+}
+
+impl DbConnectorResultView {
+    pub fn rows(&self) -> impl Iterator<Item = DbRow<'_>> {
+        self.rows_with(standard_coercion())
+    }
+
+    /// Rows whose typed accessors follow `coercion`'s NULL and boolean conventions.
+    pub fn rows_with<'a>(&'a self, coercion: &'a CellCoercion) -> impl Iterator<Item = DbRow<'a>> {
+        let (columns, rows): (&[String], &[Vec<String>]) = match self {
+            DbConnectorResultView::ResultSet { columns, rows } => (columns, rows),
+            _ => (&[], &[]),
+        };
+        rows.iter()
+            .map(move |values| DbRow::with_coercion(columns, values, coercion))
+    }
+}
+
+/// Result set whose column names are `Arc<str>` shared by every row and, via
+/// `StringInterner`, by other result sets. Cells are `Arc<str>` too, shared
+/// between equal values when the interner interns cells.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbResultSet {
+    columns: Arc<[Arc<str>]>,
+    rows: Vec<Vec<Arc<str>>>,
+}
+
+impl DbResultSet {
+    fn new(columns: Vec<String>, rows: Vec<Vec<String>>, interner: &mut StringInterner) -> Self {
+        let columns = columns
+            .iter()
+            .map(|column| interner.intern(column))
+            .collect();
+        let rows = rows
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|cell| interner.intern_cell(cell))
+                    .collect()
+            })
+            .collect();
+        Self { columns, rows }
+    }
+
+    pub fn columns(&self) -> &[Arc<str>] {
+        &self.columns
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = DbRow<'_, Arc<str>>> {
+        self.rows_with(standard_coercion())
+    }
+
+    /// Rows whose typed accessors follow `coercion`'s NULL and boolean conventions.
+    pub fn rows_with<'a>(
+        &'a self,
+        coercion: &'a CellCoercion,
+    ) -> impl Iterator<Item = DbRow<'a, Arc<str>>> {
+        self.rows
+            .iter()
+            .map(move |values| DbRow::with_coercion(&self.columns, values, coercion))
+    }
+}
+
+/// Deduplicates strings such as column names across result sets.
+///
+/// Cell values are only interned after `intern_cells(true)`, which pays off
+/// for low-cardinality columns such as status codes. Past 4096 distinct
+/// strings new values are no longer shared, so high-cardinality data cannot
+/// grow the interner without bound.
+#[derive(Debug, Default)]
+pub struct StringInterner {
+    entries: HashSet<Arc<str>>,
+    intern_cells: bool,
+}
+
+impl StringInterner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn intern_cells(mut self, enabled: bool) -> Self {
+        self.intern_cells = enabled;
+        self
+    }
+
+    pub fn interns_cells(&self) -> bool {
+        self.intern_cells
+    }
+
+    pub fn intern(&mut self, value: &str) -> Arc<str> {
+        if let Some(existing) = self.entries.get(value) {
+            return Arc::clone(existing);
+        }
+        let value: Arc<str> = Arc::from(value);
+        if self.entries.len() < INTERNER_MAX_ENTRIES {
+            self.entries.insert(Arc::clone(&value));
+        }
+        value
+    }
+
+    fn intern_cell(&mut self, value: String) -> Arc<str> {
+        if self.intern_cells {
+            self.intern(&value)
+        } else {
+            Arc::from(value)
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(status: &str) -> DbConnectorResponse {
+        DbConnectorResponse::ok(vec![DbConnectorResultView::ResultSet {
+            columns: vec!["id".into(), "status".into()],
+            rows: vec![
+                vec!["1".into(), status.into()],
+                vec!["2".into(), status.into()],
+            ],
+        }])
+    }
+
+    #[test]
+    fn result_sets_share_column_names_and_optionally_cells() {
+        let mut interner = StringInterner::new();
+        let first = response("open").into_result_sets(&mut interner);
+        let second = response("open").into_result_sets(&mut interner);
+        assert!(Arc::ptr_eq(&first[0].columns()[0], &second[0].columns()[0]));
+        let statuses = |set: &DbResultSet| {
+            set.rows()
+                .map(|row| Arc::clone(&row.values()[1]))
+                .collect::<Vec<_>>()
+        };
+        let cells = statuses(&first[0]);
+        assert!(!Arc::ptr_eq(&cells[0], &cells[1]));
+        assert_eq!(first[0].rows().next().unwrap().get::<i64>("id").unwrap(), 1);
+
+        let mut interner = StringInterner::new().intern_cells(true);
+        let cells = statuses(&response("open").into_result_sets(&mut interner)[0]);
+        assert!(Arc::ptr_eq(&cells[0], &cells[1]));
+        assert_eq!(interner.len(), 5);
+    }
+}
@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+
+use crate::connector_request::DbConnectorIntent;
+
+/// How reads that follow a write are routed by the connector.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConsistencyPolicy {
+    /// Reads may be served by any replica.
+    #[default]
+    None,
+    /// The next `reads` reads after a write go to the write endpoint.
+    PinAfterWrite { reads: u32 },
+    /// Reads carry the session token returned by the last write and wait for
+    /// a replica that has caught up to it.
+    SessionToken,
+}
+
+impl ConsistencyPolicy {
+    /// Parses `none`, `session` or `pin:<reads>` as used by `FENRIR_DB_CONSISTENCY`.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim().to_ascii_lowercase();
+        match value.as_str() {
+            "" | "none" => Ok(ConsistencyPolicy::None),
+            "session" | "session_token" => Ok(ConsistencyPolicy::SessionToken),
+            other => match other.strip_prefix("pin:") {
+                Some(reads) => reads
+                    .trim()
+                    .parse()
+                    .map(|reads| ConsistencyPolicy::PinAfterWrite { reads })
+                    .map_err(|err| format!("invalid read count '{reads}': {err}")),
+                None => Err(format!(
+                    "unknown consistency policy '{other}' (expected none, session or pin:<reads>)"
+                )),
+            },
+        }
+    }
+}
+
+/// Routing hint attached to a connector request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum DbConsistencyHint {
+    Primary,
+    AfterSession { token: String },
+}
+
+/// Per-client state backing a `ConsistencyPolicy`.
+#[derive(Debug, Default)]
+pub(crate) struct ConsistencyState {
+    policy: ConsistencyPolicy,
+    pinned_reads: u32,
+    session_token: Option<String>,
+}
+
+impl ConsistencyState {
+    pub(crate) fn new(policy: ConsistencyPolicy) -> Self {
+        Self {
+            policy,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn policy(&self) -> ConsistencyPolicy {
+        self.policy
+    }
+
+    /// Hint for the next request, consuming one pinned read if applicable.
+    pub(crate) fn hint_for(&mut self, intent: DbConnectorIntent) -> Option<DbConsistencyHint> {
+        if intent.requires_write_scope() {
+            return None;
+        }
+        match self.policy {
+            ConsistencyPolicy::None => None,
+            ConsistencyPolicy::PinAfterWrite { .. } if self.pinned_reads > 0 => {
+                self.pinned_reads -= 1;
+                Some(DbConsistencyHint::Primary)
+            }
+            ConsistencyPolicy::PinAfterWrite { .. } => None,
+            ConsistencyPolicy::SessionToken => self
+                .session_token
+                .clone()
+                .map(|token| DbConsistencyHint::AfterSession { token }),
+        }
+    }
+
+    /// Records a completed request so following reads observe it.
+    pub(crate) fn observe(&mut self, intent: DbConnectorIntent, session_token: Option<&str>) {
+        if !intent.requires_write_scope() {
+            return;
+        }
+        match self.policy {
+            ConsistencyPolicy::None => {}
+            ConsistencyPolicy::PinAfterWrite { reads } => self.pinned_reads = reads,
+            ConsistencyPolicy::SessionToken => {
+                if let Some(token) = session_token {
+                    self.session_token = Some(token.to_string());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+use crate::control_plane::ControlPlaneClient;
+use crate::error::ModuleKitError;
+use crate::http_transport::{HttpRequest, HttpResponse, HttpTransport};
+use crate::token_provider::ServiceTokenProvider;
+
+const CONTRACTS_PATH: &str = "modules/runtime/contracts/";
+
+/// Request half of a contract interaction. `path` is relative to the
+/// provider's base URL; `body`, when set, must be contained in the actual body.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContractRequest {
+    pub method: String,
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<JsonValue>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContractResponse {
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<JsonValue>,
+}
+
+/// One example exchange a provider promises to support.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContractInteraction {
+    pub name: String,
+    pub request: ContractRequest,
+    pub response: ContractResponse,
+}
+
+/// Example interactions published by the module owning `service_id`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ServiceContract {
+    pub service_id: String,
+    #[serde(default)]
+    pub interactions: Vec<ContractInteraction>,
+}
+
+impl ServiceContract {
+    pub fn new(service_id: impl Into<String>) -> Self {
+        Self {
+            service_id: service_id.into(),
+            interactions: Vec::new(),
+        }
+    }
+
+    pub fn interaction(
+        mut self,
+        name: impl Into<String>,
+        request: ContractRequest,
+        response: ContractResponse,
+    ) -> Self {
+        self.interactions.push(ContractInteraction {
+            name: name.into(),
+            request,
+            response,
+        });
+        self
+    }
+}
+
+/// Publishes and fetches service contracts through the control plane.
+pub struct ContractsClient {
+    control_plane: ControlPlaneClient,
+    tokens: Arc<ServiceTokenProvider>,
+}
+
+impl ContractsClient {
+    pub fn new(control_plane: ControlPlaneClient, tokens: Arc<ServiceTokenProvider>) -> Self {
+        Self {
+            control_plane,
+            tokens,
+        }
+    }
+
+    /// Publishes `contract` as the provider side, replacing the previous version.
+    pub fn publish(&self, contract: &ServiceContract) -> Result<(), ModuleKitError> {
+        let bearer = self.tokens.current_token()?;
+        let path = format!("{CONTRACTS_PATH}{}", contract.service_id);
+        self.control_plane
+            .post_json::<_, JsonValue>(bearer.expose(), &path, contract)?;
+        Ok(())
+    }
+
+    pub fn fetch(&self, service_id: &str) -> Result<ServiceContract, ModuleKitError> {
+        let bearer = self.tokens.current_token()?;
+        let mut url = self.control_plane.endpoint(CONTRACTS_PATH)?;
+        url.path_segments_mut()
+            .map_err(|_| ModuleKitError::ServiceNotFound(service_id.to_string()))?
+            .pop_if_empty()
+            .push(service_id);
+        self.control_plane.get_json_url(bearer.expose(), url)
+    }
+}
+
+/// Outcome of running consumer code against a `ContractStub`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContractReport {
+    pub service_id: String,
+    /// Interactions the consumer never exercised.
+    pub unexercised: Vec<String>,
+    /// Requests that matched no interaction, as `METHOD path`.
+    pub unmatched: Vec<String>,
+}
+
+impl ContractReport {
+    /// True when every request matched; unexercised interactions are allowed.
+    pub fn is_compatible(&self) -> bool {
+        self.unmatched.is_empty()
+    }
+
+    pub fn into_result(self) -> Result<Self, ModuleKitError> {
+        if self.is_compatible() {
+            Ok(self)
+        } else {
+            Err(ModuleKitError::ContractViolation(self))
+        }
+    }
+}
+
+impl fmt::Display for ContractReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} unmatched request(s) [{}], {} unexercised interaction(s) [{}]",
+            self.service_id,
+            self.unmatched.len(),
+            self.unmatched.join(", "),
+            self.unexercised.len(),
+            self.unexercised.join(", ")
+        )
+    }
+}
+
+/// `HttpTransport` that answers from a provider's contract, for verifying
+/// consumer client code in CI without the provider running.
+///
+/// Unmatched requests get a 501 response and are listed in `report`.
+pub struct ContractStub {
+    contract: ServiceContract,
+    exercised: Mutex<Vec<bool>>,
+    unmatched: Mutex<Vec<String>>,
+}
+
+impl ContractStub {
+    pub fn new(contract: ServiceContract) -> Self {
+        let exercised = vec![false; contract.interactions.len()];
+        Self {
+            contract,
+            exercised: Mutex::new(exercised),
+            unmatched: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn report(&self) -> ContractReport {
+        let exercised = self.exercised.lock().unwrap();
+        ContractReport {
+            service_id: self.contract.service_id.clone(),
+            unexercised: self
+                .contract
+                .interactions
+                .iter()
+                .zip(exercised.iter())
+                .filter(|(_, used)| !**used)
+                .map(|(interaction, _)| interaction.name.clone())
+                .collect(),
+            unmatched: self.unmatched.lock().unwrap().clone(),
+        }
+    }
+
+    fn find(&self, request: &HttpRequest) -> Option<usize> {
+        let method = request.method.as_str();
+        let body: Option<JsonValue> = request
+            .body
+            .as_deref()
+            .and_then(|bytes| serde_json::from_slice(bytes).ok());
+        self.contract.interactions.iter().position(|interaction| {
+            let expected = &interaction.request;
+            expected.method.eq_ignore_ascii_case(method)
+                && path_matches(request.url.path(), &expected.path)
+                && expected.body.as_ref().is_none_or(|expected| {
+                    body.as_ref()
+                        .is_some_and(|actual| json_contains(actual, expected))
+                })
+        })
+    }
+}
+
+impl HttpTransport for ContractStub {
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse, ModuleKitError> {
+        let Some(index) = self.find(request) else {
+            let description = format!("{} {}", request.method.as_str(), request.url.path());
+            self.unmatched.lock().unwrap().push(description.clone());
+            return Ok(HttpResponse {
+                status: 501,
+                headers: Vec::new(),
+                body: format!("no contract interaction matches {description}").into_bytes(),
+            });
+        };
+        self.exercised.lock().unwrap()[index] = true;
+        let response = &self.contract.interactions[index].response;
+        let body = match &response.body {
+            Some(body) => serde_json::to_vec(body)?,
+            None => Vec::new(),
+        };
+        Ok(HttpResponse {
+            status: response.status,
+            headers: response
+                .headers
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
+            body,
+        })
+    }
+}
+
+fn path_matches(actual: &str, expected: &str) -> bool {
+    let expected = expected.split('?').next().unwrap_or_default();
+    let expected = expected.trim_matches('/');
+    let actual = actual.trim_end_matches('/');
+    actual == expected || actual.ends_with(&format!("/{expected}"))
+}
+
+/// Whether every field in `expected` appears with the same value in `actual`.
+fn json_contains(actual: &JsonValue, expected: &JsonValue) -> bool {
+    match (actual, expected) {
+        (JsonValue::Object(actual), JsonValue::Object(expected)) => {
+            expected.iter().all(|(key, value)| {
+                actual
+                    .get(key)
+                    .is_some_and(|actual| json_contains(actual, value))
+            })
+        }
+        _ => actual == expected,
+    }
+}
@@ -1,109 +1,417 @@
+#[cfg(feature = "reqwest")]
 use std::fs;
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+#[cfg(feature = "reqwest")]
 use reqwest::blocking::Client as BlockingClient;
-use reqwest::{Certificate, Identity};
+#[cfg(feature = "reqwest")]
+use reqwest::{Certificate, Identity, NoProxy, Proxy};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use time::format_description::well_known::Rfc2822;
+use time::OffsetDateTime;
 use url::Url;
 
+use crate::data_keys::DataKeys;
 use crate::env::ControlPlaneEnvironment;
 use crate::error::ModuleKitError;
+#[cfg(unix)]
+use crate::http_transport::UnixSocketTransport;
+#[cfg(feature = "reqwest")]
+use crate::http_transport::ReqwestTransport;
+#[cfg(all(feature = "ureq", not(feature = "reqwest")))]
+use crate::http_transport::UreqTransport;
+use crate::http_transport::{HttpRequest, HttpResponse, HttpTransport, OutboundHeaders};
 use crate::tokens::{ModuleTokenExchangeRequest, ModuleTokenExchangeResponse};
 
-const TOKEN_ENDPOINT_PATH: &str = "modules/runtime/tokens";
+const VERSION_ENDPOINT_PATH: &str = "modules/runtime/version";
+const HEALTH_ENDPOINT_PATH: &str = "modules/runtime/health";
+/// Upper bound on a server-requested `Retry-After` delay.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(30);
+/// How long token exchanges use the configured path after negotiation failed.
+const NEGOTIATION_RETRY: Duration = Duration::from_secs(60);
+
+/// Operations the crate needs from the Fenrir control plane.
+///
+/// Implemented by `ControlPlaneClient`; tests can supply their own implementation.
+pub trait ControlPlane: Send + Sync {
+    fn exchange_token(
+        &self,
+        bearer: &str,
+        request: ModuleTokenExchangeRequest,
+    ) -> Result<ModuleTokenExchangeResponse, ModuleKitError>;
+
+    /// Checks that the control plane is reachable and serving requests.
+    ///
+    /// Implementations without a health endpoint report healthy.
+    fn health(&self) -> Result<(), ModuleKitError> {
+        Ok(())
+    }
+}
+
+impl<T: ControlPlane + ?Sized> ControlPlane for Arc<T> {
+    fn exchange_token(
+        &self,
+        bearer: &str,
+        request: ModuleTokenExchangeRequest,
+    ) -> Result<ModuleTokenExchangeResponse, ModuleKitError> {
+        (**self).exchange_token(bearer, request)
+    }
+
+    fn health(&self) -> Result<(), ModuleKitError> {
+        (**self).health()
+    }
+}
+
+/// API version advertised by `GET modules/runtime/version`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ControlPlaneVersion {
+    pub api_version: u32,
+    /// Token exchange path the server prefers over the configured one.
+    #[serde(default)]
+    pub token_path: Option<String>,
+    #[serde(default)]
+    pub features: Vec<String>,
+}
+
+impl ControlPlaneVersion {
+    /// Assumed for control planes that predate the version endpoint.
+    pub fn legacy() -> Self {
+        Self {
+            api_version: 1,
+            token_path: None,
+            features: Vec::new(),
+        }
+    }
+
+    pub fn supports(&self, feature: &str) -> bool {
+        self.features.iter().any(|item| item == feature)
+    }
+}
 
 #[derive(Clone)]
-pub(crate) struct ControlPlaneClient {
+pub struct ControlPlaneClient {
+    base_url: Url,
     token_url: Url,
-    http: BlockingClient,
+    version: Arc<RwLock<Option<ControlPlaneVersion>>>,
+    negotiation_failed_at: Arc<Mutex<Option<Instant>>>,
+    transport: Arc<dyn HttpTransport>,
+    headers: OutboundHeaders,
     retries: u32,
     backoff: Duration,
 }
 
 impl ControlPlaneClient {
-    pub(crate) fn new(env: &ControlPlaneEnvironment) -> Result<Self, ModuleKitError> {
+    /// Uses HTTP over `env.socket_path` when a Unix socket is configured,
+    /// else `default_transport`.
+    pub fn new(env: &ControlPlaneEnvironment) -> Result<Self, ModuleKitError> {
+        let transport: Arc<dyn HttpTransport> = match &env.socket_path {
+            #[cfg(unix)]
+            Some(path) => Arc::new(UnixSocketTransport::new(path, env.timeout)),
+            _ => default_transport(env)?,
+        };
+        Self::with_transport(env, transport)
+    }
+
+    /// Sends every request through `transport`.
+    pub fn with_transport(
+        env: &ControlPlaneEnvironment,
+        transport: Arc<dyn HttpTransport>,
+    ) -> Result<Self, ModuleKitError> {
         let base_url = env
             .url
             .clone()
-            .ok_or_else(|| ModuleKitError::ControlPlaneMissing)?;
-        let normalized = ensure_trailing_slash(base_url);
-        let token_url = normalized
-            .join(TOKEN_ENDPOINT_PATH)
+            .ok_or(ModuleKitError::ControlPlaneMissing)?;
+        let base_url = ensure_trailing_slash(base_url);
+        let token_url = base_url
+            .join(env.token_path.trim_start_matches('/'))
             .map_err(ModuleKitError::ControlPlaneUrl)?;
-        let mut builder = BlockingClient::builder().timeout(env.timeout);
-        if env.tls.accept_invalid_certs {
-            builder = builder.danger_accept_invalid_certs(true);
-        }
-        if let Some(ca_path) = &env.tls.ca_cert_path {
-            let bytes = fs::read(ca_path).map_err(|err| {
-                ModuleKitError::Tls(format!("failed to read ca cert {ca_path}: {err}"))
-            })?;
-            let cert = Certificate::from_pem(&bytes)
-                .map_err(|err| ModuleKitError::Tls(format!("invalid ca cert {ca_path}: {err}")))?;
-            builder = builder.add_root_certificate(cert);
-        }
-        if let (Some(cert_path), Some(key_path)) =
-            (&env.tls.client_cert_path, &env.tls.client_key_path)
-        {
-            let mut identity_bytes = fs::read(cert_path).map_err(|err| {
-                ModuleKitError::Tls(format!("failed to read client cert {cert_path}: {err}"))
-            })?;
-            let key_bytes = fs::read(key_path).map_err(|err| {
-                ModuleKitError::Tls(format!("failed to read client key {key_path}: {err}"))
-            })?;
-            identity_bytes.extend_from_slice(&key_bytes);
-            let identity = Identity::from_pem(&identity_bytes).map_err(|err| {
-                ModuleKitError::Tls(format!(
-                    "invalid client identity ({cert_path},{key_path}): {err}"
-                ))
-            })?;
-            builder = builder.identity(identity);
-        }
-        let client = builder.build()?;
         Ok(Self {
+            base_url,
             token_url,
-            http: client,
+            version: Arc::new(RwLock::new(None)),
+            negotiation_failed_at: Arc::new(Mutex::new(None)),
+            transport,
+            headers: OutboundHeaders::default(),
             retries: env.retries,
             backoff: env.backoff,
         })
     }
 
-    pub(crate) fn exchange_token(
+    /// Attaches `headers` to every request this client sends.
+    pub fn with_headers(mut self, headers: OutboundHeaders) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    pub fn headers(&self) -> &OutboundHeaders {
+        &self.headers
+    }
+
+    /// Fetches the server's API version once and caches it for this client
+    /// and its clones. A 404 means the server predates negotiation.
+    pub fn negotiate(&self) -> Result<ControlPlaneVersion, ModuleKitError> {
+        if let Some(version) = self.version() {
+            return Ok(version);
+        }
+        let response = self.send(&HttpRequest::get(self.endpoint(VERSION_ENDPOINT_PATH)?))?;
+        let version = if response.status == 404 {
+            ControlPlaneVersion::legacy()
+        } else {
+            parse_json_response(response)?
+        };
+        *self.version.write().unwrap() = Some(version.clone());
+        Ok(version)
+    }
+
+    /// Version found by `negotiate`, if it has run.
+    pub fn version(&self) -> Option<ControlPlaneVersion> {
+        self.version.read().unwrap().clone()
+    }
+
+    /// The server-advertised token path, negotiated on first use, or the
+    /// configured one. Negotiation failures fall back to the configured path
+    /// and are retried once `NEGOTIATION_RETRY` has passed.
+    fn token_url(&self) -> Result<Url, ModuleKitError> {
+        let backing_off = self
+            .negotiation_failed_at
+            .lock()
+            .unwrap()
+            .is_some_and(|failed_at| failed_at.elapsed() < NEGOTIATION_RETRY);
+        if backing_off {
+            return Ok(self.token_url.clone());
+        }
+        let version = self.negotiate();
+        *self.negotiation_failed_at.lock().unwrap() = version.is_err().then(Instant::now);
+        match version.ok().and_then(|version| version.token_path) {
+            Some(path) => self.endpoint(&path),
+            None => Ok(self.token_url.clone()),
+        }
+    }
+
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    /// Resolves `path` relative to the control plane base URL. Absolute
+    /// URLs and paths that resolve to another origin are refused, so the
+    /// service token is never sent elsewhere.
+    pub fn endpoint(&self, path: &str) -> Result<Url, ModuleKitError> {
+        let foreign = || ModuleKitError::ControlPlaneOrigin(path.to_string());
+        if Url::parse(path).is_ok() {
+            return Err(foreign());
+        }
+        let url = self
+            .base_url
+            .join(path.trim_start_matches('/'))
+            .map_err(ModuleKitError::ControlPlaneUrl)?;
+        if url.origin() != self.base_url.origin() {
+            return Err(foreign());
+        }
+        Ok(url)
+    }
+
+    pub fn get_json<T: DeserializeOwned>(
         &self,
         bearer: &str,
-        request: ModuleTokenExchangeRequest,
-    ) -> Result<ModuleTokenExchangeResponse, ModuleKitError> {
+        path: &str,
+    ) -> Result<T, ModuleKitError> {
+        self.get_json_url(bearer, self.endpoint(path)?)
+    }
+
+    pub(crate) fn get_json_url<T: DeserializeOwned>(
+        &self,
+        bearer: &str,
+        url: Url,
+    ) -> Result<T, ModuleKitError> {
+        let response = self.send(&HttpRequest::get(url).bearer_auth(bearer))?;
+        parse_json_response(response)
+    }
+
+    pub fn post_json<B: Serialize, T: DeserializeOwned>(
+        &self,
+        bearer: &str,
+        path: &str,
+        body: &B,
+    ) -> Result<T, ModuleKitError> {
+        let request = HttpRequest::post_json(self.endpoint(path)?, body)?.bearer_auth(bearer);
+        parse_json_response(self.send(&request)?)
+    }
+
+    pub fn data_keys<'a>(&'a self, bearer: &'a str) -> DataKeys<'a> {
+        DataKeys::new(self, bearer)
+    }
+
+    /// Sends `request` through the configured transport, retrying transport
+    /// errors with linear backoff.
+    pub fn send(&self, request: &HttpRequest) -> Result<HttpResponse, ModuleKitError> {
+        let request = self.headers.apply(request);
         let mut attempts = 0;
         loop {
-            match self
-                .http
-                .post(self.token_url.clone())
-                .bearer_auth(bearer)
-                .json(&request)
-                .send()
-            {
-                Ok(response) => {
-                    return if response.status().is_success() {
-                        response.json().map_err(ModuleKitError::from)
-                    } else {
-                        let text = response.text().unwrap_or_else(|_| "unknown error".into());
-                        Err(ModuleKitError::TokenExchange(text))
-                    };
-                }
+            match self.transport.send(&request) {
+                Ok(response) => return Ok(response),
                 Err(err) => {
                     attempts += 1;
                     if attempts > self.retries {
-                        return Err(ModuleKitError::Http(err));
+                        return Err(err);
                     }
-                    let delay = self.backoff.saturating_mul(attempts);
-                    sleep(delay);
+                    sleep(self.backoff.saturating_mul(attempts));
                 }
             }
         }
     }
 }
 
-fn ensure_trailing_slash(mut url: Url) -> Url {
+impl ControlPlane for ControlPlaneClient {
+    fn exchange_token(
+        &self,
+        bearer: &str,
+        request: ModuleTokenExchangeRequest,
+    ) -> Result<ModuleTokenExchangeResponse, ModuleKitError> {
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!(
+            "fenrir.control_plane.exchange_token",
+            scopes = %request.scopes.join(" "),
+            status = tracing::field::Empty,
+            attempts = tracing::field::Empty,
+        );
+        #[cfg(feature = "tracing")]
+        let _entered = span.enter();
+        let request = HttpRequest::post_json(self.token_url()?, &request)?.bearer_auth(bearer);
+        let mut attempts = 0;
+        loop {
+            let response = self.send(&request)?;
+            #[cfg(feature = "tracing")]
+            {
+                span.record("status", response.status);
+                span.record("attempts", attempts + 1);
+            }
+            if response.is_success() {
+                return response.json();
+            }
+            attempts += 1;
+            if !is_retryable_status(response.status) || attempts > self.retries {
+                return Err(ModuleKitError::token_exchange(
+                    Some(response.status),
+                    response.text(),
+                ));
+            }
+            let delay =
+                retry_after(&response).unwrap_or_else(|| self.backoff.saturating_mul(attempts));
+            sleep(delay.min(MAX_RETRY_AFTER));
+        }
+    }
+
+    /// `GET modules/runtime/health`; any 2xx counts as healthy.
+    fn health(&self) -> Result<(), ModuleKitError> {
+        let response = self.send(&HttpRequest::get(self.endpoint(HEALTH_ENDPOINT_PATH)?))?;
+        if response.is_success() {
+            Ok(())
+        } else {
+            Err(ModuleKitError::ControlPlaneStatus {
+                status: response.status,
+                body: response.text(),
+            })
+        }
+    }
+}
+
+/// Throttling and server-side failures; other 4xx responses will not change on retry.
+fn is_retryable_status(status: u16) -> bool {
+    status == 429 || (500..600).contains(&status)
+}
+
+/// Delay requested by a `Retry-After` header, in delta-seconds or HTTP-date form.
+fn retry_after(response: &HttpResponse) -> Option<Duration> {
+    let value = response.header("retry-after")?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = OffsetDateTime::parse(value, &Rfc2822).ok()?;
+    let remaining = at - OffsetDateTime::now_utc();
+    Some(remaining.try_into().unwrap_or(Duration::ZERO))
+}
+
+/// Transport for `env` from the HTTP stack compiled in: reqwest with the
+/// `reqwest` feature (the default), else ureq with the `ureq` feature.
+pub fn default_transport(
+    env: &ControlPlaneEnvironment,
+) -> Result<Arc<dyn HttpTransport>, ModuleKitError> {
+    #[cfg(feature = "reqwest")]
+    return Ok(Arc::new(ReqwestTransport::new(http_client(env)?)));
+    #[cfg(all(feature = "ureq", not(feature = "reqwest")))]
+    return Ok(Arc::new(UreqTransport::from_environment(env)?));
+    #[cfg(not(any(feature = "reqwest", feature = "ureq")))]
+    {
+        let _ = env;
+        Err(ModuleKitError::Transport(
+            "no HTTP transport compiled in; enable the `reqwest` or `ureq` feature".into(),
+        ))
+    }
+}
+
+/// Blocking HTTP client honoring the control plane timeout and TLS settings.
+#[cfg(feature = "reqwest")]
+pub(crate) fn http_client(env: &ControlPlaneEnvironment) -> Result<BlockingClient, ModuleKitError> {
+    let mut builder = BlockingClient::builder().timeout(env.timeout);
+    if env.tls.accept_invalid_certs {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    if let Some(proxy) = &env.proxy {
+        let mut configured = Proxy::all(proxy.url.clone())?;
+        if let Some(username) = &proxy.username {
+            let password = proxy.password.as_ref().map_or("", |p| p.expose().as_str());
+            configured = configured.basic_auth(username, password);
+        }
+        if let Some(list) = &proxy.no_proxy {
+            configured = configured.no_proxy(NoProxy::from_string(list));
+        }
+        builder = builder.proxy(configured);
+    }
+    if let Some(ca_path) = &env.tls.ca_cert_path {
+        let bytes = fs::read(ca_path).map_err(|err| {
+            ModuleKitError::Tls(format!("failed to read ca cert {ca_path}: {err}"))
+        })?;
+        let cert = Certificate::from_pem(&bytes)
+            .map_err(|err| ModuleKitError::Tls(format!("invalid ca cert {ca_path}: {err}")))?;
+        builder = builder.add_root_certificate(cert);
+    }
+    if let (Some(cert_path), Some(key_path)) =
+        (&env.tls.client_cert_path, &env.tls.client_key_path)
+    {
+        let mut identity_bytes = fs::read(cert_path).map_err(|err| {
+            ModuleKitError::Tls(format!("failed to read client cert {cert_path}: {err}"))
+        })?;
+        let key_bytes = fs::read(key_path).map_err(|err| {
+            ModuleKitError::Tls(format!("failed to read client key {key_path}: {err}"))
+        })?;
+        identity_bytes.extend_from_slice(&key_bytes);
+        let identity = Identity::from_pem(&identity_bytes).map_err(|err| {
+            ModuleKitError::Tls(format!(
+                "invalid client identity ({cert_path},{key_path}): {err}"
+            ))
+        })?;
+        builder = builder.identity(identity);
+    }
+    Ok(builder.build()?)
+}
+
+pub(crate) fn parse_json_response<T: DeserializeOwned>(
+    response: HttpResponse,
+) -> Result<T, ModuleKitError> {
+    if response.is_success() {
+        response.json()
+    } else {
+        Err(ModuleKitError::ControlPlaneStatus {
+            status: response.status,
+            body: response.text(),
+        })
+    }
+}
+
+pub(crate) fn ensure_trailing_slash(mut url: Url) -> Url {
     if !url.path().ends_with('/') {
         let mut path = url.path().to_string();
         if !path.ends_with('/') {
@@ -113,3 +421,97 @@ fn ensure_trailing_slash(mut url: Url) -> Url {
     }
     url
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn endpoint_stays_on_the_control_plane_origin() {
+        let env = ControlPlaneEnvironment {
+            url: Some(Url::parse("https://cp.internal/api").unwrap()),
+            ..ControlPlaneEnvironment::default()
+        };
+        let client = ControlPlaneClient::new(&env).unwrap();
+        assert_eq!(
+            client.endpoint("/modules/runtime/tokens").unwrap().as_str(),
+            "https://cp.internal/api/modules/runtime/tokens"
+        );
+        for path in ["https://evil.example/tokens", "\\\\evil.example/tokens"] {
+            assert!(matches!(
+                client.endpoint(path),
+                Err(ModuleKitError::ControlPlaneOrigin(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn ipc_urls_use_the_unix_socket_transport() {
+        let env = ControlPlaneEnvironment {
+            url: Some(Url::parse("ipc:///run/fenrir%20agent/agent.sock").unwrap()),
+            ..ControlPlaneEnvironment::default()
+        }
+        .resolve_socket()
+        .unwrap();
+        assert_eq!(
+            env.socket_path.as_deref(),
+            Some("/run/fenrir agent/agent.sock")
+        );
+        assert_eq!(env.url.as_ref().unwrap().as_str(), "http://localhost/");
+        assert!(ControlPlaneClient::new(&env).is_ok());
+
+        let relative = ControlPlaneEnvironment {
+            url: Some(Url::parse("ipc://run/agent.sock").unwrap()),
+            ..ControlPlaneEnvironment::default()
+        };
+        assert!(relative.resolve_socket().is_err());
+    }
+
+    /// Answers the version endpoint with 503 and token exchanges with a
+    /// token, recording the path of every request.
+    #[derive(Default)]
+    struct UnversionedTransport {
+        paths: Mutex<Vec<String>>,
+    }
+
+    impl HttpTransport for UnversionedTransport {
+        fn send(&self, request: &HttpRequest) -> Result<HttpResponse, ModuleKitError> {
+            let path = request.url.path().to_string();
+            self.paths.lock().unwrap().push(path.clone());
+            let (status, body) = if path.ends_with(VERSION_ENDPOINT_PATH) {
+                (503, "{}".to_string())
+            } else {
+                let token = r#"{"token":"t","scopes":[],"expires_in_seconds":60}"#;
+                (200, token.to_string())
+            };
+            Ok(HttpResponse {
+                status,
+                headers: Vec::new(),
+                body: body.into_bytes(),
+            })
+        }
+    }
+
+    #[test]
+    fn failed_negotiation_is_not_retried_on_every_exchange() {
+        let env = ControlPlaneEnvironment {
+            url: Some(Url::parse("https://cp.internal/api/").unwrap()),
+            ..ControlPlaneEnvironment::default()
+        };
+        let transport = Arc::new(UnversionedTransport::default());
+        let client = ControlPlaneClient::with_transport(&env, transport.clone()).unwrap();
+        for _ in 0..3 {
+            client
+                .exchange_token("bearer", ModuleTokenExchangeRequest::db_write())
+                .unwrap();
+        }
+        let paths = transport.paths.lock().unwrap();
+        let negotiations = paths
+            .iter()
+            .filter(|path| path.ends_with(VERSION_ENDPOINT_PATH))
+            .count();
+        assert_eq!(negotiations, 1);
+        assert_eq!(paths.len(), 4);
+        assert!(client.version().is_none());
+    }
+}
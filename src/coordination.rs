@@ -0,0 +1,387 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::control_plane::ControlPlaneClient;
+use crate::env::{EnvSource, ProcessEnv};
+use crate::error::ModuleKitError;
+use crate::http_transport::HttpRequest;
+use crate::shutdown::ShutdownSignal;
+use crate::token_provider::ServiceTokenProvider;
+
+const LOCK_ACQUIRE_PATH: &str = "modules/runtime/locks/acquire";
+const LOCK_RENEW_PATH: &str = "modules/runtime/locks/renew";
+const LOCK_RELEASE_PATH: &str = "modules/runtime/locks/release";
+const MIN_RENEW_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Serialize)]
+struct LockRequest<'a> {
+    name: &'a str,
+    holder: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ttl_seconds: Option<u64>,
+}
+
+/// Control plane reply to an acquire or renew.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockLease {
+    pub name: String,
+    /// Current holder, which is someone else when `acquired` is false.
+    pub holder: String,
+    pub acquired: bool,
+    /// Increases every time the lock changes hands; pass it to storage that
+    /// can reject writes from a stale holder.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fencing_token: Option<u64>,
+}
+
+/// Lease-based locks kept by the control plane, shared by `DistributedLock`
+/// and `LeaderElector`.
+///
+/// Each replica needs a distinct holder id; the default combines
+/// `HOSTNAME` with the process id.
+pub struct Coordinator {
+    control_plane: ControlPlaneClient,
+    tokens: Arc<ServiceTokenProvider>,
+    holder: String,
+}
+
+impl Coordinator {
+    pub fn new(control_plane: ControlPlaneClient, tokens: Arc<ServiceTokenProvider>) -> Self {
+        Self::new_with(control_plane, tokens, &ProcessEnv)
+    }
+
+    /// Like `new`, reading `HOSTNAME` from `vars`.
+    pub fn new_with(
+        control_plane: ControlPlaneClient,
+        tokens: Arc<ServiceTokenProvider>,
+        vars: &dyn EnvSource,
+    ) -> Self {
+        Self {
+            control_plane,
+            tokens,
+            holder: default_holder(vars),
+        }
+    }
+
+    pub fn with_holder(mut self, holder: impl Into<String>) -> Self {
+        self.holder = holder.into();
+        self
+    }
+
+    pub fn holder(&self) -> &str {
+        &self.holder
+    }
+
+    fn try_acquire(&self, name: &str, ttl: Duration) -> Result<LockLease, ModuleKitError> {
+        self.post(LOCK_ACQUIRE_PATH, name, ttl)
+    }
+
+    fn renew(&self, name: &str, ttl: Duration) -> Result<LockLease, ModuleKitError> {
+        self.post(LOCK_RENEW_PATH, name, ttl)
+    }
+
+    /// Accepts any 2xx reply, including an empty one.
+    fn release(&self, name: &str) -> Result<(), ModuleKitError> {
+        let bearer = self.tokens.current_token()?;
+        let request = LockRequest {
+            name,
+            holder: &self.holder,
+            ttl_seconds: None,
+        };
+        let url = self.control_plane.endpoint(LOCK_RELEASE_PATH)?;
+        let response = self
+            .control_plane
+            .send(&HttpRequest::post_json(url, &request)?.bearer_auth(bearer.expose()))?;
+        if response.is_success() {
+            return Ok(());
+        }
+        Err(ModuleKitError::ControlPlaneStatus {
+            status: response.status,
+            body: response.text(),
+        })
+    }
+
+    fn post(&self, path: &str, name: &str, ttl: Duration) -> Result<LockLease, ModuleKitError> {
+        let bearer = self.tokens.current_token()?;
+        let request = LockRequest {
+            name,
+            holder: &self.holder,
+            ttl_seconds: Some(ttl.as_secs().max(1)),
+        };
+        self.control_plane
+            .post_json(bearer.expose(), path, &request)
+    }
+}
+
+type LossCallback = Box<dyn Fn(&str) + Send + Sync>;
+
+struct LockState {
+    held: AtomicBool,
+    fencing_token: AtomicU64,
+    on_lost: Mutex<Vec<LossCallback>>,
+}
+
+/// A held lock, renewed on a background thread until released or dropped.
+///
+/// When renewal is refused, or keeps failing until the lease would have
+/// expired, the lock counts as lost and `on_lost` callbacks run once.
+pub struct DistributedLock {
+    name: String,
+    coordinator: Arc<Coordinator>,
+    state: Arc<LockState>,
+    stop: ShutdownSignal,
+    renewal: Option<thread::JoinHandle<()>>,
+}
+
+impl DistributedLock {
+    /// Takes `name` for `ttl`, or returns `None` while another holder has it.
+    pub fn acquire(
+        coordinator: &Arc<Coordinator>,
+        name: impl Into<String>,
+        ttl: Duration,
+    ) -> Result<Option<Self>, ModuleKitError> {
+        let name = name.into();
+        let lease = coordinator.try_acquire(&name, ttl)?;
+        if !lease.acquired {
+            return Ok(None);
+        }
+        let state = Arc::new(LockState {
+            held: AtomicBool::new(true),
+            fencing_token: AtomicU64::new(lease.fencing_token.unwrap_or_default()),
+            on_lost: Mutex::new(Vec::new()),
+        });
+        let stop = ShutdownSignal::new();
+        let renewal = {
+            let coordinator = Arc::clone(coordinator);
+            let state = Arc::clone(&state);
+            let stop = stop.clone();
+            let name = name.clone();
+            thread::spawn(move || {
+                let mut lease = LeaseTimer::new(ttl);
+                while !stop.wait_timeout(lease.renew_interval()) {
+                    let renewed = coordinator.renew(&name, ttl);
+                    if let Ok(LockLease {
+                        acquired: true,
+                        fencing_token: Some(token),
+                        ..
+                    }) = &renewed
+                    {
+                        state.fencing_token.store(*token, Ordering::SeqCst);
+                    }
+                    if !lease.record(renewed) {
+                        state.held.store(false, Ordering::SeqCst);
+                        for callback in state.on_lost.lock().unwrap().iter() {
+                            callback(&name);
+                        }
+                        break;
+                    }
+                }
+            })
+        };
+        Ok(Some(Self {
+            name,
+            coordinator: Arc::clone(coordinator),
+            state,
+            stop,
+            renewal: Some(renewal),
+        }))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// False once the lease was lost; work guarded by the lock should stop.
+    pub fn is_held(&self) -> bool {
+        self.state.held.load(Ordering::SeqCst)
+    }
+
+    pub fn fencing_token(&self) -> u64 {
+        self.state.fencing_token.load(Ordering::SeqCst)
+    }
+
+    /// Runs `callback` with the lock name if the lease is lost.
+    pub fn on_lost(&self, callback: impl Fn(&str) + Send + Sync + 'static) {
+        self.state.on_lost.lock().unwrap().push(Box::new(callback));
+    }
+
+    /// Stops renewal and gives the lock back.
+    pub fn release(mut self) -> Result<(), ModuleKitError> {
+        self.stop_renewal();
+        if self.state.held.swap(false, Ordering::SeqCst) {
+            self.coordinator.release(&self.name)?;
+        }
+        Ok(())
+    }
+
+    fn stop_renewal(&mut self) {
+        self.stop.trigger();
+        if let Some(handle) = self.renewal.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for DistributedLock {
+    fn drop(&mut self) {
+        self.stop_renewal();
+        if self.state.held.swap(false, Ordering::SeqCst) {
+            let _ = self.coordinator.release(&self.name);
+        }
+    }
+}
+
+type LeadershipCallback = Box<dyn Fn() + Send + Sync>;
+
+/// Campaigns for a named leadership lease on a background thread.
+///
+/// Non-leaders retry every half `ttl`; the leader renews every third of
+/// `ttl`. `on_elected` and `on_lost` run on that thread at each transition.
+pub struct LeaderElector {
+    coordinator: Arc<Coordinator>,
+    name: String,
+    ttl: Duration,
+    on_elected: Vec<LeadershipCallback>,
+    on_lost: Vec<LeadershipCallback>,
+}
+
+impl LeaderElector {
+    pub fn new(coordinator: Arc<Coordinator>, name: impl Into<String>, ttl: Duration) -> Self {
+        Self {
+            coordinator,
+            name: name.into(),
+            ttl,
+            on_elected: Vec::new(),
+            on_lost: Vec::new(),
+        }
+    }
+
+    pub fn on_elected(mut self, callback: impl Fn() + Send + Sync + 'static) -> Self {
+        self.on_elected.push(Box::new(callback));
+        self
+    }
+
+    pub fn on_lost(mut self, callback: impl Fn() + Send + Sync + 'static) -> Self {
+        self.on_lost.push(Box::new(callback));
+        self
+    }
+
+    /// Starts campaigning; leadership is given up when the handle drops.
+    pub fn start(self) -> LeadershipHandle {
+        let leader = Arc::new(AtomicBool::new(false));
+        let stop = ShutdownSignal::new();
+        let thread = {
+            let leader = Arc::clone(&leader);
+            let stop = stop.clone();
+            thread::spawn(move || self.campaign(&leader, &stop))
+        };
+        LeadershipHandle {
+            leader,
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    fn campaign(&self, leader: &AtomicBool, stop: &ShutdownSignal) {
+        let mut lease: Option<LeaseTimer> = None;
+        loop {
+            match lease.as_mut() {
+                Some(timer) => {
+                    if !timer.record(self.coordinator.renew(&self.name, self.ttl)) {
+                        lease = None;
+                        leader.store(false, Ordering::SeqCst);
+                        self.on_lost.iter().for_each(|callback| callback());
+                    }
+                }
+                None => {
+                    if let Ok(LockLease { acquired: true, .. }) =
+                        self.coordinator.try_acquire(&self.name, self.ttl)
+                    {
+                        lease = Some(LeaseTimer::new(self.ttl));
+                        leader.store(true, Ordering::SeqCst);
+                        self.on_elected.iter().for_each(|callback| callback());
+                    }
+                }
+            }
+            let wait = match &lease {
+                Some(timer) => timer.renew_interval(),
+                None => (self.ttl / 2).max(MIN_RENEW_INTERVAL),
+            };
+            if stop.wait_timeout(wait) {
+                break;
+            }
+        }
+        if leader.swap(false, Ordering::SeqCst) {
+            let _ = self.coordinator.release(&self.name);
+        }
+    }
+}
+
+/// Running `LeaderElector`; dropping it stops campaigning and releases
+/// leadership.
+pub struct LeadershipHandle {
+    leader: Arc<AtomicBool>,
+    stop: ShutdownSignal,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl LeadershipHandle {
+    pub fn is_leader(&self) -> bool {
+        self.leader.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for LeadershipHandle {
+    fn drop(&mut self) {
+        self.stop.trigger();
+        if let Some(handle) = self.thread.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Tracks when a held lease runs out so transient renewal errors are
+/// tolerated until it would have expired.
+struct LeaseTimer {
+    ttl: Duration,
+    valid_until: Instant,
+}
+
+impl LeaseTimer {
+    fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            valid_until: Instant::now() + ttl,
+        }
+    }
+
+    fn renew_interval(&self) -> Duration {
+        (self.ttl / 3).max(MIN_RENEW_INTERVAL)
+    }
+
+    /// Records a renewal attempt, returning whether the lease is still held.
+    fn record(&mut self, result: Result<LockLease, ModuleKitError>) -> bool {
+        match result {
+            Ok(lease) if lease.acquired => {
+                self.valid_until = Instant::now() + self.ttl;
+                true
+            }
+            Ok(_) => false,
+            Err(_) => Instant::now() + self.renew_interval() < self.valid_until,
+        }
+    }
+}
+
+/// `HOSTNAME` joined with the process id, identifying this replica.
+pub(crate) fn default_holder(vars: &dyn EnvSource) -> String {
+    let host = vars
+        .var("HOSTNAME")
+        .ok()
+        .filter(|host| !host.trim().is_empty())
+        .unwrap_or_else(|| "replica".to_string());
+    format!("{}-{}", host.trim(), std::process::id())
+}
@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+use crate::control_plane::ControlPlaneClient;
+use crate::error::ModuleKitError;
+use crate::secrets::{Secret, SecretString};
+
+const DATA_KEYS_PATH: &str = "modules/runtime/data-keys";
+const DATA_KEYS_DECRYPT_PATH: &str = "modules/runtime/data-keys/decrypt";
+const DATA_KEYS_ROTATE_PATH: &str = "modules/runtime/data-keys/rotate";
+
+/// A freshly generated data key: use `plaintext` to encrypt locally and store
+/// `ciphertext` next to the data so it can be decrypted later.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GeneratedDataKey {
+    pub key_id: String,
+    /// Base64-encoded key material.
+    pub plaintext: SecretString,
+    /// Base64-encoded key encrypted under the module's master key.
+    pub ciphertext: String,
+}
+
+impl GeneratedDataKey {
+    pub fn plaintext_bytes(&self) -> Result<Secret<Vec<u8>>, ModuleKitError> {
+        decode_key(&self.plaintext)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DecryptedDataKey {
+    pub key_id: String,
+    pub plaintext: SecretString,
+}
+
+impl DecryptedDataKey {
+    pub fn plaintext_bytes(&self) -> Result<Secret<Vec<u8>>, ModuleKitError> {
+        decode_key(&self.plaintext)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RotatedMasterKey {
+    pub key_id: String,
+    #[serde(default)]
+    pub version: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct GenerateRequest<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key_spec: Option<&'a str>,
+    context: &'a BTreeMap<String, String>,
+}
+
+#[derive(Serialize)]
+struct DecryptRequest<'a> {
+    ciphertext: &'a str,
+    context: &'a BTreeMap<String, String>,
+}
+
+#[derive(Serialize)]
+struct RotateRequest<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key_id: Option<&'a str>,
+}
+
+/// Envelope-encryption key operations backed by platform-managed master keys.
+pub struct DataKeys<'a> {
+    client: &'a ControlPlaneClient,
+    bearer: &'a str,
+}
+
+impl<'a> DataKeys<'a> {
+    pub(crate) fn new(client: &'a ControlPlaneClient, bearer: &'a str) -> Self {
+        Self { client, bearer }
+    }
+
+    /// Generates a data key; `context` is bound to the key and must be supplied
+    /// again to decrypt it.
+    pub fn generate(
+        &self,
+        key_spec: Option<&str>,
+        context: &BTreeMap<String, String>,
+    ) -> Result<GeneratedDataKey, ModuleKitError> {
+        self.client.post_json(
+            self.bearer,
+            DATA_KEYS_PATH,
+            &GenerateRequest { key_spec, context },
+        )
+    }
+
+    pub fn decrypt(
+        &self,
+        ciphertext: &str,
+        context: &BTreeMap<String, String>,
+    ) -> Result<DecryptedDataKey, ModuleKitError> {
+        self.client.post_json(
+            self.bearer,
+            DATA_KEYS_DECRYPT_PATH,
+            &DecryptRequest {
+                ciphertext,
+                context,
+            },
+        )
+    }
+
+    /// Rotates the module's master key, or the given key when `key_id` is set.
+    pub fn rotate(&self, key_id: Option<&str>) -> Result<RotatedMasterKey, ModuleKitError> {
+        self.client.post_json(
+            self.bearer,
+            DATA_KEYS_ROTATE_PATH,
+            &RotateRequest { key_id },
+        )
+    }
+}
+
+fn decode_key(value: &SecretString) -> Result<Secret<Vec<u8>>, ModuleKitError> {
+    STANDARD
+        .decode(value.expose().trim())
+        .map(Secret::new)
+        .map_err(|err| ModuleKitError::InvalidDataKey(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_material_is_redacted() {
+        let key: DecryptedDataKey =
+            serde_json::from_value(serde_json::json!({"key_id": "k1", "plaintext": "c2VjcmV0"}))
+                .unwrap();
+        assert!(!format!("{key:?}").contains("c2VjcmV0"));
+        assert_eq!(key.plaintext_bytes().unwrap().expose(), b"secret");
+    }
+}
@@ -1,36 +1,588 @@
+use std::collections::HashMap;
 use std::env;
 use std::env::VarError;
+use std::fmt;
+#[cfg(feature = "dotenv")]
+use std::path::Path;
 use std::time::Duration;
 
-use url::Url;
+use serde::Serialize;
 use time::format_description::well_known::Rfc3339;
 use time::OffsetDateTime;
+use url::Url;
 
-use crate::connector::ConnectorEndpoint;
+use crate::compression::{CompressionAlgorithm, CompressionConfig};
+use crate::connector_endpoint::ConnectorEndpoint;
+use crate::consistency::ConsistencyPolicy;
 use crate::control_plane::ControlPlaneClient;
 use crate::error::ModuleKitError;
-use crate::token_provider::{ServiceTokenLease, ServiceTokenProvider};
+use crate::secrets::SecretString;
+use crate::token_provider::{ServiceTokenLease, ServiceTokenProvider, TokenRefreshConfig};
+use crate::token_source::{FileTokenSource, TokenSource};
+use crate::wire_format::WireFormat;
 
+const ENV_PREFIX: &str = "FENRIR_";
+const ENV_PROFILE: &str = "FENRIR_PROFILE";
 const ENV_MODULE_ID: &str = "FENRIR_MODULE_ID";
 const ENV_SERVICE_ID: &str = "FENRIR_SERVICE_ID";
 const ENV_SERVICE_TOKEN: &str = "FENRIR_SERVICE_TOKEN";
+const ENV_SERVICE_TOKEN_FILE: &str = "FENRIR_SERVICE_TOKEN_FILE";
 const ENV_SERVICE_TOKEN_ISSUED_AT: &str = "FENRIR_SERVICE_TOKEN_ISSUED_AT";
 const ENV_SERVICE_TOKEN_EXPIRES_AT: &str = "FENRIR_SERVICE_TOKEN_EXPIRES_AT";
 const ENV_SERVICE_TOKEN_TTL_SECS: &str = "FENRIR_SERVICE_TOKEN_TTL_SECS";
+const ENV_SERVICE_TOKEN_REFRESH_LEAD_SECS: &str = "FENRIR_SERVICE_TOKEN_REFRESH_LEAD_SECS";
+const ENV_SERVICE_TOKEN_REFRESH_RETRY_SECS: &str = "FENRIR_SERVICE_TOKEN_REFRESH_RETRY_SECS";
+const ENV_SERVICE_TOKEN_REFRESH_MAX_RETRY_SECS: &str =
+    "FENRIR_SERVICE_TOKEN_REFRESH_MAX_RETRY_SECS";
+const ENV_SERVICE_TOKEN_AUTO_REFRESH: &str = "FENRIR_SERVICE_TOKEN_AUTO_REFRESH";
+const ENV_SERVICE_URL_PREFIX: &str = "FENRIR_SERVICE_URL_";
 const ENV_CONNECTOR_URI: &str = "FENRIR_DB_CONNECTOR_URI";
 const ENV_CONNECTOR_PROTOCOL: &str = "FENRIR_DB_CONNECTOR_PROTOCOL";
 const ENV_CONNECTOR_ENDPOINT: &str = "FENRIR_DB_CONNECTOR_ENDPOINT";
+const ENV_CONNECTOR_FALLBACK_URIS: &str = "FENRIR_DB_CONNECTOR_FALLBACK_URIS";
+const ENV_CONNECTOR_READ_URI: &str = "FENRIR_DB_CONNECTOR_READ_URI";
+const ENV_CONNECTOR_RELOCATE_URIS: &str = "FENRIR_DB_CONNECTOR_RELOCATE_URIS";
+const ENV_CONNECTOR_FORMAT: &str = "FENRIR_DB_CONNECTOR_FORMAT";
+const ENV_CONNECTOR_COMPRESSION: &str = "FENRIR_DB_CONNECTOR_COMPRESSION";
+const ENV_CONNECTOR_COMPRESSION_LEVEL: &str = "FENRIR_DB_CONNECTOR_COMPRESSION_LEVEL";
+const ENV_CONNECTOR_COMPRESSION_MIN_BYTES: &str = "FENRIR_DB_CONNECTOR_COMPRESSION_MIN_BYTES";
+const ENV_CONNECTOR_COMPRESSION_MAX_BYTES: &str = "FENRIR_DB_CONNECTOR_COMPRESSION_MAX_BYTES";
+pub(crate) const ENV_KV_CONNECTOR_URI: &str = "FENRIR_KV_CONNECTOR_URI";
+pub(crate) const ENV_BUS_CONNECTOR_URI: &str = "FENRIR_BUS_CONNECTOR_URI";
+pub(crate) const ENV_BLOB_CONNECTOR_URI: &str = "FENRIR_BLOB_CONNECTOR_URI";
+const ENV_DB_CONSISTENCY: &str = "FENRIR_DB_CONSISTENCY";
+const ENV_DB_READ_ONLY: &str = "FENRIR_DB_READ_ONLY";
+const ENV_CONNECTOR_DUMP_DIR: &str = "FENRIR_DB_CONNECTOR_DUMP_DIR";
+const ENV_HEALTH_ADDR: &str = "FENRIR_HEALTH_ADDR";
 const ENV_CONTROL_PLANE_URL: &str = "FENRIR_CONTROL_PLANE_URL";
+const ENV_CONTROL_PLANE_SOCKET: &str = "FENRIR_CONTROL_PLANE_SOCKET";
+const ENV_CONTROL_PLANE_TOKEN_PATH: &str = "FENRIR_CONTROL_PLANE_TOKEN_PATH";
 const ENV_CONTROL_PLANE_TIMEOUT_MS: &str = "FENRIR_CONTROL_PLANE_TIMEOUT_MS";
 const ENV_CONTROL_PLANE_RETRY_ATTEMPTS: &str = "FENRIR_CONTROL_PLANE_RETRY_ATTEMPTS";
 const ENV_CONTROL_PLANE_RETRY_BACKOFF_MS: &str = "FENRIR_CONTROL_PLANE_RETRY_BACKOFF_MS";
+const ENV_CONTROL_PLANE_PROXY_URL: &str = "FENRIR_CONTROL_PLANE_PROXY_URL";
+const ENV_CONTROL_PLANE_PROXY_USERNAME: &str = "FENRIR_CONTROL_PLANE_PROXY_USERNAME";
+const ENV_CONTROL_PLANE_PROXY_PASSWORD: &str = "FENRIR_CONTROL_PLANE_PROXY_PASSWORD";
+const ENV_CONTROL_PLANE_NO_PROXY: &str = "FENRIR_CONTROL_PLANE_NO_PROXY";
 const ENV_CONTROL_PLANE_TLS_CA_CERT: &str = "FENRIR_CONTROL_PLANE_TLS_CA_CERT";
 const ENV_CONTROL_PLANE_TLS_CLIENT_CERT: &str = "FENRIR_CONTROL_PLANE_TLS_CLIENT_CERT";
 const ENV_CONTROL_PLANE_TLS_CLIENT_KEY: &str = "FENRIR_CONTROL_PLANE_TLS_CLIENT_KEY";
 const ENV_CONTROL_PLANE_TLS_ACCEPT_INVALID: &str = "FENRIR_CONTROL_PLANE_TLS_ACCEPT_INVALID";
+const DEFAULT_CONTROL_PLANE_TIMEOUT_MS: u64 = 10_000;
+const DEFAULT_CONTROL_PLANE_RETRIES: u32 = 2;
+const DEFAULT_CONTROL_PLANE_BACKOFF_MS: u64 = 200;
+pub const DEFAULT_CONTROL_PLANE_TOKEN_PATH: &str = "modules/runtime/tokens";
+
+/// Base URL override for `service_id` from `FENRIR_SERVICE_URL_<SERVICE_ID>`,
+/// with the id upper-cased and non-alphanumerics mapped to `_`.
+pub(crate) fn service_url_from_source(
+    vars: &dyn EnvSource,
+    service_id: &str,
+) -> Result<Option<Url>, ModuleKitError> {
+    let name = format!("{ENV_SERVICE_URL_PREFIX}{}", env_name_segment(service_id));
+    match vars.var(&name) {
+        Ok(value) if !value.trim().is_empty() => {
+            Url::parse(value.trim())
+                .map(Some)
+                .map_err(|err| ModuleKitError::InvalidServiceUrl {
+                    service_id: service_id.to_string(),
+                    message: format!("{name}: {err}"),
+                })
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Whether a variable must be set for `ModuleEnvironment::from_env` to succeed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", content = "group", rename_all = "snake_case")]
+pub enum EnvRequirement {
+    Required,
+    Optional,
+    /// At least one variable of the named group must be set.
+    OneOf(&'static str),
+}
+
+/// One variable read by the crate, as listed by `ModuleEnvironment::manifest`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EnvVarSpec {
+    pub name: &'static str,
+    pub requirement: EnvRequirement,
+    pub default: Option<&'static str>,
+    pub description: &'static str,
+    /// Values must be stored as secrets, not plain config.
+    pub secret: bool,
+}
+
+const fn spec(
+    name: &'static str,
+    requirement: EnvRequirement,
+    default: Option<&'static str>,
+    description: &'static str,
+    secret: bool,
+) -> EnvVarSpec {
+    EnvVarSpec {
+        name,
+        requirement,
+        default,
+        description,
+        secret,
+    }
+}
+
+const TOKEN_GROUP: EnvRequirement = EnvRequirement::OneOf("service_token");
+const CONNECTOR_GROUP: EnvRequirement = EnvRequirement::OneOf("connector");
+
+const ENV_MANIFEST: &[EnvVarSpec] = &[
+    spec(
+        ENV_PROFILE,
+        EnvRequirement::Optional,
+        None,
+        "Deployment profile; FENRIR_<PROFILE>_* variables override base names",
+        false,
+    ),
+    spec(
+        ENV_MODULE_ID,
+        EnvRequirement::Required,
+        None,
+        "Module identifier",
+        false,
+    ),
+    spec(
+        ENV_SERVICE_ID,
+        EnvRequirement::Required,
+        None,
+        "Service identifier within the module",
+        false,
+    ),
+    spec(
+        ENV_SERVICE_TOKEN,
+        TOKEN_GROUP,
+        None,
+        "Service token issued by the control plane",
+        true,
+    ),
+    spec(
+        ENV_SERVICE_TOKEN_FILE,
+        TOKEN_GROUP,
+        None,
+        "Path to a file holding the service token; takes precedence over FENRIR_SERVICE_TOKEN",
+        false,
+    ),
+    spec(
+        ENV_SERVICE_TOKEN_ISSUED_AT,
+        EnvRequirement::Optional,
+        None,
+        "RFC 3339 issue time of the service token",
+        false,
+    ),
+    spec(
+        ENV_SERVICE_TOKEN_EXPIRES_AT,
+        EnvRequirement::Optional,
+        None,
+        "RFC 3339 expiry of the service token",
+        false,
+    ),
+    spec(
+        ENV_SERVICE_TOKEN_TTL_SECS,
+        EnvRequirement::Optional,
+        None,
+        "Service token lifetime in seconds",
+        false,
+    ),
+    spec(
+        ENV_SERVICE_TOKEN_REFRESH_LEAD_SECS,
+        EnvRequirement::Optional,
+        Some("60"),
+        "Seconds before expiry to refresh the service token",
+        false,
+    ),
+    spec(
+        ENV_SERVICE_TOKEN_REFRESH_RETRY_SECS,
+        EnvRequirement::Optional,
+        Some("5"),
+        "Initial retry delay after a failed refresh",
+        false,
+    ),
+    spec(
+        ENV_SERVICE_TOKEN_REFRESH_MAX_RETRY_SECS,
+        EnvRequirement::Optional,
+        Some("300"),
+        "Upper bound for the refresh retry delay",
+        false,
+    ),
+    spec(
+        ENV_SERVICE_TOKEN_AUTO_REFRESH,
+        EnvRequirement::Optional,
+        Some("true"),
+        "Refresh the service token in the background",
+        false,
+    ),
+    spec(
+        ENV_CONNECTOR_URI,
+        CONNECTOR_GROUP,
+        None,
+        "Connector URI, ipc://<path> or tcp://<host:port>; later comma-separated entries are fallbacks",
+        false,
+    ),
+    spec(
+        ENV_CONNECTOR_PROTOCOL,
+        CONNECTOR_GROUP,
+        None,
+        "Connector protocol (ipc or tcp) when FENRIR_DB_CONNECTOR_URI is unset",
+        false,
+    ),
+    spec(
+        ENV_CONNECTOR_ENDPOINT,
+        CONNECTOR_GROUP,
+        None,
+        "Connector path or address when FENRIR_DB_CONNECTOR_URI is unset",
+        false,
+    ),
+    spec(
+        ENV_CONNECTOR_FALLBACK_URIS,
+        EnvRequirement::Optional,
+        None,
+        "Comma-separated connector URIs tried in order when the primary is unreachable",
+        false,
+    ),
+    spec(
+        ENV_CONNECTOR_READ_URI,
+        EnvRequirement::Optional,
+        None,
+        "Read replica connector URI for read-intent requests",
+        false,
+    ),
+    spec(
+        ENV_CONNECTOR_RELOCATE_URIS,
+        EnvRequirement::Optional,
+        None,
+        "Comma-separated connector URIs a relocating connector may redirect to, besides other ports on its own host",
+        false,
+    ),
+    spec(
+        ENV_CONNECTOR_FORMAT,
+        EnvRequirement::Optional,
+        Some("json"),
+        "Connector wire format: json, cbor or msgpack; falls back to json if the connector declines",
+        false,
+    ),
+    spec(
+        ENV_CONNECTOR_COMPRESSION,
+        EnvRequirement::Optional,
+        Some("none"),
+        "Connector payload compression: none, gzip or zstd; used once the connector accepts it",
+        false,
+    ),
+    spec(
+        ENV_CONNECTOR_COMPRESSION_LEVEL,
+        EnvRequirement::Optional,
+        None,
+        "Compression level, 1-9 for gzip or 1-22 for zstd",
+        false,
+    ),
+    spec(
+        ENV_CONNECTOR_COMPRESSION_MIN_BYTES,
+        EnvRequirement::Optional,
+        Some("8192"),
+        "Smallest payload that is compressed",
+        false,
+    ),
+    spec(
+        ENV_CONNECTOR_COMPRESSION_MAX_BYTES,
+        EnvRequirement::Optional,
+        Some("67108864"),
+        "Largest compressed reply accepted once decompressed",
+        false,
+    ),
+    spec(
+        ENV_KV_CONNECTOR_URI,
+        EnvRequirement::Optional,
+        None,
+        "KV cache connector URI, ipc://<path> or tcp://<host:port>",
+        false,
+    ),
+    spec(
+        ENV_BUS_CONNECTOR_URI,
+        EnvRequirement::Optional,
+        None,
+        "Message bus connector URI, ipc://<path> or tcp://<host:port>",
+        false,
+    ),
+    spec(
+        ENV_BLOB_CONNECTOR_URI,
+        EnvRequirement::Optional,
+        None,
+        "Object store connector URI, ipc://<path> or tcp://<host:port>",
+        false,
+    ),
+    spec(
+        ENV_DB_CONSISTENCY,
+        EnvRequirement::Optional,
+        Some("none"),
+        "Read-after-write policy: none, session or pin:<reads>",
+        false,
+    ),
+    spec(
+        ENV_DB_READ_ONLY,
+        EnvRequirement::Optional,
+        Some("false"),
+        "Refuse every write-intent connector request before it is sent",
+        false,
+    ),
+    spec(
+        ENV_CONNECTOR_DUMP_DIR,
+        EnvRequirement::Optional,
+        None,
+        "Directory for redacted connector traffic captures",
+        false,
+    ),
+    spec(
+        ENV_HEALTH_ADDR,
+        EnvRequirement::Optional,
+        None,
+        "Listen address for the health endpoint",
+        false,
+    ),
+    spec(
+        ENV_CONTROL_PLANE_URL,
+        EnvRequirement::Optional,
+        None,
+        "Control plane base URL (http, https, http+unix or ipc, the latter two over a Unix socket)",
+        false,
+    ),
+    spec(
+        ENV_CONTROL_PLANE_SOCKET,
+        EnvRequirement::Optional,
+        None,
+        "Unix socket for control plane HTTP traffic",
+        false,
+    ),
+    spec(
+        ENV_CONTROL_PLANE_TOKEN_PATH,
+        EnvRequirement::Optional,
+        Some(DEFAULT_CONTROL_PLANE_TOKEN_PATH),
+        "Token exchange path relative to the control plane URL",
+        false,
+    ),
+    spec(
+        ENV_CONTROL_PLANE_TIMEOUT_MS,
+        EnvRequirement::Optional,
+        Some("10000"),
+        "Control plane request timeout in milliseconds",
+        false,
+    ),
+    spec(
+        ENV_CONTROL_PLANE_RETRY_ATTEMPTS,
+        EnvRequirement::Optional,
+        Some("2"),
+        "Retries for failed control plane requests",
+        false,
+    ),
+    spec(
+        ENV_CONTROL_PLANE_RETRY_BACKOFF_MS,
+        EnvRequirement::Optional,
+        Some("200"),
+        "Linear backoff step between retries in milliseconds",
+        false,
+    ),
+    spec(
+        ENV_CONTROL_PLANE_PROXY_URL,
+        EnvRequirement::Optional,
+        None,
+        "HTTP(S) proxy for control plane requests",
+        false,
+    ),
+    spec(
+        ENV_CONTROL_PLANE_PROXY_USERNAME,
+        EnvRequirement::Optional,
+        None,
+        "Basic-auth user for the control plane proxy",
+        false,
+    ),
+    spec(
+        ENV_CONTROL_PLANE_PROXY_PASSWORD,
+        EnvRequirement::Optional,
+        None,
+        "Basic-auth password for the control plane proxy",
+        true,
+    ),
+    spec(
+        ENV_CONTROL_PLANE_NO_PROXY,
+        EnvRequirement::Optional,
+        None,
+        "Comma-separated hosts reached without the proxy",
+        false,
+    ),
+    spec(
+        ENV_CONTROL_PLANE_TLS_CA_CERT,
+        EnvRequirement::Optional,
+        None,
+        "PEM CA certificate used to verify the control plane",
+        false,
+    ),
+    spec(
+        ENV_CONTROL_PLANE_TLS_CLIENT_CERT,
+        EnvRequirement::Optional,
+        None,
+        "PEM client certificate; requires the client key",
+        false,
+    ),
+    spec(
+        ENV_CONTROL_PLANE_TLS_CLIENT_KEY,
+        EnvRequirement::Optional,
+        None,
+        "PEM client key; requires the client certificate",
+        true,
+    ),
+    spec(
+        ENV_CONTROL_PLANE_TLS_ACCEPT_INVALID,
+        EnvRequirement::Optional,
+        Some("false"),
+        "Skip control plane certificate verification; refused under profiles other than dev",
+        false,
+    ),
+    spec(
+        "FENRIR_SERVICE_URL_<SERVICE_ID>",
+        EnvRequirement::Optional,
+        None,
+        "Base URL override for a peer service",
+        false,
+    ),
+];
+
+/// Upper-cases `value` and maps non-alphanumerics to `_` for use inside a variable name.
+fn env_name_segment(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Deployment profile selected by `FENRIR_PROFILE`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnvProfile {
+    Dev,
+    Staging,
+    Prod,
+    Custom(String),
+}
+
+impl EnvProfile {
+    pub fn parse(value: &str) -> Self {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "dev" | "development" => EnvProfile::Dev,
+            "staging" => EnvProfile::Staging,
+            "prod" | "production" => EnvProfile::Prod,
+            other => EnvProfile::Custom(other.to_string()),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            EnvProfile::Dev => "dev",
+            EnvProfile::Staging => "staging",
+            EnvProfile::Prod => "prod",
+            EnvProfile::Custom(name) => name,
+        }
+    }
+
+    /// Whether `FENRIR_CONTROL_PLANE_TLS_ACCEPT_INVALID` may be enabled under
+    /// this profile.
+    pub fn allows_insecure_tls(&self) -> bool {
+        matches!(self, EnvProfile::Dev)
+    }
+
+    fn from_source(vars: &dyn EnvSource) -> Result<Option<Self>, ModuleKitError> {
+        Ok(optional_env(vars, ENV_PROFILE)?
+            .filter(|value| !value.trim().is_empty())
+            .map(|value| Self::parse(&value)))
+    }
+}
 
-fn read_env(name: &'static str) -> Result<String, ModuleKitError> {
-    match env::var(name) {
+impl fmt::Display for EnvProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Where environment variables are read from.
+pub trait EnvSource {
+    fn var(&self, name: &str) -> Result<String, VarError>;
+}
+
+/// The real process environment.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcessEnv;
+
+impl EnvSource for ProcessEnv {
+    fn var(&self, name: &str) -> Result<String, VarError> {
+        env::var(name)
+    }
+}
+
+impl EnvSource for HashMap<String, String> {
+    fn var(&self, name: &str) -> Result<String, VarError> {
+        self.get(name).cloned().ok_or(VarError::NotPresent)
+    }
+}
+
+/// Looks variables up in `primary` first and falls back to `fallback`.
+pub struct LayeredEnv<'a> {
+    pub primary: &'a dyn EnvSource,
+    pub fallback: &'a dyn EnvSource,
+}
+
+impl EnvSource for LayeredEnv<'_> {
+    fn var(&self, name: &str) -> Result<String, VarError> {
+        match self.primary.var(name) {
+            Err(VarError::NotPresent) => self.fallback.var(name),
+            other => other,
+        }
+    }
+}
+
+/// Resolves `FENRIR_<NAME>` as `FENRIR_<PROFILE>_<NAME>` first, falling back
+/// to the base name, so one image can carry settings for several profiles.
+pub struct ProfileEnv<'a> {
+    pub inner: &'a dyn EnvSource,
+    pub profile: Option<EnvProfile>,
+}
+
+impl EnvSource for ProfileEnv<'_> {
+    fn var(&self, name: &str) -> Result<String, VarError> {
+        if let (Some(profile), Some(rest)) = (&self.profile, name.strip_prefix(ENV_PREFIX)) {
+            let name = format!("{ENV_PREFIX}{}_{rest}", env_name_segment(profile.name()));
+            match self.inner.var(&name) {
+                Err(VarError::NotPresent) => {}
+                other => return other,
+            }
+        }
+        self.inner.var(name)
+    }
+}
+
+/// Parses a `.env` file into a map without touching the process environment.
+#[cfg(feature = "dotenv")]
+pub fn read_dotenv(path: impl AsRef<Path>) -> Result<HashMap<String, String>, ModuleKitError> {
+    let path = path.as_ref();
+    let invalid = |err: dotenvy::Error| {
+        ModuleKitError::InvalidEnvironment(format!("{}: {err}", path.display()))
+    };
+    dotenvy::from_path_iter(path)
+        .map_err(invalid)?
+        .map(|item| item.map_err(invalid))
+        .collect()
+}
+
+fn read_env(vars: &dyn EnvSource, name: &'static str) -> Result<String, ModuleKitError> {
+    match vars.var(name) {
         Ok(value) => Ok(value),
         Err(err) => match err {
             VarError::NotPresent => Err(ModuleKitError::MissingEnv(name)),
@@ -39,8 +591,11 @@ fn read_env(name: &'static str) -> Result<String, ModuleKitError> {
     }
 }
 
-fn optional_env(name: &'static str) -> Result<Option<String>, ModuleKitError> {
-    match env::var(name) {
+fn optional_env(
+    vars: &dyn EnvSource,
+    name: &'static str,
+) -> Result<Option<String>, ModuleKitError> {
+    match vars.var(name) {
         Ok(value) if !value.trim().is_empty() => Ok(Some(value)),
         Ok(_) => Ok(None),
         Err(VarError::NotPresent) => Ok(None),
@@ -48,10 +603,40 @@ fn optional_env(name: &'static str) -> Result<Option<String>, ModuleKitError> {
     }
 }
 
+fn optional_endpoint_env(
+    vars: &dyn EnvSource,
+    name: &'static str,
+) -> Result<Option<ConnectorEndpoint>, ModuleKitError> {
+    optional_env(vars, name)?
+        .map(|uri| ConnectorEndpoint::from_uri(uri.trim()))
+        .transpose()
+}
+
+/// Parses a comma-separated list of connector URIs, skipping empty entries.
+fn endpoint_list(uris: &str) -> Result<Vec<ConnectorEndpoint>, ModuleKitError> {
+    uris.split(',')
+        .map(str::trim)
+        .filter(|uri| !uri.is_empty())
+        .map(ConnectorEndpoint::from_uri)
+        .collect()
+}
+
+/// Splits a connector URI list into the primary endpoint and its fallbacks.
+fn primary_endpoint(
+    uris: &str,
+) -> Result<(ConnectorEndpoint, Vec<ConnectorEndpoint>), ModuleKitError> {
+    let mut endpoints = endpoint_list(uris)?.into_iter();
+    let primary = endpoints
+        .next()
+        .ok_or_else(|| ModuleKitError::InvalidConnectorUri(uris.to_string()))?;
+    Ok((primary, endpoints.collect()))
+}
+
 fn optional_timestamp_env(
+    vars: &dyn EnvSource,
     name: &'static str,
 ) -> Result<Option<OffsetDateTime>, ModuleKitError> {
-    match optional_env(name)? {
+    match optional_env(vars, name)? {
         Some(value) => OffsetDateTime::parse(value.trim(), &Rfc3339)
             .map(Some)
             .map_err(|err| ModuleKitError::invalid_env_value(name, err.to_string())),
@@ -59,8 +644,11 @@ fn optional_timestamp_env(
     }
 }
 
-fn optional_u64_env(name: &'static str) -> Result<Option<u64>, ModuleKitError> {
-    match optional_env(name)? {
+fn optional_u64_env(
+    vars: &dyn EnvSource,
+    name: &'static str,
+) -> Result<Option<u64>, ModuleKitError> {
+    match optional_env(vars, name)? {
         Some(value) => value.trim().parse::<u64>().map(Some).map_err(|_| {
             ModuleKitError::invalid_env_value(name, format!("expected integer, got '{value}'"))
         }),
@@ -68,8 +656,12 @@ fn optional_u64_env(name: &'static str) -> Result<Option<u64>, ModuleKitError> {
     }
 }
 
-fn read_u64_env(name: &'static str, default: u64) -> Result<u64, ModuleKitError> {
-    match env::var(name) {
+fn read_u64_env(
+    vars: &dyn EnvSource,
+    name: &'static str,
+    default: u64,
+) -> Result<u64, ModuleKitError> {
+    match vars.var(name) {
         Ok(value) => value.trim().parse::<u64>().map_err(|_| {
             ModuleKitError::invalid_env_value(name, format!("expected integer, got '{value}'"))
         }),
@@ -78,19 +670,27 @@ fn read_u64_env(name: &'static str, default: u64) -> Result<u64, ModuleKitError>
     }
 }
 
-fn read_u32_env(name: &'static str, default: u32) -> Result<u32, ModuleKitError> {
-    let value = read_u64_env(name, default as u64)?;
+fn read_u32_env(
+    vars: &dyn EnvSource,
+    name: &'static str,
+    default: u32,
+) -> Result<u32, ModuleKitError> {
+    let value = read_u64_env(vars, name, default as u64)?;
     u32::try_from(value).map_err(|_| {
         ModuleKitError::invalid_env_value(name, format!("value '{value}' exceeds u32::MAX"))
     })
 }
 
-fn read_bool_env(name: &'static str, default: bool) -> Result<bool, ModuleKitError> {
-    match env::var(name) {
+fn read_bool_env(
+    vars: &dyn EnvSource,
+    name: &'static str,
+    default: bool,
+) -> Result<bool, ModuleKitError> {
+    match vars.var(name) {
         Ok(value) => match value.trim().to_ascii_lowercase().as_str() {
             "1" | "true" | "yes" => Ok(true),
             "0" | "false" | "no" => Ok(false),
-            other if other.is_empty() => Ok(default),
+            "" => Ok(default),
             other => Err(ModuleKitError::invalid_env_value(
                 name,
                 format!("expected boolean, got '{other}'"),
@@ -105,68 +705,831 @@ fn read_bool_env(name: &'static str, default: bool) -> Result<bool, ModuleKitErr
 pub struct ModuleEnvironment {
     pub module_id: String,
     pub service_id: String,
-    pub service_token: String,
+    pub service_token: SecretString,
+    pub service_token_file: Option<String>,
     pub connector: ConnectorEndpoint,
+    /// Connectors tried in order when `connector` is unreachable, from
+    /// further entries of `FENRIR_DB_CONNECTOR_URI` followed by
+    /// `FENRIR_DB_CONNECTOR_FALLBACK_URIS`.
+    pub connector_fallbacks: Vec<ConnectorEndpoint>,
+    /// Read replica connector, from `FENRIR_DB_CONNECTOR_READ_URI`.
+    pub read_connector: Option<ConnectorEndpoint>,
+    /// Connectors a relocation footer may name on another host, from
+    /// `FENRIR_DB_CONNECTOR_RELOCATE_URIS`.
+    pub relocation_targets: Vec<ConnectorEndpoint>,
+    /// Wire format proposed to the connector, from `FENRIR_DB_CONNECTOR_FORMAT`.
+    pub connector_format: WireFormat,
+    /// Payload compression proposed to the connector, from
+    /// `FENRIR_DB_CONNECTOR_COMPRESSION` and its `_LEVEL`, `_MIN_BYTES` and
+    /// `_MAX_BYTES`.
+    pub connector_compression: Option<CompressionConfig>,
+    /// Directory for redacted connector traffic captures, from
+    /// `FENRIR_DB_CONNECTOR_DUMP_DIR`. Debugging aid; leave unset in production.
+    pub connector_dump_dir: Option<String>,
+    /// KV cache connector, from `FENRIR_KV_CONNECTOR_URI`.
+    pub kv_connector: Option<ConnectorEndpoint>,
+    /// Message bus connector, from `FENRIR_BUS_CONNECTOR_URI`.
+    pub bus_connector: Option<ConnectorEndpoint>,
+    /// Object store connector, from `FENRIR_BLOB_CONNECTOR_URI`.
+    pub blob_connector: Option<ConnectorEndpoint>,
+    /// Read-after-write routing, from `FENRIR_DB_CONSISTENCY`.
+    pub consistency: ConsistencyPolicy,
+    /// Refuse writes client-side, from `FENRIR_DB_READ_ONLY`.
+    pub db_read_only: bool,
     pub control_plane: ControlPlaneEnvironment,
     pub service_token_lease: ServiceTokenLease,
+    pub token_refresh: TokenRefreshConfig,
+    /// Listen address for the health endpoint, from `FENRIR_HEALTH_ADDR`.
+    pub health_addr: Option<String>,
+    pub profile: Option<EnvProfile>,
 }
 
 impl ModuleEnvironment {
     pub fn from_env() -> Result<Self, ModuleKitError> {
-        let module_id = read_env(ENV_MODULE_ID)?;
-        let service_id = read_env(ENV_SERVICE_ID)?;
-        let service_token = read_env(ENV_SERVICE_TOKEN)?;
-        let issued_at = optional_timestamp_env(ENV_SERVICE_TOKEN_ISSUED_AT)?;
-        let expires_at = optional_timestamp_env(ENV_SERVICE_TOKEN_EXPIRES_AT)?;
-        let ttl_seconds = optional_u64_env(ENV_SERVICE_TOKEN_TTL_SECS)?;
-        let connector_uri = match optional_env(ENV_CONNECTOR_URI)? {
+        Self::from_source(&ProcessEnv)
+    }
+
+    /// Reads the same variables as `from_env` from `vars`, leaving the process
+    /// environment untouched.
+    pub fn from_vars(vars: &HashMap<String, String>) -> Result<Self, ModuleKitError> {
+        Self::from_source(vars)
+    }
+
+    /// Reads a `.env` file layered under the process environment: variables
+    /// already set in the process win over the file.
+    #[cfg(feature = "dotenv")]
+    pub fn from_dotenv(path: impl AsRef<Path>) -> Result<Self, ModuleKitError> {
+        let file = read_dotenv(path)?;
+        Self::from_source(&LayeredEnv {
+            primary: &ProcessEnv,
+            fallback: &file,
+        })
+    }
+
+    /// Reads the module environment from `vars`, applying `FENRIR_PROFILE`
+    /// overrides when a profile is set.
+    pub fn from_source(vars: &dyn EnvSource) -> Result<Self, ModuleKitError> {
+        let profile = EnvProfile::from_source(vars)?;
+        let vars: &dyn EnvSource = &ProfileEnv {
+            inner: vars,
+            profile: profile.clone(),
+        };
+        let module_id = read_env(vars, ENV_MODULE_ID)?;
+        let service_id = read_env(vars, ENV_SERVICE_ID)?;
+        let service_token_file =
+            optional_env(vars, ENV_SERVICE_TOKEN_FILE)?.map(|path| path.trim().to_string());
+        let service_token = match &service_token_file {
+            Some(path) => FileTokenSource::new(path).load()?.token,
+            None => SecretString::new(read_env(vars, ENV_SERVICE_TOKEN)?),
+        };
+        let issued_at = optional_timestamp_env(vars, ENV_SERVICE_TOKEN_ISSUED_AT)?;
+        let expires_at = optional_timestamp_env(vars, ENV_SERVICE_TOKEN_EXPIRES_AT)?;
+        let ttl_seconds = optional_u64_env(vars, ENV_SERVICE_TOKEN_TTL_SECS)?;
+        let connector_uri = match optional_env(vars, ENV_CONNECTOR_URI)? {
             Some(uri) => uri,
             None => {
-                let protocol = read_env(ENV_CONNECTOR_PROTOCOL)?;
-                let endpoint = read_env(ENV_CONNECTOR_ENDPOINT)?;
+                let protocol = read_env(vars, ENV_CONNECTOR_PROTOCOL)?;
+                let endpoint = read_env(vars, ENV_CONNECTOR_ENDPOINT)?;
                 format!("{protocol}://{endpoint}")
             }
         };
-        let connector = ConnectorEndpoint::from_uri(&connector_uri)?;
-        let control_plane_url = optional_env(ENV_CONTROL_PLANE_URL)?
+        let (connector, mut connector_fallbacks) = primary_endpoint(&connector_uri)?;
+        if let Some(uris) = optional_env(vars, ENV_CONNECTOR_FALLBACK_URIS)? {
+            connector_fallbacks.extend(endpoint_list(&uris)?);
+        }
+        let connector_dump_dir =
+            optional_env(vars, ENV_CONNECTOR_DUMP_DIR)?.map(|dir| dir.trim().to_string());
+        let read_connector = optional_endpoint_env(vars, ENV_CONNECTOR_READ_URI)?;
+        let relocation_targets = match optional_env(vars, ENV_CONNECTOR_RELOCATE_URIS)? {
+            Some(uris) => endpoint_list(&uris)?,
+            None => Vec::new(),
+        };
+        let connector_format = connector_format_from_source(vars)?;
+        let connector_compression = connector_compression_from_source(vars)?;
+        let kv_connector = optional_endpoint_env(vars, ENV_KV_CONNECTOR_URI)?;
+        let bus_connector = optional_endpoint_env(vars, ENV_BUS_CONNECTOR_URI)?;
+        let blob_connector = optional_endpoint_env(vars, ENV_BLOB_CONNECTOR_URI)?;
+        let consistency = consistency_from_source(vars)?;
+        let db_read_only = read_bool_env(vars, ENV_DB_READ_ONLY, false)?;
+        let control_plane_url = optional_env(vars, ENV_CONTROL_PLANE_URL)?
             .map(|value| Url::parse(value.trim()))
             .transpose()?;
-        let control_plane = ControlPlaneEnvironment::from_env(control_plane_url)?;
-        let token_lease = ServiceTokenLease::new(
-            service_token.clone(),
-            issued_at,
-            expires_at,
-            ttl_seconds,
-        );
+        let control_plane = ControlPlaneEnvironment::from_source(vars, control_plane_url)?;
+        check_insecure_tls(profile.as_ref(), &control_plane.tls)?;
+        let token_lease =
+            ServiceTokenLease::new(service_token.clone(), issued_at, expires_at, ttl_seconds);
+        let token_refresh = token_refresh_from_source(vars)?;
+        let health_addr = optional_env(vars, ENV_HEALTH_ADDR)?.map(|addr| addr.trim().to_string());
         Ok(Self {
             module_id,
             service_id,
             service_token,
+            service_token_file,
             connector,
+            connector_fallbacks,
+            read_connector,
+            relocation_targets,
+            connector_format,
+            connector_compression,
+            connector_dump_dir,
+            kv_connector,
+            bus_connector,
+            blob_connector,
+            consistency,
+            db_read_only,
             control_plane,
             service_token_lease: token_lease,
+            token_refresh,
+            health_addr,
+            profile,
         })
     }
 
+    /// Every variable the crate reads, for generating deployment values and
+    /// validating them outside the module.
+    pub fn manifest() -> &'static [EnvVarSpec] {
+        ENV_MANIFEST
+    }
+
+    /// Checks every variable `from_env` reads and reports all problems at once.
+    pub fn validate() -> EnvReport {
+        Self::validate_source(&ProcessEnv)
+    }
+
+    pub fn validate_source(vars: &dyn EnvSource) -> EnvReport {
+        let mut report = EnvReport::default();
+        let profile = report
+            .record(ENV_PROFILE, EnvProfile::from_source(vars))
+            .flatten();
+        let vars: &dyn EnvSource = &ProfileEnv {
+            inner: vars,
+            profile: profile.clone(),
+        };
+        report.record(ENV_MODULE_ID, read_env(vars, ENV_MODULE_ID));
+        report.record(ENV_SERVICE_ID, read_env(vars, ENV_SERVICE_ID));
+        let token_file = report
+            .record(
+                ENV_SERVICE_TOKEN_FILE,
+                optional_env(vars, ENV_SERVICE_TOKEN_FILE),
+            )
+            .flatten();
+        match token_file {
+            Some(path) => {
+                report.record(
+                    ENV_SERVICE_TOKEN_FILE,
+                    FileTokenSource::new(path.trim()).load(),
+                );
+            }
+            None => {
+                report.record(ENV_SERVICE_TOKEN, read_env(vars, ENV_SERVICE_TOKEN));
+            }
+        }
+        for name in [ENV_SERVICE_TOKEN_ISSUED_AT, ENV_SERVICE_TOKEN_EXPIRES_AT] {
+            report.record(name, optional_timestamp_env(vars, name));
+        }
+        report.record(
+            ENV_SERVICE_TOKEN_TTL_SECS,
+            optional_u64_env(vars, ENV_SERVICE_TOKEN_TTL_SECS),
+        );
+        match report
+            .record(ENV_CONNECTOR_URI, optional_env(vars, ENV_CONNECTOR_URI))
+            .flatten()
+        {
+            Some(uri) => {
+                report.record(ENV_CONNECTOR_URI, primary_endpoint(&uri));
+            }
+            None => {
+                let protocol = report.record(
+                    ENV_CONNECTOR_PROTOCOL,
+                    read_env(vars, ENV_CONNECTOR_PROTOCOL),
+                );
+                let endpoint = report.record(
+                    ENV_CONNECTOR_ENDPOINT,
+                    read_env(vars, ENV_CONNECTOR_ENDPOINT),
+                );
+                if let (Some(protocol), Some(endpoint)) = (protocol, endpoint) {
+                    report.record(
+                        ENV_CONNECTOR_ENDPOINT,
+                        ConnectorEndpoint::from_uri(&format!("{protocol}://{endpoint}")),
+                    );
+                }
+            }
+        }
+        if let Some(Some(uris)) = report.record(
+            ENV_CONNECTOR_FALLBACK_URIS,
+            optional_env(vars, ENV_CONNECTOR_FALLBACK_URIS),
+        ) {
+            report.record(ENV_CONNECTOR_FALLBACK_URIS, endpoint_list(&uris));
+        }
+        if let Some(Some(uris)) = report.record(
+            ENV_CONNECTOR_RELOCATE_URIS,
+            optional_env(vars, ENV_CONNECTOR_RELOCATE_URIS),
+        ) {
+            report.record(ENV_CONNECTOR_RELOCATE_URIS, endpoint_list(&uris));
+        }
+        for name in [
+            ENV_CONNECTOR_READ_URI,
+            ENV_KV_CONNECTOR_URI,
+            ENV_BUS_CONNECTOR_URI,
+            ENV_BLOB_CONNECTOR_URI,
+        ] {
+            report.record(name, optional_endpoint_env(vars, name));
+        }
+        report.record(ENV_CONNECTOR_FORMAT, connector_format_from_source(vars));
+        report.record(
+            ENV_CONNECTOR_COMPRESSION,
+            connector_compression_from_source(vars),
+        );
+        report.record(ENV_DB_CONSISTENCY, consistency_from_source(vars));
+        report.record(
+            ENV_DB_READ_ONLY,
+            read_bool_env(vars, ENV_DB_READ_ONLY, false),
+        );
+        if let Some(Some(url)) = report.record(
+            ENV_CONTROL_PLANE_URL,
+            optional_env(vars, ENV_CONTROL_PLANE_URL),
+        ) {
+            report.record(
+                ENV_CONTROL_PLANE_URL,
+                Url::parse(url.trim()).map_err(ModuleKitError::from),
+            );
+        }
+        for name in [
+            ENV_CONTROL_PLANE_TIMEOUT_MS,
+            ENV_CONTROL_PLANE_RETRY_BACKOFF_MS,
+            ENV_SERVICE_TOKEN_REFRESH_LEAD_SECS,
+            ENV_SERVICE_TOKEN_REFRESH_RETRY_SECS,
+            ENV_SERVICE_TOKEN_REFRESH_MAX_RETRY_SECS,
+        ] {
+            report.record(name, read_u64_env(vars, name, 0));
+        }
+        report.record(
+            ENV_CONTROL_PLANE_RETRY_ATTEMPTS,
+            read_u32_env(vars, ENV_CONTROL_PLANE_RETRY_ATTEMPTS, 0),
+        );
+        report.record(
+            ENV_SERVICE_TOKEN_AUTO_REFRESH,
+            read_bool_env(vars, ENV_SERVICE_TOKEN_AUTO_REFRESH, false),
+        );
+        if let Some(accept_invalid) = report.record(
+            ENV_CONTROL_PLANE_TLS_ACCEPT_INVALID,
+            read_bool_env(vars, ENV_CONTROL_PLANE_TLS_ACCEPT_INVALID, false),
+        ) {
+            let tls = ControlPlaneTlsEnvironment {
+                accept_invalid_certs: accept_invalid,
+                ..ControlPlaneTlsEnvironment::default()
+            };
+            report.record(
+                ENV_CONTROL_PLANE_TLS_ACCEPT_INVALID,
+                check_insecure_tls(profile.as_ref(), &tls),
+            );
+        }
+        report.record(
+            ENV_CONTROL_PLANE_PROXY_URL,
+            ControlPlaneProxy::from_source(vars),
+        );
+        let cert = report
+            .record(
+                ENV_CONTROL_PLANE_TLS_CLIENT_CERT,
+                optional_env(vars, ENV_CONTROL_PLANE_TLS_CLIENT_CERT),
+            )
+            .flatten();
+        let key = report
+            .record(
+                ENV_CONTROL_PLANE_TLS_CLIENT_KEY,
+                optional_env(vars, ENV_CONTROL_PLANE_TLS_CLIENT_KEY),
+            )
+            .flatten();
+        match (cert, key) {
+            (Some(_), None) => report.invalid(
+                ENV_CONTROL_PLANE_TLS_CLIENT_KEY,
+                format!("required when {ENV_CONTROL_PLANE_TLS_CLIENT_CERT} is set"),
+            ),
+            (None, Some(_)) => report.invalid(
+                ENV_CONTROL_PLANE_TLS_CLIENT_CERT,
+                format!("required when {ENV_CONTROL_PLANE_TLS_CLIENT_KEY} is set"),
+            ),
+            _ => {}
+        }
+        report
+    }
+
+    pub fn builder() -> ModuleEnvironmentBuilder {
+        ModuleEnvironmentBuilder::default()
+    }
+
     pub fn token_provider(&self) -> Result<ServiceTokenProvider, ModuleKitError> {
-        let client = match &self.control_plane.url {
-            Some(_) => Some(ControlPlaneClient::new(&self.control_plane)?),
-            None => None,
+        let mut builder = ServiceTokenProvider::builder(self.service_token_lease.clone())
+            .config(self.token_refresh.clone());
+        if self.control_plane.url.is_some() {
+            builder = builder.control_plane(ControlPlaneClient::new(&self.control_plane)?);
+        }
+        if let Some(path) = &self.service_token_file {
+            builder = builder.source(FileTokenSource::new(path));
+        }
+        Ok(builder.build())
+    }
+}
+
+/// Every missing or invalid variable found by `ModuleEnvironment::validate`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnvReport {
+    pub missing: Vec<String>,
+    pub invalid: Vec<EnvIssue>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvIssue {
+    pub name: String,
+    pub message: String,
+}
+
+impl EnvReport {
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.invalid.is_empty()
+    }
+
+    /// `Err(ModuleKitError::EnvReport)` when any problem was found.
+    pub fn into_result(self) -> Result<(), ModuleKitError> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(ModuleKitError::EnvReport(self))
+        }
+    }
+
+    fn invalid(&mut self, name: &str, message: String) {
+        self.invalid.push(EnvIssue {
+            name: name.to_string(),
+            message,
+        });
+    }
+
+    fn record<T>(&mut self, name: &str, result: Result<T, ModuleKitError>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(ModuleKitError::MissingEnv(missing)) => {
+                self.missing.push(missing.to_string());
+                None
+            }
+            Err(ModuleKitError::InvalidEnvValue { name, message }) => {
+                self.invalid(name, message);
+                None
+            }
+            Err(err) => {
+                self.invalid(name, err.to_string());
+                None
+            }
+        }
+    }
+}
+
+impl fmt::Display for EnvReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut items: Vec<String> = self
+            .missing
+            .iter()
+            .map(|name| format!("{name} is missing"))
+            .collect();
+        items.extend(
+            self.invalid
+                .iter()
+                .map(|issue| format!("{}: {}", issue.name, issue.message)),
+        );
+        write!(f, "{}", items.join("; "))
+    }
+}
+
+/// Programmatic alternative to `ModuleEnvironment::from_env`, for tests and
+/// embedding. Unset settings take the same defaults as the environment loader.
+#[derive(Debug, Clone, Default)]
+pub struct ModuleEnvironmentBuilder {
+    module_id: Option<String>,
+    service_id: Option<String>,
+    service_token: Option<SecretString>,
+    service_token_file: Option<String>,
+    token_issued_at: Option<OffsetDateTime>,
+    token_expires_at: Option<OffsetDateTime>,
+    token_ttl_seconds: Option<u64>,
+    connector: Option<ConnectorEndpoint>,
+    connector_uri: Option<String>,
+    connector_fallbacks: Vec<ConnectorEndpoint>,
+    read_connector: Option<ConnectorEndpoint>,
+    relocation_targets: Vec<ConnectorEndpoint>,
+    connector_format: WireFormat,
+    connector_compression: Option<CompressionConfig>,
+    connector_dump_dir: Option<String>,
+    kv_connector: Option<ConnectorEndpoint>,
+    bus_connector: Option<ConnectorEndpoint>,
+    blob_connector: Option<ConnectorEndpoint>,
+    consistency: ConsistencyPolicy,
+    db_read_only: bool,
+    control_plane: ControlPlaneEnvironment,
+    token_refresh: TokenRefreshConfig,
+    health_addr: Option<String>,
+    profile: Option<EnvProfile>,
+}
+
+impl ModuleEnvironmentBuilder {
+    pub fn module_id(mut self, value: impl Into<String>) -> Self {
+        self.module_id = Some(value.into());
+        self
+    }
+
+    pub fn service_id(mut self, value: impl Into<String>) -> Self {
+        self.service_id = Some(value.into());
+        self
+    }
+
+    pub fn service_token(mut self, value: impl Into<String>) -> Self {
+        self.service_token = Some(SecretString::new(value.into().trim().to_string()));
+        self
+    }
+
+    /// Reads the token from `path` at build time and re-reads it when it changes.
+    pub fn service_token_file(mut self, path: impl Into<String>) -> Self {
+        self.service_token_file = Some(path.into());
+        self
+    }
+
+    pub fn token_issued_at(mut self, value: OffsetDateTime) -> Self {
+        self.token_issued_at = Some(value);
+        self
+    }
+
+    pub fn token_expires_at(mut self, value: OffsetDateTime) -> Self {
+        self.token_expires_at = Some(value);
+        self
+    }
+
+    pub fn token_ttl_seconds(mut self, value: u64) -> Self {
+        self.token_ttl_seconds = Some(value);
+        self
+    }
+
+    pub fn connector(mut self, value: ConnectorEndpoint) -> Self {
+        self.connector = Some(value);
+        self
+    }
+
+    /// Connector URI such as `tcp://host:port` or `ipc:///path`, parsed on
+    /// build; further comma-separated URIs become fallbacks.
+    pub fn connector_uri(mut self, value: impl Into<String>) -> Self {
+        self.connector_uri = Some(value.into());
+        self
+    }
+
+    /// Adds a connector tried when the ones before it are unreachable.
+    pub fn connector_fallback(mut self, value: ConnectorEndpoint) -> Self {
+        self.connector_fallbacks.push(value);
+        self
+    }
+
+    pub fn read_connector(mut self, value: ConnectorEndpoint) -> Self {
+        self.read_connector = Some(value);
+        self
+    }
+
+    /// Allows relocation footers to redirect to `value`.
+    pub fn relocation_target(mut self, value: ConnectorEndpoint) -> Self {
+        self.relocation_targets.push(value);
+        self
+    }
+
+    pub fn connector_format(mut self, value: WireFormat) -> Self {
+        self.connector_format = value;
+        self
+    }
+
+    pub fn connector_compression(mut self, value: CompressionConfig) -> Self {
+        self.connector_compression = Some(value);
+        self
+    }
+
+    pub fn connector_dump_dir(mut self, value: impl Into<String>) -> Self {
+        self.connector_dump_dir = Some(value.into());
+        self
+    }
+
+    pub fn kv_connector(mut self, value: ConnectorEndpoint) -> Self {
+        self.kv_connector = Some(value);
+        self
+    }
+
+    pub fn bus_connector(mut self, value: ConnectorEndpoint) -> Self {
+        self.bus_connector = Some(value);
+        self
+    }
+
+    pub fn blob_connector(mut self, value: ConnectorEndpoint) -> Self {
+        self.blob_connector = Some(value);
+        self
+    }
+
+    pub fn consistency(mut self, value: ConsistencyPolicy) -> Self {
+        self.consistency = value;
+        self
+    }
+
+    pub fn db_read_only(mut self, value: bool) -> Self {
+        self.db_read_only = value;
+        self
+    }
+
+    pub fn control_plane(mut self, value: ControlPlaneEnvironment) -> Self {
+        self.control_plane = value;
+        self
+    }
+
+    pub fn control_plane_url(mut self, value: Url) -> Self {
+        self.control_plane.url = Some(value);
+        self
+    }
+
+    pub fn control_plane_socket(mut self, path: impl Into<String>) -> Self {
+        self.control_plane.socket_path = Some(path.into());
+        self
+    }
+
+    pub fn control_plane_token_path(mut self, path: impl Into<String>) -> Self {
+        self.control_plane.token_path = path.into();
+        self
+    }
+
+    pub fn control_plane_timeout(mut self, value: Duration) -> Self {
+        self.control_plane.timeout = value;
+        self
+    }
+
+    pub fn control_plane_retries(mut self, retries: u32, backoff: Duration) -> Self {
+        self.control_plane.retries = retries;
+        self.control_plane.backoff = backoff;
+        self
+    }
+
+    pub fn control_plane_proxy(mut self, value: ControlPlaneProxy) -> Self {
+        self.control_plane.proxy = Some(value);
+        self
+    }
+
+    pub fn tls(mut self, value: ControlPlaneTlsEnvironment) -> Self {
+        self.control_plane.tls = value;
+        self
+    }
+
+    pub fn tls_ca_cert(mut self, path: impl Into<String>) -> Self {
+        self.control_plane.tls.ca_cert_path = Some(path.into());
+        self
+    }
+
+    pub fn tls_client_identity(
+        mut self,
+        cert_path: impl Into<String>,
+        key_path: impl Into<String>,
+    ) -> Self {
+        self.control_plane.tls.client_cert_path = Some(cert_path.into());
+        self.control_plane.tls.client_key_path = Some(key_path.into());
+        self
+    }
+
+    /// Only accepted together with the dev profile.
+    pub fn tls_accept_invalid_certs(mut self, value: bool) -> Self {
+        self.control_plane.tls.accept_invalid_certs = value;
+        self
+    }
+
+    pub fn token_refresh(mut self, value: TokenRefreshConfig) -> Self {
+        self.token_refresh = value;
+        self
+    }
+
+    pub fn health_addr(mut self, value: impl Into<String>) -> Self {
+        self.health_addr = Some(value.into());
+        self
+    }
+
+    pub fn profile(mut self, value: EnvProfile) -> Self {
+        self.profile = Some(value);
+        self
+    }
+
+    pub fn build(self) -> Result<ModuleEnvironment, ModuleKitError> {
+        let module_id = required(self.module_id, "module_id")?;
+        let service_id = required(self.service_id, "service_id")?;
+        let service_token = match (&self.service_token_file, self.service_token) {
+            (Some(path), _) => FileTokenSource::new(path).load()?.token,
+            (None, token) => token.filter(|token| !token.is_empty()).ok_or_else(|| {
+                ModuleKitError::InvalidEnvironment("service_token is required".to_string())
+            })?,
+        };
+        let mut connector_fallbacks = Vec::new();
+        let connector = match (self.connector, self.connector_uri) {
+            (Some(connector), _) => connector,
+            (None, Some(uri)) => {
+                let (primary, fallbacks) = primary_endpoint(&uri)?;
+                connector_fallbacks = fallbacks;
+                primary
+            }
+            (None, None) => {
+                return Err(ModuleKitError::InvalidEnvironment(
+                    "connector endpoint is required".into(),
+                ))
+            }
         };
-        Ok(ServiceTokenProvider::new(
-            self.service_token_lease.clone(),
-            client,
-        ))
+        let tls = &self.control_plane.tls;
+        if tls.client_cert_path.is_some() != tls.client_key_path.is_some() {
+            return Err(ModuleKitError::InvalidEnvironment(
+                "TLS client certificate and key must be set together".into(),
+            ));
+        }
+        check_insecure_tls(self.profile.as_ref(), tls)?;
+        let control_plane = self.control_plane.resolve_socket()?;
+        let service_token_lease = ServiceTokenLease::new(
+            service_token.clone(),
+            self.token_issued_at,
+            self.token_expires_at,
+            self.token_ttl_seconds,
+        );
+        Ok(ModuleEnvironment {
+            module_id,
+            service_id,
+            service_token,
+            service_token_file: self.service_token_file,
+            connector,
+            connector_fallbacks: connector_fallbacks
+                .into_iter()
+                .chain(self.connector_fallbacks)
+                .collect(),
+            read_connector: self.read_connector,
+            relocation_targets: self.relocation_targets,
+            connector_format: self.connector_format,
+            connector_compression: self.connector_compression,
+            connector_dump_dir: self.connector_dump_dir,
+            kv_connector: self.kv_connector,
+            bus_connector: self.bus_connector,
+            blob_connector: self.blob_connector,
+            consistency: self.consistency,
+            db_read_only: self.db_read_only,
+            control_plane,
+            service_token_lease,
+            token_refresh: self.token_refresh,
+            health_addr: self.health_addr,
+            profile: self.profile,
+        })
+    }
+}
+
+/// Rejects `accept_invalid_certs` under any profile but dev. Without a
+/// profile the flag is honoured, as it was before profiles existed.
+fn check_insecure_tls(
+    profile: Option<&EnvProfile>,
+    tls: &ControlPlaneTlsEnvironment,
+) -> Result<(), ModuleKitError> {
+    match profile {
+        Some(profile) if tls.accept_invalid_certs && !profile.allows_insecure_tls() => {
+            Err(ModuleKitError::InvalidEnvValue {
+                name: ENV_CONTROL_PLANE_TLS_ACCEPT_INVALID,
+                message: format!("not allowed in the {profile} profile"),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+fn required(value: Option<String>, field: &str) -> Result<String, ModuleKitError> {
+    value
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .ok_or_else(|| ModuleKitError::InvalidEnvironment(format!("{field} is required")))
+}
+
+fn consistency_from_source(vars: &dyn EnvSource) -> Result<ConsistencyPolicy, ModuleKitError> {
+    match optional_env(vars, ENV_DB_CONSISTENCY)? {
+        Some(value) => ConsistencyPolicy::parse(&value)
+            .map_err(|message| ModuleKitError::invalid_env_value(ENV_DB_CONSISTENCY, message)),
+        None => Ok(ConsistencyPolicy::default()),
+    }
+}
+
+fn connector_format_from_source(vars: &dyn EnvSource) -> Result<WireFormat, ModuleKitError> {
+    match optional_env(vars, ENV_CONNECTOR_FORMAT)? {
+        Some(value) => WireFormat::parse(&value)
+            .map_err(|message| ModuleKitError::invalid_env_value(ENV_CONNECTOR_FORMAT, message)),
+        None => Ok(WireFormat::default()),
+    }
+}
+
+fn connector_compression_from_source(
+    vars: &dyn EnvSource,
+) -> Result<Option<CompressionConfig>, ModuleKitError> {
+    let Some(value) = optional_env(vars, ENV_CONNECTOR_COMPRESSION)? else {
+        return Ok(None);
+    };
+    let algorithm = match CompressionAlgorithm::parse(&value)
+        .map_err(|message| ModuleKitError::invalid_env_value(ENV_CONNECTOR_COMPRESSION, message))?
+    {
+        Some(algorithm) => algorithm,
+        None => return Ok(None),
+    };
+    let mut config = CompressionConfig::new(algorithm);
+    if let Some(level) = optional_u64_env(vars, ENV_CONNECTOR_COMPRESSION_LEVEL)? {
+        let max = algorithm.max_level();
+        if level == 0 || level > u64::from(max) {
+            return Err(ModuleKitError::invalid_env_value(
+                ENV_CONNECTOR_COMPRESSION_LEVEL,
+                format!("expected 1-{max} for {}, got {level}", algorithm.as_str()),
+            ));
+        }
+        config = config.level(level as u32);
+    }
+    if let Some(min_bytes) = optional_u64_env(vars, ENV_CONNECTOR_COMPRESSION_MIN_BYTES)? {
+        config = config.min_bytes(min_bytes as usize);
     }
+    if let Some(max_bytes) = optional_u64_env(vars, ENV_CONNECTOR_COMPRESSION_MAX_BYTES)? {
+        config = config.max_decompressed_bytes(max_bytes as usize);
+    }
+    Ok(Some(config))
+}
+
+fn token_refresh_from_source(vars: &dyn EnvSource) -> Result<TokenRefreshConfig, ModuleKitError> {
+    let defaults = TokenRefreshConfig::default();
+    Ok(TokenRefreshConfig {
+        lead: Duration::from_secs(read_u64_env(
+            vars,
+            ENV_SERVICE_TOKEN_REFRESH_LEAD_SECS,
+            defaults.lead.as_secs(),
+        )?),
+        retry_interval: Duration::from_secs(read_u64_env(
+            vars,
+            ENV_SERVICE_TOKEN_REFRESH_RETRY_SECS,
+            defaults.retry_interval.as_secs(),
+        )?),
+        max_retry_interval: Duration::from_secs(read_u64_env(
+            vars,
+            ENV_SERVICE_TOKEN_REFRESH_MAX_RETRY_SECS,
+            defaults.max_retry_interval.as_secs(),
+        )?),
+        auto_refresh: read_bool_env(vars, ENV_SERVICE_TOKEN_AUTO_REFRESH, defaults.auto_refresh)?,
+    })
 }
 
 #[derive(Debug, Clone)]
 pub struct ControlPlaneEnvironment {
     pub url: Option<Url>,
+    /// Unix socket carrying the control plane's HTTP traffic, from
+    /// `FENRIR_CONTROL_PLANE_SOCKET` or an `http+unix://` URL.
+    pub socket_path: Option<String>,
+    /// Token exchange endpoint, relative to `url`.
+    pub token_path: String,
     pub timeout: Duration,
     pub retries: u32,
     pub backoff: Duration,
     pub tls: ControlPlaneTlsEnvironment,
+    pub proxy: Option<ControlPlaneProxy>,
+}
+
+/// Egress proxy for control plane requests.
+#[derive(Debug, Clone)]
+pub struct ControlPlaneProxy {
+    pub url: Url,
+    pub username: Option<String>,
+    pub password: Option<SecretString>,
+    /// Comma-separated hosts, domains or CIDRs reached directly.
+    pub no_proxy: Option<String>,
+}
+
+impl ControlPlaneProxy {
+    pub fn new(url: Url) -> Self {
+        Self {
+            url,
+            username: None,
+            password: None,
+            no_proxy: None,
+        }
+    }
+
+    fn from_source(vars: &dyn EnvSource) -> Result<Option<Self>, ModuleKitError> {
+        let Some(url) = optional_env(vars, ENV_CONTROL_PLANE_PROXY_URL)? else {
+            return Ok(None);
+        };
+        let url = Url::parse(url.trim()).map_err(|err| {
+            ModuleKitError::invalid_env_value(ENV_CONTROL_PLANE_PROXY_URL, err.to_string())
+        })?;
+        let username = optional_env(vars, ENV_CONTROL_PLANE_PROXY_USERNAME)?;
+        let password = optional_env(vars, ENV_CONTROL_PLANE_PROXY_PASSWORD)?;
+        if password.is_some() && username.is_none() {
+            return Err(ModuleKitError::invalid_env_value(
+                ENV_CONTROL_PLANE_PROXY_USERNAME,
+                format!("required when {ENV_CONTROL_PLANE_PROXY_PASSWORD} is set"),
+            ));
+        }
+        Ok(Some(Self {
+            url,
+            username,
+            password: password.map(SecretString::new),
+            no_proxy: optional_env(vars, ENV_CONTROL_PLANE_NO_PROXY)?,
+        }))
+    }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -178,24 +1541,114 @@ pub struct ControlPlaneTlsEnvironment {
 }
 
 impl ControlPlaneEnvironment {
-    fn from_env(url: Option<Url>) -> Result<Self, ModuleKitError> {
-        Ok(Self {
+    fn from_source(vars: &dyn EnvSource, url: Option<Url>) -> Result<Self, ModuleKitError> {
+        let env = Self {
             url,
-            timeout: Duration::from_millis(read_u64_env(ENV_CONTROL_PLANE_TIMEOUT_MS, 10_000)?),
-            retries: read_u32_env(ENV_CONTROL_PLANE_RETRY_ATTEMPTS, 2)?,
-            backoff: Duration::from_millis(read_u64_env(ENV_CONTROL_PLANE_RETRY_BACKOFF_MS, 200)?),
-            tls: ControlPlaneTlsEnvironment::from_env()?,
-        })
+            socket_path: optional_env(vars, ENV_CONTROL_PLANE_SOCKET)?
+                .map(|path| path.trim().to_string()),
+            token_path: optional_env(vars, ENV_CONTROL_PLANE_TOKEN_PATH)?
+                .map(|path| path.trim().to_string())
+                .unwrap_or_else(|| DEFAULT_CONTROL_PLANE_TOKEN_PATH.to_string()),
+            timeout: Duration::from_millis(read_u64_env(
+                vars,
+                ENV_CONTROL_PLANE_TIMEOUT_MS,
+                DEFAULT_CONTROL_PLANE_TIMEOUT_MS,
+            )?),
+            retries: read_u32_env(
+                vars,
+                ENV_CONTROL_PLANE_RETRY_ATTEMPTS,
+                DEFAULT_CONTROL_PLANE_RETRIES,
+            )?,
+            backoff: Duration::from_millis(read_u64_env(
+                vars,
+                ENV_CONTROL_PLANE_RETRY_BACKOFF_MS,
+                DEFAULT_CONTROL_PLANE_BACKOFF_MS,
+            )?),
+            tls: ControlPlaneTlsEnvironment::from_source(vars)?,
+            proxy: ControlPlaneProxy::from_source(vars)?,
+        };
+        env.resolve_socket()
+    }
+
+    /// Moves the socket path out of an `http+unix://<percent-encoded path>/`
+    /// or `ipc:///<path>` URL, leaving an `http://localhost/` URL for request
+    /// paths; a socket without a URL gets that URL too.
+    pub(crate) fn resolve_socket(mut self) -> Result<Self, ModuleKitError> {
+        if let Some(url) = self.url.as_ref().filter(|url| url.scheme() == "ipc") {
+            let socket = percent_decode(url.path());
+            if url.host_str().is_some_and(|host| !host.is_empty()) || socket.len() <= 1 {
+                return Err(ModuleKitError::invalid_env_value(
+                    ENV_CONTROL_PLANE_URL,
+                    "ipc URL needs an absolute socket path, e.g. ipc:///run/agent.sock".into(),
+                ));
+            }
+            self.url = Some(Url::parse("http://localhost/")?);
+            self.socket_path = Some(socket);
+        } else if let Some(url) = self.url.as_ref().filter(|url| url.scheme() == "http+unix") {
+            let socket = percent_decode(url.host_str().unwrap_or_default());
+            if socket.is_empty() {
+                return Err(ModuleKitError::invalid_env_value(
+                    ENV_CONTROL_PLANE_URL,
+                    "http+unix URL needs a percent-encoded socket path as host".into(),
+                ));
+            }
+            let mut local = Url::parse("http://localhost/")?;
+            local.set_path(url.path());
+            local.set_query(url.query());
+            self.url = Some(local);
+            self.socket_path = Some(socket);
+        } else if self.socket_path.is_some() && self.url.is_none() {
+            self.url = Some(Url::parse("http://localhost/")?);
+        }
+        Ok(self)
+    }
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = (bytes[index] == b'%')
+            .then(|| value.get(index + 1..index + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+impl Default for ControlPlaneEnvironment {
+    fn default() -> Self {
+        Self {
+            url: None,
+            socket_path: None,
+            token_path: DEFAULT_CONTROL_PLANE_TOKEN_PATH.to_string(),
+            timeout: Duration::from_millis(DEFAULT_CONTROL_PLANE_TIMEOUT_MS),
+            retries: DEFAULT_CONTROL_PLANE_RETRIES,
+            backoff: Duration::from_millis(DEFAULT_CONTROL_PLANE_BACKOFF_MS),
+            tls: ControlPlaneTlsEnvironment::default(),
+            proxy: None,
+        }
     }
 }
 
 impl ControlPlaneTlsEnvironment {
-    fn from_env() -> Result<Self, ModuleKitError> {
+    fn from_source(vars: &dyn EnvSource) -> Result<Self, ModuleKitError> {
         Ok(Self {
-            ca_cert_path: optional_env(ENV_CONTROL_PLANE_TLS_CA_CERT)?,
-            client_cert_path: optional_env(ENV_CONTROL_PLANE_TLS_CLIENT_CERT)?,
-            client_key_path: optional_env(ENV_CONTROL_PLANE_TLS_CLIENT_KEY)?,
-            accept_invalid_certs: read_bool_env(ENV_CONTROL_PLANE_TLS_ACCEPT_INVALID, false)?,
+            ca_cert_path: optional_env(vars, ENV_CONTROL_PLANE_TLS_CA_CERT)?,
+            client_cert_path: optional_env(vars, ENV_CONTROL_PLANE_TLS_CLIENT_CERT)?,
+            client_key_path: optional_env(vars, ENV_CONTROL_PLANE_TLS_CLIENT_KEY)?,
+            accept_invalid_certs: read_bool_env(vars, ENV_CONTROL_PLANE_TLS_ACCEPT_INVALID, false)?,
         })
     }
 }
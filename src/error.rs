@@ -1,9 +1,19 @@
+use std::fmt;
 use std::io;
 
+#[cfg(feature = "reqwest")]
 use reqwest::Error as ReqwestError;
 use thiserror::Error;
 use url::ParseError;
 
+use crate::capabilities::EngineFeature;
+use crate::connector_response::DbConnectorError;
+use crate::contracts::ContractReport;
+use crate::env::EnvReport;
+use crate::schema::SchemaDriftReport;
+use crate::service::DescriptorViolation;
+use crate::tokens::ScopeGrantReport;
+
 #[derive(Debug, Error)]
 pub enum ModuleKitError {
     #[error("environment variable '{0}' missing")]
@@ -15,25 +25,258 @@ pub enum ModuleKitError {
     },
     #[error("environment variable '{name}' invalid value: {message}")]
     InvalidEnvValue { name: &'static str, message: String },
+    #[error("invalid module environment: {0}")]
+    InvalidEnvironment(String),
+    #[error("module environment has problems: {0}")]
+    EnvReport(EnvReport),
     #[error("invalid connector URI: {0}")]
     InvalidConnectorUri(String),
     #[error("connector IO error: {0}")]
     ConnectorIo(#[from] io::Error),
+    #[error("connector request to {endpoint} failed for statement {statement_fingerprint}: {source}")]
+    ConnectorRequestFailed {
+        endpoint: String,
+        /// `statement_fingerprint` of the statement; never the statement text.
+        statement_fingerprint: String,
+        source: io::Error,
+    },
     #[error("serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
+    #[error("connector wire format error: {0}")]
+    WireFormat(String),
+    #[cfg(feature = "reqwest")]
     #[error("control plane request failed: {0}")]
     Http(#[from] ReqwestError),
+    #[error("HTTP transport error: {0}")]
+    Transport(String),
+    #[error("HTTP connection failed: {0}")]
+    TransportConnect(String),
+    #[error("HTTP request timed out: {0}")]
+    TransportTimeout(String),
     #[error("control plane URL invalid: {0}")]
     ControlPlaneUrl(#[from] ParseError),
+    #[error("control plane returned {status}: {body}")]
+    ControlPlaneStatus { status: u16, body: String },
+    #[error("control plane path '{0}' resolves outside the control plane origin")]
+    ControlPlaneOrigin(String),
     #[error("control plane not configured")]
     ControlPlaneMissing,
-    #[error("token exchange rejected: {0}")]
-    TokenExchange(String),
+    #[error("connector rejected request: {0}")]
+    ConnectorRejected(String),
+    #[error("connector rejected request: {0}")]
+    ConnectorFailed(DbConnectorError),
+    #[error(
+        "connector does not expose engine '{engine}' (available: {})",
+        .available.join(", ")
+    )]
+    UnknownEngine {
+        engine: String,
+        available: Vec<String>,
+    },
+    #[error("engine '{engine}' does not support {feature}")]
+    UnsupportedEngineFeature {
+        engine: String,
+        feature: EngineFeature,
+    },
+    #[error("invalid schema name '{0}'")]
+    InvalidSchemaName(String),
+    #[error("schema drift detected: {0}")]
+    SchemaDrift(SchemaDriftReport),
+    #[error("connector request aborted by watchdog after {0:?}")]
+    QueryKilled(std::time::Duration),
+    #[error("connector request {0} was cancelled")]
+    QueryCancelled(String),
+    #[error("connector circuit open for {endpoint}; retry in {retry_after:?}")]
+    CircuitOpen {
+        endpoint: String,
+        retry_after: std::time::Duration,
+    },
+    #[error("contract violated: {0}")]
+    ContractViolation(ContractReport),
+    #[error("invalid seed fixture: {0}")]
+    InvalidFixture(String),
+    #[error("invalid SQL identifier '{0}'")]
+    InvalidIdentifier(String),
+    #[error("invalid change feed cursor: {0}")]
+    InvalidCursor(String),
+    #[error("invalid SQL template: {0}")]
+    InvalidSqlTemplate(String),
+    #[error("invalid SQL statement: {0}")]
+    InvalidSqlStatement(String),
+    #[error("invalid URL for service '{service_id}': {message}")]
+    InvalidServiceUrl { service_id: String, message: String },
+    #[error("invalid module capabilities: {}", .0.join("; "))]
+    InvalidCapabilities(Vec<String>),
+    #[error(
+        "invalid service descriptor: {}",
+        .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ")
+    )]
+    InvalidServiceDescriptor(Vec<DescriptorViolation>),
+    #[error("invalid service manifest: {0}")]
+    InvalidManifest(String),
+    #[error("invalid migration: {0}")]
+    InvalidMigration(String),
+    #[error("migrations are locked: {0}")]
+    MigrationLocked(String),
+    #[error("invalid tenant policy: {0}")]
+    InvalidTenantPolicy(String),
+    #[error("request is scoped to tenant '{requested}' but the caller's token claims '{claimed}'")]
+    TenantMismatch { claimed: String, requested: String },
+    #[error("invalid trigger: {0}")]
+    InvalidTrigger(String),
+    #[error("service '{0}' could not be resolved")]
+    ServiceNotFound(String),
+    #[error("forbidden: missing scopes {missing_scopes:?}, requires one of roles {required_roles:?}")]
+    Forbidden {
+        missing_scopes: Vec<String>,
+        required_roles: Vec<String>,
+    },
+    #[error("module identity lacks required scopes: {0}")]
+    ScopesNotGranted(ScopeGrantReport),
+    #[error("invalid secret name '{0}'")]
+    InvalidSecretName(String),
+    #[error("invalid data key: {0}")]
+    InvalidDataKey(String),
+    #[error("export failed: {0}")]
+    ExportFailed(String),
+    #[error("read-only client refused request: {0}")]
+    ReadOnlyViolation(String),
+    #[error("data access policy violated: {0}")]
+    AccessPolicyViolation(String),
+    #[error(
+        "token exchange rejected{}: {message}",
+        .status.map(|status| format!(" with status {status}")).unwrap_or_default()
+    )]
+    TokenExchange {
+        /// Final HTTP status, absent when the failure came from a local agent.
+        status: Option<u16>,
+        message: String,
+    },
+    #[error("cell '{column}' invalid: {message}")]
+    InvalidCell { column: String, message: String },
+    #[error("invalid token: {0}")]
+    InvalidToken(String),
+    #[error("token source failed: {0}")]
+    TokenSource(String),
     #[error("tls error: {0}")]
     Tls(String),
+    #[error(
+        "call to service '{service_id}' at {path} failed{}: {message}",
+        .status.map(|status| format!(" with status {status}")).unwrap_or_default()
+    )]
+    RpcFailed {
+        service_id: String,
+        path: String,
+        /// Response status, absent when no response arrived in time.
+        status: Option<u16>,
+        message: String,
+    },
+    #[error("{domain} not ready: {message}")]
+    NotReady {
+        domain: FailureDomain,
+        message: String,
+    },
+}
+
+/// Layer an error originated in, used to tag metrics and traces so an
+/// incident points at the failing component rather than a single error rate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FailureDomain {
+    /// The connector could not be reached or the exchange broke off.
+    ConnectorTransport,
+    /// The connector answered, but the engine refused or failed the request.
+    ConnectorEngine,
+    /// Control plane requests, token exchange and service resolution.
+    ControlPlane,
+    /// Another module's service failed the call or did not answer.
+    PeerService,
+    /// Environment, configuration or input rejected before any call was made.
+    LocalConfig,
+}
+
+impl FailureDomain {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailureDomain::ConnectorTransport => "connector-transport",
+            FailureDomain::ConnectorEngine => "connector-engine",
+            FailureDomain::ControlPlane => "control-plane",
+            FailureDomain::PeerService => "peer-service",
+            FailureDomain::LocalConfig => "local-config",
+        }
+    }
+}
+
+impl fmt::Display for FailureDomain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 impl ModuleKitError {
+    pub fn failure_domain(&self) -> FailureDomain {
+        use ModuleKitError::*;
+        match self {
+            ConnectorIo(_)
+            | ConnectorRequestFailed { .. }
+            | QueryKilled(_)
+            | QueryCancelled(_)
+            | CircuitOpen { .. }
+            | Serialization(_)
+            | WireFormat(_) => FailureDomain::ConnectorTransport,
+            ConnectorRejected(_)
+            | ConnectorFailed(_)
+            | UnsupportedEngineFeature { .. }
+            | SchemaDrift(_)
+            | ContractViolation(_)
+            | InvalidCell { .. }
+            | MigrationLocked(_) => FailureDomain::ConnectorEngine,
+            #[cfg(feature = "reqwest")]
+            Http(_) => FailureDomain::ControlPlane,
+            Transport(_)
+            | TransportConnect(_)
+            | TransportTimeout(_)
+            | ControlPlaneStatus { .. }
+            | ControlPlaneOrigin(_)
+            | TokenExchange { .. }
+            | InvalidToken(_)
+            | TokenSource(_)
+            | ScopesNotGranted(_)
+            | ServiceNotFound(_) => FailureDomain::ControlPlane,
+            RpcFailed { .. } => FailureDomain::PeerService,
+            MissingEnv(_)
+            | InvalidEnv { .. }
+            | InvalidEnvValue { .. }
+            | InvalidEnvironment(_)
+            | EnvReport(_)
+            | InvalidConnectorUri(_)
+            | UnknownEngine { .. }
+            | ControlPlaneUrl(_)
+            | ControlPlaneMissing
+            | InvalidSchemaName(_)
+            | InvalidFixture(_)
+            | InvalidIdentifier(_)
+            | InvalidCursor(_)
+            | InvalidSqlTemplate(_)
+            | InvalidSqlStatement(_)
+            | InvalidServiceUrl { .. }
+            | InvalidCapabilities(_)
+            | InvalidServiceDescriptor(_)
+            | InvalidManifest(_)
+            | InvalidTrigger(_)
+            | InvalidTenantPolicy(_)
+            | TenantMismatch { .. }
+            | InvalidMigration(_)
+            | Forbidden { .. }
+            | InvalidSecretName(_)
+            | InvalidDataKey(_)
+            | ExportFailed(_)
+            | AccessPolicyViolation(_)
+            | ReadOnlyViolation(_)
+            | Tls(_) => FailureDomain::LocalConfig,
+            NotReady { domain, .. } => *domain,
+        }
+    }
+
     pub fn invalid_env(name: &'static str, source: std::env::VarError) -> Self {
         Self::InvalidEnv { name, source }
     }
@@ -41,4 +284,18 @@ impl ModuleKitError {
     pub fn invalid_env_value(name: &'static str, message: String) -> Self {
         Self::InvalidEnvValue { name, message }
     }
+
+    pub fn token_exchange(status: Option<u16>, message: impl Into<String>) -> Self {
+        Self::TokenExchange {
+            status,
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_cell(column: &str, message: String) -> Self {
+        Self::InvalidCell {
+            column: column.to_string(),
+            message,
+        }
+    }
 }
@@ -0,0 +1,429 @@
+use std::cell::Cell;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use serde_json::{Map, Value as JsonValue};
+
+use crate::connector::DbConnectorClient;
+use crate::connector_request::{DbConnectorCommand, DbConnectorIntent};
+use crate::error::ModuleKitError;
+use crate::pagination::Paginator;
+use crate::sql::Select;
+use crate::values::DbRow;
+
+const DEFAULT_CHUNK_ROWS: usize = 1_000;
+
+/// Destination for exported data, written chunk by chunk.
+pub trait ExportSink: Send {
+    fn write_chunk(&mut self, index: usize, bytes: &[u8]) -> Result<(), ModuleKitError>;
+
+    /// Finalizes the export and returns a download link when the sink provides one.
+    fn finish(&mut self) -> Result<Option<String>, ModuleKitError>;
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    #[default]
+    Jsonl,
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Jsonl => "application/x-ndjson",
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExportProgress {
+    pub rows_written: u64,
+    pub chunks_written: usize,
+    pub finished: bool,
+    pub link: Option<String>,
+    pub error: Option<String>,
+}
+
+type ProgressCallback = Box<dyn Fn(&ExportProgress) + Send>;
+
+/// What an export reads.
+enum ExportSource {
+    /// Run once; the whole reply is held in memory while it is serialized.
+    Statement(DbConnectorCommand),
+    /// Fetched one chunk per page through a `Paginator`, keyset-paged when
+    /// `key` is set and offset-paged otherwise.
+    Pages {
+        select: Select,
+        key: Option<String>,
+        integer_key: bool,
+    },
+}
+
+pub struct ExportJobBuilder {
+    source: ExportSource,
+    engine: Option<String>,
+    format: ExportFormat,
+    chunk_rows: usize,
+    on_progress: Option<ProgressCallback>,
+}
+
+impl ExportJobBuilder {
+    pub fn output_format(&self) -> ExportFormat {
+        self.format
+    }
+
+    pub fn engine(mut self, value: impl Into<String>) -> Self {
+        self.engine = Some(value.into());
+        self
+    }
+
+    pub fn format(mut self, value: ExportFormat) -> Self {
+        self.format = value;
+        self
+    }
+
+    /// Rows per chunk, and per page for `ExportJob::select` exports.
+    pub fn chunk_rows(mut self, value: usize) -> Self {
+        self.chunk_rows = value.max(1);
+        self
+    }
+
+    /// `ExportJob::select` exports only: pages by the unique, non-null
+    /// `column` instead of by offset, so rows inserted during the export do
+    /// not shift later pages.
+    pub fn keyset(mut self, column: impl Into<String>) -> Self {
+        if let ExportSource::Pages { key, .. } = &mut self.source {
+            *key = Some(column.into());
+        }
+        self
+    }
+
+    /// With `keyset`: compares the key column as an integer instead of as
+    /// text.
+    pub fn integer_key(mut self) -> Self {
+        if let ExportSource::Pages { integer_key, .. } = &mut self.source {
+            *integer_key = true;
+        }
+        self
+    }
+
+    /// Called after every chunk and once more when the job completes.
+    pub fn on_progress(mut self, callback: impl Fn(&ExportProgress) + Send + 'static) -> Self {
+        self.on_progress = Some(Box::new(callback));
+        self
+    }
+
+    /// Runs the query on a background thread, writing results to `sink`.
+    pub fn start(
+        self,
+        client: Arc<DbConnectorClient>,
+        mut sink: impl ExportSink + 'static,
+    ) -> ExportJob {
+        let progress = Arc::new(Mutex::new(ExportProgress::default()));
+        let thread_progress = Arc::clone(&progress);
+        let handle = thread::spawn(move || {
+            let result = self.run(&client, &mut sink, &thread_progress);
+            let mut guard = thread_progress.lock().unwrap();
+            guard.finished = true;
+            match &result {
+                Ok(link) => guard.link = link.clone(),
+                Err(err) => guard.error = Some(err.to_string()),
+            }
+            if let Some(callback) = &self.on_progress {
+                callback(&guard);
+            }
+            result
+        });
+        ExportJob {
+            progress,
+            thread: Some(handle),
+        }
+    }
+
+    fn run(
+        &self,
+        client: &DbConnectorClient,
+        sink: &mut dyn ExportSink,
+        progress: &Mutex<ExportProgress>,
+    ) -> Result<Option<String>, ModuleKitError> {
+        let command = match &self.source {
+            ExportSource::Statement(command) => command.clone(),
+            ExportSource::Pages {
+                select,
+                key,
+                integer_key,
+            } => {
+                let key = key.as_deref().map(|column| (column, *integer_key));
+                return self.run_pages(client, select, key, sink, progress);
+            }
+        };
+        let response = client
+            .execute(
+                command,
+                DbConnectorIntent::Read,
+                self.engine.as_deref(),
+                None,
+            )?
+            .into_result()?;
+        let mut chunk = Vec::new();
+        let mut chunk_len = 0;
+        for result in response.results.iter().flatten() {
+            for (index, row) in result.rows().enumerate() {
+                self.write_row(&mut chunk, row, index == 0)?;
+                chunk_len += 1;
+                if chunk_len == self.chunk_rows {
+                    self.flush(sink, progress, &mut chunk, &mut chunk_len)?;
+                }
+            }
+        }
+        if chunk_len > 0 || !chunk.is_empty() {
+            self.flush(sink, progress, &mut chunk, &mut chunk_len)?;
+        }
+        sink.finish()
+    }
+
+    /// Fetches and writes one page at a time, so at most one chunk of rows
+    /// is held in memory.
+    fn run_pages(
+        &self,
+        client: &DbConnectorClient,
+        select: &Select,
+        key: Option<(&str, bool)>,
+        sink: &mut dyn ExportSink,
+        progress: &Mutex<ExportProgress>,
+    ) -> Result<Option<String>, ModuleKitError> {
+        let first_row = Cell::new(true);
+        let serialize = |row: DbRow<'_>| {
+            let mut line = Vec::new();
+            self.write_row(&mut line, row, first_row.replace(false))?;
+            Ok(line)
+        };
+        let mut pages = match key {
+            Some((column, false)) => Paginator::keyset(client, select.clone(), column, serialize),
+            Some((column, true)) => {
+                Paginator::keyset(client, select.clone(), column, serialize).integer_key()
+            }
+            None => Paginator::offset(client, select.clone(), serialize),
+        }
+        .page_size(u32::try_from(self.chunk_rows).unwrap_or(u32::MAX));
+        if let Some(engine) = &self.engine {
+            pages = pages.engine(engine.clone());
+        }
+        while pages.has_more() {
+            let page = pages.next_page()?;
+            let mut chunk_len = page.items.len();
+            let mut chunk = page.items.concat();
+            if chunk_len > 0 {
+                self.flush(sink, progress, &mut chunk, &mut chunk_len)?;
+            }
+        }
+        sink.finish()
+    }
+
+    /// Appends `row` to `out`, preceded by the CSV header line when `first`.
+    fn write_row(
+        &self,
+        out: &mut Vec<u8>,
+        row: DbRow<'_>,
+        first: bool,
+    ) -> Result<(), ModuleKitError> {
+        match self.format {
+            ExportFormat::Csv => {
+                if first {
+                    write_csv_line(out, row.columns().iter().map(String::as_str));
+                }
+                write_csv_line(out, row.values().iter().map(String::as_str));
+            }
+            ExportFormat::Jsonl => {
+                let object: Map<String, JsonValue> = row
+                    .columns()
+                    .iter()
+                    .zip(row.values())
+                    .map(|(column, value)| (column.clone(), JsonValue::String(value.clone())))
+                    .collect();
+                serde_json::to_writer(&mut *out, &object)?;
+                out.push(b'\n');
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(
+        &self,
+        sink: &mut dyn ExportSink,
+        progress: &Mutex<ExportProgress>,
+        chunk: &mut Vec<u8>,
+        chunk_len: &mut usize,
+    ) -> Result<(), ModuleKitError> {
+        let index = progress.lock().unwrap().chunks_written;
+        sink.write_chunk(index, chunk)?;
+        let mut guard = progress.lock().unwrap();
+        guard.chunks_written += 1;
+        guard.rows_written += *chunk_len as u64;
+        if let Some(callback) = &self.on_progress {
+            callback(&guard);
+        }
+        chunk.clear();
+        *chunk_len = 0;
+        Ok(())
+    }
+}
+
+/// Background export of a query result into an `ExportSink`.
+pub struct ExportJob {
+    progress: Arc<Mutex<ExportProgress>>,
+    thread: Option<thread::JoinHandle<Result<Option<String>, ModuleKitError>>>,
+}
+
+impl ExportJob {
+    /// Export of a single statement. Its whole reply is loaded before being
+    /// written out chunk by chunk; use `select` to bound memory on large
+    /// exports.
+    pub fn builder(command: DbConnectorCommand) -> ExportJobBuilder {
+        Self::from_source(ExportSource::Statement(command))
+    }
+
+    /// Export of `select`, fetched one page of `chunk_rows` rows at a time.
+    ///
+    /// Pages by offset unless `ExportJobBuilder::keyset` names a key column;
+    /// offset paging needs an `ORDER BY` on `select` for a stable order.
+    pub fn select(select: Select) -> ExportJobBuilder {
+        Self::from_source(ExportSource::Pages {
+            select,
+            key: None,
+            integer_key: false,
+        })
+    }
+
+    fn from_source(source: ExportSource) -> ExportJobBuilder {
+        ExportJobBuilder {
+            source,
+            engine: None,
+            format: ExportFormat::default(),
+            chunk_rows: DEFAULT_CHUNK_ROWS,
+            on_progress: None,
+        }
+    }
+
+    pub fn progress(&self) -> ExportProgress {
+        self.progress.lock().unwrap().clone()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.progress.lock().unwrap().finished
+    }
+
+    /// Blocks until the export completes and returns the sink's link.
+    pub fn wait(mut self) -> Result<Option<String>, ModuleKitError> {
+        match self.thread.take().map(|handle| handle.join()) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => Err(ModuleKitError::ExportFailed(
+                "export thread panicked".into(),
+            )),
+            None => Err(ModuleKitError::ExportFailed(
+                "export already awaited".into(),
+            )),
+        }
+    }
+}
+
+fn write_csv_line<'a>(out: &mut Vec<u8>, fields: impl Iterator<Item = &'a str>) {
+    for (index, field) in fields.enumerate() {
+        if index > 0 {
+            out.push(b',');
+        }
+        if field.contains([',', '"', '\n', '\r']) {
+            out.push(b'"');
+            out.extend_from_slice(field.replace('"', "\"\"").as_bytes());
+            out.push(b'"');
+        } else {
+            out.extend_from_slice(field.as_bytes());
+        }
+    }
+    out.push(b'\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    use super::*;
+    use crate::connector_endpoint::ConnectorEndpoint;
+    use crate::env::ModuleEnvironment;
+
+    /// Serves `rows` ids from an `items` table, honouring LIMIT and OFFSET.
+    fn paging_connector(rows: u64) -> (ConnectorEndpoint, Arc<Mutex<Vec<u64>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let offsets = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&offsets);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request = String::new();
+                stream.read_to_string(&mut request).unwrap();
+                let number_after = |keyword: &str| -> u64 {
+                    let start = request.find(keyword).unwrap() + keyword.len();
+                    let digits: String = request[start..]
+                        .chars()
+                        .take_while(char::is_ascii_digit)
+                        .collect();
+                    digits.parse().unwrap()
+                };
+                let (limit, offset) = (number_after("LIMIT "), number_after("OFFSET "));
+                seen.lock().unwrap().push(offset);
+                let page: Vec<_> = (offset + 1..=rows.min(offset + limit))
+                    .map(|id| vec![id.to_string()])
+                    .collect();
+                let reply = serde_json::json!({
+                    "ok": true,
+                    "results": [{ "type": "result_set", "columns": ["id"], "rows": page }],
+                });
+                stream.write_all(reply.to_string().as_bytes()).unwrap();
+            }
+        });
+        (ConnectorEndpoint::Tcp { addr }, offsets)
+    }
+
+    #[derive(Clone, Default)]
+    struct MemorySink(Arc<Mutex<Vec<Vec<u8>>>>);
+
+    impl ExportSink for MemorySink {
+        fn write_chunk(&mut self, _index: usize, bytes: &[u8]) -> Result<(), ModuleKitError> {
+            self.0.lock().unwrap().push(bytes.to_vec());
+            Ok(())
+        }
+
+        fn finish(&mut self) -> Result<Option<String>, ModuleKitError> {
+            Ok(None)
+        }
+    }
+
+    #[test]
+    fn select_exports_fetch_one_page_per_chunk() {
+        let (connector, offsets) = paging_connector(5);
+        let env = ModuleEnvironment::builder()
+            .module_id("module")
+            .service_id("service")
+            .service_token("token")
+            .connector(connector)
+            .build()
+            .unwrap();
+        let client = Arc::new(DbConnectorClient::from_environment(env).unwrap());
+        let sink = MemorySink::default();
+        let select = Select::from("items").columns(["id"]).order_by("id");
+        let job = ExportJob::select(select)
+            .format(ExportFormat::Csv)
+            .chunk_rows(2)
+            .start(client, sink.clone());
+
+        job.wait().unwrap();
+        let chunks = sink.0.lock().unwrap();
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks.concat(), b"id\n1\n2\n3\n4\n5\n");
+        assert_eq!(*offsets.lock().unwrap(), vec![0, 2, 4]);
+    }
+}
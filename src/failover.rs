@@ -0,0 +1,123 @@
+use std::time::{Duration, Instant};
+
+use crate::connector_endpoint::ConnectorEndpoint;
+
+/// How long an endpoint that refused a connection is tried only after the
+/// healthy ones.
+const DOWN_COOLDOWN: Duration = Duration::from_secs(5);
+
+/// Reachability of one connector endpoint, as last observed by the client.
+#[derive(Debug, Clone)]
+pub struct EndpointHealth {
+    pub endpoint: ConnectorEndpoint,
+    pub healthy: bool,
+    /// Requests currently go to this endpoint.
+    pub active: bool,
+    /// Connection failures since it last accepted one.
+    pub consecutive_failures: u32,
+}
+
+/// Which configured connector served a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EndpointRole {
+    Primary,
+    ReadReplica,
+}
+
+struct PooledEndpoint {
+    endpoint: ConnectorEndpoint,
+    failures: u32,
+    down_until: Option<Instant>,
+}
+
+impl PooledEndpoint {
+    fn new(endpoint: ConnectorEndpoint) -> Self {
+        Self {
+            endpoint,
+            failures: 0,
+            down_until: None,
+        }
+    }
+
+    fn is_healthy(&self, now: Instant) -> bool {
+        self.down_until.is_none_or(|until| until <= now)
+    }
+}
+
+/// Primary connector plus fallbacks, in preference order.
+///
+/// The primary is retried after its cooldown, so requests move back to it
+/// once it accepts connections again.
+pub(crate) struct EndpointPool {
+    endpoints: Vec<PooledEndpoint>,
+    active: usize,
+}
+
+impl EndpointPool {
+    pub(crate) fn new(primary: ConnectorEndpoint, fallbacks: Vec<ConnectorEndpoint>) -> Self {
+        Self {
+            endpoints: std::iter::once(primary)
+                .chain(fallbacks)
+                .map(PooledEndpoint::new)
+                .collect(),
+            active: 0,
+        }
+    }
+
+    pub(crate) fn active(&self) -> &ConnectorEndpoint {
+        &self.endpoints[self.active].endpoint
+    }
+
+    /// Endpoints to try with their index: healthy ones in preference order,
+    /// then, with `include_down`, those cooling down, so a request is
+    /// attempted even if all are marked down.
+    pub(crate) fn candidates(&self, include_down: bool) -> Vec<(usize, ConnectorEndpoint)> {
+        let now = Instant::now();
+        let (healthy, down): (Vec<_>, Vec<_>) = self
+            .endpoints
+            .iter()
+            .enumerate()
+            .partition(|(_, pooled)| pooled.is_healthy(now));
+        let down = if include_down { down } else { Vec::new() };
+        healthy
+            .into_iter()
+            .chain(down)
+            .map(|(index, pooled)| (index, pooled.endpoint.clone()))
+            .collect()
+    }
+
+    pub(crate) fn mark_up(&mut self, index: usize) {
+        if let Some(pooled) = self.endpoints.get_mut(index) {
+            pooled.failures = 0;
+            pooled.down_until = None;
+            self.active = index;
+        }
+    }
+
+    pub(crate) fn mark_down(&mut self, index: usize) {
+        if let Some(pooled) = self.endpoints.get_mut(index) {
+            pooled.failures = pooled.failures.saturating_add(1);
+            pooled.down_until = Some(Instant::now() + DOWN_COOLDOWN);
+        }
+    }
+
+    /// Replaces the active endpoint with the one a relocating connector
+    /// advertised.
+    pub(crate) fn relocate(&mut self, endpoint: ConnectorEndpoint) {
+        self.endpoints[self.active] = PooledEndpoint::new(endpoint);
+    }
+
+    pub(crate) fn health(&self) -> Vec<EndpointHealth> {
+        let now = Instant::now();
+        self.endpoints
+            .iter()
+            .enumerate()
+            .map(|(index, pooled)| EndpointHealth {
+                endpoint: pooled.endpoint.clone(),
+                healthy: pooled.is_healthy(now),
+                active: index == self.active,
+                consecutive_failures: pooled.failures,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,242 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::control_plane::ControlPlaneClient;
+use crate::error::ModuleKitError;
+use crate::token_provider::ServiceTokenProvider;
+
+const FLAGS_PATH: &str = "modules/runtime/flags";
+const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Who a flag is evaluated for.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FlagContext {
+    pub tenant: Option<String>,
+    pub user: Option<String>,
+    pub attributes: BTreeMap<String, String>,
+}
+
+impl FlagContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn tenant(mut self, value: impl Into<String>) -> Self {
+        self.tenant = Some(value.into());
+        self
+    }
+
+    pub fn user(mut self, value: impl Into<String>) -> Self {
+        self.user = Some(value.into());
+        self
+    }
+
+    pub fn attribute(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.attributes.insert(key.into(), value.into());
+        self
+    }
+}
+
+/// Targeting rule for one flag. Tenant and user allow-lists win over the rollout percentage.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlagRule {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub tenants: Vec<String>,
+    #[serde(default)]
+    pub users: Vec<String>,
+    /// Share of users (or tenants when no user is known) that see the flag, 0-100.
+    #[serde(default)]
+    pub rollout_percent: Option<u8>,
+}
+
+impl FlagRule {
+    pub fn evaluate(&self, flag: &str, context: &FlagContext) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        if context
+            .tenant
+            .as_ref()
+            .is_some_and(|t| self.tenants.contains(t))
+            || context
+                .user
+                .as_ref()
+                .is_some_and(|u| self.users.contains(u))
+        {
+            return true;
+        }
+        let targeted = !self.tenants.is_empty() || !self.users.is_empty();
+        match self.rollout_percent {
+            Some(percent) => context
+                .user
+                .as_ref()
+                .or(context.tenant.as_ref())
+                .is_some_and(|key| rollout_bucket(flag, key) < u32::from(percent.min(100))),
+            None => !targeted,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct FlagDocument {
+    #[serde(default)]
+    flags: HashMap<String, FlagRule>,
+}
+
+/// Source of flag decisions.
+pub trait FeatureFlagProvider: Send + Sync {
+    fn is_enabled(&self, flag: &str, context: &FlagContext) -> Result<bool, ModuleKitError>;
+}
+
+/// In-memory flags for tests and local runs.
+#[derive(Debug, Default)]
+pub struct StaticFlagProvider {
+    rules: Mutex<HashMap<String, FlagRule>>,
+}
+
+impl StaticFlagProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_flag(self, flag: impl Into<String>, enabled: bool) -> Self {
+        self.set(flag, enabled);
+        self
+    }
+
+    pub fn set(&self, flag: impl Into<String>, enabled: bool) {
+        self.set_rule(
+            flag,
+            FlagRule {
+                enabled,
+                ..FlagRule::default()
+            },
+        );
+    }
+
+    pub fn set_rule(&self, flag: impl Into<String>, rule: FlagRule) {
+        self.rules.lock().unwrap().insert(flag.into(), rule);
+    }
+}
+
+impl FeatureFlagProvider for StaticFlagProvider {
+    fn is_enabled(&self, flag: &str, context: &FlagContext) -> Result<bool, ModuleKitError> {
+        Ok(self
+            .rules
+            .lock()
+            .unwrap()
+            .get(flag)
+            .is_some_and(|rule| rule.evaluate(flag, context)))
+    }
+}
+
+/// Flag rules fetched from the control plane and re-fetched once `refresh_interval` has passed.
+pub struct ControlPlaneFlagProvider {
+    control_plane: ControlPlaneClient,
+    tokens: Arc<ServiceTokenProvider>,
+    refresh_interval: Duration,
+    cache: Mutex<Option<(Instant, Arc<FlagDocument>)>>,
+}
+
+impl ControlPlaneFlagProvider {
+    pub fn new(control_plane: ControlPlaneClient, tokens: Arc<ServiceTokenProvider>) -> Self {
+        Self {
+            control_plane,
+            tokens,
+            refresh_interval: DEFAULT_REFRESH_INTERVAL,
+            cache: Mutex::new(None),
+        }
+    }
+
+    pub fn refresh_interval(mut self, value: Duration) -> Self {
+        self.refresh_interval = value;
+        self
+    }
+
+    /// Fetches the flag rules now, regardless of the cache age.
+    pub fn refresh(&self) -> Result<(), ModuleKitError> {
+        self.fetch().map(|_| ())
+    }
+
+    fn fetch(&self) -> Result<Arc<FlagDocument>, ModuleKitError> {
+        let bearer = self.tokens.current_token()?;
+        let document = Arc::new(
+            self.control_plane
+                .get_json::<FlagDocument>(bearer.expose(), FLAGS_PATH)?,
+        );
+        *self.cache.lock().unwrap() = Some((Instant::now(), Arc::clone(&document)));
+        Ok(document)
+    }
+
+    fn document(&self) -> Result<Arc<FlagDocument>, ModuleKitError> {
+        let cached = self.cache.lock().unwrap().clone();
+        match cached {
+            Some((fetched_at, document)) if fetched_at.elapsed() < self.refresh_interval => {
+                Ok(document)
+            }
+            stale => match self.fetch() {
+                Ok(document) => Ok(document),
+                // Keep serving the last known rules while the control plane is unreachable.
+                Err(err) => stale.map(|(_, document)| document).ok_or(err),
+            },
+        }
+    }
+}
+
+impl FeatureFlagProvider for ControlPlaneFlagProvider {
+    fn is_enabled(&self, flag: &str, context: &FlagContext) -> Result<bool, ModuleKitError> {
+        Ok(self
+            .document()?
+            .flags
+            .get(flag)
+            .is_some_and(|rule| rule.evaluate(flag, context)))
+    }
+}
+
+/// Evaluates feature flags; unknown flags and provider failures read as disabled.
+#[derive(Clone)]
+pub struct FeatureFlags {
+    provider: Arc<dyn FeatureFlagProvider>,
+}
+
+impl FeatureFlags {
+    pub fn new(provider: impl FeatureFlagProvider + 'static) -> Self {
+        Self {
+            provider: Arc::new(provider),
+        }
+    }
+
+    pub fn from_control_plane(
+        control_plane: ControlPlaneClient,
+        tokens: Arc<ServiceTokenProvider>,
+    ) -> Self {
+        Self::new(ControlPlaneFlagProvider::new(control_plane, tokens))
+    }
+
+    pub fn is_enabled(&self, flag: &str, context: &FlagContext) -> bool {
+        self.try_is_enabled(flag, context).unwrap_or(false)
+    }
+
+    pub fn try_is_enabled(
+        &self,
+        flag: &str,
+        context: &FlagContext,
+    ) -> Result<bool, ModuleKitError> {
+        self.provider.is_enabled(flag, context)
+    }
+}
+
+/// Stable 0-99 bucket so a subject keeps its rollout decision across processes.
+fn rollout_bucket(flag: &str, key: &str) -> u32 {
+    let mut hash: u32 = 0x811c_9dc5;
+    for byte in flag.bytes().chain([b':']).chain(key.bytes()) {
+        hash ^= u32::from(byte);
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    hash % 100
+}
@@ -0,0 +1,351 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::connector::DbConnectorClient;
+use crate::control_plane::ControlPlane;
+use crate::error::ModuleKitError;
+use crate::service_endpoint::{EndpointResponse, ServiceEndpoint};
+
+const HEALTH_IO_TIMEOUT: Duration = Duration::from_secs(2);
+/// Connections served at once; further probes are closed unanswered.
+const HEALTH_MAX_CONNECTIONS: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    Healthy,
+    Degraded,
+    Unhealthy,
+}
+
+/// How a failing check affects overall readiness.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HealthCheckMode {
+    /// Report the dependency as degraded but keep serving.
+    Warn,
+    #[default]
+    Fail,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthCheckResult {
+    pub name: String,
+    pub status: HealthStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// Round-trip time of dependency pings.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+}
+
+impl HealthCheckResult {
+    fn from_outcome(name: &str, mode: HealthCheckMode, outcome: Result<(), String>) -> Self {
+        let (status, detail) = match outcome {
+            Ok(()) => (HealthStatus::Healthy, None),
+            Err(detail) if mode == HealthCheckMode::Warn => (HealthStatus::Degraded, Some(detail)),
+            Err(detail) => (HealthStatus::Unhealthy, Some(detail)),
+        };
+        Self {
+            name: name.to_string(),
+            status,
+            detail,
+            latency_ms: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    pub checks: Vec<HealthCheckResult>,
+}
+
+impl HealthReport {
+    /// Report whose overall status is the worst individual status.
+    pub fn from_checks(checks: Vec<HealthCheckResult>) -> Self {
+        Self {
+            status: checks
+                .iter()
+                .map(|check| check.status)
+                .max()
+                .unwrap_or(HealthStatus::Healthy),
+            checks,
+        }
+    }
+}
+
+type HealthCheckFn = Box<dyn Fn() -> Result<(), String> + Send + Sync>;
+
+/// Named readiness checks evaluated together by `/readyz`.
+#[derive(Default)]
+pub struct HealthRegistry {
+    checks: Mutex<Vec<(String, HealthCheckMode, Arc<HealthCheckFn>)>>,
+}
+
+impl HealthRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a check; an `Err` message becomes the check's detail.
+    pub fn register(
+        &self,
+        name: impl Into<String>,
+        mode: HealthCheckMode,
+        check: impl Fn() -> Result<(), String> + Send + Sync + 'static,
+    ) {
+        let check: HealthCheckFn = Box::new(check);
+        self.checks
+            .lock()
+            .unwrap()
+            .push((name.into(), mode, Arc::new(check)));
+    }
+
+    /// Runs every check; the overall status is the worst individual status.
+    pub fn report(&self) -> HealthReport {
+        let checks = self.checks.lock().unwrap().clone();
+        HealthReport::from_checks(
+            checks
+                .iter()
+                .map(|(name, mode, check)| HealthCheckResult::from_outcome(name, *mode, check()))
+                .collect(),
+        )
+    }
+}
+
+/// Pings the connector and control plane a module depends on, so readiness
+/// probes do not need to issue queries of their own.
+///
+/// A failing connector makes the module unhealthy by default; a failing
+/// control plane only degrades it, since cached tokens keep it serving.
+#[derive(Clone)]
+pub struct ModuleHealth {
+    connector: Option<Arc<DbConnectorClient>>,
+    engine: Option<String>,
+    control_plane: Option<Arc<dyn ControlPlane>>,
+    connector_mode: HealthCheckMode,
+    control_plane_mode: HealthCheckMode,
+}
+
+impl Default for ModuleHealth {
+    fn default() -> Self {
+        Self {
+            connector: None,
+            engine: None,
+            control_plane: None,
+            connector_mode: HealthCheckMode::Fail,
+            control_plane_mode: HealthCheckMode::Warn,
+        }
+    }
+}
+
+impl ModuleHealth {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn connector(mut self, client: Arc<DbConnectorClient>) -> Self {
+        self.connector = Some(client);
+        self
+    }
+
+    /// Engine the connector ping is routed to.
+    pub fn engine(mut self, engine: impl Into<String>) -> Self {
+        self.engine = Some(engine.into());
+        self
+    }
+
+    pub fn control_plane(mut self, client: impl ControlPlane + 'static) -> Self {
+        self.control_plane = Some(Arc::new(client));
+        self
+    }
+
+    pub fn connector_mode(mut self, mode: HealthCheckMode) -> Self {
+        self.connector_mode = mode;
+        self
+    }
+
+    pub fn control_plane_mode(mut self, mode: HealthCheckMode) -> Self {
+        self.control_plane_mode = mode;
+        self
+    }
+
+    /// Checks every configured dependency, named `connector` and `control_plane`.
+    pub fn check(&self) -> HealthReport {
+        let mut checks = Vec::new();
+        if let Some(client) = &self.connector {
+            checks.push(self.check_connector(client));
+        }
+        if let Some(control_plane) = &self.control_plane {
+            checks.push(self.check_control_plane(control_plane.as_ref()));
+        }
+        HealthReport::from_checks(checks)
+    }
+
+    /// Adds the dependency checks to `registry` so `/readyz` reports them.
+    pub fn register(&self, registry: &HealthRegistry) {
+        if let Some(client) = self.connector.clone() {
+            let health = self.clone();
+            registry.register("connector", self.connector_mode, move || {
+                health.check_connector(&client).detail.map_or(Ok(()), Err)
+            });
+        }
+        if let Some(control_plane) = self.control_plane.clone() {
+            let health = self.clone();
+            registry.register("control_plane", self.control_plane_mode, move || {
+                health
+                    .check_control_plane(control_plane.as_ref())
+                    .detail
+                    .map_or(Ok(()), Err)
+            });
+        }
+    }
+
+    fn check_connector(&self, client: &DbConnectorClient) -> HealthCheckResult {
+        let outcome = client
+            .ping(self.engine.as_deref())
+            .map_err(|err| err.to_string());
+        dependency_result("connector", self.connector_mode, outcome)
+    }
+
+    fn check_control_plane(&self, control_plane: &dyn ControlPlane) -> HealthCheckResult {
+        let started = Instant::now();
+        let outcome = control_plane
+            .health()
+            .map(|()| started.elapsed())
+            .map_err(|err| err.to_string());
+        dependency_result("control_plane", self.control_plane_mode, outcome)
+    }
+}
+
+fn dependency_result(
+    name: &str,
+    mode: HealthCheckMode,
+    outcome: Result<Duration, String>,
+) -> HealthCheckResult {
+    let latency_ms = outcome
+        .as_ref()
+        .ok()
+        .map(|latency| latency.as_millis() as u64);
+    let mut result = HealthCheckResult::from_outcome(name, mode, outcome.map(|_| ()));
+    result.latency_ms = latency_ms;
+    result
+}
+
+/// Minimal HTTP responder for `GET /healthz` (liveness, always 200) and
+/// `GET /readyz` (the registry report, 503 when unhealthy), backed by a
+/// `ServiceEndpoint`.
+///
+/// Stops listening when dropped.
+pub struct HealthServer {
+    addr: SocketAddr,
+    shutdown: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl HealthServer {
+    pub fn start(addr: impl ToSocketAddrs) -> Result<Self, ModuleKitError> {
+        Self::start_with_registry(addr, Arc::new(HealthRegistry::new()))
+    }
+
+    pub fn start_with_registry(
+        addr: impl ToSocketAddrs,
+        registry: Arc<HealthRegistry>,
+    ) -> Result<Self, ModuleKitError> {
+        Self::start_with_endpoint(addr, ServiceEndpoint::new(registry))
+    }
+
+    /// Serves everything `endpoint` handles, including `/.fenrir/services`
+    /// when it has services set.
+    pub fn start_with_endpoint(
+        addr: impl ToSocketAddrs,
+        endpoint: ServiceEndpoint,
+    ) -> Result<Self, ModuleKitError> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let thread_shutdown = Arc::clone(&shutdown);
+        let handle = thread::spawn(move || {
+            let active = Arc::new(AtomicUsize::new(0));
+            for stream in listener.incoming() {
+                if thread_shutdown.load(Ordering::SeqCst) {
+                    break;
+                }
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                // Readiness checks can take seconds; serve each probe on its
+                // own thread so a slow one does not hold up the accept loop.
+                if active.fetch_add(1, Ordering::SeqCst) >= HEALTH_MAX_CONNECTIONS {
+                    active.fetch_sub(1, Ordering::SeqCst);
+                    continue;
+                }
+                let endpoint = endpoint.clone();
+                let active = Arc::clone(&active);
+                thread::spawn(move || {
+                    let _ = respond(stream, &endpoint);
+                    active.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+        Ok(Self {
+            addr,
+            shutdown,
+            thread: Some(handle),
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for HealthServer {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        let mut wake = self.addr;
+        if wake.ip().is_unspecified() {
+            wake.set_ip(match wake.ip() {
+                IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+                IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+            });
+        }
+        // Unblocks `accept` so the thread observes the shutdown flag.
+        let _ = TcpStream::connect_timeout(&wake, HEALTH_IO_TIMEOUT);
+        if let Some(handle) = self.thread.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn respond(mut stream: TcpStream, endpoint: &ServiceEndpoint) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(HEALTH_IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(HEALTH_IO_TIMEOUT))?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("GET");
+    let path = parts.next().unwrap_or("");
+    let response = endpoint
+        .respond(method, path)
+        .unwrap_or_else(|| EndpointResponse {
+            status: 404,
+            body: r#"{"status":"not_found"}"#.to_string(),
+        });
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status_line(),
+        EndpointResponse::CONTENT_TYPE,
+        response.body.len(),
+        response.body
+    )
+}
@@ -0,0 +1,556 @@
+#[cfg(unix)]
+use std::io::{Read, Write};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(feature = "reqwest")]
+use reqwest::blocking::Client as BlockingClient;
+#[cfg(feature = "reqwest")]
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use url::Url;
+
+use crate::error::ModuleKitError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpMethod {
+    Get,
+    Post,
+    Delete,
+}
+
+impl HttpMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HttpMethod::Get => "GET",
+            HttpMethod::Post => "POST",
+            HttpMethod::Delete => "DELETE",
+        }
+    }
+}
+
+/// Request handed to an `HttpTransport`.
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    pub method: HttpMethod,
+    pub url: Url,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<Vec<u8>>,
+    /// Overrides the transport's timeout for this request.
+    pub timeout: Option<Duration>,
+}
+
+impl HttpRequest {
+    pub fn new(method: HttpMethod, url: Url) -> Self {
+        Self {
+            method,
+            url,
+            headers: Vec::new(),
+            body: None,
+            timeout: None,
+        }
+    }
+
+    pub fn get(url: Url) -> Self {
+        Self::new(HttpMethod::Get, url)
+    }
+
+    pub fn post_json(url: Url, body: &impl serde::Serialize) -> Result<Self, ModuleKitError> {
+        Ok(Self::new(HttpMethod::Post, url).json_body(serde_json::to_vec(body)?))
+    }
+
+    pub fn delete(url: Url) -> Self {
+        Self::new(HttpMethod::Delete, url)
+    }
+
+    /// Sets an already serialized JSON body.
+    pub fn json_body(self, body: Vec<u8>) -> Self {
+        Self {
+            body: Some(body),
+            ..self.header("content-type", "application/json")
+        }
+    }
+
+    pub fn timeout(mut self, value: Duration) -> Self {
+        self.timeout = Some(value);
+        self
+    }
+
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn bearer_auth(self, token: &str) -> Self {
+        self.header("authorization", format!("Bearer {token}"))
+    }
+}
+
+/// Header carrying the platform's correlation ID.
+pub const REQUEST_ID_HEADER: &str = "x-fenrir-request-id";
+/// Header letting the receiver drop a repeated POST it already applied.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+/// Header carrying the calling module's version.
+pub const MODULE_VERSION_HEADER: &str = "x-fenrir-module-version";
+
+type HeaderInterceptor = Arc<dyn Fn(&mut Vec<(String, String)>) + Send + Sync>;
+
+/// Headers attached to every outbound request of a client.
+///
+/// Fixed headers are added as-is; interceptors run per request and can add
+/// values that change between calls, such as the current request ID.
+#[derive(Clone, Default)]
+pub struct OutboundHeaders {
+    fixed: Vec<(String, String)>,
+    interceptors: Vec<HeaderInterceptor>,
+}
+
+impl OutboundHeaders {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.fixed.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn module_version(self, version: impl Into<String>) -> Self {
+        self.header(MODULE_VERSION_HEADER, version)
+    }
+
+    /// Sends `X-Fenrir-Request-Id` with whatever `current` returns at call time.
+    pub fn request_id(self, current: impl Fn() -> Option<String> + Send + Sync + 'static) -> Self {
+        self.interceptor(move |headers| {
+            if let Some(id) = current() {
+                headers.push((REQUEST_ID_HEADER.to_string(), id));
+            }
+        })
+    }
+
+    pub fn interceptor(
+        mut self,
+        intercept: impl Fn(&mut Vec<(String, String)>) + Send + Sync + 'static,
+    ) -> Self {
+        self.interceptors.push(Arc::new(intercept));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fixed.is_empty() && self.interceptors.is_empty()
+    }
+
+    /// Headers for one request, skipping names the request already sets.
+    pub fn resolve(&self, existing: &[(String, String)]) -> Vec<(String, String)> {
+        let mut headers = self.fixed.clone();
+        for intercept in &self.interceptors {
+            intercept(&mut headers);
+        }
+        headers.retain(|(name, _)| {
+            !existing
+                .iter()
+                .any(|(present, _)| present.eq_ignore_ascii_case(name))
+        });
+        headers
+    }
+
+    pub(crate) fn apply(&self, request: &HttpRequest) -> HttpRequest {
+        let mut request = request.clone();
+        let extra = self.resolve(&request.headers);
+        request.headers.extend(extra);
+        request
+    }
+}
+
+/// Fully read response returned by an `HttpTransport`.
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// First value of header `name`, compared case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, ModuleKitError> {
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+}
+
+/// Refuses header names that are not HTTP tokens and values containing
+/// CR, LF or NUL, which would let a value inject headers or a second request
+/// into transports that write the request themselves.
+pub fn check_headers(headers: &[(String, String)]) -> Result<(), ModuleKitError> {
+    const SEPARATORS: &[u8] = b"!#$%&'*+-.^_`|~";
+    for (name, value) in headers {
+        let valid_name = !name.is_empty()
+            && name
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || SEPARATORS.contains(&b));
+        if !valid_name {
+            return Err(ModuleKitError::Transport(format!(
+                "invalid header name {name:?}"
+            )));
+        }
+        if value.bytes().any(|b| matches!(b, b'\r' | b'\n' | b'\0')) {
+            return Err(ModuleKitError::Transport(format!(
+                "header '{name}' has a line break in its value"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// HTTP stack used by `ControlPlaneClient`.
+///
+/// `send` fails only for transport problems; non-2xx statuses are returned as
+/// responses so the caller decides how to surface them.
+pub trait HttpTransport: Send + Sync {
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse, ModuleKitError>;
+}
+
+/// Transport backed by reqwest's blocking client.
+#[cfg(feature = "reqwest")]
+#[derive(Clone)]
+pub struct ReqwestTransport {
+    client: BlockingClient,
+}
+
+#[cfg(feature = "reqwest")]
+impl ReqwestTransport {
+    pub fn new(client: BlockingClient) -> Self {
+        Self { client }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl HttpTransport for ReqwestTransport {
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse, ModuleKitError> {
+        let method = match request.method {
+            HttpMethod::Get => Method::GET,
+            HttpMethod::Post => Method::POST,
+            HttpMethod::Delete => Method::DELETE,
+        };
+        let mut builder = self.client.request(method, request.url.clone());
+        for (name, value) in &request.headers {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+        if let Some(body) = &request.body {
+            builder = builder.body(body.clone());
+        }
+        if let Some(timeout) = request.timeout {
+            builder = builder.timeout(timeout);
+        }
+        let response = builder.send().map_err(reqwest_error)?;
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                value
+                    .to_str()
+                    .ok()
+                    .map(|value| (name.as_str().to_string(), value.to_string()))
+            })
+            .collect();
+        let body = response.bytes().map_err(reqwest_error)?.to_vec();
+        Ok(HttpResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+}
+
+#[cfg(feature = "reqwest")]
+fn reqwest_error(err: reqwest::Error) -> ModuleKitError {
+    if err.is_timeout() {
+        ModuleKitError::TransportTimeout(err.to_string())
+    } else if err.is_connect() {
+        ModuleKitError::TransportConnect(err.to_string())
+    } else {
+        ModuleKitError::Http(err)
+    }
+}
+
+/// HTTP/1.1 over a Unix domain socket, for control plane agents running as a
+/// local sidecar without a TCP listener.
+#[cfg(unix)]
+#[derive(Debug, Clone)]
+pub struct UnixSocketTransport {
+    path: PathBuf,
+    timeout: Duration,
+}
+
+#[cfg(unix)]
+impl UnixSocketTransport {
+    pub fn new(path: impl Into<PathBuf>, timeout: Duration) -> Self {
+        Self {
+            path: path.into(),
+            timeout,
+        }
+    }
+
+    fn exchange(&self, request: &HttpRequest) -> std::io::Result<Vec<u8>> {
+        let mut stream = UnixStream::connect(&self.path)?;
+        let timeout = request.timeout.unwrap_or(self.timeout);
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        let mut target = request.url.path().to_string();
+        if let Some(query) = request.url.query() {
+            target.push('?');
+            target.push_str(query);
+        }
+        let body = request.body.as_deref().unwrap_or_default();
+        let mut head = format!(
+            "{} {target} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: {}\r\n",
+            request.method.as_str(),
+            body.len()
+        );
+        for (name, value) in &request.headers {
+            head.push_str(&format!("{name}: {value}\r\n"));
+        }
+        head.push_str("\r\n");
+        stream.write_all(head.as_bytes())?;
+        stream.write_all(body)?;
+        let mut raw = Vec::new();
+        stream.read_to_end(&mut raw)?;
+        Ok(raw)
+    }
+}
+
+#[cfg(unix)]
+impl HttpTransport for UnixSocketTransport {
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse, ModuleKitError> {
+        check_headers(&request.headers)?;
+        let raw = self.exchange(request).map_err(|err| {
+            let message = format!("{}: {err}", self.path.display());
+            match err.kind() {
+                std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock => {
+                    ModuleKitError::TransportTimeout(message)
+                }
+                std::io::ErrorKind::NotFound | std::io::ErrorKind::ConnectionRefused => {
+                    ModuleKitError::TransportConnect(message)
+                }
+                _ => ModuleKitError::Transport(message),
+            }
+        })?;
+        parse_http_response(&raw)
+    }
+}
+
+#[cfg(unix)]
+fn parse_http_response(raw: &[u8]) -> Result<HttpResponse, ModuleKitError> {
+    let malformed = || ModuleKitError::Transport("malformed HTTP response".into());
+    let split = raw
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or_else(malformed)?;
+    let head = std::str::from_utf8(&raw[..split]).map_err(|_| malformed())?;
+    let mut lines = head.split("\r\n");
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(malformed)?;
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+        .collect();
+    let mut response = HttpResponse {
+        status,
+        headers,
+        body: raw[split + 4..].to_vec(),
+    };
+    if response
+        .header("transfer-encoding")
+        .is_some_and(|value| value.eq_ignore_ascii_case("chunked"))
+    {
+        response.body = decode_chunked(&response.body).ok_or_else(malformed)?;
+    } else if let Some(length) = response
+        .header("content-length")
+        .and_then(|value| value.parse::<usize>().ok())
+    {
+        response.body.truncate(length);
+    }
+    Ok(response)
+}
+
+#[cfg(unix)]
+fn decode_chunked(mut data: &[u8]) -> Option<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let line_end = data.windows(2).position(|window| window == b"\r\n")?;
+        let size_text = std::str::from_utf8(&data[..line_end]).ok()?;
+        let size_text = size_text.split(';').next()?.trim();
+        let size = usize::from_str_radix(size_text, 16).ok()?;
+        data = &data[line_end + 2..];
+        if size == 0 {
+            return Some(body);
+        }
+        body.extend_from_slice(data.get(..size)?);
+        data = data.get(size + 2..)?;
+    }
+}
+
+/// Lightweight transport backed by ureq, without an async runtime.
+///
+/// Uses ureq's bundled root certificates; custom CA and client identity
+/// settings and `no_proxy` lists are not supported.
+#[cfg(feature = "ureq")]
+#[derive(Clone)]
+pub struct UreqTransport {
+    agent: ureq::Agent,
+}
+
+#[cfg(feature = "ureq")]
+impl UreqTransport {
+    pub fn new(agent: ureq::Agent) -> Self {
+        Self { agent }
+    }
+
+    pub fn from_environment(
+        env: &crate::env::ControlPlaneEnvironment,
+    ) -> Result<Self, ModuleKitError> {
+        let tls = &env.tls;
+        if tls.ca_cert_path.is_some() || tls.client_cert_path.is_some() || tls.accept_invalid_certs
+        {
+            return Err(ModuleKitError::Tls(
+                "custom TLS settings are not supported by the ureq transport".into(),
+            ));
+        }
+        let mut builder = ureq::AgentBuilder::new().timeout(env.timeout);
+        if let Some(proxy) = &env.proxy {
+            builder = builder.proxy(ureq_proxy(proxy)?);
+        }
+        Ok(Self::new(builder.build()))
+    }
+}
+
+/// ureq takes proxy credentials as part of the proxy URL.
+#[cfg(feature = "ureq")]
+fn ureq_proxy(proxy: &crate::env::ControlPlaneProxy) -> Result<ureq::Proxy, ModuleKitError> {
+    if proxy.no_proxy.is_some() {
+        return Err(ModuleKitError::Transport(
+            "no_proxy lists are not supported by the ureq transport".into(),
+        ));
+    }
+    let mut url = proxy.url.clone();
+    if let Some(username) = &proxy.username {
+        let invalid = |_| ModuleKitError::Transport("proxy URL cannot carry credentials".into());
+        url.set_username(username).map_err(invalid)?;
+        url.set_password(proxy.password.as_ref().map(|p| p.expose().as_str()))
+            .map_err(invalid)?;
+    }
+    ureq::Proxy::new(url.as_str())
+        .map_err(|err| ModuleKitError::Transport(format!("invalid proxy: {err}")))
+}
+
+#[cfg(feature = "ureq")]
+impl HttpTransport for UreqTransport {
+    fn send(&self, request: &HttpRequest) -> Result<HttpResponse, ModuleKitError> {
+        check_headers(&request.headers)?;
+        let mut call = self
+            .agent
+            .request_url(request.method.as_str(), &request.url);
+        for (name, value) in &request.headers {
+            call = call.set(name, value);
+        }
+        if let Some(timeout) = request.timeout {
+            call = call.timeout(timeout);
+        }
+        let result = match &request.body {
+            Some(body) => call.send_bytes(body),
+            None => call.call(),
+        };
+        let response = match result {
+            Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+            Err(ureq::Error::Transport(err)) => {
+                let message = err.to_string();
+                return Err(match err.kind() {
+                    ureq::ErrorKind::Dns | ureq::ErrorKind::ConnectionFailed => {
+                        ModuleKitError::TransportConnect(message)
+                    }
+                    ureq::ErrorKind::Io if message.contains("timed out") => {
+                        ModuleKitError::TransportTimeout(message)
+                    }
+                    _ => ModuleKitError::Transport(message),
+                });
+            }
+        };
+        let status = response.status();
+        let headers = response
+            .headers_names()
+            .into_iter()
+            .filter_map(|name| {
+                let value = response.header(&name)?.to_string();
+                Some((name, value))
+            })
+            .collect();
+        let mut body = Vec::new();
+        std::io::Read::read_to_end(&mut response.into_reader(), &mut body)?;
+        Ok(HttpResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(name: &str, value: &str) -> Vec<(String, String)> {
+        vec![(name.to_string(), value.to_string())]
+    }
+
+    #[test]
+    fn check_headers_refuses_line_breaks() {
+        assert!(check_headers(&header(REQUEST_ID_HEADER, "req-1")).is_ok());
+        assert!(check_headers(&header(REQUEST_ID_HEADER, "a\r\nx-admin: 1")).is_err());
+        assert!(check_headers(&header("x-bad\r\nname", "1")).is_err());
+        assert!(check_headers(&header("x bad", "1")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn parses_chunked_responses() {
+        let raw = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n";
+        let response = parse_http_response(raw).unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, b"hello");
+    }
+
+    #[cfg(feature = "ureq")]
+    #[test]
+    fn ureq_honors_the_proxy_setting() {
+        let mut proxy =
+            crate::env::ControlPlaneProxy::new(url::Url::parse("http://proxy:3128").unwrap());
+        proxy.username = Some("module".into());
+        proxy.password = Some("s3cret".into());
+        assert!(ureq_proxy(&proxy).is_ok());
+
+        proxy.no_proxy = Some("internal".into());
+        assert!(ureq_proxy(&proxy).is_err());
+    }
+}
@@ -0,0 +1,67 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::{Deserialize, Deserializer, Serialize};
+use time::OffsetDateTime;
+
+use crate::error::ModuleKitError;
+
+/// Claims decoded from a JWT service token.
+///
+/// The signature is not verified; these values only drive local lease
+/// bookkeeping and must not be used for authorization decisions.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TokenClaims {
+    #[serde(default)]
+    pub sub: Option<String>,
+    #[serde(default)]
+    pub exp: Option<i64>,
+    #[serde(default)]
+    pub iat: Option<i64>,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub aud: Vec<String>,
+    #[serde(default, alias = "tenant_id")]
+    pub tenant: Option<String>,
+}
+
+impl TokenClaims {
+    pub fn decode_unverified(token: &str) -> Result<Self, ModuleKitError> {
+        let mut segments = token.trim().split('.');
+        let payload = match (segments.next(), segments.next(), segments.next()) {
+            (Some(_), Some(payload), Some(_)) => payload,
+            _ => return Err(ModuleKitError::InvalidToken("expected three segments".into())),
+        };
+        let bytes = URL_SAFE_NO_PAD
+            .decode(payload.trim_end_matches('='))
+            .map_err(|err| ModuleKitError::InvalidToken(format!("payload not base64url: {err}")))?;
+        serde_json::from_slice(&bytes)
+            .map_err(|err| ModuleKitError::InvalidToken(format!("payload not valid claims: {err}")))
+    }
+
+    pub fn expires_at(&self) -> Option<OffsetDateTime> {
+        self.exp
+            .and_then(|exp| OffsetDateTime::from_unix_timestamp(exp).ok())
+    }
+
+    pub fn issued_at(&self) -> Option<OffsetDateTime> {
+        self.iat
+            .and_then(|iat| OffsetDateTime::from_unix_timestamp(iat).ok())
+    }
+}
+
+fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match Option::<OneOrMany>::deserialize(deserializer)? {
+        Some(OneOrMany::One(value)) => vec![value],
+        Some(OneOrMany::Many(values)) => values,
+        None => Vec::new(),
+    })
+}
@@ -0,0 +1,226 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+use crate::connector_endpoint::ConnectorEndpoint;
+use crate::connector_envelope::{ConnectorReply, ConnectorRequest};
+use crate::connector_request::DbConnectorIntent;
+use crate::env::{ModuleEnvironment, ENV_KV_CONNECTOR_URI};
+use crate::error::ModuleKitError;
+use crate::token_provider::{ScopedTokenCache, ServiceTokenProvider};
+use crate::tokens::ModuleTokenExchangeRequest;
+
+/// Request sent to the KV cache connector, one per connection like
+/// `DbConnectorRequest`, always with the command's intent.
+pub type KvConnectorRequest = ConnectorRequest<KvCommand>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum KvCommand {
+    Get {
+        key: String,
+    },
+    Set {
+        key: String,
+        value: JsonValue,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ttl_seconds: Option<u64>,
+    },
+    Del {
+        key: String,
+    },
+    /// Adds `by` to an integer value, starting from 0 when the key is unset.
+    Incr {
+        key: String,
+        by: i64,
+    },
+    Expire {
+        key: String,
+        ttl_seconds: u64,
+    },
+}
+
+impl KvCommand {
+    pub fn key(&self) -> &str {
+        match self {
+            KvCommand::Get { key }
+            | KvCommand::Set { key, .. }
+            | KvCommand::Del { key }
+            | KvCommand::Incr { key, .. }
+            | KvCommand::Expire { key, .. } => key,
+        }
+    }
+
+    /// Every command but `Get` needs a `cache:write` token.
+    pub fn intent(&self) -> DbConnectorIntent {
+        match self {
+            KvCommand::Get { .. } => DbConnectorIntent::Read,
+            _ => DbConnectorIntent::Write,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KvConnectorResponse {
+    pub ok: bool,
+    /// Stored value for `get`, new value for `incr`, and whether the key
+    /// existed for `del` and `expire`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<JsonValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ConnectorReply for KvConnectorResponse {
+    fn is_ok(&self) -> bool {
+        self.ok
+    }
+
+    fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+/// Client for the KV cache connector.
+///
+/// Reads use the service token; writes exchange it for a `cache:write`
+/// token, cached until shortly before it expires.
+pub struct KvConnectorClient {
+    endpoint: ConnectorEndpoint,
+    tokens: Arc<ServiceTokenProvider>,
+    write_token: ScopedTokenCache,
+}
+
+impl KvConnectorClient {
+    pub fn from_env() -> Result<Self, ModuleKitError> {
+        Self::from_environment(ModuleEnvironment::from_env()?)
+    }
+
+    /// Fails with `MissingEnv` when `FENRIR_KV_CONNECTOR_URI` is unset.
+    pub fn from_environment(env: ModuleEnvironment) -> Result<Self, ModuleKitError> {
+        let tokens = Arc::new(env.token_provider()?);
+        Self::with_token_provider(env, tokens)
+    }
+
+    /// Client for `env.kv_connector` whose writes exchange `tokens` for
+    /// `cache:write`. Pass the provider the DB client already holds so the
+    /// service token is refreshed once.
+    pub fn with_token_provider(
+        env: ModuleEnvironment,
+        tokens: Arc<ServiceTokenProvider>,
+    ) -> Result<Self, ModuleKitError> {
+        let endpoint = env
+            .kv_connector
+            .ok_or(ModuleKitError::MissingEnv(ENV_KV_CONNECTOR_URI))?;
+        Ok(Self::new(endpoint, tokens))
+    }
+
+    pub fn new(endpoint: ConnectorEndpoint, tokens: Arc<ServiceTokenProvider>) -> Self {
+        Self {
+            endpoint,
+            tokens,
+            write_token: ScopedTokenCache::new(ModuleTokenExchangeRequest::cache_write),
+        }
+    }
+
+    pub fn endpoint(&self) -> &ConnectorEndpoint {
+        &self.endpoint
+    }
+
+    pub fn execute(&self, command: KvCommand) -> Result<KvConnectorResponse, ModuleKitError> {
+        let intent = command.intent();
+        let token = if intent.requires_write_scope() {
+            self.write_token.get(&self.tokens)?
+        } else {
+            self.tokens.current_token()?
+        };
+        let request = KvConnectorRequest::new(token, command).intent(intent);
+        let payload = serde_json::to_vec(&request)?;
+        let bytes = self.endpoint.send(&payload, |_| {})?;
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    /// Stored value, or `None` when `key` is unset or expired.
+    pub fn get(&self, key: impl Into<String>) -> Result<Option<JsonValue>, ModuleKitError> {
+        let value = self
+            .execute(KvCommand::Get { key: key.into() })?
+            .into_result()?
+            .value;
+        Ok(value.filter(|value| !value.is_null()))
+    }
+
+    /// `get` deserialized into `T`.
+    pub fn get_as<T: serde::de::DeserializeOwned>(
+        &self,
+        key: impl Into<String>,
+    ) -> Result<Option<T>, ModuleKitError> {
+        self.get(key)?
+            .map(|value| serde_json::from_value(value).map_err(ModuleKitError::from))
+            .transpose()
+    }
+
+    /// Stores `value`, expiring after `ttl` when given.
+    pub fn set(
+        &self,
+        key: impl Into<String>,
+        value: &impl Serialize,
+        ttl: Option<Duration>,
+    ) -> Result<(), ModuleKitError> {
+        self.execute(KvCommand::Set {
+            key: key.into(),
+            value: serde_json::to_value(value)?,
+            ttl_seconds: ttl.map(|ttl| ttl.as_secs().max(1)),
+        })?
+        .into_result()?;
+        Ok(())
+    }
+
+    /// Removes `key`, returning whether it existed.
+    pub fn del(&self, key: impl Into<String>) -> Result<bool, ModuleKitError> {
+        let value = self
+            .execute(KvCommand::Del { key: key.into() })?
+            .into_result()?
+            .value;
+        Ok(flag(value))
+    }
+
+    /// Adds `by` to the counter at `key` and returns the new value.
+    pub fn incr(&self, key: impl Into<String>, by: i64) -> Result<i64, ModuleKitError> {
+        let key = key.into();
+        let value = self
+            .execute(KvCommand::Incr {
+                key: key.clone(),
+                by,
+            })?
+            .into_result()?
+            .value;
+        value.as_ref().and_then(JsonValue::as_i64).ok_or_else(|| {
+            ModuleKitError::ConnectorRejected(format!(
+                "incr on '{key}' returned a non-integer value"
+            ))
+        })
+    }
+
+    /// Sets the time to live of `key`, returning whether it existed.
+    pub fn expire(&self, key: impl Into<String>, ttl: Duration) -> Result<bool, ModuleKitError> {
+        let value = self
+            .execute(KvCommand::Expire {
+                key: key.into(),
+                ttl_seconds: ttl.as_secs().max(1),
+            })?
+            .into_result()?
+            .value;
+        Ok(flag(value))
+    }
+}
+
+/// Reads a boolean or count reply; connectors backed by Redis send counts.
+fn flag(value: Option<JsonValue>) -> bool {
+    match value {
+        Some(JsonValue::Bool(value)) => value,
+        Some(JsonValue::Number(count)) => count.as_u64().is_some_and(|count| count > 0),
+        _ => false,
+    }
+}
@@ -1,14 +1,135 @@
-mod control_plane;
+mod compat;
+pub mod access_policy;
+#[cfg(feature = "jwt-verify")]
+pub mod authz;
+pub mod blob;
+pub mod bus;
+pub mod cancellation;
+pub mod capabilities;
+pub mod changefeed;
+pub mod circuit_breaker;
+pub mod compression;
 pub mod connector;
+pub mod connector_endpoint;
+pub mod connector_envelope;
+pub mod connector_request;
+pub mod connector_response;
+pub mod consistency;
+pub mod contracts;
+pub mod control_plane;
+pub mod coordination;
+pub mod data_keys;
 pub mod env;
 pub mod error;
+pub mod export;
+pub mod failover;
+pub mod feature_flags;
+pub mod health;
+pub mod http_transport;
+#[cfg(feature = "jwt")]
+pub mod jwt;
+pub mod kv;
+pub mod manifest;
+pub mod metrics;
+pub mod migrations;
+pub mod module_config;
+pub mod module_http;
+pub mod openapi;
+pub mod pagination;
+pub mod projection;
+pub mod quickstart;
+pub mod rate_limit;
+pub mod schema;
+pub mod result_cache;
+pub mod retention;
+pub mod rpc;
+pub mod runtime;
+pub mod scheduler;
+pub mod secrets;
+pub mod seed;
 pub mod service;
+pub mod service_diff;
+pub mod service_endpoint;
+pub mod settings;
+pub mod shutdown;
+pub mod sql;
+#[cfg(feature = "axum")]
+pub mod streaming;
+pub mod tenant_context;
 pub mod tokens;
+pub mod traffic_dump;
 pub mod token_provider;
+pub mod token_source;
+pub mod values;
+#[cfg(feature = "jwt-verify")]
+pub mod verifier;
+pub mod watchdog;
+pub mod wire_format;
+pub mod write_usage;
 
+pub use access_policy::*;
+#[cfg(feature = "jwt-verify")]
+pub use authz::*;
+pub use blob::*;
+pub use bus::*;
+pub use cancellation::*;
+pub use capabilities::*;
+pub use changefeed::*;
+pub use circuit_breaker::*;
+pub use compression::*;
 pub use connector::*;
+pub use connector_endpoint::*;
+pub use connector_envelope::*;
+pub use connector_request::*;
+pub use connector_response::*;
+pub use consistency::*;
+pub use contracts::*;
+pub use control_plane::*;
+pub use coordination::*;
+pub use data_keys::*;
 pub use env::*;
 pub use error::*;
+pub use export::*;
+pub use failover::*;
+pub use feature_flags::*;
+pub use health::*;
+pub use http_transport::*;
+#[cfg(feature = "jwt")]
+pub use jwt::*;
+pub use kv::*;
+pub use manifest::*;
+pub use metrics::*;
+pub use migrations::*;
+pub use module_config::*;
+pub use module_http::*;
+pub use pagination::*;
+pub use projection::*;
+pub use quickstart::*;
+pub use rate_limit::*;
+pub use schema::*;
+pub use result_cache::*;
+pub use retention::*;
+pub use rpc::*;
+pub use runtime::*;
+pub use scheduler::*;
+pub use secrets::*;
+pub use seed::*;
 pub use service::*;
+pub use service_diff::*;
+pub use service_endpoint::*;
+pub use settings::*;
+pub use shutdown::*;
+pub use sql::*;
+#[cfg(feature = "axum")]
+pub use streaming::*;
+pub use tenant_context::*;
 pub use tokens::*;
+pub use traffic_dump::*;
 pub use token_provider::*;
+pub use token_source::*;
+pub use values::*;
+#[cfg(feature = "jwt-verify")]
+pub use verifier::*;
+pub use watchdog::*;
+pub use wire_format::*;
+pub use write_usage::*;
@@ -0,0 +1,251 @@
+use std::env::VarError;
+use std::fs;
+use std::path::Path;
+
+use serde_json::{Map, Value as JsonValue};
+
+use crate::env::{EnvSource, ProcessEnv};
+use crate::error::ModuleKitError;
+use crate::service::{ModuleCapabilities, ModuleReportedServices, ModuleServiceDescriptor};
+
+/// Syntax of a service manifest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManifestFormat {
+    Json,
+    /// Requires the `yaml` feature.
+    Yaml,
+    /// Requires the `toml` feature.
+    Toml,
+}
+
+impl ManifestFormat {
+    /// Format for a `.json`, `.yaml`/`.yml` or `.toml` file.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "json" => Some(ManifestFormat::Json),
+            "yaml" | "yml" => Some(ManifestFormat::Yaml),
+            "toml" => Some(ManifestFormat::Toml),
+            _ => None,
+        }
+    }
+}
+
+impl ModuleReportedServices {
+    /// Reads services declared in a manifest file, picking the format from
+    /// the extension and interpolating `${VAR}` from the process environment.
+    pub fn from_manifest(path: impl AsRef<Path>) -> Result<Self, ModuleKitError> {
+        Self::from_manifest_with(path, &ProcessEnv)
+    }
+
+    pub fn from_manifest_with(
+        path: impl AsRef<Path>,
+        vars: &dyn EnvSource,
+    ) -> Result<Self, ModuleKitError> {
+        let path = path.as_ref();
+        let invalid = |message: String| {
+            ModuleKitError::InvalidManifest(format!("{}: {message}", path.display()))
+        };
+        let format = ManifestFormat::from_path(path)
+            .ok_or_else(|| invalid("unsupported manifest format".to_string()))?;
+        let text = fs::read_to_string(path).map_err(|err| invalid(err.to_string()))?;
+        Self::from_manifest_str(&text, format, vars).map_err(|err| match err {
+            ModuleKitError::InvalidManifest(message) => invalid(message),
+            other => other,
+        })
+    }
+
+    /// Parses a manifest, interpolates every string value, rejects unknown
+    /// fields and runs `validate`.
+    ///
+    /// `${VAR}` is replaced from `vars`, `${VAR:-fallback}` falls back when
+    /// `VAR` is unset or empty, and `$$` produces a literal `$`.
+    pub fn from_manifest_str(
+        text: &str,
+        format: ManifestFormat,
+        vars: &dyn EnvSource,
+    ) -> Result<Self, ModuleKitError> {
+        let mut document = parse_document(text, format)?;
+        let mut missing = Vec::new();
+        interpolate_value(&mut document, vars, &mut missing)?;
+        if !missing.is_empty() {
+            missing.sort();
+            missing.dedup();
+            return Err(ModuleKitError::InvalidManifest(format!(
+                "unset variables: {}",
+                missing.join(", ")
+            )));
+        }
+        check_known_fields(&document)?;
+        let services: Self = serde_json::from_value(document)
+            .map_err(|err| ModuleKitError::InvalidManifest(err.to_string()))?;
+        services.validate()?;
+        Ok(services)
+    }
+}
+
+fn parse_document(text: &str, format: ManifestFormat) -> Result<JsonValue, ModuleKitError> {
+    let invalid = |message: String| ModuleKitError::InvalidManifest(message);
+    match format {
+        ManifestFormat::Json => serde_json::from_str(text).map_err(|err| invalid(err.to_string())),
+        #[cfg(feature = "yaml")]
+        ManifestFormat::Yaml => serde_yaml::from_str(text).map_err(|err| invalid(err.to_string())),
+        #[cfg(feature = "toml")]
+        ManifestFormat::Toml => {
+            let document = text
+                .parse::<toml_edit::DocumentMut>()
+                .map_err(|err| invalid(err.to_string()))?;
+            Ok(toml_table_to_json(document.as_table()))
+        }
+        #[allow(unreachable_patterns)]
+        other => Err(invalid(format!(
+            "{other:?} manifests need the matching crate feature"
+        ))),
+    }
+}
+
+#[cfg(feature = "toml")]
+fn toml_table_to_json(table: &toml_edit::Table) -> JsonValue {
+    JsonValue::Object(
+        table
+            .iter()
+            .filter_map(|(key, item)| Some((key.to_string(), toml_item_to_json(item)?)))
+            .collect(),
+    )
+}
+
+#[cfg(feature = "toml")]
+fn toml_item_to_json(item: &toml_edit::Item) -> Option<JsonValue> {
+    match item {
+        toml_edit::Item::None => None,
+        toml_edit::Item::Value(value) => Some(toml_value_to_json(value)),
+        toml_edit::Item::Table(table) => Some(toml_table_to_json(table)),
+        toml_edit::Item::ArrayOfTables(tables) => Some(JsonValue::Array(
+            tables.iter().map(toml_table_to_json).collect(),
+        )),
+    }
+}
+
+#[cfg(feature = "toml")]
+fn toml_value_to_json(value: &toml_edit::Value) -> JsonValue {
+    use toml_edit::Value;
+
+    match value {
+        Value::String(value) => JsonValue::String(value.value().clone()),
+        Value::Integer(value) => JsonValue::from(*value.value()),
+        Value::Float(value) => JsonValue::from(*value.value()),
+        Value::Boolean(value) => JsonValue::Bool(*value.value()),
+        Value::Datetime(value) => JsonValue::String(value.value().to_string()),
+        Value::Array(array) => JsonValue::Array(array.iter().map(toml_value_to_json).collect()),
+        Value::InlineTable(table) => JsonValue::Object(
+            table
+                .iter()
+                .map(|(key, value)| (key.to_string(), toml_value_to_json(value)))
+                .collect(),
+        ),
+    }
+}
+
+fn interpolate_value(
+    value: &mut JsonValue,
+    vars: &dyn EnvSource,
+    missing: &mut Vec<String>,
+) -> Result<(), ModuleKitError> {
+    match value {
+        JsonValue::String(text) => *text = interpolate(text, vars, missing)?,
+        JsonValue::Array(items) => {
+            for item in items {
+                interpolate_value(item, vars, missing)?;
+            }
+        }
+        JsonValue::Object(fields) => {
+            for field in fields.values_mut() {
+                interpolate_value(field, vars, missing)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn interpolate(
+    text: &str,
+    vars: &dyn EnvSource,
+    missing: &mut Vec<String>,
+) -> Result<String, ModuleKitError> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('$') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+        if let Some(after) = rest.strip_prefix('$') {
+            out.push('$');
+            rest = after;
+            continue;
+        }
+        let Some(body) = rest.strip_prefix('{') else {
+            out.push('$');
+            continue;
+        };
+        let end = body.find('}').ok_or_else(|| {
+            ModuleKitError::InvalidManifest(format!("unterminated '${{' in '{text}'"))
+        })?;
+        let (name, fallback) = match body[..end].split_once(":-") {
+            Some((name, fallback)) => (name.trim(), Some(fallback)),
+            None => (body[..end].trim(), None),
+        };
+        match (vars.var(name), fallback) {
+            (Ok(value), _) if !value.is_empty() => out.push_str(&value),
+            (Ok(_) | Err(VarError::NotPresent), Some(fallback)) => out.push_str(fallback),
+            (Ok(value), None) => out.push_str(&value),
+            (Err(_), _) => missing.push(name.to_string()),
+        }
+        rest = &body[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Rejects keys the descriptor types do not have, so typos in hand-written
+/// manifests fail instead of being silently dropped.
+fn check_known_fields(document: &JsonValue) -> Result<(), ModuleKitError> {
+    let Some(root) = document.as_object() else {
+        return Err(ModuleKitError::InvalidManifest(
+            "manifest must be a table of fields".to_string(),
+        ));
+    };
+    let known_root =
+        field_names(&ModuleReportedServices::new("").with_capabilities(ModuleCapabilities::new()));
+    let known_service = field_names(&ModuleServiceDescriptor::default());
+    let mut unknown: Vec<String> = root
+        .keys()
+        .filter(|key| !known_root.contains_key(*key))
+        .cloned()
+        .collect();
+    let services = root.get("services").and_then(JsonValue::as_array);
+    for (index, service) in services.into_iter().flatten().enumerate() {
+        if let Some(fields) = service.as_object() {
+            unknown.extend(
+                fields
+                    .keys()
+                    .filter(|key| !known_service.contains_key(*key))
+                    .map(|key| format!("services[{index}].{key}")),
+            );
+        }
+    }
+    if unknown.is_empty() {
+        Ok(())
+    } else {
+        Err(ModuleKitError::InvalidManifest(format!(
+            "unknown fields: {}",
+            unknown.join(", ")
+        )))
+    }
+}
+
+fn field_names(value: &impl serde::Serialize) -> Map<String, JsonValue> {
+    match serde_json::to_value(value) {
+        Ok(JsonValue::Object(fields)) => fields,
+        _ => Map::new(),
+    }
+}
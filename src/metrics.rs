@@ -0,0 +1,148 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+
+use crate::token_provider::ServiceTokenProvider;
+
+/// Connector requests, labelled by `intent` and `outcome` (`ok`, `rejected`,
+/// `error`); failed requests also carry `domain`.
+pub const METRIC_CONNECTOR_REQUESTS: &str = "fenrir_connector_requests_total";
+/// Connector round-trip latency in seconds, labelled by `intent`.
+pub const METRIC_CONNECTOR_LATENCY: &str = "fenrir_connector_latency_seconds";
+/// Service token refresh attempts, labelled by `outcome` (`success`, `failure`);
+/// failures also carry `domain`.
+pub const METRIC_TOKEN_REFRESH: &str = "fenrir_token_refresh_total";
+/// Write-scoped token lookups, labelled by `result` (`hit`, `miss`).
+pub const METRIC_SCOPED_TOKEN_CACHE: &str = "fenrir_scoped_token_cache_total";
+/// Result cache lookups, labelled by `result` (`hit`, `miss`).
+pub const METRIC_RESULT_CACHE: &str = "fenrir_result_cache_total";
+
+pub type MetricLabels<'a> = &'a [(&'static str, &'a str)];
+
+/// Sink for the crate's counters and histograms.
+///
+/// Implement this to forward into a metrics facade, or use `InMemoryMetrics`
+/// and export its snapshots.
+pub trait MetricsRecorder: Send + Sync {
+    fn increment_counter(&self, name: &'static str, labels: MetricLabels<'_>, value: u64);
+    fn record_histogram(&self, name: &'static str, labels: MetricLabels<'_>, value: f64);
+}
+
+/// Metric name plus its label pairs, sorted by label name.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct MetricKey {
+    pub name: String,
+    pub labels: Vec<(String, String)>,
+}
+
+impl MetricKey {
+    fn new(name: &str, labels: MetricLabels<'_>) -> Self {
+        let mut labels: Vec<(String, String)> = labels
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        labels.sort();
+        Self {
+            name: name.to_string(),
+            labels,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct HistogramSummary {
+    pub count: u64,
+    pub sum: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl HistogramSummary {
+    fn observe(&mut self, value: f64) {
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+        self.count += 1;
+        self.sum += value;
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum / self.count as f64)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MetricsSnapshot {
+    pub counters: BTreeMap<MetricKey, u64>,
+    pub histograms: BTreeMap<MetricKey, HistogramSummary>,
+}
+
+impl MetricsSnapshot {
+    /// Sum of counter `name` across all label sets.
+    pub fn counter_total(&self, name: &str) -> u64 {
+        self.counters
+            .iter()
+            .filter(|(key, _)| key.name == name)
+            .map(|(_, value)| value)
+            .sum()
+    }
+}
+
+/// Recorder that keeps totals in memory for scraping through `snapshot`.
+#[derive(Debug, Default)]
+pub struct InMemoryMetrics {
+    counters: Mutex<BTreeMap<MetricKey, u64>>,
+    histograms: Mutex<BTreeMap<MetricKey, HistogramSummary>>,
+}
+
+impl InMemoryMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            counters: self.counters.lock().unwrap().clone(),
+            histograms: self.histograms.lock().unwrap().clone(),
+        }
+    }
+}
+
+impl MetricsRecorder for InMemoryMetrics {
+    fn increment_counter(&self, name: &'static str, labels: MetricLabels<'_>, value: u64) {
+        *self
+            .counters
+            .lock()
+            .unwrap()
+            .entry(MetricKey::new(name, labels))
+            .or_default() += value;
+    }
+
+    fn record_histogram(&self, name: &'static str, labels: MetricLabels<'_>, value: f64) {
+        self.histograms
+            .lock()
+            .unwrap()
+            .entry(MetricKey::new(name, labels))
+            .or_default()
+            .observe(value);
+    }
+}
+
+impl ServiceTokenProvider {
+    /// Counts every refresh attempt into `METRIC_TOKEN_REFRESH`.
+    pub fn record_metrics(&self, recorder: Arc<dyn MetricsRecorder>) {
+        self.on_refresh(move |event| match event.result.failure_domain {
+            None => recorder.increment_counter(METRIC_TOKEN_REFRESH, &[("outcome", "success")], 1),
+            Some(domain) => recorder.increment_counter(
+                METRIC_TOKEN_REFRESH,
+                &[("outcome", "failure"), ("domain", domain.as_str())],
+                1,
+            ),
+        });
+    }
+}
@@ -0,0 +1,440 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use time::OffsetDateTime;
+
+use crate::connector::DbConnectorClient;
+use crate::connector_request::{
+    DbConnectorCommand, DbConnectorIntent, DbPreparedParam, ExecuteOptions,
+};
+use crate::connector_response::DbErrorCategory;
+use crate::coordination::default_holder;
+use crate::env::ProcessEnv;
+use crate::error::ModuleKitError;
+use crate::health::{HealthCheckMode, HealthRegistry};
+use crate::runtime::retry_until;
+
+const MIGRATIONS_TABLE: &str = "_modulekit_migrations";
+/// Holds at most one row while a runner applies migrations.
+const MIGRATIONS_LOCK_TABLE: &str = "_modulekit_migrations_lock";
+const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// One versioned SQL migration; versions must be unique and increasing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    pub version: i64,
+    pub name: String,
+    pub sql: String,
+}
+
+impl Migration {
+    pub fn new(version: i64, name: impl Into<String>, sql: impl Into<String>) -> Self {
+        Self {
+            version,
+            name: name.into(),
+            sql: sql.into(),
+        }
+    }
+
+    /// Migration named by its file: `<version>_<name>.sql`, or Flyway-style
+    /// `V<version>__<name>.sql`. Directories in `path` are ignored.
+    pub fn from_file_name(path: &str, sql: impl Into<String>) -> Result<Self, ModuleKitError> {
+        let file = path.rsplit(['/', '\\']).next().unwrap_or(path);
+        let invalid = || {
+            ModuleKitError::InvalidMigration(format!("'{file}' is not named <version>_<name>.sql"))
+        };
+        let stem = file.strip_suffix(".sql").ok_or_else(invalid)?;
+        let stem = stem.strip_prefix(['V', 'v']).unwrap_or(stem);
+        let (version, name) = stem.split_once('_').ok_or_else(invalid)?;
+        let version = version.parse::<i64>().map_err(|_| invalid())?;
+        let name = name.trim_start_matches('_');
+        if name.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self::new(version, name, sql))
+    }
+
+    /// Migrations from `(file name, sql)` pairs, as produced by
+    /// `embed_migrations!`; fails on duplicate versions.
+    pub fn from_sources(sources: &[(&str, &str)]) -> Result<Vec<Self>, ModuleKitError> {
+        let migrations = sources
+            .iter()
+            .map(|(path, sql)| Self::from_file_name(path, *sql))
+            .collect::<Result<Vec<_>, _>>()?;
+        check_unique_versions(migrations)
+    }
+
+    /// Every `.sql` file directly inside `dir`, sorted by version.
+    pub fn load_dir(dir: impl AsRef<Path>) -> Result<Vec<Self>, ModuleKitError> {
+        let dir = dir.as_ref();
+        let invalid = |err: std::io::Error| {
+            ModuleKitError::InvalidMigration(format!("{}: {err}", dir.display()))
+        };
+        let mut migrations = Vec::new();
+        for entry in fs::read_dir(dir).map_err(invalid)? {
+            let path = entry.map_err(invalid)?.path();
+            let Some(file) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            if !path.is_file() || !file.ends_with(".sql") {
+                continue;
+            }
+            let sql = fs::read_to_string(&path).map_err(invalid)?;
+            migrations.push(Self::from_file_name(file, sql)?);
+        }
+        check_unique_versions(migrations)
+    }
+}
+
+/// Embeds SQL migration files at compile time, evaluating to
+/// `Result<Vec<Migration>, ModuleKitError>`.
+///
+/// Paths are relative to the invoking source file, as with `include_str!`,
+/// and file names follow `Migration::from_file_name`.
+#[macro_export]
+macro_rules! embed_migrations {
+    ($($path:literal),* $(,)?) => {
+        $crate::Migration::from_sources(&[$(($path, include_str!($path))),*])
+    };
+}
+
+/// What `MigrationRunner::run` would do, computed without writing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MigrationPlan {
+    pub applied: Vec<i64>,
+    pub pending: Vec<Migration>,
+    /// Applied versions no known migration declares, e.g. from a newer
+    /// release of the module.
+    pub unknown: Vec<i64>,
+}
+
+impl MigrationPlan {
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// Tracks applied migrations in `_modulekit_migrations` through the connector.
+pub struct MigrationRunner<'a> {
+    client: &'a DbConnectorClient,
+    engine: Option<String>,
+    migrations: Vec<Migration>,
+    lock_timeout: Duration,
+    stale_lock_after: Option<Duration>,
+    holder: String,
+}
+
+impl<'a> MigrationRunner<'a> {
+    pub fn new(client: &'a DbConnectorClient, mut migrations: Vec<Migration>) -> Self {
+        migrations.sort_by_key(|migration| migration.version);
+        Self {
+            client,
+            engine: None,
+            migrations,
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
+            stale_lock_after: None,
+            holder: default_holder(&ProcessEnv),
+        }
+    }
+
+    /// How long `run` waits for another runner's lock; defaults to 60 seconds.
+    pub fn lock_timeout(mut self, value: Duration) -> Self {
+        self.lock_timeout = value;
+        self
+    }
+
+    /// Treats a lock taken longer than `value` ago as left behind by a
+    /// crashed runner and takes it over. Off by default; pick an age well
+    /// above the longest expected migration run.
+    pub fn stale_lock_after(mut self, value: Duration) -> Self {
+        self.stale_lock_after = Some(value);
+        self
+    }
+
+    /// Identifies this runner in the lock row; defaults to `HOSTNAME` and
+    /// the process id.
+    pub fn holder(mut self, value: impl Into<String>) -> Self {
+        self.holder = value.into();
+        self
+    }
+
+    pub fn engine(mut self, value: impl Into<String>) -> Self {
+        self.engine = Some(value.into());
+        self
+    }
+
+    pub fn migrations(&self) -> &[Migration] {
+        &self.migrations
+    }
+
+    /// Versions recorded as applied; empty when the tracking table does not exist yet.
+    pub fn applied(&self) -> Result<BTreeSet<i64>, ModuleKitError> {
+        let command = DbConnectorCommand::Simple {
+            statement: format!("SELECT version FROM {MIGRATIONS_TABLE}"),
+        };
+        let response = match self
+            .client
+            .execute_with(
+                command,
+                DbConnectorIntent::Read,
+                self.engine.as_deref(),
+                None,
+                &untenanted(),
+            )
+            .and_then(|response| response.into_result())
+        {
+            Ok(response) => response,
+            Err(ModuleKitError::ConnectorRejected(message)) if is_missing_table(&message) => {
+                return Ok(BTreeSet::new())
+            }
+            Err(ModuleKitError::ConnectorFailed(err))
+                if err.category == DbErrorCategory::UndefinedObject
+                    || is_missing_table(&err.message) =>
+            {
+                return Ok(BTreeSet::new())
+            }
+            Err(err) => return Err(err),
+        };
+        let mut applied = BTreeSet::new();
+        for result in response.results.iter().flatten() {
+            for row in result.rows() {
+                applied.insert(row.get::<i64>("version")?);
+            }
+        }
+        Ok(applied)
+    }
+
+    /// Known migrations not yet applied, in version order. Read-only.
+    pub fn pending(&self) -> Result<Vec<&Migration>, ModuleKitError> {
+        let applied = self.applied()?;
+        Ok(self
+            .migrations
+            .iter()
+            .filter(|migration| !applied.contains(&migration.version))
+            .collect())
+    }
+
+    /// Dry run: applied, pending and unknown versions. Read-only.
+    pub fn plan(&self) -> Result<MigrationPlan, ModuleKitError> {
+        let applied = self.applied()?;
+        let known: BTreeSet<i64> = self.migrations.iter().map(|m| m.version).collect();
+        Ok(MigrationPlan {
+            pending: self
+                .migrations
+                .iter()
+                .filter(|migration| !applied.contains(&migration.version))
+                .cloned()
+                .collect(),
+            unknown: applied.difference(&known).copied().collect(),
+            applied: applied.into_iter().collect(),
+        })
+    }
+
+    /// Applies pending migrations in version order with write-intent
+    /// requests, returning the versions applied.
+    ///
+    /// Runners on other replicas wait on a row in `_modulekit_migrations_lock`
+    /// for up to `lock_timeout`. Each migration and its tracking row are
+    /// separate requests, so migrations should be idempotent where the
+    /// engine allows (`IF NOT EXISTS`).
+    pub fn run(&self) -> Result<Vec<i64>, ModuleKitError> {
+        self.ensure_tables()?;
+        self.lock()?;
+        let result = self.apply_pending();
+        let unlocked = self.unlock();
+        let applied = result?;
+        unlocked?;
+        Ok(applied)
+    }
+
+    /// Removes the lock row left behind by a runner that crashed mid-run.
+    pub fn force_unlock(&self) -> Result<(), ModuleKitError> {
+        self.write(DbConnectorCommand::Simple {
+            statement: format!("DELETE FROM {MIGRATIONS_LOCK_TABLE}"),
+        })
+    }
+
+    fn apply_pending(&self) -> Result<Vec<i64>, ModuleKitError> {
+        let mut applied = Vec::new();
+        for migration in self.plan()?.pending {
+            self.write(DbConnectorCommand::Simple {
+                statement: migration.sql.clone(),
+            })
+            .map_err(|err| {
+                ModuleKitError::InvalidMigration(format!(
+                    "{} ({}) failed: {err}",
+                    migration.version, migration.name
+                ))
+            })?;
+            self.write(DbConnectorCommand::Prepared {
+                statement: format!(
+                    "INSERT INTO {MIGRATIONS_TABLE} (version, name) VALUES (:version, :name)"
+                ),
+                params: vec![
+                    DbPreparedParam::new("version", migration.version),
+                    DbPreparedParam::new("name", migration.name.clone()),
+                ],
+            })?;
+            applied.push(migration.version);
+        }
+        Ok(applied)
+    }
+
+    fn ensure_tables(&self) -> Result<(), ModuleKitError> {
+        self.write(DbConnectorCommand::Simple {
+            statement: format!(
+                "CREATE TABLE IF NOT EXISTS {MIGRATIONS_TABLE} (\
+                 version BIGINT PRIMARY KEY, \
+                 name VARCHAR(255) NOT NULL, \
+                 applied_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP)"
+            ),
+        })?;
+        self.write(DbConnectorCommand::Simple {
+            statement: format!(
+                "CREATE TABLE IF NOT EXISTS {MIGRATIONS_LOCK_TABLE} (\
+                 id INTEGER PRIMARY KEY, \
+                 holder VARCHAR(255) NOT NULL, \
+                 locked_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP)"
+            ),
+        })
+    }
+
+    /// Inserts the single lock row, retrying while another runner holds it
+    /// and clearing it first once it is older than `stale_lock_after`.
+    ///
+    /// `locked_at` is written from this host's UTC clock so the age check
+    /// does not depend on the database's time zone.
+    fn lock(&self) -> Result<(), ModuleKitError> {
+        retry_until(self.lock_timeout, || {
+            if let Some(stale_after) = self.stale_lock_after {
+                let cutoff = OffsetDateTime::now_utc() - stale_after;
+                self.write(DbConnectorCommand::Prepared {
+                    statement: format!(
+                        "DELETE FROM {MIGRATIONS_LOCK_TABLE} WHERE locked_at < :cutoff"
+                    ),
+                    params: vec![DbPreparedParam::new("cutoff", lock_timestamp(cutoff))],
+                })?;
+            }
+            self.write(DbConnectorCommand::Prepared {
+                statement: format!(
+                    "INSERT INTO {MIGRATIONS_LOCK_TABLE} (id, holder, locked_at) \
+                     VALUES (1, :holder, :locked_at)"
+                ),
+                params: vec![
+                    DbPreparedParam::new("holder", self.holder.clone()),
+                    DbPreparedParam::new("locked_at", lock_timestamp(OffsetDateTime::now_utc())),
+                ],
+            })
+        })
+        .map_err(|err| {
+            ModuleKitError::MigrationLocked(format!(
+                "no lock within {:?}, last attempt: {err}",
+                self.lock_timeout
+            ))
+        })
+    }
+
+    /// Removes this runner's lock row, leaving one a newer runner took over.
+    fn unlock(&self) -> Result<(), ModuleKitError> {
+        self.write(DbConnectorCommand::Prepared {
+            statement: format!("DELETE FROM {MIGRATIONS_LOCK_TABLE} WHERE holder = :holder"),
+            params: vec![DbPreparedParam::new("holder", self.holder.clone())],
+        })
+    }
+
+    fn write(&self, command: DbConnectorCommand) -> Result<(), ModuleKitError> {
+        self.client
+            .execute_with(
+                command,
+                DbConnectorIntent::Write,
+                self.engine.as_deref(),
+                None,
+                &untenanted(),
+            )?
+            .into_result()?;
+        Ok(())
+    }
+}
+
+/// Migrations and the tracking and lock tables are schema-wide, so no tenant
+/// context is bound into them.
+fn untenanted() -> ExecuteOptions {
+    ExecuteOptions::new().without_tenant()
+}
+
+/// `YYYY-MM-DD HH:MM:SS`, which SQL engines accept as a timestamp literal
+/// and which sorts chronologically as text.
+fn lock_timestamp(at: OffsetDateTime) -> String {
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        at.year(),
+        u8::from(at.month()),
+        at.day(),
+        at.hour(),
+        at.minute(),
+        at.second()
+    )
+}
+
+fn check_unique_versions(mut migrations: Vec<Migration>) -> Result<Vec<Migration>, ModuleKitError> {
+    migrations.sort_by_key(|migration| migration.version);
+    if let Some(pair) = migrations
+        .windows(2)
+        .find(|pair| pair[0].version == pair[1].version)
+    {
+        return Err(ModuleKitError::InvalidMigration(format!(
+            "version {} is used by both '{}' and '{}'",
+            pair[0].version, pair[0].name, pair[1].name
+        )));
+    }
+    Ok(migrations)
+}
+
+impl HealthRegistry {
+    /// Adds a `migrations` check that reports pending migrations as degraded
+    /// or unhealthy depending on `mode`.
+    pub fn register_pending_migrations(
+        &self,
+        client: Arc<DbConnectorClient>,
+        migrations: Vec<Migration>,
+        engine: Option<String>,
+        mode: HealthCheckMode,
+    ) {
+        self.register("migrations", mode, move || {
+            let mut runner = MigrationRunner::new(&client, migrations.clone());
+            if let Some(engine) = &engine {
+                runner = runner.engine(engine.clone());
+            }
+            let pending = runner.pending().map_err(|err| err.to_string())?;
+            if pending.is_empty() {
+                return Ok(());
+            }
+            let versions: Vec<String> = pending.iter().map(|m| m.version.to_string()).collect();
+            Err(format!("pending migrations: {}", versions.join(", ")))
+        });
+    }
+}
+
+fn is_missing_table(message: &str) -> bool {
+    let message = message.to_ascii_lowercase();
+    message.contains(MIGRATIONS_TABLE)
+        && (message.contains("does not exist")
+            || message.contains("no such table")
+            || message.contains("doesn't exist"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lock_timestamps_sort_chronologically() {
+        let at = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+        assert_eq!(lock_timestamp(at), "2023-11-14 22:13:20");
+        let earlier = lock_timestamp(at - Duration::from_secs(600));
+        assert!(earlier < lock_timestamp(at));
+    }
+}
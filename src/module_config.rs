@@ -0,0 +1,165 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+use std::thread;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+
+use crate::control_plane::ControlPlaneClient;
+use crate::error::ModuleKitError;
+use crate::http_transport::HttpRequest;
+use crate::shutdown::TaskSupervisor;
+use crate::token_provider::ServiceTokenProvider;
+
+const CONFIG_ENDPOINT_PATH: &str = "modules/runtime/config";
+
+/// Fetches the module's runtime configuration document from the control plane.
+pub struct ModuleConfigClient<T> {
+    control_plane: ControlPlaneClient,
+    tokens: Arc<ServiceTokenProvider>,
+    path: String,
+    cache: Mutex<Option<CachedConfig<T>>>,
+}
+
+struct CachedConfig<T> {
+    value: Arc<T>,
+    etag: Option<String>,
+    body: String,
+}
+
+impl<T> ModuleConfigClient<T>
+where
+    T: DeserializeOwned + Send + Sync + 'static,
+{
+    pub fn new(control_plane: ControlPlaneClient, tokens: Arc<ServiceTokenProvider>) -> Self {
+        Self {
+            control_plane,
+            tokens,
+            path: CONFIG_ENDPOINT_PATH.to_string(),
+            cache: Mutex::new(None),
+        }
+    }
+
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    /// Last successfully fetched configuration, if any.
+    pub fn current(&self) -> Option<Arc<T>> {
+        self.cache
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|cached| Arc::clone(&cached.value))
+    }
+
+    /// Fetches the configuration, reusing the cached copy when the server
+    /// reports it unchanged.
+    pub fn fetch(&self) -> Result<Arc<T>, ModuleKitError> {
+        self.poll().map(|(value, _)| value)
+    }
+
+    /// Polls every `interval` on a background thread and calls `on_change`
+    /// whenever a new configuration version is fetched.
+    pub fn watch(
+        self: &Arc<Self>,
+        interval: Duration,
+        on_change: impl Fn(Arc<T>) + Send + 'static,
+    ) -> ConfigWatchHandle {
+        let client = Arc::clone(self);
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let thread_shutdown = Arc::clone(&shutdown);
+        let handle = thread::spawn(move || {
+            while !thread_shutdown.load(Ordering::SeqCst) {
+                if let Ok((value, true)) = client.poll() {
+                    on_change(value);
+                }
+                thread::park_timeout(interval);
+            }
+        });
+        ConfigWatchHandle {
+            shutdown,
+            thread: Some(handle),
+        }
+    }
+
+    /// Like `watch`, but runs under `supervisor` and stops with its shutdown signal.
+    pub fn watch_supervised(
+        self: &Arc<Self>,
+        supervisor: &TaskSupervisor,
+        interval: Duration,
+        on_change: impl Fn(Arc<T>) + Send + 'static,
+    ) {
+        let client = Arc::clone(self);
+        supervisor.spawn("fenrir-config-watch", move |signal| loop {
+            if let Ok((value, true)) = client.poll() {
+                on_change(value);
+            }
+            if signal.wait_timeout(interval) {
+                break;
+            }
+        });
+    }
+
+    fn poll(&self) -> Result<(Arc<T>, bool), ModuleKitError> {
+        let bearer = self.tokens.current_token()?;
+        let url = self.control_plane.endpoint(&self.path)?;
+        let etag = self
+            .cache
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|cached| cached.etag.clone());
+        let mut request = HttpRequest::get(url).bearer_auth(bearer.expose());
+        if let Some(tag) = etag {
+            request = request.header("if-none-match", tag);
+        }
+        let response = self.control_plane.send(&request)?;
+        if response.status == 304 {
+            if let Some(value) = self.current() {
+                return Ok((value, false));
+            }
+        }
+        if !response.is_success() {
+            return Err(ModuleKitError::ControlPlaneStatus {
+                status: response.status,
+                body: response.text(),
+            });
+        }
+        let etag = response.header("etag").map(str::to_string);
+        let body = response.text();
+        let mut cache = self.cache.lock().unwrap();
+        if let Some(cached) = cache.as_mut() {
+            if cached.body == body {
+                cached.etag = etag;
+                return Ok((Arc::clone(&cached.value), false));
+            }
+        }
+        let value = Arc::new(serde_json::from_str::<T>(&body)?);
+        *cache = Some(CachedConfig {
+            value: Arc::clone(&value),
+            etag,
+            body,
+        });
+        Ok((value, true))
+    }
+}
+
+/// Stops the configuration watcher when dropped.
+pub struct ConfigWatchHandle {
+    shutdown: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Drop for ConfigWatchHandle {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.thread.take() {
+            handle.thread().unpark();
+            let _ = handle.join();
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::control_plane::{default_transport, ensure_trailing_slash, ControlPlaneClient};
+use crate::env::{service_url_from_source, EnvSource, ModuleEnvironment, ProcessEnv};
+use crate::error::ModuleKitError;
+use crate::http_transport::{
+    HttpMethod, HttpRequest, HttpResponse, HttpTransport, OutboundHeaders,
+};
+use crate::token_provider::ServiceTokenProvider;
+
+const SERVICES_PATH: &str = "modules/runtime/services/";
+
+#[derive(Deserialize)]
+struct ServiceLocation {
+    base_url: Url,
+}
+
+/// HTTP client for calling other Fenrir services with the module's service token.
+///
+/// Targets resolve from `FENRIR_SERVICE_URL_<SERVICE_ID>` first and then from
+/// the control plane service directory; resolved URLs are cached.
+pub struct ModuleHttpClient {
+    transport: Arc<dyn HttpTransport>,
+    tokens: Arc<ServiceTokenProvider>,
+    control_plane: Option<ControlPlaneClient>,
+    headers: OutboundHeaders,
+    vars: Box<dyn EnvSource + Send + Sync>,
+    resolved: Mutex<HashMap<String, Url>>,
+}
+
+impl ModuleHttpClient {
+    pub fn new(
+        env: &ModuleEnvironment,
+        tokens: Arc<ServiceTokenProvider>,
+    ) -> Result<Self, ModuleKitError> {
+        let control_plane = match env.control_plane.url {
+            Some(_) => Some(ControlPlaneClient::new(&env.control_plane)?),
+            None => None,
+        };
+        Ok(Self {
+            transport: default_transport(&env.control_plane)?,
+            tokens,
+            control_plane,
+            headers: OutboundHeaders::default(),
+            vars: Box::new(ProcessEnv),
+            resolved: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Sends service calls through `transport` instead of the default one.
+    pub fn with_transport(mut self, transport: Arc<dyn HttpTransport>) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Reads `FENRIR_SERVICE_URL_<SERVICE_ID>` overrides from `vars` instead of
+    /// the process environment.
+    pub fn with_env_source(mut self, vars: impl EnvSource + Send + Sync + 'static) -> Self {
+        self.vars = Box::new(vars);
+        self
+    }
+
+    /// Attaches `headers` to every service call and directory lookup.
+    pub fn with_headers(mut self, headers: OutboundHeaders) -> Self {
+        self.control_plane = self
+            .control_plane
+            .map(|control_plane| control_plane.with_headers(headers.clone()));
+        self.headers = headers;
+        self
+    }
+
+    /// Base URL of `service_id`, with a trailing slash.
+    pub fn resolve(&self, service_id: &str) -> Result<Url, ModuleKitError> {
+        if let Some(url) = self.resolved.lock().unwrap().get(service_id) {
+            return Ok(url.clone());
+        }
+        let url = match service_url_from_source(self.vars.as_ref(), service_id)? {
+            Some(url) => url,
+            None => self.lookup(service_id)?,
+        };
+        let url = ensure_trailing_slash(url);
+        self.resolved
+            .lock()
+            .unwrap()
+            .insert(service_id.to_string(), url.clone());
+        Ok(url)
+    }
+
+    pub fn get(&self, service_id: &str, path: &str) -> Result<HttpResponse, ModuleKitError> {
+        self.send(HttpMethod::Get, service_id, path, |request| request)
+    }
+
+    pub fn post_json<B: Serialize>(
+        &self,
+        service_id: &str,
+        path: &str,
+        body: &B,
+    ) -> Result<HttpResponse, ModuleKitError> {
+        let body = serde_json::to_vec(body)?;
+        self.send(HttpMethod::Post, service_id, path, |request| {
+            request.json_body(body.clone())
+        })
+    }
+
+    /// Sends `method path` to `service_id`, letting `customize` add headers or a body.
+    ///
+    /// A 401 triggers one token refresh and one retry; the second response is
+    /// returned as-is.
+    pub fn send(
+        &self,
+        method: HttpMethod,
+        service_id: &str,
+        path: &str,
+        customize: impl Fn(HttpRequest) -> HttpRequest,
+    ) -> Result<HttpResponse, ModuleKitError> {
+        let url = self
+            .resolve(service_id)?
+            .join(path.trim_start_matches('/'))
+            .map_err(ModuleKitError::ControlPlaneUrl)?;
+        let send = |bearer: &str| {
+            let request = customize(HttpRequest::new(method, url.clone()).bearer_auth(bearer));
+            self.transport.send(&self.headers.apply(&request))
+        };
+        let response = send(self.tokens.current_token()?.expose())?;
+        if response.status != 401 {
+            return Ok(response);
+        }
+        if self.tokens.refresh_now().is_err() {
+            return Ok(response);
+        }
+        send(self.tokens.current_token()?.expose())
+    }
+
+    fn lookup(&self, service_id: &str) -> Result<Url, ModuleKitError> {
+        let control_plane = self
+            .control_plane
+            .as_ref()
+            .ok_or_else(|| ModuleKitError::ServiceNotFound(service_id.to_string()))?;
+        let mut url = control_plane.endpoint(SERVICES_PATH)?;
+        url.path_segments_mut()
+            .map_err(|_| ModuleKitError::ServiceNotFound(service_id.to_string()))?
+            .pop_if_empty()
+            .push(service_id);
+        let bearer = self.tokens.current_token()?;
+        match control_plane.get_json_url::<ServiceLocation>(bearer.expose(), url) {
+            Ok(location) => Ok(location.base_url),
+            Err(ModuleKitError::ControlPlaneStatus { status: 404, .. }) => {
+                Err(ModuleKitError::ServiceNotFound(service_id.to_string()))
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
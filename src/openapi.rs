@@ -0,0 +1,147 @@
+use serde_json::{json, Map, Value as JsonValue};
+
+use crate::service::{ModuleEndpointDescriptor, ModuleReportedServices, ModuleServiceDescriptor};
+
+const OPENAPI_VERSION: &str = "3.1.0";
+const SECURITY_SCHEME: &str = "fenrirBearer";
+/// Methods documented for endpoints that do not restrict methods.
+const ANY_METHODS: &[&str] = &["get", "post", "put", "patch", "delete"];
+
+impl ModuleReportedServices {
+    /// Minimal OpenAPI 3.1 document built from the declared endpoints.
+    ///
+    /// Each service becomes a tag and each endpoint a path below its
+    /// `route_prefix`. Required scopes, service-wide and per endpoint, are
+    /// listed on a bearer security requirement. Services without endpoints
+    /// appear only as tags. `info.version` is `0.0.0`; set it afterwards if
+    /// the catalog should show a release.
+    pub fn to_openapi(&self) -> JsonValue {
+        let mut paths = Map::new();
+        for service in &self.services {
+            for endpoint in &service.endpoints {
+                let path = join_path(service.route_prefix.as_deref(), &endpoint.path);
+                let item = paths
+                    .entry(path.clone())
+                    .or_insert_with(|| JsonValue::Object(Map::new()));
+                if let JsonValue::Object(item) = item {
+                    for method in endpoint_methods(endpoint) {
+                        item.insert(method.clone(), operation(service, endpoint, &path, &method));
+                    }
+                }
+            }
+        }
+        let tags: Vec<JsonValue> = self
+            .services
+            .iter()
+            .map(|service| {
+                let mut tag = json!({ "name": service.service_id });
+                if let Some(description) = service.description.as_ref().or(service.name.as_ref()) {
+                    tag["description"] = json!(description);
+                }
+                tag
+            })
+            .collect();
+        json!({
+            "openapi": OPENAPI_VERSION,
+            "info": {
+                "title": self.module_id,
+                "version": "0.0.0",
+            },
+            "tags": tags,
+            "paths": paths,
+            "components": {
+                "securitySchemes": {
+                    SECURITY_SCHEME: {
+                        "type": "http",
+                        "scheme": "bearer",
+                        "bearerFormat": "JWT",
+                    }
+                }
+            },
+        })
+    }
+}
+
+fn operation(
+    service: &ModuleServiceDescriptor,
+    endpoint: &ModuleEndpointDescriptor,
+    path: &str,
+    method: &str,
+) -> JsonValue {
+    let mut scopes: Vec<&String> = service
+        .required_scopes
+        .iter()
+        .chain(&endpoint.required_scopes)
+        .collect();
+    scopes.sort();
+    scopes.dedup();
+    let parameters: Vec<JsonValue> = path
+        .split('/')
+        .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+        .map(|name| {
+            json!({
+                "name": name,
+                "in": "path",
+                "required": true,
+                "schema": { "type": "string" },
+            })
+        })
+        .collect();
+    let mut operation = json!({
+        "operationId": operation_id(&service.service_id, method, path),
+        "tags": [service.service_id],
+        "security": [{ SECURITY_SCHEME: scopes }],
+        "responses": {
+            "default": { "description": "Response from the module" }
+        },
+    });
+    if let Some(summary) = &endpoint.summary {
+        operation["summary"] = json!(summary);
+    }
+    if !parameters.is_empty() {
+        operation["parameters"] = json!(parameters);
+    }
+    if let Some(limit) = &endpoint.rate_limit {
+        operation["x-fenrir-rate-limit"] = json!(limit);
+    }
+    operation
+}
+
+fn endpoint_methods(endpoint: &ModuleEndpointDescriptor) -> Vec<String> {
+    if endpoint.methods.is_empty() {
+        ANY_METHODS
+            .iter()
+            .map(|method| method.to_string())
+            .collect()
+    } else {
+        endpoint
+            .methods
+            .iter()
+            .map(|method| method.to_ascii_lowercase())
+            .collect()
+    }
+}
+
+fn join_path(prefix: Option<&str>, path: &str) -> String {
+    let prefix = prefix.unwrap_or_default().trim_end_matches('/');
+    match path.trim_start_matches('/') {
+        "" if prefix.is_empty() => "/".to_string(),
+        "" => prefix.to_string(),
+        rest => format!("{prefix}/{rest}"),
+    }
+}
+
+/// `service.method.segment_segment`, with parameters reduced to their names.
+fn operation_id(service_id: &str, method: &str, path: &str) -> String {
+    let segments: Vec<&str> = path
+        .split('/')
+        .map(|segment| segment.trim_start_matches('{').trim_end_matches('}'))
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    let name = if segments.is_empty() {
+        "root".to_string()
+    } else {
+        segments.join("_")
+    };
+    format!("{service_id}.{method}.{name}")
+}
@@ -0,0 +1,357 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+use serde_json::Value as JsonValue;
+
+use crate::connector::DbConnectorClient;
+use crate::connector_request::DbConnectorIntent;
+use crate::error::ModuleKitError;
+use crate::sql::{Filter, Select, SqlDialect, SqlOp};
+use crate::values::{standard_coercion, DbRow};
+
+const DEFAULT_PAGE_SIZE: u32 = 50;
+
+/// One page of results. `next_cursor` resumes after the last item and is
+/// `None` on the last page.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+    pub has_more: bool,
+}
+
+/// Position encoded in a page cursor. Keyset positions record the key type
+/// so a resumed cursor binds the key the same way it was read.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum PagePosition {
+    Offset(u64),
+    After(String),
+    AfterInteger(i64),
+}
+
+impl PagePosition {
+    fn encode(&self) -> Result<String, ModuleKitError> {
+        Ok(URL_SAFE_NO_PAD.encode(serde_json::to_vec(self)?))
+    }
+
+    fn decode(cursor: &str) -> Result<Self, ModuleKitError> {
+        let invalid = |message: String| ModuleKitError::InvalidCursor(message);
+        let bytes = URL_SAFE_NO_PAD
+            .decode(cursor)
+            .map_err(|err| invalid(err.to_string()))?;
+        serde_json::from_slice(&bytes).map_err(|err| invalid(err.to_string()))
+    }
+}
+
+enum PageMode {
+    Offset,
+    Keyset {
+        column: String,
+        descending: bool,
+        integer: bool,
+    },
+}
+
+type RowMapper<'a, T> = Box<dyn Fn(DbRow<'_>) -> Result<T, ModuleKitError> + 'a>;
+
+/// Walks a `Select` page by page, mapping each row to `T`.
+///
+/// Offset mode adds `LIMIT`/`OFFSET` to the query and relies on its own
+/// `ORDER BY` for a stable order. Keyset mode orders by a unique, non-null
+/// column and resumes after the last value seen, so rows inserted while
+/// paging do not shift later pages; the query must not set its own order.
+/// Keys are compared as text unless `integer_key` is set.
+///
+/// Cursors are opaque strings that can be handed to API clients and fed
+/// back through `resume`.
+pub struct Paginator<'a, T> {
+    client: &'a DbConnectorClient,
+    select: Select,
+    map: RowMapper<'a, T>,
+    mode: PageMode,
+    page_size: u32,
+    engine: Option<String>,
+    position: Option<PagePosition>,
+    done: bool,
+}
+
+impl<'a, T> Paginator<'a, T> {
+    pub fn offset(
+        client: &'a DbConnectorClient,
+        select: Select,
+        map: impl Fn(DbRow<'_>) -> Result<T, ModuleKitError> + 'a,
+    ) -> Self {
+        Self::with_mode(client, select, map, PageMode::Offset)
+    }
+
+    pub fn keyset(
+        client: &'a DbConnectorClient,
+        select: Select,
+        column: impl Into<String>,
+        map: impl Fn(DbRow<'_>) -> Result<T, ModuleKitError> + 'a,
+    ) -> Self {
+        let mode = PageMode::Keyset {
+            column: column.into(),
+            descending: false,
+            integer: false,
+        };
+        Self::with_mode(client, select, map, mode)
+    }
+
+    fn with_mode(
+        client: &'a DbConnectorClient,
+        select: Select,
+        map: impl Fn(DbRow<'_>) -> Result<T, ModuleKitError> + 'a,
+        mode: PageMode,
+    ) -> Self {
+        Self {
+            client,
+            select,
+            map: Box::new(map),
+            mode,
+            page_size: DEFAULT_PAGE_SIZE,
+            engine: None,
+            position: None,
+            done: false,
+        }
+    }
+
+    /// Keyset mode only: walks the key column from highest to lowest.
+    pub fn descending(mut self) -> Self {
+        if let PageMode::Keyset { descending, .. } = &mut self.mode {
+            *descending = true;
+        }
+        self
+    }
+
+    /// Keyset mode only: reads and binds the key column as an integer, so it
+    /// compares numerically instead of as text.
+    pub fn integer_key(mut self) -> Self {
+        if let PageMode::Keyset { integer, .. } = &mut self.mode {
+            *integer = true;
+        }
+        self
+    }
+
+    /// Items per page; defaults to 50.
+    pub fn page_size(mut self, value: u32) -> Self {
+        self.page_size = value.max(1);
+        self
+    }
+
+    pub fn engine(mut self, value: impl Into<String>) -> Self {
+        self.engine = Some(value.into());
+        self
+    }
+
+    /// Continues from a `Page::next_cursor` returned earlier.
+    pub fn resume(mut self, cursor: &str) -> Result<Self, ModuleKitError> {
+        let position = PagePosition::decode(cursor)?;
+        let matches_mode = matches!(
+            (&position, &self.mode),
+            (PagePosition::Offset(_), PageMode::Offset)
+                | (
+                    PagePosition::After(_),
+                    PageMode::Keyset { integer: false, .. }
+                )
+                | (
+                    PagePosition::AfterInteger(_),
+                    PageMode::Keyset { integer: true, .. }
+                )
+        );
+        if !matches_mode {
+            return Err(ModuleKitError::InvalidCursor(
+                "cursor was issued for another pagination mode or key type".into(),
+            ));
+        }
+        self.position = Some(position);
+        Ok(self)
+    }
+
+    /// False once a page with `has_more == false` was returned.
+    pub fn has_more(&self) -> bool {
+        !self.done
+    }
+
+    /// Fetches the next page; returns empty pages after the last one.
+    pub fn next_page(&mut self) -> Result<Page<T>, ModuleKitError> {
+        if self.done {
+            return Ok(Page {
+                items: Vec::new(),
+                next_cursor: None,
+                has_more: false,
+            });
+        }
+        let dialect = self
+            .engine
+            .as_deref()
+            .map(SqlDialect::for_engine)
+            .unwrap_or_default();
+        let response = self
+            .client
+            .execute(
+                self.page_query()?.build(dialect)?,
+                DbConnectorIntent::Read,
+                self.engine.as_deref(),
+                None,
+            )?
+            .into_result()?;
+        let coercion = match &self.engine {
+            Some(engine) => self.client.cell_coercion(engine),
+            None => standard_coercion().clone(),
+        };
+        let mut items = Vec::new();
+        let mut last_key = None;
+        let mut fetched = 0;
+        for result in response.results.iter().flatten() {
+            for row in result.rows_with(&coercion) {
+                fetched += 1;
+                if fetched > self.page_size {
+                    break;
+                }
+                if let PageMode::Keyset {
+                    column, integer, ..
+                } = &self.mode
+                {
+                    last_key = Some(if *integer {
+                        PagePosition::AfterInteger(row.get::<i64>(column)?)
+                    } else {
+                        PagePosition::After(row.get::<String>(column)?)
+                    });
+                }
+                items.push((self.map)(row)?);
+            }
+        }
+        let has_more = fetched > self.page_size;
+        self.position = match &self.mode {
+            PageMode::Offset => {
+                let skipped = match &self.position {
+                    Some(PagePosition::Offset(offset)) => *offset,
+                    _ => 0,
+                };
+                Some(PagePosition::Offset(skipped + items.len() as u64))
+            }
+            PageMode::Keyset { .. } => last_key,
+        };
+        self.done = !has_more;
+        let next_cursor = match (&self.position, has_more) {
+            (Some(position), true) => Some(position.encode()?),
+            _ => None,
+        };
+        Ok(Page {
+            items,
+            next_cursor,
+            has_more,
+        })
+    }
+
+    /// The query for the next page, fetching one extra row to detect more.
+    fn page_query(&self) -> Result<Select, ModuleKitError> {
+        let limit = self.page_size as u64 + 1;
+        match (&self.mode, &self.position) {
+            (PageMode::Offset, position) => {
+                let offset = match position {
+                    Some(PagePosition::Offset(offset)) => *offset,
+                    _ => 0,
+                };
+                Ok(self.select.clone().limit(limit).offset(offset))
+            }
+            (
+                PageMode::Keyset {
+                    column, descending, ..
+                },
+                position,
+            ) => {
+                if self.select.is_ordered() {
+                    return Err(ModuleKitError::InvalidSqlStatement(
+                        "keyset pagination orders by its key column; remove the select's ORDER BY"
+                            .into(),
+                    ));
+                }
+                let mut select = self.select.clone();
+                let key = match position {
+                    Some(PagePosition::After(key)) => Some(JsonValue::from(key.as_str())),
+                    Some(PagePosition::AfterInteger(key)) => Some(JsonValue::from(*key)),
+                    _ => None,
+                };
+                if let Some(key) = key {
+                    let op = if *descending { SqlOp::Lt } else { SqlOp::Gt };
+                    select = select.filter(Filter::compare(column.clone(), op, key));
+                }
+                let select = if *descending {
+                    select.order_by_desc(column.clone())
+                } else {
+                    select.order_by(column.clone())
+                };
+                Ok(select.limit(limit))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connector_endpoint::ConnectorEndpoint;
+    use crate::connector_request::DbConnectorCommand;
+    use crate::env::ModuleEnvironment;
+
+    fn client() -> DbConnectorClient {
+        let env = ModuleEnvironment::builder()
+            .module_id("module")
+            .service_id("service")
+            .service_token("token")
+            .connector(ConnectorEndpoint::Tcp {
+                addr: "127.0.0.1:9".into(),
+            })
+            .build()
+            .unwrap();
+        DbConnectorClient::from_environment(env).unwrap()
+    }
+
+    fn bound_key<T>(paginator: &Paginator<'_, T>) -> JsonValue {
+        let command = paginator
+            .page_query()
+            .unwrap()
+            .build(SqlDialect::Ansi)
+            .unwrap();
+        let DbConnectorCommand::Prepared { mut params, .. } = command else {
+            panic!("selects build prepared commands");
+        };
+        params.remove(0).value
+    }
+
+    #[test]
+    fn keyset_cursors_bind_keys_as_the_type_they_were_read() {
+        let client = client();
+        let text = Paginator::keyset(&client, Select::from("t"), "code", |_| Ok(()))
+            .resume(&PagePosition::After("0042".into()).encode().unwrap())
+            .unwrap();
+        assert_eq!(bound_key(&text), JsonValue::from("0042"));
+
+        let cursor = PagePosition::AfterInteger(42).encode().unwrap();
+        let integer = Paginator::keyset(&client, Select::from("t"), "id", |_| Ok(()))
+            .integer_key()
+            .resume(&cursor)
+            .unwrap();
+        assert_eq!(bound_key(&integer), JsonValue::from(42));
+        assert!(
+            Paginator::keyset(&client, Select::from("t"), "id", |_| Ok(()))
+                .resume(&cursor)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn keyset_refuses_a_select_with_its_own_order() {
+        let client = client();
+        let select = Select::from("t").order_by("created_at");
+        let mut pages = Paginator::keyset(&client, select, "id", |_| Ok(()));
+        assert!(matches!(
+            pages.next_page(),
+            Err(ModuleKitError::InvalidSqlStatement(_))
+        ));
+    }
+}
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use crate::connector::DbConnectorClient;
+use crate::connector_request::{
+    DbConnectorCommand, DbConnectorIntent, DbPreparedParam, ExecuteOptions,
+};
+use crate::connector_response::DbConnectorResponse;
+use crate::error::ModuleKitError;
+
+const OFFSETS_TABLE: &str = "_modulekit_projection_offsets";
+
+/// An event delivered to a projection, positioned by its per-topic offset.
+#[derive(Debug, Clone)]
+pub struct ProjectionEvent<E> {
+    pub topic: String,
+    pub offset: u64,
+    pub payload: E,
+}
+
+/// Maps an event to the idempotent upserts that apply it to the read model.
+pub trait ProjectionHandler<E> {
+    fn commands(
+        &self,
+        event: &ProjectionEvent<E>,
+    ) -> Result<Vec<DbConnectorCommand>, ModuleKitError>;
+}
+
+impl<E, F> ProjectionHandler<E> for F
+where
+    F: Fn(&ProjectionEvent<E>) -> Result<Vec<DbConnectorCommand>, ModuleKitError>,
+{
+    fn commands(
+        &self,
+        event: &ProjectionEvent<E>,
+    ) -> Result<Vec<DbConnectorCommand>, ModuleKitError> {
+        self(event)
+    }
+}
+
+/// Applies events to database tables and records the last applied offset per topic.
+///
+/// The connector protocol executes one command per request, so an event's
+/// commands and its offset update are not atomic. Delivery is therefore
+/// at-least-once and handlers must emit idempotent upserts.
+pub struct Projection<'a, E, H> {
+    client: &'a DbConnectorClient,
+    name: String,
+    handler: H,
+    engine: Option<String>,
+    offsets: HashMap<String, u64>,
+    _events: PhantomData<fn(E)>,
+}
+
+impl<'a, E, H> Projection<'a, E, H>
+where
+    H: ProjectionHandler<E>,
+{
+    pub fn new(client: &'a DbConnectorClient, name: impl Into<String>, handler: H) -> Self {
+        Self {
+            client,
+            name: name.into(),
+            handler,
+            engine: None,
+            offsets: HashMap::new(),
+            _events: PhantomData,
+        }
+    }
+
+    pub fn engine(mut self, value: impl Into<String>) -> Self {
+        self.engine = Some(value.into());
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn offset(&self, topic: &str) -> Option<u64> {
+        self.offsets.get(topic).copied()
+    }
+
+    pub fn ensure_offsets_table(&self) -> Result<(), ModuleKitError> {
+        let statement = format!(
+            "CREATE TABLE IF NOT EXISTS {OFFSETS_TABLE} (\
+             projection TEXT NOT NULL, \
+             topic TEXT NOT NULL, \
+             last_offset BIGINT NOT NULL, \
+             PRIMARY KEY (projection, topic))"
+        );
+        self.run_offsets(
+            DbConnectorCommand::Simple { statement },
+            DbConnectorIntent::Write,
+        )?;
+        Ok(())
+    }
+
+    /// Loads the stored offsets for this projection, replacing any cached ones.
+    pub fn load_offsets(&mut self) -> Result<(), ModuleKitError> {
+        let command = DbConnectorCommand::Prepared {
+            statement: format!(
+                "SELECT topic, last_offset FROM {OFFSETS_TABLE} WHERE projection = :projection"
+            ),
+            params: vec![DbPreparedParam::new("projection", self.name.as_str())],
+        };
+        let response = self.run_offsets(command, DbConnectorIntent::Read)?;
+        self.offsets.clear();
+        for result in response.results.iter().flatten() {
+            for row in result.rows() {
+                let offset = row.get::<i64>("last_offset")?.max(0) as u64;
+                self.offsets.insert(row.get::<String>("topic")?, offset);
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies `event` unless its offset was already applied; returns whether it ran.
+    pub fn apply(&mut self, event: &ProjectionEvent<E>) -> Result<bool, ModuleKitError> {
+        if self
+            .offset(&event.topic)
+            .is_some_and(|applied| event.offset <= applied)
+        {
+            return Ok(false);
+        }
+        for command in self.handler.commands(event)? {
+            self.run(command, DbConnectorIntent::Write)?;
+        }
+        self.store_offset(&event.topic, event.offset)?;
+        self.offsets.insert(event.topic.clone(), event.offset);
+        Ok(true)
+    }
+
+    /// Applies events in order, stopping at the first failure; returns how many ran.
+    pub fn apply_all<'e>(
+        &mut self,
+        events: impl IntoIterator<Item = &'e ProjectionEvent<E>>,
+    ) -> Result<usize, ModuleKitError>
+    where
+        E: 'e,
+    {
+        let mut applied = 0;
+        for event in events {
+            if self.apply(event)? {
+                applied += 1;
+            }
+        }
+        Ok(applied)
+    }
+
+    fn store_offset(&self, topic: &str, offset: u64) -> Result<(), ModuleKitError> {
+        let command = DbConnectorCommand::Prepared {
+            statement: format!(
+                "INSERT INTO {OFFSETS_TABLE} (projection, topic, last_offset) \
+                 VALUES (:projection, :topic, :last_offset) \
+                 ON CONFLICT (projection, topic) DO UPDATE SET last_offset = excluded.last_offset"
+            ),
+            params: vec![
+                DbPreparedParam::new("projection", self.name.as_str()),
+                DbPreparedParam::new("topic", topic),
+                DbPreparedParam::new("last_offset", offset as i64),
+            ],
+        };
+        self.run_offsets(command, DbConnectorIntent::Write)?;
+        Ok(())
+    }
+
+    fn run(
+        &self,
+        command: DbConnectorCommand,
+        intent: DbConnectorIntent,
+    ) -> Result<DbConnectorResponse, ModuleKitError> {
+        self.client
+            .execute(command, intent, self.engine.as_deref(), None)?
+            .into_result()
+    }
+
+    /// Statements on the offsets table, which every tenant shares, so no
+    /// tenant context is bound into them.
+    fn run_offsets(
+        &self,
+        command: DbConnectorCommand,
+        intent: DbConnectorIntent,
+    ) -> Result<DbConnectorResponse, ModuleKitError> {
+        let options = ExecuteOptions::new().without_tenant();
+        self.client
+            .execute_with(command, intent, self.engine.as_deref(), None, &options)?
+            .into_result()
+    }
+}
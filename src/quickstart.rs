@@ -0,0 +1,80 @@
+use std::sync::Arc;
+
+use crate::connector::DbConnectorClient;
+use crate::env::ModuleEnvironment;
+use crate::error::ModuleKitError;
+use crate::health::{HealthRegistry, HealthServer};
+use crate::service::ModuleReportedServices;
+use crate::service_endpoint::ServiceEndpoint;
+use crate::token_provider::ServiceTokenProvider;
+
+const DEFAULT_HEALTH_ADDR: &str = "0.0.0.0:8080";
+
+/// Everything a small module needs, wired from environment defaults.
+pub struct QuickStart {
+    pub env: ModuleEnvironment,
+    pub db: Arc<DbConnectorClient>,
+    pub tokens: Arc<ServiceTokenProvider>,
+    /// Serves `/healthz`, `/readyz` and, with `verify_service_scopes`,
+    /// `/.fenrir/services` until dropped.
+    pub health: HealthServer,
+    /// Checks reported by `/readyz`.
+    pub health_registry: Arc<HealthRegistry>,
+}
+
+/// Reads the module environment, builds a connector client sharing one token
+/// provider, and starts the health endpoint on `FENRIR_HEALTH_ADDR`
+/// (default `0.0.0.0:8080`).
+///
+/// Intentionally opinionated; modules that need control over any of these
+/// steps should wire `ModuleEnvironment` and the clients themselves.
+pub fn quickstart() -> Result<QuickStart, ModuleKitError> {
+    quickstart_with(QuickStartOptions::default())
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct QuickStartOptions {
+    services: Option<ModuleReportedServices>,
+}
+
+impl QuickStartOptions {
+    /// Exchanges every `required_scopes` entry of `services` before anything
+    /// else starts, failing with `ModuleKitError::ScopesNotGranted` if the
+    /// module identity lacks a grant.
+    pub fn verify_service_scopes(mut self, services: ModuleReportedServices) -> Self {
+        self.services = Some(services);
+        self
+    }
+}
+
+/// `quickstart` with startup checks enabled by `options`.
+pub fn quickstart_with(options: QuickStartOptions) -> Result<QuickStart, ModuleKitError> {
+    let env = ModuleEnvironment::from_env()?;
+    let tokens = Arc::new(env.token_provider()?);
+    if let Some(services) = &options.services {
+        let report = tokens.preflight_scopes(services.required_scopes());
+        if !report.is_complete() {
+            return Err(ModuleKitError::ScopesNotGranted(report));
+        }
+    }
+    let health_registry = Arc::new(HealthRegistry::new());
+    let mut endpoint = ServiceEndpoint::new(Arc::clone(&health_registry));
+    if let Some(services) = options.services {
+        endpoint = endpoint.services(services);
+    }
+    let health = HealthServer::start_with_endpoint(
+        env.health_addr.as_deref().unwrap_or(DEFAULT_HEALTH_ADDR),
+        endpoint,
+    )?;
+    let db = Arc::new(DbConnectorClient::with_token_provider(
+        env.clone(),
+        Arc::clone(&tokens),
+    ));
+    Ok(QuickStart {
+        env,
+        db,
+        tokens,
+        health,
+        health_registry,
+    })
+}
@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+use crate::control_plane::ControlPlaneClient;
+use crate::error::ModuleKitError;
+use crate::token_provider::ServiceTokenProvider;
+
+const QUOTAS_PATH: &str = "modules/runtime/quotas";
+
+/// Throttling state for one caller, rendered as `RateLimit-*` and `Retry-After` headers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitStatus {
+    pub limit: u64,
+    pub remaining: u64,
+    /// Time until the current window resets.
+    pub reset: Duration,
+    pub window: Duration,
+}
+
+impl RateLimitStatus {
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining == 0
+    }
+
+    /// Header names and values to attach to a response; includes `Retry-After`
+    /// once the quota is exhausted.
+    pub fn headers(&self) -> Vec<(&'static str, String)> {
+        let reset = ceil_secs(self.reset);
+        let mut headers = vec![
+            ("RateLimit-Limit", self.limit.to_string()),
+            ("RateLimit-Remaining", self.remaining.to_string()),
+            ("RateLimit-Reset", reset.to_string()),
+            (
+                "RateLimit-Policy",
+                format!("{};w={}", self.limit, ceil_secs(self.window)),
+            ),
+        ];
+        if self.is_exhausted() {
+            headers.push(("Retry-After", reset.max(1).to_string()));
+        }
+        headers
+    }
+
+    /// The stricter of two statuses, e.g. the local limiter and the control plane quota.
+    pub fn min(self, other: RateLimitStatus) -> RateLimitStatus {
+        if other.remaining < self.remaining {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+/// Fixed-window request limiter keyed by tenant.
+pub struct TenantRateLimiter {
+    limit: u64,
+    window: Duration,
+    windows: Mutex<HashMap<String, (Instant, u64)>>,
+}
+
+impl TenantRateLimiter {
+    pub fn new(limit: u64, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Counts one request for `tenant`; `Err` carries the status to return with a 429.
+    pub fn check(&self, tenant: &str) -> Result<RateLimitStatus, RateLimitStatus> {
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap();
+        let (started, used) = windows.entry(tenant.to_string()).or_insert((now, 0));
+        if now.duration_since(*started) >= self.window {
+            *started = now;
+            *used = 0;
+        }
+        let reset = self.window.saturating_sub(now.duration_since(*started));
+        let allowed = *used < self.limit;
+        if allowed {
+            *used += 1;
+        }
+        let status = RateLimitStatus {
+            limit: self.limit,
+            remaining: self.limit - *used,
+            reset,
+            window: self.window,
+        };
+        if allowed {
+            Ok(status)
+        } else {
+            Err(status)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TenantQuota {
+    pub limit: u64,
+    pub remaining: u64,
+    pub reset_seconds: u64,
+    pub window_seconds: u64,
+}
+
+impl From<TenantQuota> for RateLimitStatus {
+    fn from(quota: TenantQuota) -> Self {
+        Self {
+            limit: quota.limit,
+            remaining: quota.remaining.min(quota.limit),
+            reset: Duration::from_secs(quota.reset_seconds),
+            window: Duration::from_secs(quota.window_seconds),
+        }
+    }
+}
+
+/// Reads per-tenant quota usage tracked by the control plane.
+pub struct TenantQuotas {
+    control_plane: ControlPlaneClient,
+    tokens: Arc<ServiceTokenProvider>,
+}
+
+impl TenantQuotas {
+    pub fn new(control_plane: ControlPlaneClient, tokens: Arc<ServiceTokenProvider>) -> Self {
+        Self {
+            control_plane,
+            tokens,
+        }
+    }
+
+    pub fn fetch(&self, tenant_id: &str) -> Result<TenantQuota, ModuleKitError> {
+        let mut url = self.control_plane.endpoint(QUOTAS_PATH)?;
+        url.query_pairs_mut().append_pair("tenant_id", tenant_id);
+        let bearer = self.tokens.current_token()?;
+        self.control_plane.get_json_url(bearer.expose(), url)
+    }
+}
+
+fn ceil_secs(duration: Duration) -> u64 {
+    duration.as_secs() + u64::from(duration.subsec_nanos() > 0)
+}
@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::connector_request::{
+    statement_fingerprint, DbConnectorCommand, DbLocale, DbTenantPolicy,
+};
+use crate::connector_response::DbConnectorResponse;
+
+/// Settings for `DbConnectorClient::set_result_cache`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResultCacheConfig {
+    /// How long a response is served from the cache.
+    pub ttl: Duration,
+    /// Responses kept before the oldest is evicted.
+    pub max_entries: usize,
+}
+
+impl Default for ResultCacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl: Duration::from_secs(30),
+            max_entries: 1024,
+        }
+    }
+}
+
+impl ResultCacheConfig {
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        Self { ttl, max_entries }
+    }
+}
+
+/// Everything that can change a read's result: the full statement and its
+/// parameters (which carry the tenant value), the tenant binding, engine and
+/// locale. The fingerprint only groups entries for debugging.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct ResultCacheKey {
+    fingerprint: String,
+    engine: Option<String>,
+    statement: String,
+    params: String,
+    tenant: Option<String>,
+    locale: Option<String>,
+}
+
+impl ResultCacheKey {
+    /// `None` for commands whose results are never cached.
+    pub(crate) fn new(
+        command: &DbConnectorCommand,
+        engine: Option<&str>,
+        tenant: Option<&DbTenantPolicy>,
+        locale: Option<&DbLocale>,
+    ) -> Option<Self> {
+        let (statement, params) = match command {
+            DbConnectorCommand::Simple { statement } => (statement, String::new()),
+            DbConnectorCommand::Prepared { statement, params } => {
+                (statement, serde_json::to_string(params).ok()?)
+            }
+            _ => return None,
+        };
+        Some(Self {
+            fingerprint: statement_fingerprint(statement),
+            engine: engine.map(str::to_string),
+            statement: statement.clone(),
+            params,
+            tenant: tenant.and_then(|policy| serde_json::to_string(policy).ok()),
+            locale: locale.and_then(|locale| serde_json::to_string(locale).ok()),
+        })
+    }
+}
+
+struct CachedResponse {
+    response: DbConnectorResponse,
+    expires_at: Instant,
+    tags: Vec<String>,
+}
+
+/// Read-through cache of successful read responses, owned by the client.
+pub(crate) struct ResultCache {
+    config: ResultCacheConfig,
+    entries: HashMap<ResultCacheKey, CachedResponse>,
+    /// Bumped by `clear`, so a read that started before can tell its result
+    /// may be stale.
+    generation: u64,
+}
+
+impl ResultCache {
+    pub(crate) fn new(config: ResultCacheConfig) -> Self {
+        Self {
+            config,
+            entries: HashMap::new(),
+            generation: 0,
+        }
+    }
+
+    pub(crate) fn config(&self) -> &ResultCacheConfig {
+        &self.config
+    }
+
+    pub(crate) fn get(&mut self, key: &ResultCacheKey) -> Option<DbConnectorResponse> {
+        let entry = self.entries.get(key)?;
+        if entry.expires_at <= Instant::now() {
+            self.entries.remove(key);
+            return None;
+        }
+        Some(entry.response.clone())
+    }
+
+    pub(crate) fn insert(
+        &mut self,
+        key: ResultCacheKey,
+        response: &DbConnectorResponse,
+        tags: &[String],
+    ) {
+        if self.config.max_entries == 0 {
+            return;
+        }
+        let now = Instant::now();
+        if self.entries.len() >= self.config.max_entries {
+            self.entries.retain(|_, entry| entry.expires_at > now);
+        }
+        while self.entries.len() >= self.config.max_entries {
+            // Every entry has the same TTL, so the earliest expiry is the oldest.
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.expires_at)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+        self.entries.insert(
+            key,
+            CachedResponse {
+                response: response.clone(),
+                expires_at: now + self.config.ttl,
+                tags: tags.to_vec(),
+            },
+        );
+    }
+
+    /// Drops entries stored with `tag`, returning how many.
+    pub(crate) fn invalidate(&mut self, tag: &str) -> usize {
+        let before = self.entries.len();
+        self.entries
+            .retain(|_, entry| !entry.tags.iter().any(|t| t == tag));
+        before - self.entries.len()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+        self.generation += 1;
+    }
+
+    pub(crate) fn generation(&self) -> u64 {
+        self.generation
+    }
+}
@@ -0,0 +1,197 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use time::{Duration, OffsetDateTime};
+
+use crate::connector::DbConnectorClient;
+use crate::connector_request::{DbConnectorCommand, DbConnectorIntent, DbPreparedParam};
+use crate::connector_response::DbConnectorResultView;
+use crate::control_plane::ControlPlaneClient;
+use crate::error::ModuleKitError;
+use crate::sql::SafeIdent;
+use crate::token_provider::ServiceTokenProvider;
+
+const RETENTION_PATH: &str = "modules/runtime/retention";
+const DEFAULT_TENANT_COLUMN: &str = "tenant_id";
+
+/// Retention obligations the control plane holds for one tenant.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    pub tenant_id: String,
+    #[serde(default)]
+    pub rules: Vec<RetentionRule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionRule {
+    pub table: String,
+    /// Column compared against the cutoff.
+    pub timestamp_column: String,
+    #[serde(default = "default_tenant_column")]
+    pub tenant_column: String,
+    pub max_age_days: u32,
+    #[serde(flatten)]
+    pub action: RetentionAction,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum RetentionAction {
+    Delete,
+    /// Sets the listed columns to NULL instead of removing the row.
+    Anonymize {
+        columns: Vec<String>,
+    },
+}
+
+impl RetentionRule {
+    pub fn cutoff(&self, now: OffsetDateTime) -> OffsetDateTime {
+        now - Duration::days(i64::from(self.max_age_days))
+    }
+
+    /// Builds the statement purging `tenant_id`'s rows older than the cutoff.
+    pub fn command(
+        &self,
+        tenant_id: &str,
+        now: OffsetDateTime,
+    ) -> Result<DbConnectorCommand, ModuleKitError> {
+        let table = SafeIdent::new(self.table.as_str())?;
+        let timestamp_column = SafeIdent::new(self.timestamp_column.as_str())?;
+        let tenant_column = SafeIdent::new(self.tenant_column.as_str())?;
+        let filter = format!("{tenant_column} = :tenant_id AND {timestamp_column} < :cutoff");
+        let statement = match &self.action {
+            RetentionAction::Delete => format!("DELETE FROM {table} WHERE {filter}"),
+            RetentionAction::Anonymize { columns } => {
+                if columns.is_empty() {
+                    return Err(ModuleKitError::InvalidIdentifier(format!(
+                        "anonymize rule for {table} lists no columns"
+                    )));
+                }
+                let assignments = columns
+                    .iter()
+                    .map(|column| SafeIdent::new(column.as_str()).map(|c| format!("{c} = NULL")))
+                    .collect::<Result<Vec<_>, _>>()?
+                    .join(", ");
+                format!("UPDATE {table} SET {assignments} WHERE {filter}")
+            }
+        };
+        Ok(DbConnectorCommand::Prepared {
+            statement,
+            params: vec![
+                DbPreparedParam::new("tenant_id", tenant_id),
+                DbPreparedParam::new("cutoff", self.cutoff(now)),
+            ],
+        })
+    }
+}
+
+/// Record of one rule applied by `RetentionRunner`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetentionAuditEntry {
+    pub tenant_id: String,
+    pub table: String,
+    pub action: RetentionAction,
+    pub cutoff: OffsetDateTime,
+    pub rows_affected: Option<u64>,
+    pub executed_at: OffsetDateTime,
+}
+
+/// Reads per-tenant retention policies from the control plane.
+pub struct RetentionPolicies {
+    control_plane: ControlPlaneClient,
+    tokens: Arc<ServiceTokenProvider>,
+}
+
+impl RetentionPolicies {
+    pub fn new(control_plane: ControlPlaneClient, tokens: Arc<ServiceTokenProvider>) -> Self {
+        Self {
+            control_plane,
+            tokens,
+        }
+    }
+
+    pub fn fetch(&self, tenant_id: &str) -> Result<RetentionPolicy, ModuleKitError> {
+        let mut url = self.control_plane.endpoint(RETENTION_PATH)?;
+        url.query_pairs_mut().append_pair("tenant_id", tenant_id);
+        let bearer = self.tokens.current_token()?;
+        self.control_plane.get_json_url(bearer.expose(), url)
+    }
+}
+
+type AuditCallback = Box<dyn Fn(&RetentionAuditEntry) + Send + Sync>;
+
+/// Executes retention rules and reports every purge to the audit callback.
+pub struct RetentionRunner<'a> {
+    client: &'a DbConnectorClient,
+    engine: Option<String>,
+    on_audit: Option<AuditCallback>,
+}
+
+impl<'a> RetentionRunner<'a> {
+    pub fn new(client: &'a DbConnectorClient) -> Self {
+        Self {
+            client,
+            engine: None,
+            on_audit: None,
+        }
+    }
+
+    pub fn engine(mut self, value: impl Into<String>) -> Self {
+        self.engine = Some(value.into());
+        self
+    }
+
+    pub fn on_audit(
+        mut self,
+        callback: impl Fn(&RetentionAuditEntry) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_audit = Some(Box::new(callback));
+        self
+    }
+
+    /// Applies every rule in order, stopping at the first failure.
+    pub fn run(
+        &self,
+        policy: &RetentionPolicy,
+    ) -> Result<Vec<RetentionAuditEntry>, ModuleKitError> {
+        let now = OffsetDateTime::now_utc();
+        let mut entries = Vec::with_capacity(policy.rules.len());
+        for rule in &policy.rules {
+            let command = rule.command(&policy.tenant_id, now)?;
+            let response = self
+                .client
+                .execute(
+                    command,
+                    DbConnectorIntent::Write,
+                    self.engine.as_deref(),
+                    None,
+                )?
+                .into_result()?;
+            let rows_affected = response
+                .results
+                .iter()
+                .flatten()
+                .find_map(|result| match result {
+                    DbConnectorResultView::AffectedRows { count } => Some(*count),
+                    _ => None,
+                });
+            let entry = RetentionAuditEntry {
+                tenant_id: policy.tenant_id.clone(),
+                table: rule.table.clone(),
+                action: rule.action.clone(),
+                cutoff: rule.cutoff(now),
+                rows_affected,
+                executed_at: OffsetDateTime::now_utc(),
+            };
+            if let Some(callback) = &self.on_audit {
+                callback(&entry);
+            }
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+}
+
+fn default_tenant_column() -> String {
+    DEFAULT_TENANT_COLUMN.to_string()
+}
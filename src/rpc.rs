@@ -0,0 +1,172 @@
+use std::sync::Arc;
+use std::thread::sleep;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+
+use crate::cancellation::unique_request_id;
+use crate::env::ModuleEnvironment;
+use crate::error::ModuleKitError;
+use crate::http_transport::{HttpMethod, IDEMPOTENCY_KEY_HEADER};
+use crate::module_http::ModuleHttpClient;
+use crate::token_provider::ServiceTokenProvider;
+
+const DEFAULT_RPC_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_RPC_RETRIES: u32 = 2;
+const DEFAULT_RPC_BACKOFF: Duration = Duration::from_millis(100);
+/// Longest error body quoted in `RpcFailed` when it is not JSON.
+const MAX_ERROR_BODY_CHARS: usize = 512;
+
+/// Typed JSON calls to other modules' internal services.
+///
+/// Each call POSTs the request to `path` on the service resolved by
+/// `ModuleHttpClient`, with the module's service token, and decodes a 2xx
+/// body as the response type. Failures and timeouts surface as
+/// `ModuleKitError::RpcFailed`.
+///
+/// Connection failures, timeouts and 502/503/504 replies are retried with
+/// linear backoff. Every attempt of one call carries the same
+/// `idempotency-key` header so the handler can drop repeats; set
+/// `retries(0)` for handlers that ignore it and are not idempotent.
+pub struct RpcClient {
+    http: Arc<ModuleHttpClient>,
+    timeout: Duration,
+    retries: u32,
+    backoff: Duration,
+}
+
+impl RpcClient {
+    pub fn new(http: Arc<ModuleHttpClient>) -> Self {
+        Self {
+            http,
+            timeout: DEFAULT_RPC_TIMEOUT,
+            retries: DEFAULT_RPC_RETRIES,
+            backoff: DEFAULT_RPC_BACKOFF,
+        }
+    }
+
+    pub fn from_environment(
+        env: &ModuleEnvironment,
+        tokens: Arc<ServiceTokenProvider>,
+    ) -> Result<Self, ModuleKitError> {
+        Ok(Self::new(Arc::new(ModuleHttpClient::new(env, tokens)?)))
+    }
+
+    /// Per-attempt timeout; defaults to 10 seconds.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Retries after the first attempt; defaults to 2.
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Linear backoff step between retries; defaults to 100ms.
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    pub fn http(&self) -> &Arc<ModuleHttpClient> {
+        &self.http
+    }
+
+    /// Sends `request` to `path` on `service_id` and decodes the reply.
+    ///
+    /// An empty 2xx body decodes as JSON `null`, so `()` and `Option<T>`
+    /// work for calls without a result.
+    pub fn call<Req, Resp>(
+        &self,
+        service_id: &str,
+        path: &str,
+        request: &Req,
+    ) -> Result<Resp, ModuleKitError>
+    where
+        Req: Serialize + ?Sized,
+        Resp: DeserializeOwned,
+    {
+        let failed = |status: Option<u16>, message: String| ModuleKitError::RpcFailed {
+            service_id: service_id.to_string(),
+            path: path.to_string(),
+            status,
+            message,
+        };
+        let body = serde_json::to_vec(request)?;
+        let idempotency_key = unique_request_id();
+        let mut attempt = 0;
+        let response = loop {
+            let result = self
+                .http
+                .send(HttpMethod::Post, service_id, path, |request| {
+                    request
+                        .timeout(self.timeout)
+                        .header(IDEMPOTENCY_KEY_HEADER, idempotency_key.as_str())
+                        .json_body(body.clone())
+                });
+            let retryable = match &result {
+                Ok(response) => matches!(response.status, 502..=504),
+                Err(ModuleKitError::TransportConnect(_) | ModuleKitError::TransportTimeout(_)) => {
+                    true
+                }
+                Err(_) => false,
+            };
+            if !retryable || attempt >= self.retries {
+                break result;
+            }
+            attempt += 1;
+            sleep(self.backoff.saturating_mul(attempt));
+        };
+        let response = match response {
+            Ok(response) => response,
+            Err(ModuleKitError::TransportTimeout(_)) => {
+                return Err(failed(None, format!("timed out after {:?}", self.timeout)));
+            }
+            Err(ModuleKitError::TransportConnect(message) | ModuleKitError::Transport(message)) => {
+                return Err(failed(None, message));
+            }
+            #[cfg(feature = "reqwest")]
+            Err(ModuleKitError::Http(err)) => return Err(failed(None, err.to_string())),
+            Err(err) => return Err(err),
+        };
+        let status = response.status;
+        let text = response.text();
+        if !response.is_success() {
+            return Err(failed(Some(status), error_message(&text)));
+        }
+        let text = if text.trim().is_empty() {
+            "null"
+        } else {
+            &text
+        };
+        serde_json::from_str(text).map_err(|err| {
+            failed(
+                Some(status),
+                format!("response did not match the expected type: {err}"),
+            )
+        })
+    }
+}
+
+/// `error`, `error.message` or `message` from a JSON error body, else the
+/// body itself, shortened.
+fn error_message(body: &str) -> String {
+    if let Ok(value) = serde_json::from_str::<JsonValue>(body) {
+        let message = value
+            .get("error")
+            .and_then(|error| error.as_str().or_else(|| error.get("message")?.as_str()))
+            .or_else(|| value.get("message")?.as_str());
+        if let Some(message) = message {
+            return message.to_string();
+        }
+    }
+    let body = body.trim();
+    if body.is_empty() {
+        return "empty response".to_string();
+    }
+    body.chars().take(MAX_ERROR_BODY_CHARS).collect()
+}
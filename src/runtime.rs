@@ -0,0 +1,225 @@
+use std::sync::Arc;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+
+use crate::connector::DbConnectorClient;
+use crate::control_plane::{ControlPlane, ControlPlaneClient};
+use crate::env::ModuleEnvironment;
+use crate::error::{FailureDomain, ModuleKitError};
+use crate::service::ModuleReportedServices;
+use crate::shutdown::{ShutdownReport, TaskSupervisor};
+use crate::token_provider::ServiceTokenProvider;
+
+const SERVICES_REGISTER_PATH: &str = "modules/runtime/services";
+const HEARTBEAT_PATH: &str = "modules/runtime/heartbeat";
+const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+const READY_BACKOFF_START: Duration = Duration::from_millis(50);
+const READY_BACKOFF_MAX: Duration = Duration::from_secs(2);
+
+#[derive(Serialize)]
+struct Heartbeat<'a> {
+    module_id: &'a str,
+    service_id: &'a str,
+}
+
+pub struct ModuleRuntimeBuilder {
+    env: Option<ModuleEnvironment>,
+    services: Option<ModuleReportedServices>,
+    heartbeat_interval: Option<Duration>,
+}
+
+impl ModuleRuntimeBuilder {
+    /// Uses `env` instead of reading `ModuleEnvironment::from_env`.
+    pub fn environment(mut self, env: ModuleEnvironment) -> Self {
+        self.env = Some(env);
+        self
+    }
+
+    /// Registers `services` with the control plane during `init`.
+    pub fn services(mut self, services: ModuleReportedServices) -> Self {
+        self.services = Some(services);
+        self
+    }
+
+    pub fn heartbeat_interval(mut self, value: Duration) -> Self {
+        self.heartbeat_interval = Some(value);
+        self
+    }
+
+    /// Disables heartbeats even when a control plane is configured.
+    pub fn without_heartbeat(mut self) -> Self {
+        self.heartbeat_interval = Some(Duration::ZERO);
+        self
+    }
+
+    pub fn init(self) -> Result<ModuleRuntime, ModuleKitError> {
+        let env = match self.env {
+            Some(env) => env,
+            None => ModuleEnvironment::from_env()?,
+        };
+        let tokens = Arc::new(env.token_provider()?);
+        let db = Arc::new(DbConnectorClient::with_token_provider(
+            env.clone(),
+            Arc::clone(&tokens),
+        ));
+        let control_plane = match &env.control_plane.url {
+            Some(url) if matches!(url.scheme(), "http" | "https") => {
+                Some(ControlPlaneClient::new(&env.control_plane)?)
+            }
+            _ => None,
+        };
+        if let (Some(services), Some(client)) = (&self.services, &control_plane) {
+            let bearer = tokens.current_token()?;
+            client.post_json::<_, JsonValue>(bearer.expose(), SERVICES_REGISTER_PATH, services)?;
+        }
+        let interval = self
+            .heartbeat_interval
+            .unwrap_or(DEFAULT_HEARTBEAT_INTERVAL);
+        let supervisor = TaskSupervisor::new();
+        if let Some(client) = control_plane.clone().filter(|_| !interval.is_zero()) {
+            spawn_heartbeat(&supervisor, client, Arc::clone(&tokens), &env, interval);
+        }
+        Ok(ModuleRuntime {
+            env,
+            tokens,
+            db,
+            control_plane,
+            supervisor,
+        })
+    }
+}
+
+/// Environment, token provider and connector client wired together, plus
+/// service registration and heartbeats against the control plane.
+pub struct ModuleRuntime {
+    env: ModuleEnvironment,
+    tokens: Arc<ServiceTokenProvider>,
+    db: Arc<DbConnectorClient>,
+    control_plane: Option<ControlPlaneClient>,
+    supervisor: TaskSupervisor,
+}
+
+impl ModuleRuntime {
+    pub fn builder() -> ModuleRuntimeBuilder {
+        ModuleRuntimeBuilder {
+            env: None,
+            services: None,
+            heartbeat_interval: None,
+        }
+    }
+
+    /// Initializes from the process environment with default settings.
+    pub fn init() -> Result<Self, ModuleKitError> {
+        Self::builder().init()
+    }
+
+    pub fn env(&self) -> &ModuleEnvironment {
+        &self.env
+    }
+
+    pub fn tokens(&self) -> &Arc<ServiceTokenProvider> {
+        &self.tokens
+    }
+
+    pub fn db(&self) -> &Arc<DbConnectorClient> {
+        &self.db
+    }
+
+    /// HTTP control plane client, when `FENRIR_CONTROL_PLANE_URL` is an http(s) URL.
+    pub fn control_plane(&self) -> Option<&ControlPlaneClient> {
+        self.control_plane.as_ref()
+    }
+
+    /// Supervisor that module-defined background loops and cleanup hooks can join.
+    pub fn supervisor(&self) -> &TaskSupervisor {
+        &self.supervisor
+    }
+
+    /// Blocks until the connector accepts connections and, when configured,
+    /// the control plane reports healthy, polling each with backoff.
+    ///
+    /// Fails with `ModuleKitError::NotReady` for the dependency still down
+    /// when `timeout` runs out.
+    pub fn wait_ready(&self, timeout: Duration) -> Result<(), ModuleKitError> {
+        let deadline = Instant::now() + timeout;
+        self.db.endpoint().wait_until_reachable(timeout)?;
+        if let Some(client) = &self.control_plane {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            retry_until(remaining, || client.health()).map_err(|err| ModuleKitError::NotReady {
+                domain: FailureDomain::ControlPlane,
+                message: err.to_string(),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Stops supervised tasks and token auto-refresh, waiting up to 10 seconds.
+    pub fn shutdown(self) -> ShutdownReport {
+        self.shutdown_with_timeout(DEFAULT_SHUTDOWN_TIMEOUT)
+    }
+
+    pub fn shutdown_with_timeout(self, timeout: Duration) -> ShutdownReport {
+        self.stop(timeout)
+    }
+
+    fn stop(&self, timeout: Duration) -> ShutdownReport {
+        let report = self.supervisor.shutdown_with_timeout(timeout);
+        self.tokens.stop_auto_refresh();
+        report
+    }
+}
+
+impl Drop for ModuleRuntime {
+    fn drop(&mut self) {
+        self.stop(DEFAULT_SHUTDOWN_TIMEOUT);
+    }
+}
+
+fn spawn_heartbeat(
+    supervisor: &TaskSupervisor,
+    client: ControlPlaneClient,
+    tokens: Arc<ServiceTokenProvider>,
+    env: &ModuleEnvironment,
+    interval: Duration,
+) {
+    let module_id = env.module_id.clone();
+    let service_id = env.service_id.clone();
+    supervisor.spawn("fenrir-heartbeat", move |signal| loop {
+        let body = Heartbeat {
+            module_id: &module_id,
+            service_id: &service_id,
+        };
+        if let Ok(bearer) = tokens.current_token() {
+            let _ = client.post_json::<_, JsonValue>(bearer.expose(), HEARTBEAT_PATH, &body);
+        }
+        if signal.wait_timeout(interval) {
+            break;
+        }
+    });
+}
+
+/// Calls `attempt` with exponential backoff until it succeeds or `timeout`
+/// has passed, returning the last error.
+pub(crate) fn retry_until<E>(
+    timeout: Duration,
+    mut attempt: impl FnMut() -> Result<(), E>,
+) -> Result<(), E> {
+    let deadline = Instant::now() + timeout;
+    let mut delay = READY_BACKOFF_START;
+    loop {
+        let err = match attempt() {
+            Ok(()) => return Ok(()),
+            Err(err) => err,
+        };
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(err);
+        }
+        sleep(delay.min(remaining));
+        delay = delay.saturating_mul(2).min(READY_BACKOFF_MAX);
+    }
+}
@@ -0,0 +1,232 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+use crate::control_plane::ControlPlaneClient;
+use crate::error::ModuleKitError;
+use crate::http_transport::HttpRequest;
+use crate::token_provider::ServiceTokenProvider;
+#[cfg(feature = "jwt-verify")]
+use crate::verifier::TokenVerifier;
+
+const TRIGGERS_PATH: &str = "modules/runtime/triggers";
+/// Accepted `@name` schedules.
+const SCHEDULE_MACROS: &[&str] = &["yearly", "annually", "monthly", "weekly", "daily", "hourly"];
+/// Scope carried by the platform scheduler's invocation tokens.
+pub const TRIGGER_INVOKE_SCOPE: &str = "scheduler:invoke";
+
+/// What the scheduler calls when a trigger fires.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TriggerTarget {
+    /// POST to a path on the module's own service.
+    Endpoint { path: String },
+    /// Delivered to the module's callback handler under `id`.
+    Callback { id: String },
+}
+
+impl TriggerTarget {
+    pub fn endpoint(path: impl Into<String>) -> Self {
+        TriggerTarget::Endpoint { path: path.into() }
+    }
+
+    pub fn callback(id: impl Into<String>) -> Self {
+        TriggerTarget::Callback { id: id.into() }
+    }
+}
+
+/// Cron-like trigger registered with the control plane, identified by `name`
+/// within the module.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TriggerSpec {
+    pub name: String,
+    /// Five-field cron expression, or a macro such as `@hourly`.
+    pub schedule: String,
+    pub target: TriggerTarget,
+    /// IANA time zone the schedule is evaluated in; UTC when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    /// Sent with every invocation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload: Option<JsonValue>,
+}
+
+impl TriggerSpec {
+    pub fn new(
+        name: impl Into<String>,
+        schedule: impl Into<String>,
+        target: TriggerTarget,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            schedule: schedule.into(),
+            target,
+            timezone: None,
+            payload: None,
+        }
+    }
+
+    pub fn timezone(mut self, value: impl Into<String>) -> Self {
+        self.timezone = Some(value.into());
+        self
+    }
+
+    pub fn payload(mut self, value: JsonValue) -> Self {
+        self.payload = Some(value);
+        self
+    }
+
+    /// Checks the name and the shape of the schedule; the control plane
+    /// validates field ranges.
+    pub fn validate(&self) -> Result<(), ModuleKitError> {
+        let name_ok = !self.name.is_empty()
+            && self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if !name_ok {
+            return Err(ModuleKitError::InvalidTrigger(format!(
+                "name '{}' must be non-empty ASCII letters, digits, '-', '_' or '.'",
+                self.name
+            )));
+        }
+        let schedule = self.schedule.trim();
+        let schedule_ok = match schedule.strip_prefix('@') {
+            Some(name) => SCHEDULE_MACROS.contains(&name),
+            None => schedule.split_whitespace().count() == 5,
+        };
+        if !schedule_ok {
+            return Err(ModuleKitError::InvalidTrigger(format!(
+                "schedule '{}' of '{}' is not a five-field cron expression or known macro",
+                self.schedule, self.name
+            )));
+        }
+        let target_ok = match &self.target {
+            TriggerTarget::Endpoint { path } => path.starts_with('/'),
+            TriggerTarget::Callback { id } => !id.trim().is_empty(),
+        };
+        if !target_ok {
+            return Err(ModuleKitError::InvalidTrigger(format!(
+                "target of '{}' needs a path starting with '/' or a callback id",
+                self.name
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// A trigger as stored by the control plane.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegisteredTrigger {
+    #[serde(flatten)]
+    pub spec: TriggerSpec,
+    /// RFC 3339 timestamps.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_run_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_run_at: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TriggerList {
+    triggers: Vec<RegisteredTrigger>,
+}
+
+/// Registers and removes the module's scheduled triggers.
+pub struct SchedulerClient {
+    control_plane: ControlPlaneClient,
+    tokens: Arc<ServiceTokenProvider>,
+}
+
+impl SchedulerClient {
+    pub fn new(control_plane: ControlPlaneClient, tokens: Arc<ServiceTokenProvider>) -> Self {
+        Self {
+            control_plane,
+            tokens,
+        }
+    }
+
+    /// Creates the trigger, or replaces the one with the same name.
+    pub fn register(&self, spec: &TriggerSpec) -> Result<RegisteredTrigger, ModuleKitError> {
+        spec.validate()?;
+        let bearer = self.tokens.current_token()?;
+        self.control_plane
+            .post_json(bearer.expose(), TRIGGERS_PATH, spec)
+    }
+
+    /// Triggers registered by this module, sorted by name.
+    pub fn list(&self) -> Result<Vec<RegisteredTrigger>, ModuleKitError> {
+        let bearer = self.tokens.current_token()?;
+        let mut triggers = self
+            .control_plane
+            .get_json::<TriggerList>(bearer.expose(), TRIGGERS_PATH)?
+            .triggers;
+        triggers.sort_by(|a, b| a.spec.name.cmp(&b.spec.name));
+        Ok(triggers)
+    }
+
+    /// Removes trigger `name`, returning whether it existed.
+    pub fn deregister(&self, name: &str) -> Result<bool, ModuleKitError> {
+        let bearer = self.tokens.current_token()?;
+        let mut url = self.control_plane.endpoint(TRIGGERS_PATH)?;
+        url.path_segments_mut()
+            .map_err(|_| ModuleKitError::InvalidTrigger(name.to_string()))?
+            .pop_if_empty()
+            .push(name);
+        let response = self
+            .control_plane
+            .send(&HttpRequest::delete(url).bearer_auth(bearer.expose()))?;
+        match response.status {
+            404 => Ok(false),
+            _ if response.is_success() => Ok(true),
+            status => Err(ModuleKitError::ControlPlaneStatus {
+                status,
+                body: response.text(),
+            }),
+        }
+    }
+}
+
+/// Body the scheduler POSTs to a trigger's target.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TriggerInvocation {
+    pub trigger: String,
+    /// RFC 3339 time the run was scheduled for.
+    pub scheduled_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub callback_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload: Option<JsonValue>,
+}
+
+/// Checks that an incoming invocation came from the platform scheduler and
+/// decodes its body.
+///
+/// `authorization` is the request's `Authorization` header. The bearer token
+/// must verify, carry `scheduler:invoke` and, when it has a `trigger` claim,
+/// name the trigger in the body.
+#[cfg(feature = "jwt-verify")]
+pub fn verify_trigger_invocation(
+    verifier: &TokenVerifier,
+    authorization: Option<&str>,
+    body: &[u8],
+) -> Result<TriggerInvocation, ModuleKitError> {
+    let token = authorization
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .ok_or_else(|| ModuleKitError::InvalidToken("missing bearer token".into()))?;
+    let claims = verifier.verify(token)?;
+    crate::authz::require_scopes(&claims, &[TRIGGER_INVOKE_SCOPE])?;
+    let invocation: TriggerInvocation = serde_json::from_slice(body)?;
+    if let Some(trigger) = claims.extra.get("trigger").and_then(JsonValue::as_str) {
+        if trigger != invocation.trigger {
+            return Err(ModuleKitError::InvalidToken(format!(
+                "token issued for trigger '{trigger}', body names '{}'",
+                invocation.trigger
+            )));
+        }
+    }
+    Ok(invocation)
+}
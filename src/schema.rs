@@ -0,0 +1,186 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::connector::DbConnectorClient;
+use crate::connector_request::{DbConnectorCommand, DbConnectorIntent};
+use crate::error::ModuleKitError;
+
+const DEFAULT_SCHEMA: &str = "public";
+
+/// Expected tables and their column types, typically committed alongside the
+/// module's migrations and regenerated with `SchemaGuard::snapshot`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaSnapshot {
+    pub tables: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl SchemaSnapshot {
+    pub fn from_json(value: &str) -> Result<Self, ModuleKitError> {
+        Ok(serde_json::from_str(value)?)
+    }
+
+    pub fn to_json_pretty(&self) -> Result<String, ModuleKitError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn with_column(
+        mut self,
+        table: impl Into<String>,
+        column: impl Into<String>,
+        data_type: impl Into<String>,
+    ) -> Self {
+        self.tables
+            .entry(table.into())
+            .or_default()
+            .insert(column.into(), data_type.into());
+        self
+    }
+
+    /// Differences that would break code written against `self`.
+    ///
+    /// Extra tables and columns in `actual` are not reported.
+    pub fn diff(&self, actual: &SchemaSnapshot) -> SchemaDriftReport {
+        let mut report = SchemaDriftReport::default();
+        for (table, columns) in &self.tables {
+            let Some(actual_columns) = actual.tables.get(table) else {
+                report.missing_tables.push(table.clone());
+                continue;
+            };
+            for (column, expected_type) in columns {
+                match actual_columns.get(column) {
+                    None => report.missing_columns.push(format!("{table}.{column}")),
+                    Some(actual_type) if !actual_type.eq_ignore_ascii_case(expected_type) => {
+                        report.type_mismatches.push(SchemaTypeMismatch {
+                            column: format!("{table}.{column}"),
+                            expected: expected_type.clone(),
+                            actual: actual_type.clone(),
+                        });
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+        report
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaDriftReport {
+    pub missing_tables: Vec<String>,
+    pub missing_columns: Vec<String>,
+    pub type_mismatches: Vec<SchemaTypeMismatch>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaTypeMismatch {
+    pub column: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl SchemaDriftReport {
+    pub fn is_empty(&self) -> bool {
+        self.missing_tables.is_empty()
+            && self.missing_columns.is_empty()
+            && self.type_mismatches.is_empty()
+    }
+}
+
+impl fmt::Display for SchemaDriftReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut items = Vec::new();
+        items.extend(
+            self.missing_tables
+                .iter()
+                .map(|table| format!("missing table {table}")),
+        );
+        items.extend(
+            self.missing_columns
+                .iter()
+                .map(|column| format!("missing column {column}")),
+        );
+        items.extend(self.type_mismatches.iter().map(|mismatch| {
+            format!(
+                "column {} is {} (expected {})",
+                mismatch.column, mismatch.actual, mismatch.expected
+            )
+        }));
+        write!(f, "{}", items.join("; "))
+    }
+}
+
+/// Compares the live database schema with a committed `SchemaSnapshot`.
+pub struct SchemaGuard<'a> {
+    client: &'a DbConnectorClient,
+    engine: Option<String>,
+    schema: String,
+}
+
+impl<'a> SchemaGuard<'a> {
+    pub fn new(client: &'a DbConnectorClient) -> Self {
+        Self {
+            client,
+            engine: None,
+            schema: DEFAULT_SCHEMA.to_string(),
+        }
+    }
+
+    pub fn engine(mut self, value: impl Into<String>) -> Self {
+        self.engine = Some(value.into());
+        self
+    }
+
+    pub fn schema(mut self, value: impl Into<String>) -> Self {
+        self.schema = value.into();
+        self
+    }
+
+    /// Reads the live schema; also used to generate the committed snapshot.
+    pub fn snapshot(&self) -> Result<SchemaSnapshot, ModuleKitError> {
+        if !self
+            .schema
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            return Err(ModuleKitError::InvalidSchemaName(self.schema.clone()));
+        }
+        let statement = format!(
+            "SELECT table_name, column_name, data_type FROM information_schema.columns \
+             WHERE table_schema = '{}'",
+            self.schema
+        );
+        let response = self
+            .client
+            .execute(
+                DbConnectorCommand::Simple { statement },
+                DbConnectorIntent::Read,
+                self.engine.as_deref(),
+                None,
+            )?
+            .into_result()?;
+        let mut snapshot = SchemaSnapshot::default();
+        for result in response.results.iter().flatten() {
+            for row in result.rows() {
+                snapshot = snapshot.with_column(
+                    row.get::<String>("table_name")?,
+                    row.get::<String>("column_name")?,
+                    row.get::<String>("data_type")?,
+                );
+            }
+        }
+        Ok(snapshot)
+    }
+
+    /// Fails with `ModuleKitError::SchemaDrift` listing every missing table,
+    /// missing column and type mismatch.
+    pub fn verify(&self, expected: &SchemaSnapshot) -> Result<(), ModuleKitError> {
+        let report = expected.diff(&self.snapshot()?);
+        if report.is_empty() {
+            Ok(())
+        } else {
+            Err(ModuleKitError::SchemaDrift(report))
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use zeroize::Zeroize;
+
+use crate::control_plane::ControlPlaneClient;
+use crate::error::ModuleKitError;
+use crate::token_provider::ServiceTokenProvider;
+
+const SECRETS_PATH: &str = "modules/runtime/secrets/";
+const DEFAULT_SECRET_TTL: Duration = Duration::from_secs(300);
+
+/// Value whose `Debug` and `Display` output never reveals it and whose memory
+/// is zeroed on drop.
+///
+/// Serializes transparently so it can travel in wire payloads; keep it out of
+/// log output that serializes whole structs.
+#[derive(Clone, PartialEq, Eq, Default)]
+pub struct Secret<T: Zeroize>(T);
+
+pub type SecretString = Secret<String>;
+
+impl<T: Zeroize> Secret<T> {
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+}
+
+impl Secret<String> {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<String> for Secret<String> {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for Secret<String> {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl<T: Zeroize> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl<T: Zeroize> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(<redacted>)")
+    }
+}
+
+impl<T: Zeroize> fmt::Display for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
+impl<T: Zeroize + Serialize> Serialize for Secret<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de, T: Zeroize + Deserialize<'de>> Deserialize<'de> for Secret<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Self)
+    }
+}
+
+#[derive(Deserialize)]
+struct SecretResponse {
+    value: SecretString,
+    #[serde(default)]
+    ttl_seconds: Option<u64>,
+}
+
+struct CachedSecret {
+    value: SecretString,
+    /// `None` when the TTL is too large to represent; the value never expires.
+    expires_at: Option<Instant>,
+}
+
+/// Fetches named secrets from the control plane and caches them until their TTL lapses.
+pub struct SecretsClient {
+    control_plane: ControlPlaneClient,
+    tokens: Arc<ServiceTokenProvider>,
+    default_ttl: Duration,
+    cache: Mutex<HashMap<String, CachedSecret>>,
+}
+
+impl SecretsClient {
+    pub fn new(control_plane: ControlPlaneClient, tokens: Arc<ServiceTokenProvider>) -> Self {
+        Self {
+            control_plane,
+            tokens,
+            default_ttl: DEFAULT_SECRET_TTL,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// TTL applied when the control plane does not send one.
+    pub fn with_default_ttl(mut self, ttl: Duration) -> Self {
+        self.default_ttl = ttl;
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Result<SecretString, ModuleKitError> {
+        if let Some(cached) = self.cache.lock().unwrap().get(name) {
+            if cached.expires_at.is_none_or(|at| at > Instant::now()) {
+                return Ok(cached.value.clone());
+            }
+        }
+        let response = self.fetch(name)?;
+        let ttl = response
+            .ttl_seconds
+            .map(Duration::from_secs)
+            .unwrap_or(self.default_ttl);
+        let value = response.value;
+        self.cache.lock().unwrap().insert(
+            name.to_string(),
+            CachedSecret {
+                value: value.clone(),
+                expires_at: Instant::now().checked_add(ttl),
+            },
+        );
+        Ok(value)
+    }
+
+    pub fn invalidate(&self, name: &str) {
+        self.cache.lock().unwrap().remove(name);
+    }
+
+    pub fn clear(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    fn fetch(&self, name: &str) -> Result<SecretResponse, ModuleKitError> {
+        if name.trim().is_empty() {
+            return Err(ModuleKitError::InvalidSecretName(name.to_string()));
+        }
+        let mut url = self.control_plane.endpoint(SECRETS_PATH)?;
+        url.path_segments_mut()
+            .map_err(|_| ModuleKitError::InvalidSecretName(name.to_string()))?
+            .pop_if_empty()
+            .push(name);
+        let bearer = self.tokens.current_token()?;
+        self.control_plane.get_json_url(bearer.expose(), url)
+    }
+}
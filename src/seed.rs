@@ -0,0 +1,274 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+use serde_json::{Map, Value as JsonValue};
+
+use crate::connector::DbConnectorClient;
+use crate::connector_request::{DbConnectorCommand, DbConnectorIntent, DbPreparedParam};
+use crate::error::ModuleKitError;
+use crate::sql::SafeIdent;
+
+/// Rows to insert into one table.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SeedTable {
+    pub table: String,
+    /// Columns identifying a row; rows whose key already exists are skipped.
+    #[serde(default = "default_key")]
+    pub key: Vec<String>,
+    /// Tables that must be seeded first, e.g. targets of foreign keys.
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    pub rows: Vec<Map<String, JsonValue>>,
+}
+
+/// Contents of one fixture file.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SeedFixture {
+    pub tables: Vec<SeedTable>,
+}
+
+impl SeedFixture {
+    pub fn from_json(text: &str) -> Result<Self, ModuleKitError> {
+        serde_json::from_str(text).map_err(|err| ModuleKitError::InvalidFixture(err.to_string()))
+    }
+
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(text: &str) -> Result<Self, ModuleKitError> {
+        serde_yaml::from_str(text).map_err(|err| ModuleKitError::InvalidFixture(err.to_string()))
+    }
+
+    /// Reads a `.json`, or with the `yaml` feature a `.yaml`/`.yml`, fixture file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ModuleKitError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|err| ModuleKitError::InvalidFixture(format!("{}: {err}", path.display())))?;
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let parsed = match extension.as_str() {
+            "json" => Self::from_json(&text),
+            #[cfg(feature = "yaml")]
+            "yaml" | "yml" => Self::from_yaml(&text),
+            other => Err(ModuleKitError::InvalidFixture(format!(
+                "unsupported fixture format '{other}'"
+            ))),
+        };
+        parsed.map_err(|err| match err {
+            ModuleKitError::InvalidFixture(message) => {
+                ModuleKitError::InvalidFixture(format!("{}: {message}", path.display()))
+            }
+            other => other,
+        })
+    }
+}
+
+fn default_key() -> Vec<String> {
+    vec!["id".to_string()]
+}
+
+/// Rows inserted and skipped for one table.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SeedTableReport {
+    pub inserted: u64,
+    pub skipped: u64,
+}
+
+/// Inserts fixture rows through the connector in dependency order.
+///
+/// Re-running is safe: each row is looked up by its key columns first and
+/// only inserted when missing.
+pub struct Seeder<'a> {
+    client: &'a DbConnectorClient,
+    engine: Option<String>,
+    tables: Vec<SeedTable>,
+}
+
+impl<'a> Seeder<'a> {
+    pub fn new(client: &'a DbConnectorClient) -> Self {
+        Self {
+            client,
+            engine: None,
+            tables: Vec::new(),
+        }
+    }
+
+    pub fn engine(mut self, value: impl Into<String>) -> Self {
+        self.engine = Some(value.into());
+        self
+    }
+
+    pub fn fixture(mut self, fixture: SeedFixture) -> Self {
+        self.tables.extend(fixture.tables);
+        self
+    }
+
+    pub fn file(self, path: impl AsRef<Path>) -> Result<Self, ModuleKitError> {
+        Ok(self.fixture(SeedFixture::from_file(path)?))
+    }
+
+    pub fn run(&self) -> Result<BTreeMap<String, SeedTableReport>, ModuleKitError> {
+        let mut reports = BTreeMap::new();
+        for table in self.ordered()? {
+            let report: &mut SeedTableReport = reports.entry(table.table.clone()).or_default();
+            let name = SafeIdent::new(table.table.as_str())?;
+            for row in &table.rows {
+                if self.exists(&name, &table.key, row)? {
+                    report.skipped += 1;
+                } else {
+                    self.insert(&name, row)?;
+                    report.inserted += 1;
+                }
+            }
+        }
+        Ok(reports)
+    }
+
+    /// Tables sorted so every table follows the ones it depends on.
+    fn ordered(&self) -> Result<Vec<&SeedTable>, ModuleKitError> {
+        let mut pending: HashMap<&str, usize> = HashMap::new();
+        for table in &self.tables {
+            pending.entry(table.table.as_str()).or_insert(0);
+        }
+        for table in &self.tables {
+            let known = table
+                .depends_on
+                .iter()
+                .filter(|dependency| {
+                    *dependency != &table.table && pending.contains_key(dependency.as_str())
+                })
+                .count();
+            *pending.get_mut(table.table.as_str()).unwrap() += known;
+        }
+        let mut ready: VecDeque<&str> = self
+            .tables
+            .iter()
+            .map(|table| table.table.as_str())
+            .filter(|name| pending[name] == 0)
+            .collect();
+        let mut order: Vec<&str> = Vec::new();
+        while let Some(name) = ready.pop_front() {
+            if order.contains(&name) {
+                continue;
+            }
+            order.push(name);
+            for table in &self.tables {
+                let satisfied = table
+                    .depends_on
+                    .iter()
+                    .filter(|dependency| dependency.as_str() == name && table.table != name)
+                    .count();
+                if satisfied == 0 {
+                    continue;
+                }
+                let remaining = pending.get_mut(table.table.as_str()).unwrap();
+                *remaining -= satisfied;
+                if *remaining == 0 {
+                    ready.push_back(table.table.as_str());
+                }
+            }
+        }
+        if order.len() < pending.len() {
+            let mut blocked: Vec<&str> = pending
+                .keys()
+                .filter(|name| !order.contains(name))
+                .copied()
+                .collect();
+            blocked.sort_unstable();
+            return Err(ModuleKitError::InvalidFixture(format!(
+                "dependency cycle between tables: {}",
+                blocked.join(", ")
+            )));
+        }
+        Ok(order
+            .into_iter()
+            .flat_map(|name| self.tables.iter().filter(move |table| table.table == name))
+            .collect())
+    }
+
+    fn exists(
+        &self,
+        table: &SafeIdent,
+        key: &[String],
+        row: &Map<String, JsonValue>,
+    ) -> Result<bool, ModuleKitError> {
+        if key.is_empty() {
+            return Err(ModuleKitError::InvalidFixture(format!(
+                "table '{table}' has no key columns"
+            )));
+        }
+        let mut conditions = Vec::with_capacity(key.len());
+        let mut params = Vec::with_capacity(key.len());
+        for (index, column) in key.iter().enumerate() {
+            let value = row
+                .get(column)
+                .filter(|value| !value.is_null())
+                .ok_or_else(|| {
+                    ModuleKitError::InvalidFixture(format!(
+                        "row in '{table}' has no value for key column '{column}'"
+                    ))
+                })?;
+            conditions.push(format!("{} = :k{index}", SafeIdent::new(column.as_str())?));
+            params.push(DbPreparedParam {
+                name: format!("k{index}"),
+                value: value.clone(),
+            });
+        }
+        let command = DbConnectorCommand::Prepared {
+            statement: format!("SELECT 1 FROM {table} WHERE {}", conditions.join(" AND ")),
+            params,
+        };
+        let response = self
+            .client
+            .execute(
+                command,
+                DbConnectorIntent::Read,
+                self.engine.as_deref(),
+                None,
+            )?
+            .into_result()?;
+        Ok(response
+            .results
+            .iter()
+            .flatten()
+            .any(|result| result.rows().next().is_some()))
+    }
+
+    fn insert(
+        &self,
+        table: &SafeIdent,
+        row: &Map<String, JsonValue>,
+    ) -> Result<(), ModuleKitError> {
+        let mut columns = Vec::with_capacity(row.len());
+        let mut placeholders = Vec::with_capacity(row.len());
+        let mut params = Vec::with_capacity(row.len());
+        for (index, (column, value)) in row.iter().enumerate() {
+            columns.push(SafeIdent::new(column.as_str())?.to_string());
+            placeholders.push(format!(":v{index}"));
+            params.push(DbPreparedParam {
+                name: format!("v{index}"),
+                value: value.clone(),
+            });
+        }
+        let command = DbConnectorCommand::Prepared {
+            statement: format!(
+                "INSERT INTO {table} ({}) VALUES ({})",
+                columns.join(", "),
+                placeholders.join(", ")
+            ),
+            params,
+        };
+        self.client
+            .execute(
+                command,
+                DbConnectorIntent::Write,
+                self.engine.as_deref(),
+                None,
+            )?
+            .into_result()?;
+        Ok(())
+    }
+}
@@ -1,5 +1,11 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 
+use crate::error::ModuleKitError;
+
 /// Payload that Fenrir modules can expose under `/.fenrir/services` so the runtime
 /// can register their service descriptors dynamically.
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -7,6 +13,8 @@ pub struct ModuleReportedServices {
     pub module_id: String,
     #[serde(default)]
     pub services: Vec<ModuleServiceDescriptor>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capabilities: Option<ModuleCapabilities>,
 }
 
 impl ModuleReportedServices {
@@ -14,9 +22,15 @@ impl ModuleReportedServices {
         Self {
             module_id: module_id.into(),
             services: Vec::new(),
+            capabilities: None,
         }
     }
 
+    pub fn with_capabilities(mut self, capabilities: ModuleCapabilities) -> Self {
+        self.capabilities = Some(capabilities);
+        self
+    }
+
     pub fn with_service(mut self, descriptor: ModuleServiceDescriptor) -> Self {
         self.services.push(descriptor);
         self
@@ -25,6 +39,51 @@ impl ModuleReportedServices {
     pub fn push(&mut self, descriptor: ModuleServiceDescriptor) {
         self.services.push(descriptor);
     }
+
+    pub fn service(&self, service_id: &str) -> Option<&ModuleServiceDescriptor> {
+        self.services
+            .iter()
+            .find(|service| service.service_id == service_id)
+    }
+
+    /// Checks the module id and every descriptor, and that no `service_id`
+    /// is declared twice, reporting all violations in one error.
+    pub fn validate(&self) -> Result<(), ModuleKitError> {
+        violations_result(self.violations())
+    }
+
+    pub fn violations(&self) -> Vec<DescriptorViolation> {
+        let mut violations = Vec::new();
+        if !is_catalog_name(&self.module_id) {
+            violations.push(DescriptorViolation::module(
+                "module_id",
+                format!(
+                    "'{}' must start with a lowercase letter and use only a-z, 0-9, '.', '_' or '-'",
+                    self.module_id
+                ),
+            ));
+        }
+        let mut seen = BTreeSet::new();
+        for service in &self.services {
+            if !seen.insert(service.service_id.as_str()) {
+                violations.push(DescriptorViolation::service(
+                    service,
+                    "service_id",
+                    "is declared more than once",
+                ));
+            }
+            violations.extend(service.violations());
+        }
+        violations
+    }
+
+    /// Union of `required_scopes` across all services, sorted and deduplicated.
+    pub fn required_scopes(&self) -> BTreeSet<String> {
+        self.services
+            .iter()
+            .flat_map(|service| service.required_scopes.iter().cloned())
+            .collect()
+    }
 }
 
 /// Service descriptor representation that matches Fenrir's runtime schema.
@@ -36,7 +95,7 @@ pub struct ModuleServiceDescriptor {
     #[serde(default)]
     pub description: Option<String>,
     #[serde(default)]
-    pub kind: Option<String>,
+    pub kind: Option<ServiceKind>,
     #[serde(default)]
     pub route_prefix: Option<String>,
     #[serde(default)]
@@ -44,21 +103,460 @@ pub struct ModuleServiceDescriptor {
     #[serde(default)]
     pub internal_only: Option<bool>,
     #[serde(default)]
-    pub ingress_access: Option<String>,
+    pub ingress_access: Option<IngressAccess>,
     #[serde(default)]
-    pub protocols: Vec<String>,
+    pub protocols: Vec<ServiceProtocol>,
     #[serde(default)]
     pub required_scopes: Vec<String>,
     #[serde(default)]
     pub allowed_roles: Vec<String>,
     #[serde(default)]
     pub tags: Vec<String>,
+    /// Per-route methods and scopes, on top of the service-wide ones.
+    #[serde(default)]
+    pub endpoints: Vec<ModuleEndpointDescriptor>,
 }
 
 impl ModuleServiceDescriptor {
     pub fn builder(service_id: impl Into<String>) -> ModuleServiceDescriptorBuilder {
         ModuleServiceDescriptorBuilder::new(service_id.into())
     }
+
+    /// Checks the descriptor the way runtime registration does, reporting
+    /// every violation in one `ModuleKitError::InvalidServiceDescriptor`.
+    pub fn validate(&self) -> Result<(), ModuleKitError> {
+        violations_result(self.violations())
+    }
+
+    pub fn violations(&self) -> Vec<DescriptorViolation> {
+        let mut violations = Vec::new();
+        let mut violation = |field: &'static str, message: String| {
+            violations.push(DescriptorViolation::service(self, field, message));
+        };
+        if !is_catalog_name(&self.service_id) {
+            violation(
+                "service_id",
+                "must start with a lowercase letter and use only a-z, 0-9, '.', '_' or '-'"
+                    .to_string(),
+            );
+        }
+        if let Some(prefix) = &self.route_prefix {
+            if let Err(message) = check_route_path(prefix) {
+                violation("route_prefix", format!("'{prefix}' {message}"));
+            }
+        }
+        if let Some(path) = &self.health_path {
+            if let Err(message) = check_route_path(path) {
+                violation("health_path", format!("'{path}' {message}"));
+            }
+        }
+        for protocol in &self.protocols {
+            if let ServiceProtocol::Other(value) = protocol {
+                violation(
+                    "protocols",
+                    format!(
+                        "unknown protocol '{value}', expected one of {}",
+                        ServiceProtocol::KNOWN.join(", ")
+                    ),
+                );
+            }
+        }
+        if let Some(ServiceKind::Other(value)) = &self.kind {
+            violation(
+                "kind",
+                format!(
+                    "unknown kind '{value}', expected one of {}",
+                    ServiceKind::KNOWN.join(", ")
+                ),
+            );
+        }
+        if let Some(access) = &self.ingress_access {
+            if let IngressAccess::Other(value) = access {
+                violation(
+                    "ingress_access",
+                    format!(
+                        "unknown value '{value}', expected one of {}",
+                        IngressAccess::KNOWN.join(", ")
+                    ),
+                );
+            } else if *access == IngressAccess::Public && self.internal_only == Some(true) {
+                violation(
+                    "ingress_access",
+                    "is 'public' but internal_only is set; drop one of them".to_string(),
+                );
+            }
+        }
+        for scope in &self.required_scopes {
+            if !is_scope_name(scope) {
+                violation(
+                    "required_scopes",
+                    format!("'{scope}' should look like 'resource:action', e.g. 'db:write'"),
+                );
+            }
+        }
+        let mut seen = BTreeSet::new();
+        for endpoint in &self.endpoints {
+            let path = &endpoint.path;
+            if let Err(message) = check_path(path, true) {
+                violation("endpoints", format!("'{path}' {message}"));
+            }
+            if !seen.insert(path.as_str()) {
+                violation("endpoints", format!("'{path}' is declared more than once"));
+            }
+            for method in &endpoint.methods {
+                if !HTTP_METHODS.contains(&method.as_str()) {
+                    violation(
+                        "endpoints",
+                        format!("'{path}' has unknown method '{method}'"),
+                    );
+                }
+            }
+            for scope in &endpoint.required_scopes {
+                if !is_scope_name(scope) {
+                    violation(
+                        "endpoints",
+                        format!("'{path}' scope '{scope}' should look like 'resource:action'"),
+                    );
+                }
+            }
+            if endpoint
+                .rate_limit
+                .is_some_and(|limit| limit.requests == 0 || limit.per_seconds == 0)
+            {
+                violation(
+                    "endpoints",
+                    format!("'{path}' rate limit needs non-zero requests and per_seconds"),
+                );
+            }
+        }
+        violations
+    }
+}
+
+const HTTP_METHODS: &[&str] = &["GET", "HEAD", "POST", "PUT", "PATCH", "DELETE", "OPTIONS"];
+
+/// One route of a service with its own methods and authorization.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct ModuleEndpointDescriptor {
+    /// Path below the service's `route_prefix`; `{name}` segments are parameters.
+    pub path: String,
+    /// Uppercase HTTP methods; empty allows any method.
+    #[serde(default)]
+    pub methods: Vec<String>,
+    /// Scopes required in addition to the service's `required_scopes`.
+    #[serde(default)]
+    pub required_scopes: Vec<String>,
+    #[serde(default)]
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub rate_limit: Option<RateLimitHint>,
+}
+
+impl ModuleEndpointDescriptor {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn method(mut self, method: impl AsRef<str>) -> Self {
+        self.methods.push(method.as_ref().to_ascii_uppercase());
+        self
+    }
+
+    pub fn methods(self, methods: &[&str]) -> Self {
+        methods
+            .iter()
+            .fold(self, |endpoint, method| endpoint.method(method))
+    }
+
+    pub fn scope(mut self, scope: impl Into<String>) -> Self {
+        self.required_scopes.push(scope.into());
+        self
+    }
+
+    pub fn summary(mut self, value: impl Into<String>) -> Self {
+        self.summary = Some(value.into());
+        self
+    }
+
+    pub fn rate_limit(mut self, requests: u64, per: Duration) -> Self {
+        self.rate_limit = Some(RateLimitHint {
+            requests,
+            per_seconds: per.as_secs().max(1),
+            burst: None,
+        });
+        self
+    }
+
+    /// Whether `method` is allowed on this endpoint.
+    pub fn allows(&self, method: &str) -> bool {
+        self.methods.is_empty()
+            || self
+                .methods
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(method))
+    }
+}
+
+/// Advisory limit the runtime may enforce at ingress for one endpoint.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitHint {
+    pub requests: u64,
+    pub per_seconds: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub burst: Option<u64>,
+}
+
+/// Protocol the runtime uses to route to a service.
+///
+/// Serialized as its lowercase name; unknown names round-trip through `Other`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum ServiceProtocol {
+    Http,
+    Grpc,
+    WebSocket,
+    Other(String),
+}
+
+impl ServiceProtocol {
+    pub const KNOWN: &'static [&'static str] = &["http", "grpc", "websocket"];
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            ServiceProtocol::Http => "http",
+            ServiceProtocol::Grpc => "grpc",
+            ServiceProtocol::WebSocket => "websocket",
+            ServiceProtocol::Other(value) => value,
+        }
+    }
+}
+
+impl From<&str> for ServiceProtocol {
+    fn from(value: &str) -> Self {
+        match value.to_ascii_lowercase().as_str() {
+            "http" => ServiceProtocol::Http,
+            "grpc" => ServiceProtocol::Grpc,
+            "websocket" | "ws" => ServiceProtocol::WebSocket,
+            _ => ServiceProtocol::Other(value.to_string()),
+        }
+    }
+}
+
+impl From<String> for ServiceProtocol {
+    fn from(value: String) -> Self {
+        Self::from(value.as_str())
+    }
+}
+
+impl From<ServiceProtocol> for String {
+    fn from(value: ServiceProtocol) -> Self {
+        value.as_str().to_string()
+    }
+}
+
+impl fmt::Display for ServiceProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What a service is for, used by the catalog to group services.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum ServiceKind {
+    /// Machine-facing API.
+    Api,
+    /// User-facing web frontend.
+    Ui,
+    /// Background processor without public routes.
+    Worker,
+    Other(String),
+}
+
+impl ServiceKind {
+    pub const KNOWN: &'static [&'static str] = &["api", "ui", "worker"];
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            ServiceKind::Api => "api",
+            ServiceKind::Ui => "ui",
+            ServiceKind::Worker => "worker",
+            ServiceKind::Other(value) => value,
+        }
+    }
+}
+
+impl From<&str> for ServiceKind {
+    fn from(value: &str) -> Self {
+        match value.to_ascii_lowercase().as_str() {
+            "api" => ServiceKind::Api,
+            "ui" => ServiceKind::Ui,
+            "worker" => ServiceKind::Worker,
+            _ => ServiceKind::Other(value.to_string()),
+        }
+    }
+}
+
+impl From<String> for ServiceKind {
+    fn from(value: String) -> Self {
+        Self::from(value.as_str())
+    }
+}
+
+impl From<ServiceKind> for String {
+    fn from(value: ServiceKind) -> Self {
+        value.as_str().to_string()
+    }
+}
+
+impl fmt::Display for ServiceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Who the platform ingress lets reach a service.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum IngressAccess {
+    Public,
+    /// Reachable only from other modules inside the platform.
+    Internal,
+    Other(String),
+}
+
+impl IngressAccess {
+    pub const KNOWN: &'static [&'static str] = &["public", "internal"];
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            IngressAccess::Public => "public",
+            IngressAccess::Internal => "internal",
+            IngressAccess::Other(value) => value,
+        }
+    }
+}
+
+impl From<&str> for IngressAccess {
+    fn from(value: &str) -> Self {
+        match value.to_ascii_lowercase().as_str() {
+            "public" => IngressAccess::Public,
+            "internal" => IngressAccess::Internal,
+            _ => IngressAccess::Other(value.to_string()),
+        }
+    }
+}
+
+impl From<String> for IngressAccess {
+    fn from(value: String) -> Self {
+        Self::from(value.as_str())
+    }
+}
+
+impl From<IngressAccess> for String {
+    fn from(value: IngressAccess) -> Self {
+        value.as_str().to_string()
+    }
+}
+
+impl fmt::Display for IngressAccess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// One problem found while validating service descriptors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DescriptorViolation {
+    /// Service the problem belongs to; `None` for module-level fields.
+    pub service_id: Option<String>,
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl DescriptorViolation {
+    fn module(field: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            service_id: None,
+            field,
+            message: message.into(),
+        }
+    }
+
+    fn service(
+        descriptor: &ModuleServiceDescriptor,
+        field: &'static str,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            service_id: Some(descriptor.service_id.clone()),
+            field,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for DescriptorViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.service_id {
+            Some(service_id) => {
+                write!(f, "service '{service_id}' {}: {}", self.field, self.message)
+            }
+            None => write!(f, "{}: {}", self.field, self.message),
+        }
+    }
+}
+
+fn violations_result(violations: Vec<DescriptorViolation>) -> Result<(), ModuleKitError> {
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(ModuleKitError::InvalidServiceDescriptor(violations))
+    }
+}
+
+fn check_route_path(path: &str) -> Result<(), &'static str> {
+    check_path(path, false)
+}
+
+/// Absolute path without empty segments, a trailing slash, a query or a
+/// fragment; `/` on its own is allowed. With `params`, whole segments may be
+/// `{name}` placeholders.
+fn check_path(path: &str, params: bool) -> Result<(), &'static str> {
+    if !path.starts_with('/') {
+        return Err("must start with '/'");
+    }
+    if path == "/" {
+        return Ok(());
+    }
+    if path.ends_with('/') {
+        return Err("must not end with '/'");
+    }
+    if path[1..].split('/').any(str::is_empty) {
+        return Err("must not contain empty segments ('//')");
+    }
+    let allowed = |c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '~');
+    for segment in path[1..].split('/') {
+        if let Some(name) = segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+            if !params {
+                return Err("must not contain '{param}' segments");
+            }
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return Err("has an invalid '{param}' name");
+            }
+        } else if !segment.chars().all(allowed) {
+            return Err("may only use letters, digits, '/', '-', '_', '.' and '~'");
+        }
+    }
+    Ok(())
+}
+
+/// Colon-separated catalog names, such as `db:write` or `billing.invoices:read`.
+fn is_scope_name(scope: &str) -> bool {
+    scope.split(':').all(is_catalog_name)
 }
 
 pub struct ModuleServiceDescriptorBuilder {
@@ -70,7 +568,7 @@ impl ModuleServiceDescriptorBuilder {
         Self {
             inner: ModuleServiceDescriptor {
                 service_id,
-                protocols: vec!["http".to_string()],
+                protocols: vec![ServiceProtocol::Http],
                 ..ModuleServiceDescriptor::default()
             },
         }
@@ -86,7 +584,8 @@ impl ModuleServiceDescriptorBuilder {
         self
     }
 
-    pub fn kind(mut self, value: impl Into<String>) -> Self {
+    /// Accepts a `ServiceKind` or its string name.
+    pub fn kind(mut self, value: impl Into<ServiceKind>) -> Self {
         self.inner.kind = Some(value.into());
         self
     }
@@ -104,7 +603,7 @@ impl ModuleServiceDescriptorBuilder {
     pub fn internal_only(mut self, value: bool) -> Self {
         self.inner.internal_only = Some(value);
         if !value {
-            self.inner.ingress_access = Some("public".to_string());
+            self.inner.ingress_access = Some(IngressAccess::Public);
         }
         self
     }
@@ -113,7 +612,8 @@ impl ModuleServiceDescriptorBuilder {
         self.internal_only(false)
     }
 
-    pub fn ingress_access(mut self, value: impl Into<String>) -> Self {
+    /// Accepts an `IngressAccess` or its string name.
+    pub fn ingress_access(mut self, value: impl Into<IngressAccess>) -> Self {
         self.inner.ingress_access = Some(value.into());
         self
     }
@@ -139,7 +639,8 @@ impl ModuleServiceDescriptorBuilder {
         self
     }
 
-    pub fn add_protocol(mut self, value: impl Into<String>) -> Self {
+    /// Accepts a `ServiceProtocol` or its string name.
+    pub fn add_protocol(mut self, value: impl Into<ServiceProtocol>) -> Self {
         self.inner.protocols.push(value.into());
         self
     }
@@ -149,10 +650,163 @@ impl ModuleServiceDescriptorBuilder {
         self
     }
 
+    pub fn add_endpoint(mut self, endpoint: ModuleEndpointDescriptor) -> Self {
+        self.inner.endpoints.push(endpoint);
+        self
+    }
+
+    /// Shorthand for an endpoint with `methods` and `scopes` and no other settings.
+    pub fn route(self, path: impl Into<String>, methods: &[&str], scopes: &[&str]) -> Self {
+        let endpoint = scopes.iter().fold(
+            ModuleEndpointDescriptor::new(path).methods(methods),
+            |endpoint, scope| endpoint.scope(*scope),
+        );
+        self.add_endpoint(endpoint)
+    }
+
     pub fn build(mut self) -> ModuleServiceDescriptor {
         if self.inner.protocols.is_empty() {
-            self.inner.protocols.push("http".to_string());
+            self.inner.protocols.push(ServiceProtocol::Http);
         }
         self.inner
     }
+
+    /// `build` followed by `ModuleServiceDescriptor::validate`.
+    pub fn try_build(self) -> Result<ModuleServiceDescriptor, ModuleKitError> {
+        let descriptor = self.build();
+        descriptor.validate()?;
+        Ok(descriptor)
+    }
+}
+
+/// What a module offers beyond its HTTP routes, shown in the platform catalog.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct ModuleCapabilities {
+    #[serde(default)]
+    pub webhooks: Vec<WebhookCapability>,
+    #[serde(default)]
+    pub events_published: Vec<String>,
+    #[serde(default)]
+    pub events_consumed: Vec<String>,
+    #[serde(default)]
+    pub admin_operations: Vec<AdminOperation>,
+}
+
+/// Webhook a module can deliver to subscribers.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct WebhookCapability {
+    pub name: String,
+    /// Event types that trigger the webhook.
+    #[serde(default)]
+    pub events: Vec<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// Operator-facing action such as a reindex or cache flush.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct AdminOperation {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required_scopes: Vec<String>,
+}
+
+impl ModuleCapabilities {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn webhook(mut self, name: impl Into<String>, events: &[&str]) -> Self {
+        self.webhooks.push(WebhookCapability {
+            name: name.into(),
+            events: events.iter().map(|event| event.to_string()).collect(),
+            description: None,
+        });
+        self
+    }
+
+    pub fn publishes(mut self, event_type: impl Into<String>) -> Self {
+        self.events_published.push(event_type.into());
+        self
+    }
+
+    pub fn consumes(mut self, event_type: impl Into<String>) -> Self {
+        self.events_consumed.push(event_type.into());
+        self
+    }
+
+    pub fn admin_operation(
+        mut self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        required_scopes: &[&str],
+    ) -> Self {
+        self.admin_operations.push(AdminOperation {
+            name: name.into(),
+            description: Some(description.into()),
+            required_scopes: required_scopes
+                .iter()
+                .map(|scope| scope.to_string())
+                .collect(),
+        });
+        self
+    }
+
+    /// Checks names and event types, reporting every problem at once.
+    ///
+    /// Names and event types use lowercase letters, digits, `.`, `_` and `-`;
+    /// webhook events must be ones the module publishes.
+    pub fn validate(&self) -> Result<(), ModuleKitError> {
+        let mut problems = Vec::new();
+        let mut check_name = |kind: &str, value: &str, seen: &mut BTreeSet<String>| {
+            if !is_catalog_name(value) {
+                problems.push(format!("{kind} '{value}' has an invalid name"));
+            } else if !seen.insert(value.to_string()) {
+                problems.push(format!("{kind} '{value}' is declared twice"));
+            }
+        };
+        let mut seen = BTreeSet::new();
+        for webhook in &self.webhooks {
+            check_name("webhook", &webhook.name, &mut seen);
+        }
+        let mut seen = BTreeSet::new();
+        for event in &self.events_published {
+            check_name("published event", event, &mut seen);
+        }
+        let mut seen = BTreeSet::new();
+        for event in &self.events_consumed {
+            check_name("consumed event", event, &mut seen);
+        }
+        let mut seen = BTreeSet::new();
+        for operation in &self.admin_operations {
+            check_name("admin operation", &operation.name, &mut seen);
+        }
+        for webhook in &self.webhooks {
+            if webhook.events.is_empty() {
+                problems.push(format!("webhook '{}' lists no events", webhook.name));
+            }
+            for event in &webhook.events {
+                if !self.events_published.contains(event) {
+                    problems.push(format!(
+                        "webhook '{}' fires on '{event}', which is not a published event",
+                        webhook.name
+                    ));
+                }
+            }
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ModuleKitError::InvalidCapabilities(problems))
+        }
+    }
+}
+
+fn is_catalog_name(value: &str) -> bool {
+    value.chars().next().is_some_and(|c| c.is_ascii_lowercase())
+        && value
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '.' | '_' | '-'))
 }
@@ -0,0 +1,125 @@
+use std::collections::BTreeSet;
+
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+
+use crate::service::{ModuleReportedServices, ModuleServiceDescriptor};
+
+/// One descriptor field whose value differs, compared in serialized form.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: JsonValue,
+    pub after: JsonValue,
+}
+
+/// Changed fields of a service present on both sides.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ServiceChange {
+    pub service_id: String,
+    pub fields: Vec<FieldChange>,
+}
+
+/// What registering one set of services over another would change.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ServicesChangeSet {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub modified: Vec<ServiceChange>,
+    /// Set when the module capabilities differ.
+    pub capabilities_changed: bool,
+}
+
+impl ServicesChangeSet {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.modified.is_empty()
+            && !self.capabilities_changed
+    }
+}
+
+impl ModuleServiceDescriptor {
+    /// Fields that change going from `self` to `other`, sorted by name.
+    pub fn diff(&self, other: &ModuleServiceDescriptor) -> Vec<FieldChange> {
+        let before = to_object(self);
+        let after = to_object(other);
+        let fields: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+        fields
+            .into_iter()
+            .filter_map(|field| {
+                let old = before.get(field).cloned().unwrap_or(JsonValue::Null);
+                let new = after.get(field).cloned().unwrap_or(JsonValue::Null);
+                (old != new).then(|| FieldChange {
+                    field: field.clone(),
+                    before: old,
+                    after: new,
+                })
+            })
+            .collect()
+    }
+}
+
+impl ModuleReportedServices {
+    /// Services added, removed and modified going from `self` to `other`,
+    /// matched by `service_id`.
+    pub fn diff(&self, other: &ModuleReportedServices) -> ServicesChangeSet {
+        let mut changes = ServicesChangeSet {
+            capabilities_changed: self.capabilities != other.capabilities,
+            ..ServicesChangeSet::default()
+        };
+        for service in &self.services {
+            match other.service(&service.service_id) {
+                Some(updated) => {
+                    let fields = service.diff(updated);
+                    if !fields.is_empty() {
+                        changes.modified.push(ServiceChange {
+                            service_id: service.service_id.clone(),
+                            fields,
+                        });
+                    }
+                }
+                None => changes.removed.push(service.service_id.clone()),
+            }
+        }
+        changes.added = other
+            .services
+            .iter()
+            .filter(|service| self.service(&service.service_id).is_none())
+            .map(|service| service.service_id.clone())
+            .collect();
+        changes
+    }
+
+    /// Adds services from `other` and replaces those with the same
+    /// `service_id`, keeping services only `self` declares. Capabilities are
+    /// taken from `other` when it has them.
+    ///
+    /// Returns the resulting change; `removed` is always empty.
+    pub fn merge(&mut self, other: ModuleReportedServices) -> ServicesChangeSet {
+        let mut merged = self.clone();
+        for service in other.services {
+            match merged
+                .services
+                .iter_mut()
+                .find(|existing| existing.service_id == service.service_id)
+            {
+                Some(existing) => *existing = service,
+                None => merged.services.push(service),
+            }
+        }
+        if other.capabilities.is_some() {
+            merged.capabilities = other.capabilities;
+        }
+        let changes = self.diff(&merged);
+        *self = merged;
+        changes
+    }
+}
+
+fn to_object(descriptor: &ModuleServiceDescriptor) -> serde_json::Map<String, JsonValue> {
+    match serde_json::to_value(descriptor) {
+        Ok(JsonValue::Object(fields)) => fields,
+        _ => serde_json::Map::new(),
+    }
+}
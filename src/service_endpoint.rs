@@ -0,0 +1,150 @@
+use std::sync::Arc;
+
+use crate::health::{HealthRegistry, HealthStatus};
+use crate::service::ModuleReportedServices;
+
+/// Path the runtime polls for a module's service descriptors.
+pub const SERVICES_PATH: &str = "/.fenrir/services";
+pub const LIVENESS_PATH: &str = "/healthz";
+pub const READINESS_PATH: &str = "/readyz";
+
+/// JSON reply produced by `ServiceEndpoint::respond`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+impl EndpointResponse {
+    pub const CONTENT_TYPE: &'static str = "application/json";
+
+    fn json(status: u16, body: String) -> Self {
+        Self { status, body }
+    }
+
+    /// Status line text such as `200 OK`.
+    pub fn status_line(&self) -> &'static str {
+        match self.status {
+            200 => "200 OK",
+            404 => "404 Not Found",
+            405 => "405 Method Not Allowed",
+            503 => "503 Service Unavailable",
+            _ => "500 Internal Server Error",
+        }
+    }
+}
+
+/// Serves `/.fenrir/services`, `/healthz` and `/readyz` from crate types, so
+/// modules only route requests to it from whatever HTTP stack they use.
+///
+/// Readiness runs the registry's checks synchronously; call `respond` from a
+/// blocking context in async servers, or use `handle` under axum.
+#[derive(Clone)]
+pub struct ServiceEndpoint {
+    services: Option<Arc<ModuleReportedServices>>,
+    registry: Arc<HealthRegistry>,
+}
+
+impl ServiceEndpoint {
+    pub fn new(registry: Arc<HealthRegistry>) -> Self {
+        Self {
+            services: None,
+            registry,
+        }
+    }
+
+    /// Payload returned by `/.fenrir/services`; the path 404s until set.
+    pub fn services(mut self, services: ModuleReportedServices) -> Self {
+        self.services = Some(Arc::new(services));
+        self
+    }
+
+    pub fn registry(&self) -> &Arc<HealthRegistry> {
+        &self.registry
+    }
+
+    /// Reply for `method` and `path`, or `None` for paths this endpoint does
+    /// not own so the caller can fall through to its own routes.
+    pub fn respond(&self, method: &str, path: &str) -> Option<EndpointResponse> {
+        let path = path.split('?').next().unwrap_or_default();
+        if !matches!(path, SERVICES_PATH | LIVENESS_PATH | READINESS_PATH) {
+            return None;
+        }
+        let head = method.eq_ignore_ascii_case("HEAD");
+        if !head && !method.eq_ignore_ascii_case("GET") {
+            return Some(EndpointResponse::json(
+                405,
+                r#"{"status":"method_not_allowed"}"#.to_string(),
+            ));
+        }
+        let mut response = match path {
+            SERVICES_PATH => match &self.services {
+                Some(services) => EndpointResponse::json(
+                    200,
+                    serde_json::to_string(services.as_ref()).unwrap_or_default(),
+                ),
+                None => EndpointResponse::json(404, r#"{"status":"not_found"}"#.to_string()),
+            },
+            LIVENESS_PATH => EndpointResponse::json(200, r#"{"status":"healthy"}"#.to_string()),
+            _ => {
+                let report = self.registry.report();
+                let status = if report.status == HealthStatus::Unhealthy {
+                    503
+                } else {
+                    200
+                };
+                EndpointResponse::json(status, serde_json::to_string(&report).unwrap_or_default())
+            }
+        };
+        if head {
+            response.body.clear();
+        }
+        Some(response)
+    }
+}
+
+#[cfg(feature = "axum")]
+impl ServiceEndpoint {
+    /// `respond` for an axum request, usable from a fallback handler or
+    /// middleware. Readiness checks run on tokio's blocking pool.
+    pub fn handle<B>(
+        &self,
+        request: &http::Request<B>,
+    ) -> impl std::future::Future<Output = Option<axum_core::response::Response>> + Send + 'static
+    {
+        use axum_core::response::IntoResponse;
+
+        let endpoint = self.clone();
+        let method = request.method().clone();
+        let path = request.uri().path().to_string();
+        async move {
+            let response = if path == READINESS_PATH {
+                tokio::task::spawn_blocking(move || endpoint.respond(method.as_str(), &path))
+                    .await
+                    .unwrap_or_else(|_| {
+                        Some(EndpointResponse::json(
+                            503,
+                            r#"{"status":"unhealthy"}"#.to_string(),
+                        ))
+                    })
+            } else {
+                endpoint.respond(method.as_str(), &path)
+            };
+            response.map(IntoResponse::into_response)
+        }
+    }
+}
+
+#[cfg(feature = "axum")]
+impl axum_core::response::IntoResponse for EndpointResponse {
+    fn into_response(self) -> axum_core::response::Response {
+        let status = http::StatusCode::from_u16(self.status)
+            .unwrap_or(http::StatusCode::INTERNAL_SERVER_ERROR);
+        (
+            status,
+            [(http::header::CONTENT_TYPE, Self::CONTENT_TYPE)],
+            self.body,
+        )
+            .into_response()
+    }
+}
@@ -0,0 +1,279 @@
+use std::env::VarError;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Number, Value as JsonValue};
+
+use crate::env::{EnvIssue, EnvReport, EnvSource, ProcessEnv};
+use crate::error::ModuleKitError;
+use crate::module_config::ModuleConfigClient;
+use crate::shutdown::TaskSupervisor;
+
+const REDACTED: &str = "***";
+
+type Validator = Arc<dyn Fn(&JsonValue) -> Result<(), String> + Send + Sync>;
+
+/// How a setting's environment variable is parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingKind {
+    String,
+    Integer,
+    Float,
+    Bool,
+    /// The variable holds a JSON document.
+    Json,
+}
+
+/// One field of a module's settings struct.
+#[derive(Clone)]
+pub struct SettingField {
+    name: String,
+    kind: SettingKind,
+    env: Option<String>,
+    config_key: Option<String>,
+    default: Option<JsonValue>,
+    required: bool,
+    secret: bool,
+    validator: Option<Validator>,
+}
+
+impl SettingField {
+    /// Field `name` of the target struct, read from nowhere until `env` or
+    /// `config_key` is set.
+    pub fn new(name: impl Into<String>, kind: SettingKind) -> Self {
+        Self {
+            name: name.into(),
+            kind,
+            env: None,
+            config_key: None,
+            default: None,
+            required: false,
+            secret: false,
+            validator: None,
+        }
+    }
+
+    pub fn string(name: impl Into<String>) -> Self {
+        Self::new(name, SettingKind::String)
+    }
+
+    pub fn integer(name: impl Into<String>) -> Self {
+        Self::new(name, SettingKind::Integer)
+    }
+
+    pub fn float(name: impl Into<String>) -> Self {
+        Self::new(name, SettingKind::Float)
+    }
+
+    pub fn bool(name: impl Into<String>) -> Self {
+        Self::new(name, SettingKind::Bool)
+    }
+
+    pub fn json(name: impl Into<String>) -> Self {
+        Self::new(name, SettingKind::Json)
+    }
+
+    pub fn env(mut self, name: impl Into<String>) -> Self {
+        self.env = Some(name.into());
+        self
+    }
+
+    /// Dotted path into the control plane config document, e.g. `db.pool_size`.
+    pub fn config_key(mut self, key: impl Into<String>) -> Self {
+        self.config_key = Some(key.into());
+        self
+    }
+
+    pub fn default_value(mut self, value: impl Into<JsonValue>) -> Self {
+        self.default = Some(value.into());
+        self
+    }
+
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    /// Redacted by `ModuleSettings::describe`; pair with a `SecretString` field.
+    pub fn secret(mut self) -> Self {
+        self.secret = true;
+        self
+    }
+
+    pub fn validate(
+        mut self,
+        check: impl Fn(&JsonValue) -> Result<(), String> + Send + Sync + 'static,
+    ) -> Self {
+        self.validator = Some(Arc::new(check));
+        self
+    }
+
+    fn source_name(&self) -> String {
+        self.env
+            .clone()
+            .or_else(|| self.config_key.clone())
+            .unwrap_or_else(|| self.name.clone())
+    }
+
+    fn read_env(&self, vars: &dyn EnvSource) -> Result<Option<JsonValue>, String> {
+        let Some(name) = &self.env else {
+            return Ok(None);
+        };
+        let raw = match vars.var(name) {
+            Ok(value) if !value.trim().is_empty() => value,
+            Ok(_) | Err(VarError::NotPresent) => return Ok(None),
+            Err(err) => return Err(err.to_string()),
+        };
+        let value = raw.trim();
+        let parsed = match self.kind {
+            SettingKind::String => JsonValue::String(raw),
+            SettingKind::Integer => value
+                .parse::<i64>()
+                .map(JsonValue::from)
+                .map_err(|_| format!("expected integer, got '{value}'"))?,
+            SettingKind::Float => value
+                .parse::<f64>()
+                .ok()
+                .and_then(Number::from_f64)
+                .map(JsonValue::Number)
+                .ok_or_else(|| format!("expected number, got '{value}'"))?,
+            SettingKind::Bool => match value.to_ascii_lowercase().as_str() {
+                "1" | "true" | "yes" => JsonValue::Bool(true),
+                "0" | "false" | "no" => JsonValue::Bool(false),
+                other => return Err(format!("expected boolean, got '{other}'")),
+            },
+            SettingKind::Json => serde_json::from_str(value).map_err(|err| err.to_string())?,
+        };
+        Ok(Some(parsed))
+    }
+
+    fn read_config(&self, config: Option<&JsonValue>) -> Option<JsonValue> {
+        let key = self.config_key.as_ref()?;
+        key.split('.')
+            .try_fold(config?, |node, segment| node.get(segment))
+            .filter(|value| !value.is_null())
+            .cloned()
+    }
+}
+
+/// Maps a settings struct's fields to environment variables and control
+/// plane config keys, replacing hand-rolled `settings.rs` loaders.
+///
+/// Environment variables win over the config document, which wins over
+/// defaults. Fields not declared here are left to serde's own defaults.
+#[derive(Clone, Default)]
+pub struct ModuleSettings {
+    fields: Vec<SettingField>,
+}
+
+impl ModuleSettings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn field(mut self, field: SettingField) -> Self {
+        self.fields.push(field);
+        self
+    }
+
+    /// Resolves every field and deserializes the result into `T`.
+    ///
+    /// Problems from all fields are collected into one `ModuleKitError::EnvReport`.
+    pub fn load<T: DeserializeOwned>(
+        &self,
+        vars: &dyn EnvSource,
+        config: Option<&JsonValue>,
+    ) -> Result<T, ModuleKitError> {
+        let values = self.resolve(vars, config)?;
+        serde_json::from_value(JsonValue::Object(values)).map_err(|err| {
+            ModuleKitError::EnvReport(EnvReport {
+                missing: Vec::new(),
+                invalid: vec![EnvIssue {
+                    name: "settings".to_string(),
+                    message: err.to_string(),
+                }],
+            })
+        })
+    }
+
+    pub fn from_env<T: DeserializeOwned>(&self) -> Result<T, ModuleKitError> {
+        self.load(&ProcessEnv, None)
+    }
+
+    /// Resolved values with secret fields replaced by `***`, for startup logs.
+    pub fn describe(
+        &self,
+        vars: &dyn EnvSource,
+        config: Option<&JsonValue>,
+    ) -> Result<Map<String, JsonValue>, ModuleKitError> {
+        let mut values = self.resolve(vars, config)?;
+        for field in self.fields.iter().filter(|field| field.secret) {
+            if let Some(value) = values.get_mut(&field.name) {
+                *value = JsonValue::String(REDACTED.to_string());
+            }
+        }
+        Ok(values)
+    }
+
+    /// Reloads settings whenever `config` fetches a new document, calling
+    /// `on_change` with the result. Documents that fail validation are skipped.
+    pub fn watch_supervised<T, E>(
+        self: &Arc<Self>,
+        config: &Arc<ModuleConfigClient<JsonValue>>,
+        supervisor: &TaskSupervisor,
+        interval: Duration,
+        vars: E,
+        on_change: impl Fn(Arc<T>) + Send + 'static,
+    ) where
+        T: DeserializeOwned + Send + Sync + 'static,
+        E: EnvSource + Send + 'static,
+    {
+        let settings = Arc::clone(self);
+        config.watch_supervised(supervisor, interval, move |document| {
+            if let Ok(value) = settings.load::<T>(&vars, Some(&document)) {
+                on_change(Arc::new(value));
+            }
+        });
+    }
+
+    fn resolve(
+        &self,
+        vars: &dyn EnvSource,
+        config: Option<&JsonValue>,
+    ) -> Result<Map<String, JsonValue>, ModuleKitError> {
+        let mut report = EnvReport::default();
+        let mut values = Map::new();
+        for field in &self.fields {
+            let value = match field.read_env(vars) {
+                Ok(Some(value)) => Some(value),
+                Ok(None) => field.read_config(config).or_else(|| field.default.clone()),
+                Err(message) => {
+                    report.invalid.push(EnvIssue {
+                        name: field.source_name(),
+                        message,
+                    });
+                    continue;
+                }
+            };
+            let Some(value) = value else {
+                if field.required {
+                    report.missing.push(field.source_name());
+                }
+                continue;
+            };
+            if let Some(check) = &field.validator {
+                if let Err(message) = check(&value) {
+                    report.invalid.push(EnvIssue {
+                        name: field.source_name(),
+                        message,
+                    });
+                    continue;
+                }
+            }
+            values.insert(field.name.clone(), value);
+        }
+        report.into_result()?;
+        Ok(values)
+    }
+}
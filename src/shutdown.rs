@@ -0,0 +1,125 @@
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const JOIN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Cloneable flag background loops wait on instead of sleeping.
+#[derive(Clone, Default)]
+pub struct ShutdownSignal {
+    inner: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl ShutdownSignal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn trigger(&self) {
+        let (flag, condvar) = &*self.inner;
+        *flag.lock().unwrap() = true;
+        condvar.notify_all();
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        *self.inner.0.lock().unwrap()
+    }
+
+    /// Sleeps up to `timeout`, returning early with `true` once shutdown is triggered.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let (flag, condvar) = &*self.inner;
+        let guard = flag.lock().unwrap();
+        let (guard, _) = condvar
+            .wait_timeout_while(guard, timeout, |triggered| !*triggered)
+            .unwrap();
+        *guard
+    }
+
+    pub fn wait(&self) {
+        let (flag, condvar) = &*self.inner;
+        let guard = flag.lock().unwrap();
+        let _guard = condvar.wait_while(guard, |triggered| !*triggered).unwrap();
+    }
+}
+
+/// Tasks that stopped and tasks still running when the shutdown deadline passed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShutdownReport {
+    pub stopped: Vec<String>,
+    pub timed_out: Vec<String>,
+}
+
+impl ShutdownReport {
+    pub fn is_clean(&self) -> bool {
+        self.timed_out.is_empty()
+    }
+}
+
+type CleanupHook = Box<dyn FnOnce() + Send>;
+
+/// Owns background threads that share one `ShutdownSignal`.
+#[derive(Default)]
+pub struct TaskSupervisor {
+    signal: ShutdownSignal,
+    tasks: Mutex<Vec<(String, thread::JoinHandle<()>)>>,
+    hooks: Mutex<Vec<CleanupHook>>,
+}
+
+impl TaskSupervisor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn signal(&self) -> ShutdownSignal {
+        self.signal.clone()
+    }
+
+    /// Runs `task` on a named thread; it should return soon after the signal triggers.
+    pub fn spawn(
+        &self,
+        name: impl Into<String>,
+        task: impl FnOnce(ShutdownSignal) + Send + 'static,
+    ) {
+        let name = name.into();
+        let signal = self.signal.clone();
+        let handle = thread::Builder::new()
+            .name(name.clone())
+            .spawn(move || task(signal))
+            .expect("failed to spawn supervised task");
+        self.tasks.lock().unwrap().push((name, handle));
+    }
+
+    /// Registers cleanup run after the tasks stop, in registration order.
+    pub fn on_shutdown(&self, hook: impl FnOnce() + Send + 'static) {
+        self.hooks.lock().unwrap().push(Box::new(hook));
+    }
+
+    /// Triggers the signal, waits up to `timeout` for every task, then runs the cleanup hooks.
+    ///
+    /// Tasks still running at the deadline are detached and listed in the report.
+    pub fn shutdown_with_timeout(&self, timeout: Duration) -> ShutdownReport {
+        self.signal.trigger();
+        let deadline = Instant::now() + timeout;
+        let mut pending = std::mem::take(&mut *self.tasks.lock().unwrap());
+        let mut report = ShutdownReport::default();
+        loop {
+            let (finished, running): (Vec<_>, Vec<_>) = pending
+                .into_iter()
+                .partition(|(_, handle)| handle.is_finished());
+            for (name, handle) in finished {
+                let _ = handle.join();
+                report.stopped.push(name);
+            }
+            pending = running;
+            if pending.is_empty() || Instant::now() >= deadline {
+                break;
+            }
+            thread::sleep(JOIN_POLL_INTERVAL);
+        }
+        report.timed_out = pending.into_iter().map(|(name, _)| name).collect();
+        for hook in std::mem::take(&mut *self.hooks.lock().unwrap()) {
+            hook();
+        }
+        report
+    }
+}
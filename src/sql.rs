@@ -0,0 +1,1225 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::connector_request::{DbConnectorCommand, DbPreparedParam};
+use crate::error::ModuleKitError;
+use crate::values::DbParamValue;
+
+/// Identifier quoting rules of an SQL engine.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SqlDialect {
+    /// `"name"`, used by Postgres, SQLite and the SQL standard.
+    #[default]
+    Ansi,
+    /// `` `name` ``, used by MySQL and MariaDB.
+    MySql,
+    /// `[name]`, used by SQL Server.
+    MsSql,
+}
+
+impl SqlDialect {
+    pub fn for_engine(engine: &str) -> Self {
+        match engine.to_ascii_lowercase().as_str() {
+            "mysql" | "mariadb" => SqlDialect::MySql,
+            "mssql" | "sqlserver" => SqlDialect::MsSql,
+            _ => SqlDialect::Ansi,
+        }
+    }
+
+    fn quote_part(&self, part: &str) -> String {
+        match self {
+            SqlDialect::Ansi => format!("\"{part}\""),
+            SqlDialect::MySql => format!("`{part}`"),
+            SqlDialect::MsSql => format!("[{part}]"),
+        }
+    }
+}
+
+/// Table or column name checked to contain only `[A-Za-z0-9_]`, optionally
+/// qualified with dots (`schema.table`), so it can be spliced into SQL.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SafeIdent(String);
+
+impl SafeIdent {
+    pub fn new(value: impl Into<String>) -> Result<Self, ModuleKitError> {
+        let value = value.into();
+        let valid = !value.is_empty()
+            && value.split('.').all(|part| {
+                part.chars()
+                    .next()
+                    .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                    && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            });
+        if !valid {
+            return Err(ModuleKitError::InvalidIdentifier(value));
+        }
+        Ok(Self(value))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The identifier quoted for `dialect`, each dotted part separately.
+    pub fn quoted(&self, dialect: SqlDialect) -> String {
+        self.0
+            .split('.')
+            .map(|part| dialect.quote_part(part))
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+}
+
+impl fmt::Display for SafeIdent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl TryFrom<&str> for SafeIdent {
+    type Error = ModuleKitError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+
+impl TryFrom<String> for SafeIdent {
+    type Error = ModuleKitError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+
+/// SQL text with `{name}` slots filled only by `SafeIdent`s; values still go
+/// through prepared parameters. `{{` and `}}` produce literal braces.
+#[derive(Debug, Clone)]
+pub struct SqlTemplate {
+    text: String,
+    idents: HashMap<String, SafeIdent>,
+}
+
+impl SqlTemplate {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            idents: HashMap::new(),
+        }
+    }
+
+    pub fn ident(mut self, slot: impl Into<String>, ident: SafeIdent) -> Self {
+        self.idents.insert(slot.into(), ident);
+        self
+    }
+
+    /// Statement text with every slot replaced by its quoted identifier.
+    pub fn render(&self, dialect: SqlDialect) -> Result<String, ModuleKitError> {
+        let invalid = |message: String| ModuleKitError::InvalidSqlTemplate(message);
+        let mut out = String::with_capacity(self.text.len());
+        let mut chars = self.text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    out.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    out.push('}');
+                }
+                '{' => {
+                    let mut slot = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => slot.push(c),
+                            None => return Err(invalid("unterminated '{'".to_string())),
+                        }
+                    }
+                    let ident = self
+                        .idents
+                        .get(slot.trim())
+                        .ok_or_else(|| invalid(format!("no identifier bound to '{{{slot}}}'")))?;
+                    out.push_str(&ident.quoted(dialect));
+                }
+                '}' => return Err(invalid("unmatched '}'".to_string())),
+                other => out.push(other),
+            }
+        }
+        Ok(out)
+    }
+
+    pub fn prepared(
+        &self,
+        dialect: SqlDialect,
+        params: Vec<DbPreparedParam>,
+    ) -> Result<DbConnectorCommand, ModuleKitError> {
+        Ok(DbConnectorCommand::Prepared {
+            statement: self.render(dialect)?,
+            params,
+        })
+    }
+}
+
+/// Comparison operator of a `Filter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SqlOp {
+    Eq,
+    NotEq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Like,
+}
+
+impl SqlOp {
+    fn as_sql(&self) -> &'static str {
+        match self {
+            SqlOp::Eq => "=",
+            SqlOp::NotEq => "<>",
+            SqlOp::Lt => "<",
+            SqlOp::Le => "<=",
+            SqlOp::Gt => ">",
+            SqlOp::Ge => ">=",
+            SqlOp::Like => "LIKE",
+        }
+    }
+}
+
+/// One `WHERE` condition; a statement's filters are joined with `AND`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    Compare {
+        column: String,
+        op: SqlOp,
+        value: DbParamValue,
+    },
+    IsNull {
+        column: String,
+    },
+    IsNotNull {
+        column: String,
+    },
+    /// Matches nothing when `values` is empty.
+    In {
+        column: String,
+        values: Vec<DbParamValue>,
+    },
+}
+
+impl Filter {
+    pub fn eq(column: impl Into<String>, value: impl Into<DbParamValue>) -> Self {
+        Self::compare(column, SqlOp::Eq, value)
+    }
+
+    pub fn compare(column: impl Into<String>, op: SqlOp, value: impl Into<DbParamValue>) -> Self {
+        Filter::Compare {
+            column: column.into(),
+            op,
+            value: value.into(),
+        }
+    }
+
+    pub fn is_null(column: impl Into<String>) -> Self {
+        Filter::IsNull {
+            column: column.into(),
+        }
+    }
+
+    pub fn is_not_null(column: impl Into<String>) -> Self {
+        Filter::IsNotNull {
+            column: column.into(),
+        }
+    }
+
+    pub fn in_list<V: Into<DbParamValue>>(
+        column: impl Into<String>,
+        values: impl IntoIterator<Item = V>,
+    ) -> Self {
+        Filter::In {
+            column: column.into(),
+            values: values.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    fn render(&self, dialect: SqlDialect, params: &mut Params) -> Result<String, ModuleKitError> {
+        Ok(match self {
+            Filter::Compare { column, op, value } => format!(
+                "{} {} {}",
+                quote_ident(column, dialect)?,
+                op.as_sql(),
+                params.bind(value.clone())
+            ),
+            Filter::IsNull { column } => format!("{} IS NULL", quote_ident(column, dialect)?),
+            Filter::IsNotNull { column } => {
+                format!("{} IS NOT NULL", quote_ident(column, dialect)?)
+            }
+            Filter::In { column, values } if values.is_empty() => {
+                quote_ident(column, dialect)?;
+                "1 = 0".to_string()
+            }
+            Filter::In { column, values } => format!(
+                "{} IN ({})",
+                quote_ident(column, dialect)?,
+                values
+                    .iter()
+                    .map(|value| params.bind(value.clone()))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        })
+    }
+}
+
+/// `SELECT` built from checked identifiers and bound values.
+#[derive(Debug, Clone)]
+pub struct Select {
+    table: String,
+    columns: Vec<String>,
+    filters: Vec<Filter>,
+    order_by: Vec<(String, bool)>,
+    limit: Option<u64>,
+    offset: Option<u64>,
+}
+
+impl Select {
+    /// Selects every column (`*`) until `columns` is set.
+    pub fn from(table: impl Into<String>) -> Self {
+        Self {
+            table: table.into(),
+            columns: Vec::new(),
+            filters: Vec::new(),
+            order_by: Vec::new(),
+            limit: None,
+            offset: None,
+        }
+    }
+
+    pub fn columns<S: Into<String>>(mut self, columns: impl IntoIterator<Item = S>) -> Self {
+        self.columns = columns.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    pub fn where_eq(self, column: impl Into<String>, value: impl Into<DbParamValue>) -> Self {
+        self.filter(Filter::eq(column, value))
+    }
+
+    pub fn order_by(mut self, column: impl Into<String>) -> Self {
+        self.order_by.push((column.into(), false));
+        self
+    }
+
+    pub fn order_by_desc(mut self, column: impl Into<String>) -> Self {
+        self.order_by.push((column.into(), true));
+        self
+    }
+
+    pub(crate) fn is_ordered(&self) -> bool {
+        !self.order_by.is_empty()
+    }
+
+    pub fn limit(mut self, value: u64) -> Self {
+        self.limit = Some(value);
+        self
+    }
+
+    pub fn offset(mut self, value: u64) -> Self {
+        self.offset = Some(value);
+        self
+    }
+
+    pub fn build(&self, dialect: SqlDialect) -> Result<DbConnectorCommand, ModuleKitError> {
+        let mut params = Params::default();
+        let columns = if self.columns.is_empty() {
+            "*".to_string()
+        } else {
+            quote_list(&self.columns, dialect)?
+        };
+        let mut statement = format!(
+            "SELECT {columns} FROM {}",
+            quote_ident(&self.table, dialect)?
+        );
+        statement.push_str(&render_filters(&self.filters, dialect, &mut params)?);
+        if !self.order_by.is_empty() {
+            let order = self
+                .order_by
+                .iter()
+                .map(|(column, desc)| {
+                    let direction = if *desc { " DESC" } else { "" };
+                    Ok(format!("{}{direction}", quote_ident(column, dialect)?))
+                })
+                .collect::<Result<Vec<_>, ModuleKitError>>()?;
+            statement.push_str(&format!(" ORDER BY {}", order.join(", ")));
+        }
+        statement.push_str(&self.render_window(dialect));
+        Ok(params.into_command(statement))
+    }
+
+    fn render_window(&self, dialect: SqlDialect) -> String {
+        if self.limit.is_none() && self.offset.is_none() {
+            return String::new();
+        }
+        let offset = self.offset.unwrap_or(0);
+        match dialect {
+            SqlDialect::MsSql => {
+                // OFFSET/FETCH is only valid after an ORDER BY.
+                let mut window = if self.order_by.is_empty() {
+                    " ORDER BY (SELECT NULL)".to_string()
+                } else {
+                    String::new()
+                };
+                window.push_str(&format!(" OFFSET {offset} ROWS"));
+                if let Some(limit) = self.limit {
+                    window.push_str(&format!(" FETCH NEXT {limit} ROWS ONLY"));
+                }
+                window
+            }
+            SqlDialect::Ansi => match self.limit {
+                Some(limit) => format!(" LIMIT {limit} OFFSET {offset}"),
+                None => format!(" OFFSET {offset}"),
+            },
+            // MySQL has no OFFSET without LIMIT; its documented workaround
+            // is the largest row count.
+            SqlDialect::MySql => {
+                format!(" LIMIT {} OFFSET {offset}", self.limit.unwrap_or(u64::MAX))
+            }
+        }
+    }
+}
+
+/// Single-row `INSERT`.
+#[derive(Debug, Clone)]
+pub struct Insert {
+    table: String,
+    values: Vec<(String, DbParamValue)>,
+}
+
+impl Insert {
+    pub fn into(table: impl Into<String>) -> Self {
+        Self {
+            table: table.into(),
+            values: Vec::new(),
+        }
+    }
+
+    pub fn value(mut self, column: impl Into<String>, value: impl Into<DbParamValue>) -> Self {
+        self.values.push((column.into(), value.into()));
+        self
+    }
+
+    pub fn build(&self, dialect: SqlDialect) -> Result<DbConnectorCommand, ModuleKitError> {
+        let (columns, row): (Vec<_>, Vec<_>) = self.values.iter().cloned().unzip();
+        InsertMany::new(self.table.clone(), columns)
+            .row(row)
+            .build(dialect)
+    }
+}
+
+/// Multi-row `INSERT ... VALUES (...), (...)` with every value bound as its
+/// own parameter, so `rows × columns` parameters in total.
+#[derive(Debug, Clone)]
+pub struct InsertMany {
+    table: String,
+    columns: Vec<String>,
+    rows: Vec<Vec<DbParamValue>>,
+    conflict_keys: Option<Vec<String>>,
+}
+
+impl InsertMany {
+    pub fn new<S: Into<String>>(
+        table: impl Into<String>,
+        columns: impl IntoIterator<Item = S>,
+    ) -> Self {
+        Self {
+            table: table.into(),
+            columns: columns.into_iter().map(Into::into).collect(),
+            rows: Vec::new(),
+            conflict_keys: None,
+        }
+    }
+
+    /// Turns the insert into an upsert: rows clashing on `keys` update the
+    /// existing row's other columns instead.
+    ///
+    /// MySQL ignores `keys` and resolves clashes on any unique index; SQL
+    /// Server has no single-statement form and fails to build.
+    pub fn upsert_on<S: Into<String>>(mut self, keys: impl IntoIterator<Item = S>) -> Self {
+        self.conflict_keys = Some(keys.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Adds a row; its values are matched to the columns by position.
+    pub fn row<V: Into<DbParamValue>>(mut self, values: impl IntoIterator<Item = V>) -> Self {
+        self.rows.push(values.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn rows<R, V>(self, rows: impl IntoIterator<Item = R>) -> Self
+    where
+        R: IntoIterator<Item = V>,
+        V: Into<DbParamValue>,
+    {
+        rows.into_iter().fold(self, |insert, row| insert.row(row))
+    }
+
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Splits the rows into inserts of at most `max_params` parameters each.
+    pub fn chunks(&self, max_params: usize) -> Result<Vec<InsertMany>, ModuleKitError> {
+        if self.columns.len() > max_params {
+            return Err(ModuleKitError::InvalidSqlStatement(format!(
+                "insert into '{}' has {} columns, over the limit of {max_params} parameters",
+                self.table,
+                self.columns.len()
+            )));
+        }
+        let rows_per_chunk = (max_params / self.columns.len().max(1)).max(1);
+        Ok(self
+            .rows
+            .chunks(rows_per_chunk)
+            .map(|rows| InsertMany {
+                table: self.table.clone(),
+                columns: self.columns.clone(),
+                rows: rows.to_vec(),
+                conflict_keys: self.conflict_keys.clone(),
+            })
+            .collect())
+    }
+
+    pub fn build(&self, dialect: SqlDialect) -> Result<DbConnectorCommand, ModuleKitError> {
+        let invalid = |message: String| ModuleKitError::InvalidSqlStatement(message);
+        if self.columns.is_empty() {
+            return Err(invalid(format!(
+                "insert into '{}' has no columns",
+                self.table
+            )));
+        }
+        if self.rows.is_empty() {
+            return Err(invalid(format!("insert into '{}' has no rows", self.table)));
+        }
+        let mut params = Params::default();
+        let mut tuples = Vec::with_capacity(self.rows.len());
+        for (index, row) in self.rows.iter().enumerate() {
+            if row.len() != self.columns.len() {
+                return Err(invalid(format!(
+                    "row {index} of insert into '{}' has {} values for {} columns",
+                    self.table,
+                    row.len(),
+                    self.columns.len()
+                )));
+            }
+            let values = row
+                .iter()
+                .map(|value| params.bind(value.clone()))
+                .collect::<Vec<_>>();
+            tuples.push(format!("({})", values.join(", ")));
+        }
+        let mut statement = format!(
+            "INSERT INTO {} ({}) VALUES {}",
+            quote_ident(&self.table, dialect)?,
+            quote_list(&self.columns, dialect)?,
+            tuples.join(", ")
+        );
+        if let Some(keys) = &self.conflict_keys {
+            statement.push_str(&self.render_conflict(keys, dialect)?);
+        }
+        Ok(params.into_command(statement))
+    }
+
+    fn render_conflict(
+        &self,
+        keys: &[String],
+        dialect: SqlDialect,
+    ) -> Result<String, ModuleKitError> {
+        let invalid = |message: String| ModuleKitError::InvalidSqlStatement(message);
+        if keys.is_empty() {
+            return Err(invalid(format!(
+                "upsert into '{}' has no conflict keys",
+                self.table
+            )));
+        }
+        if let Some(key) = keys.iter().find(|key| !self.columns.contains(key)) {
+            return Err(invalid(format!(
+                "conflict key '{key}' of upsert into '{}' is not an inserted column",
+                self.table
+            )));
+        }
+        let updated = self
+            .columns
+            .iter()
+            .filter(|column| !keys.contains(column))
+            .map(|column| quote_ident(column, dialect))
+            .collect::<Result<Vec<_>, _>>()?;
+        match dialect {
+            SqlDialect::Ansi => {
+                let action = if updated.is_empty() {
+                    "NOTHING".to_string()
+                } else {
+                    let assignments = updated
+                        .iter()
+                        .map(|column| format!("{column} = EXCLUDED.{column}"))
+                        .collect::<Vec<_>>();
+                    format!("UPDATE SET {}", assignments.join(", "))
+                };
+                Ok(format!(
+                    " ON CONFLICT ({}) DO {action}",
+                    quote_list(keys, dialect)?
+                ))
+            }
+            SqlDialect::MySql => {
+                // With only key columns, a self-assignment keeps the row as is.
+                let updated = if updated.is_empty() {
+                    vec![quote_ident(&keys[0], dialect)?]
+                } else {
+                    updated
+                };
+                let assignments = updated
+                    .iter()
+                    .map(|column| format!("{column} = VALUES({column})"))
+                    .collect::<Vec<_>>();
+                Ok(format!(
+                    " ON DUPLICATE KEY UPDATE {}",
+                    assignments.join(", ")
+                ))
+            }
+            SqlDialect::MsSql => Err(invalid(format!(
+                "upsert into '{}' is not supported for SQL Server; use MERGE",
+                self.table
+            ))),
+        }
+    }
+}
+
+/// `UPDATE`; refuses to build without a filter unless `all_rows` is set.
+#[derive(Debug, Clone)]
+pub struct Update {
+    table: String,
+    values: Vec<(String, DbParamValue)>,
+    filters: Vec<Filter>,
+    all_rows: bool,
+}
+
+impl Update {
+    pub fn table(table: impl Into<String>) -> Self {
+        Self {
+            table: table.into(),
+            values: Vec::new(),
+            filters: Vec::new(),
+            all_rows: false,
+        }
+    }
+
+    pub fn set(mut self, column: impl Into<String>, value: impl Into<DbParamValue>) -> Self {
+        self.values.push((column.into(), value.into()));
+        self
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    pub fn where_eq(self, column: impl Into<String>, value: impl Into<DbParamValue>) -> Self {
+        self.filter(Filter::eq(column, value))
+    }
+
+    /// Allows an update without filters.
+    pub fn all_rows(mut self) -> Self {
+        self.all_rows = true;
+        self
+    }
+
+    pub fn build(&self, dialect: SqlDialect) -> Result<DbConnectorCommand, ModuleKitError> {
+        if self.values.is_empty() {
+            return Err(ModuleKitError::InvalidSqlStatement(format!(
+                "update of '{}' sets no columns",
+                self.table
+            )));
+        }
+        check_unfiltered("update", &self.table, &self.filters, self.all_rows)?;
+        let mut params = Params::default();
+        let assignments = self
+            .values
+            .iter()
+            .map(|(column, value)| {
+                Ok(format!(
+                    "{} = {}",
+                    quote_ident(column, dialect)?,
+                    params.bind(value.clone())
+                ))
+            })
+            .collect::<Result<Vec<_>, ModuleKitError>>()?;
+        let mut statement = format!(
+            "UPDATE {} SET {}",
+            quote_ident(&self.table, dialect)?,
+            assignments.join(", ")
+        );
+        statement.push_str(&render_filters(&self.filters, dialect, &mut params)?);
+        Ok(params.into_command(statement))
+    }
+}
+
+/// `DELETE`; refuses to build without a filter unless `all_rows` is set.
+#[derive(Debug, Clone)]
+pub struct Delete {
+    table: String,
+    filters: Vec<Filter>,
+    all_rows: bool,
+}
+
+impl Delete {
+    pub fn from(table: impl Into<String>) -> Self {
+        Self {
+            table: table.into(),
+            filters: Vec::new(),
+            all_rows: false,
+        }
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    pub fn where_eq(self, column: impl Into<String>, value: impl Into<DbParamValue>) -> Self {
+        self.filter(Filter::eq(column, value))
+    }
+
+    /// Allows a delete without filters.
+    pub fn all_rows(mut self) -> Self {
+        self.all_rows = true;
+        self
+    }
+
+    pub fn build(&self, dialect: SqlDialect) -> Result<DbConnectorCommand, ModuleKitError> {
+        check_unfiltered("delete", &self.table, &self.filters, self.all_rows)?;
+        let mut params = Params::default();
+        let mut statement = format!("DELETE FROM {}", quote_ident(&self.table, dialect)?);
+        statement.push_str(&render_filters(&self.filters, dialect, &mut params)?);
+        Ok(params.into_command(statement))
+    }
+}
+
+/// Quotes a table or column name for `dialect` after checking it with
+/// `SafeIdent`.
+pub fn quote_ident(name: &str, dialect: SqlDialect) -> Result<String, ModuleKitError> {
+    Ok(SafeIdent::new(name)?.quoted(dialect))
+}
+
+fn quote_list(names: &[String], dialect: SqlDialect) -> Result<String, ModuleKitError> {
+    Ok(names
+        .iter()
+        .map(|name| quote_ident(name, dialect))
+        .collect::<Result<Vec<_>, _>>()?
+        .join(", "))
+}
+
+fn render_filters(
+    filters: &[Filter],
+    dialect: SqlDialect,
+    params: &mut Params,
+) -> Result<String, ModuleKitError> {
+    if filters.is_empty() {
+        return Ok(String::new());
+    }
+    let conditions = filters
+        .iter()
+        .map(|filter| filter.render(dialect, params))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(format!(" WHERE {}", conditions.join(" AND ")))
+}
+
+fn check_unfiltered(
+    verb: &str,
+    table: &str,
+    filters: &[Filter],
+    all_rows: bool,
+) -> Result<(), ModuleKitError> {
+    if filters.is_empty() && !all_rows {
+        return Err(ModuleKitError::InvalidSqlStatement(format!(
+            "{verb} of '{table}' has no filter; call all_rows() to affect every row"
+        )));
+    }
+    Ok(())
+}
+
+/// Names of the `:name` placeholders in `statement`, skipping quoted text
+/// and `::` casts.
+pub(crate) fn named_placeholders(statement: &str) -> Vec<&str> {
+    let bytes = statement.as_bytes();
+    let is_name = |b: u8| b.is_ascii_alphanumeric() || b == b'_';
+    let mut names = Vec::new();
+    let mut quote = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        match quote {
+            Some(open) if b == open => quote = None,
+            Some(_) => {}
+            None if b == b'\'' || b == b'"' => quote = Some(b),
+            None if b == b':' && bytes.get(i + 1) == Some(&b':') => i += 1,
+            None if b == b':' => {
+                let end = bytes[i + 1..]
+                    .iter()
+                    .position(|&b| !is_name(b))
+                    .map_or(bytes.len(), |len| i + 1 + len);
+                if end > i + 1 {
+                    names.push(&statement[i + 1..end]);
+                }
+                i = end;
+                continue;
+            }
+            None => {}
+        }
+        i += 1;
+    }
+    names
+}
+
+/// Lexical token of an SQL statement; see `tokenize`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum SqlToken {
+    /// Keyword or identifier. Bare words are lowercased; quoted identifiers
+    /// lose their quotes and keep their case.
+    Word { text: String, quoted: bool },
+    /// String or numeric literal, or a placeholder.
+    Value,
+    /// String literal containing a backslash. MySQL, and Postgres in
+    /// `E'...'` strings, read it as an escape, so the literal may end
+    /// elsewhere than where the standard rules put it.
+    EscapedValue,
+    /// Any other character, such as `(`, `)`, `,`, `;` or `.`.
+    Symbol(char),
+}
+
+impl SqlToken {
+    /// The text of an unquoted word, which is how keywords appear.
+    pub(crate) fn keyword(&self) -> Option<&str> {
+        match self {
+            SqlToken::Word {
+                text,
+                quoted: false,
+            } => Some(text),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn is_keyword(&self, keyword: &str) -> bool {
+        self.keyword() == Some(keyword)
+    }
+}
+
+/// Splits `statement` into tokens, dropping whitespace, `--` comments and
+/// (non-nested) `/* */` comments. String literals,
+/// including `$tag$` dollar quoting, become `SqlToken::Value` so their
+/// content is never mistaken for SQL. Quoted literals are delimited by the
+/// standard rules, with `''` as the only escape; those containing a
+/// backslash become `SqlToken::EscapedValue`.
+pub(crate) fn tokenize(statement: &str) -> Vec<SqlToken> {
+    let mut tokens = Vec::new();
+    let mut chars = statement.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '-' if chars.peek().map(|&(_, next)| next) == Some('-') => {
+                for (_, next) in chars.by_ref() {
+                    if next == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek().map(|&(_, next)| next) == Some('*') => {
+                chars.next();
+                let mut previous = ' ';
+                for (_, next) in chars.by_ref() {
+                    if previous == '*' && next == '/' {
+                        break;
+                    }
+                    previous = next;
+                }
+            }
+            '\'' => {
+                let text = skip_quoted(&mut chars, '\'');
+                tokens.push(if text.contains('\\') {
+                    SqlToken::EscapedValue
+                } else {
+                    SqlToken::Value
+                });
+            }
+            '"' | '`' | '[' => {
+                let close = match c {
+                    '[' => ']',
+                    other => other,
+                };
+                let text = skip_quoted(&mut chars, close);
+                tokens.push(SqlToken::Word { text, quoted: true });
+            }
+            '$' => {
+                let rest = &statement[start + 1..];
+                let tag_len = rest
+                    .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+                if rest[tag_len..].starts_with('$')
+                    && !rest.starts_with(|c: char| c.is_ascii_digit())
+                {
+                    // `$tag$ ... $tag$`; an unterminated body runs to the end.
+                    let delimiter = &statement[start..start + tag_len + 2];
+                    let body_start = start + delimiter.len();
+                    let end = statement[body_start..]
+                        .find(delimiter)
+                        .map_or(statement.len(), |at| body_start + at + delimiter.len());
+                    while chars.peek().is_some_and(|&(at, _)| at < end) {
+                        chars.next();
+                    }
+                } else {
+                    // Positional placeholder such as `$1`.
+                    while chars.peek().is_some_and(|&(_, next)| next.is_ascii_digit()) {
+                        chars.next();
+                    }
+                }
+                tokens.push(SqlToken::Value);
+            }
+            ':' if chars
+                .peek()
+                .is_some_and(|&(_, next)| next.is_alphanumeric() || next == '_') =>
+            {
+                while chars
+                    .peek()
+                    .is_some_and(|&(_, next)| next.is_alphanumeric() || next == '_')
+                {
+                    chars.next();
+                }
+                tokens.push(SqlToken::Value);
+            }
+            c if c.is_ascii_digit() => {
+                while chars
+                    .peek()
+                    .is_some_and(|&(_, next)| next.is_alphanumeric() || next == '.')
+                {
+                    chars.next();
+                }
+                tokens.push(SqlToken::Value);
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut text = c.to_lowercase().collect::<String>();
+                while let Some(&(_, next)) = chars.peek() {
+                    if !(next.is_alphanumeric() || next == '_' || next == '$') {
+                        break;
+                    }
+                    text.extend(next.to_lowercase());
+                    chars.next();
+                }
+                tokens.push(SqlToken::Word {
+                    text,
+                    quoted: false,
+                });
+            }
+            other => tokens.push(SqlToken::Symbol(other)),
+        }
+    }
+    tokens
+}
+
+/// Leading keywords of statements that only read.
+const READ_KEYWORDS: &[&str] = &[
+    "select", "with", "show", "describe", "desc", "explain", "values",
+];
+/// Keywords that make a statement write wherever they appear, such as in a
+/// data-modifying CTE, after `EXPLAIN ANALYZE` or in `SELECT ... INTO`.
+const WRITE_KEYWORDS: &[&str] = &[
+    "insert", "update", "delete", "merge", "upsert", "into", "create", "alter", "drop", "truncate",
+    "rename", "grant", "revoke", "copy", "call", "exec", "execute", "do", "lock", "vacuum",
+    "reindex", "cluster", "refresh", "comment",
+];
+
+/// Why `statement` might write, or `None` for a single statement that
+/// starts with a read keyword and names no write keyword outside literals,
+/// quoted identifiers and comments.
+///
+/// Errs on the side of refusing: `SELECT ... FOR UPDATE` counts as a write,
+/// and so does any statement with a backslash in a string literal, since
+/// where that literal ends depends on the engine.
+pub(crate) fn write_reason(statement: &str) -> Option<String> {
+    let tokens = tokenize(statement);
+    if tokens.contains(&SqlToken::EscapedValue) {
+        return Some("has a backslash inside a string literal".to_string());
+    }
+    let mut words = tokens.iter().filter_map(SqlToken::keyword);
+    match words.next() {
+        Some(first) if READ_KEYWORDS.contains(&first) => {}
+        Some(first) => return Some(format!("starts with '{first}'")),
+        None => return None,
+    }
+    if let Some(word) = words.find(|word| WRITE_KEYWORDS.contains(word)) {
+        return Some(format!("contains '{word}'"));
+    }
+    let end = tokens
+        .iter()
+        .rposition(|token| *token != SqlToken::Symbol(';'))
+        .map_or(0, |last| last + 1);
+    tokens[..end]
+        .contains(&SqlToken::Symbol(';'))
+        .then(|| "contains more than one statement".to_string())
+}
+
+/// Consumes a quoted run up to `close`, where a doubled `close` is an
+/// escaped one, and returns its content.
+fn skip_quoted(chars: &mut std::iter::Peekable<std::str::CharIndices<'_>>, close: char) -> String {
+    let mut text = String::new();
+    while let Some((_, next)) = chars.next() {
+        if next == close {
+            if chars.peek().map(|&(_, after)| after) == Some(close) {
+                chars.next();
+            } else {
+                break;
+            }
+        }
+        text.push(next);
+    }
+    text
+}
+
+/// Prepared parameters named `p0`, `p1`, ... in binding order.
+#[derive(Default)]
+struct Params(Vec<DbPreparedParam>);
+
+impl Params {
+    /// Binds `value` and returns its placeholder.
+    fn bind(&mut self, value: DbParamValue) -> String {
+        let name = format!("p{}", self.0.len());
+        let placeholder = format!(":{name}");
+        self.0.push(DbPreparedParam::new(name, value));
+        placeholder
+    }
+
+    fn into_command(self, statement: String) -> DbConnectorCommand {
+        DbConnectorCommand::Prepared {
+            statement,
+            params: self.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value as JsonValue;
+
+    use super::*;
+
+    fn words(statement: &str) -> Vec<String> {
+        tokenize(statement)
+            .into_iter()
+            .filter_map(|token| match token {
+                SqlToken::Word { text, .. } => Some(text),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn prepared(command: DbConnectorCommand) -> (String, Vec<(String, JsonValue)>) {
+        let DbConnectorCommand::Prepared { statement, params } = command else {
+            panic!("builders produce prepared commands");
+        };
+        let params = params
+            .into_iter()
+            .map(|param| (param.name, param.value))
+            .collect();
+        (statement, params)
+    }
+
+    #[test]
+    fn safe_ident_accepts_only_plain_dotted_names() {
+        assert_eq!(
+            SafeIdent::new("app.orders")
+                .unwrap()
+                .quoted(SqlDialect::Ansi),
+            "\"app\".\"orders\""
+        );
+        for name in ["", "1abc", "a.", "a b", "a;drop", "a\"b"] {
+            assert!(SafeIdent::new(name).is_err(), "{name:?}");
+        }
+    }
+
+    #[test]
+    fn tokenize_drops_comments_and_literals() {
+        assert_eq!(
+            words("SELECT /* from x */ a -- delete\nFROM t WHERE b = 'it''s drop'"),
+            ["select", "a", "from", "t", "where", "b"]
+        );
+        assert_eq!(
+            words("SELECT $body$ DELETE FROM t $body$, $1, :name"),
+            ["select"]
+        );
+    }
+
+    #[test]
+    fn tokenize_unquotes_identifiers() {
+        assert_eq!(
+            tokenize("\"My\"\"Table\".`c`"),
+            [
+                SqlToken::Word {
+                    text: "My\"Table".into(),
+                    quoted: true
+                },
+                SqlToken::Symbol('.'),
+                SqlToken::Word {
+                    text: "c".into(),
+                    quoted: true
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn write_reason_scans_the_whole_statement() {
+        assert_eq!(
+            write_reason("  select * from t where note = 'delete me';"),
+            None
+        );
+        assert_eq!(write_reason("SELECT \"update\" FROM t -- drop"), None);
+        for statement in [
+            "SELECT 1; DELETE FROM t",
+            "WITH d AS (DELETE FROM t RETURNING id) SELECT * FROM d",
+            "EXPLAIN ANALYZE DELETE FROM t",
+            "SELECT * INTO copy_of_t FROM t",
+            "SET ROLE admin",
+            "select id from t for update",
+            "SELECT 'a\\' , ' ; DELETE FROM t ; SELECT ''",
+            "SELECT E'\\' ; DELETE FROM t --'",
+        ] {
+            assert!(write_reason(statement).is_some(), "{statement}");
+        }
+    }
+
+    #[test]
+    fn named_placeholders_skip_casts_and_quotes() {
+        assert_eq!(
+            named_placeholders("SELECT ':no', a::text FROM t WHERE id = :id AND x = :x_1"),
+            ["id", "x_1"]
+        );
+    }
+
+    #[test]
+    fn select_quotes_identifiers_and_binds_values() {
+        let select = Select::from("app.orders")
+            .columns(["id", "total"])
+            .where_eq("status", "open")
+            .order_by_desc("id")
+            .limit(10);
+        let (statement, params) = prepared(select.build(SqlDialect::Ansi).unwrap());
+        assert_eq!(
+            statement,
+            "SELECT \"id\", \"total\" FROM \"app\".\"orders\" WHERE \"status\" = :p0 \
+             ORDER BY \"id\" DESC LIMIT 10 OFFSET 0"
+        );
+        assert_eq!(params, [("p0".to_string(), JsonValue::from("open"))]);
+
+        let (statement, _) = prepared(
+            Select::from("t")
+                .offset(5)
+                .build(SqlDialect::MsSql)
+                .unwrap(),
+        );
+        assert_eq!(
+            statement,
+            "SELECT * FROM [t] ORDER BY (SELECT NULL) OFFSET 5 ROWS"
+        );
+        let (statement, _) = prepared(Select::from("t").offset(5).build(SqlDialect::Ansi).unwrap());
+        assert_eq!(statement, "SELECT * FROM \"t\" OFFSET 5");
+        let (statement, _) = prepared(
+            Select::from("t")
+                .offset(5)
+                .build(SqlDialect::MySql)
+                .unwrap(),
+        );
+        assert_eq!(
+            statement,
+            format!("SELECT * FROM `t` LIMIT {} OFFSET 5", u64::MAX)
+        );
+        assert!(Select::from("t; drop").build(SqlDialect::Ansi).is_err());
+    }
+
+    #[test]
+    fn insert_many_binds_each_value_and_splits_into_chunks() {
+        let insert = InsertMany::new("t", ["a", "b"]).rows([[1, 2], [3, 4], [5, 6]]);
+        let (statement, params) = prepared(insert.build(SqlDialect::MySql).unwrap());
+        assert_eq!(
+            statement,
+            "INSERT INTO `t` (`a`, `b`) VALUES (:p0, :p1), (:p2, :p3), (:p4, :p5)"
+        );
+        assert_eq!(params.len(), 6);
+
+        let chunks = insert.chunks(4).unwrap();
+        assert_eq!(
+            chunks.iter().map(InsertMany::len).collect::<Vec<_>>(),
+            [2, 1]
+        );
+        assert!(insert.chunks(1).is_err());
+        assert!(InsertMany::new("t", ["a", "b"])
+            .row([1])
+            .build(SqlDialect::Ansi)
+            .is_err());
+    }
+
+    #[test]
+    fn upsert_renders_per_dialect() {
+        let upsert = InsertMany::new("t", ["id", "name"])
+            .row([JsonValue::from(1), JsonValue::from("x")])
+            .upsert_on(["id"]);
+        let (statement, _) = prepared(upsert.build(SqlDialect::Ansi).unwrap());
+        assert!(
+            statement.ends_with(" ON CONFLICT (\"id\") DO UPDATE SET \"name\" = EXCLUDED.\"name\"")
+        );
+        let (statement, _) = prepared(upsert.build(SqlDialect::MySql).unwrap());
+        assert!(statement.ends_with(" ON DUPLICATE KEY UPDATE `name` = VALUES(`name`)"));
+        assert!(upsert.build(SqlDialect::MsSql).is_err());
+        assert!(upsert
+            .clone()
+            .upsert_on(["missing"])
+            .build(SqlDialect::Ansi)
+            .is_err());
+    }
+
+    #[test]
+    fn update_and_delete_require_a_filter() {
+        assert!(Update::table("t")
+            .set("a", 1)
+            .build(SqlDialect::Ansi)
+            .is_err());
+        assert!(Delete::from("t").build(SqlDialect::Ansi).is_err());
+        let (statement, params) = prepared(
+            Update::table("t")
+                .set("a", 1)
+                .where_eq("id", 7)
+                .build(SqlDialect::Ansi)
+                .unwrap(),
+        );
+        assert_eq!(statement, "UPDATE \"t\" SET \"a\" = :p0 WHERE \"id\" = :p1");
+        assert_eq!(params.len(), 2);
+        let (statement, params) = prepared(
+            Delete::from("t")
+                .all_rows()
+                .build(SqlDialect::MsSql)
+                .unwrap(),
+        );
+        assert_eq!(statement, "DELETE FROM [t]");
+        assert!(params.is_empty());
+    }
+}
@@ -0,0 +1,66 @@
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::thread;
+
+use axum_core::body::Body;
+use bytes::Bytes;
+use futures_core::Stream;
+use tokio::sync::mpsc;
+
+use crate::connector::DbConnectorClient;
+use crate::error::ModuleKitError;
+use crate::export::{ExportJobBuilder, ExportSink};
+
+/// Chunks buffered between the export thread and the HTTP response.
+const STREAM_BUFFER_CHUNKS: usize = 4;
+
+type ChunkResult = Result<Bytes, ModuleKitError>;
+
+/// Streams an export into an axum response body as it is serialized.
+///
+/// The export thread blocks once `STREAM_BUFFER_CHUNKS` chunks are waiting,
+/// so a slow client throttles serialization instead of growing memory; a
+/// disconnected client aborts the export. Only jobs built with
+/// `ExportJob::select` also bound what is read from the connector; a
+/// statement export loads its whole reply first. Pair with
+/// `ExportJobBuilder::output_format().content_type()` for the header.
+pub fn export_body(client: Arc<DbConnectorClient>, job: ExportJobBuilder) -> Body {
+    let (tx, rx) = mpsc::channel(STREAM_BUFFER_CHUNKS);
+    let error_tx = tx.clone();
+    let job = job.start(client, ChannelSink { tx });
+    thread::spawn(move || {
+        if let Err(err) = job.wait() {
+            let _ = error_tx.blocking_send(Err(err));
+        }
+    });
+    Body::from_stream(ChunkStream { rx })
+}
+
+struct ChannelSink {
+    tx: mpsc::Sender<ChunkResult>,
+}
+
+impl ExportSink for ChannelSink {
+    fn write_chunk(&mut self, _index: usize, bytes: &[u8]) -> Result<(), ModuleKitError> {
+        self.tx
+            .blocking_send(Ok(Bytes::copy_from_slice(bytes)))
+            .map_err(|_| ModuleKitError::ExportFailed("response stream closed".into()))
+    }
+
+    fn finish(&mut self) -> Result<Option<String>, ModuleKitError> {
+        Ok(None)
+    }
+}
+
+struct ChunkStream {
+    rx: mpsc::Receiver<ChunkResult>,
+}
+
+impl Stream for ChunkStream {
+    type Item = ChunkResult;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
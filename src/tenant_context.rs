@@ -0,0 +1,315 @@
+use std::cell::RefCell;
+use std::marker::PhantomData;
+
+use crate::connector_request::{
+    DbConnectorCommand, DbPreparedParam, DbTenantBindingMode, DbTenantPolicy,
+};
+use crate::error::ModuleKitError;
+#[cfg(feature = "jwt-verify")]
+use crate::verifier::VerifiedClaims;
+
+/// Prepared parameter that carries the tenant id unless `TenantContext::param`
+/// says otherwise.
+pub const DEFAULT_TENANT_PARAM: &str = "tenant_id";
+
+thread_local! {
+    static AMBIENT_TENANT: RefCell<Option<TenantContext>> = const { RefCell::new(None) };
+}
+
+/// Tenant that requests are bound to when the call passes no
+/// `DbTenantPolicy` of its own.
+///
+/// A context entered on the current thread takes precedence over one set
+/// with `DbConnectorClient::set_tenant_context`. The thread-local context
+/// does not follow async tasks across threads; set it on the client or pass
+/// the policy explicitly there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TenantContext {
+    pub tenant_id: String,
+    /// Prepared parameter the tenant id is bound to.
+    pub param: String,
+    /// Further parameters and their values, such as a workspace id.
+    pub extra_params: Vec<(String, String)>,
+    pub mode: DbTenantBindingMode,
+    /// Tenant schema for `DbTenantBindingMode::SchemaSwitch`.
+    pub schema: Option<String>,
+    /// The tenant id comes from a verified token. Requests scoped to
+    /// another tenant are then refused.
+    pub claimed: bool,
+}
+
+impl TenantContext {
+    pub fn new(tenant_id: impl Into<String>) -> Self {
+        Self {
+            tenant_id: tenant_id.into(),
+            param: DEFAULT_TENANT_PARAM.to_string(),
+            extra_params: Vec::new(),
+            mode: DbTenantBindingMode::default(),
+            schema: None,
+            claimed: false,
+        }
+    }
+
+    /// Context for the tenant named in a caller's verified token, or `None`
+    /// when the token carries no tenant.
+    #[cfg(feature = "jwt-verify")]
+    pub fn from_claims(claims: &VerifiedClaims) -> Option<Self> {
+        let tenant = claims.tenant.as_deref()?.trim();
+        if tenant.is_empty() {
+            return None;
+        }
+        Some(Self {
+            claimed: true,
+            ..Self::new(tenant)
+        })
+    }
+
+    pub fn param(mut self, value: impl Into<String>) -> Self {
+        self.param = value.into();
+        self
+    }
+
+    pub fn mode(mut self, value: DbTenantBindingMode) -> Self {
+        self.mode = value;
+        self
+    }
+
+    /// Binds one more parameter to the tenant.
+    pub fn and_param(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.extra_params.push((name.into(), value.into()));
+        self
+    }
+
+    /// Runs requests in `schema` instead of binding parameters.
+    pub fn schema(mut self, value: impl Into<String>) -> Self {
+        self.schema = Some(value.into());
+        self.mode = DbTenantBindingMode::SchemaSwitch;
+        self
+    }
+
+    pub fn policy(&self) -> DbTenantPolicy {
+        DbTenantPolicy {
+            param: match self.mode {
+                DbTenantBindingMode::SchemaSwitch => String::new(),
+                _ => self.param.clone(),
+            },
+            extra_params: match self.mode {
+                DbTenantBindingMode::SchemaSwitch => Vec::new(),
+                _ => self
+                    .extra_params
+                    .iter()
+                    .map(|(name, _)| name.clone())
+                    .collect(),
+            },
+            mode: self.mode,
+            schema: self.schema.clone(),
+        }
+    }
+
+    /// Context entered on the current thread, if any.
+    pub fn current() -> Option<TenantContext> {
+        AMBIENT_TENANT.with(|ambient| ambient.borrow().clone())
+    }
+
+    /// Makes this the current thread's context until the guard drops, when
+    /// the previous one is restored.
+    pub fn enter(self) -> TenantGuard {
+        let previous = AMBIENT_TENANT.with(|ambient| ambient.replace(Some(self)));
+        TenantGuard {
+            previous,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Runs `f` with this as the current thread's context.
+    pub fn scope<R>(self, f: impl FnOnce() -> R) -> R {
+        let _guard = self.enter();
+        f()
+    }
+
+    /// Binds the tenant parameters to `command`, turning a simple statement
+    /// into a prepared one. Parameters of the same name already set by the
+    /// caller are kept, unless the context is claimed: its values then
+    /// replace them. Returns false for commands without a statement.
+    pub(crate) fn bind(&self, command: &mut DbConnectorCommand) -> bool {
+        if self.mode == DbTenantBindingMode::SchemaSwitch {
+            return matches!(
+                command,
+                DbConnectorCommand::Simple { .. } | DbConnectorCommand::Prepared { .. }
+            );
+        }
+        if let DbConnectorCommand::Simple { statement } = command {
+            *command = DbConnectorCommand::Prepared {
+                statement: std::mem::take(statement),
+                params: Vec::new(),
+            };
+        }
+        let DbConnectorCommand::Prepared { params, .. } = command else {
+            return false;
+        };
+        let bindings = std::iter::once((&self.param, &self.tenant_id))
+            .chain(self.extra_params.iter().map(|(name, value)| (name, value)));
+        for (name, value) in bindings {
+            if self.claimed {
+                params.retain(|param| &param.name != name);
+            } else if params.iter().any(|param| &param.name == name) {
+                continue;
+            }
+            params.push(DbPreparedParam::new(name.clone(), value.clone()));
+        }
+        true
+    }
+
+    /// Refuses a request scoped to another tenant than the claimed one: a
+    /// tenant parameter bound to a different id, or a schema other than the
+    /// context's (the tenant id when the context names none). Unclaimed
+    /// contexts accept everything.
+    pub(crate) fn check_claimed(
+        &self,
+        policy: &DbTenantPolicy,
+        command: &DbConnectorCommand,
+    ) -> Result<(), ModuleKitError> {
+        if !self.claimed {
+            return Ok(());
+        }
+        if policy.mode == DbTenantBindingMode::SchemaSwitch {
+            let own = self.schema.as_deref().unwrap_or(&self.tenant_id);
+            return match policy.schema.as_deref() {
+                Some(schema) if schema != own => Err(ModuleKitError::TenantMismatch {
+                    claimed: self.tenant_id.clone(),
+                    requested: schema.to_string(),
+                }),
+                _ => Ok(()),
+            };
+        }
+        let DbConnectorCommand::Prepared { params, .. } = command else {
+            return Ok(());
+        };
+        let bound = params
+            .iter()
+            .filter(|param| param.name == policy.param)
+            .map(|param| match &param.value {
+                serde_json::Value::String(value) => value.clone(),
+                other => other.to_string(),
+            })
+            .find(|value| *value != self.tenant_id);
+        match bound {
+            Some(requested) => Err(ModuleKitError::TenantMismatch {
+                claimed: self.tenant_id.clone(),
+                requested,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Restores the previous thread-local `TenantContext` when dropped.
+#[must_use = "the tenant context is left as soon as the guard drops"]
+pub struct TenantGuard {
+    previous: Option<TenantContext>,
+    // Must be dropped on the thread that entered the context.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for TenantGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        AMBIENT_TENANT.with(|ambient| *ambient.borrow_mut() = previous);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(command: &DbConnectorCommand) -> Vec<(&str, &serde_json::Value)> {
+        let DbConnectorCommand::Prepared { params, .. } = command else {
+            panic!("expected a prepared command");
+        };
+        params
+            .iter()
+            .map(|param| (param.name.as_str(), &param.value))
+            .collect()
+    }
+
+    fn prepared(name: &str, value: &str) -> DbConnectorCommand {
+        DbConnectorCommand::Prepared {
+            statement: "SELECT 1".into(),
+            params: vec![DbPreparedParam::new(name, value)],
+        }
+    }
+
+    #[test]
+    fn bind_prepares_simple_statements() {
+        let context = TenantContext::new("t1").and_param("workspace_id", "w1");
+        let mut command = DbConnectorCommand::Simple {
+            statement: "SELECT 1".into(),
+        };
+        assert!(context.bind(&mut command));
+        assert_eq!(
+            params(&command),
+            [
+                ("tenant_id", &serde_json::json!("t1")),
+                ("workspace_id", &serde_json::json!("w1")),
+            ]
+        );
+        assert!(!context.bind(&mut DbConnectorCommand::Capabilities));
+    }
+
+    #[test]
+    fn bind_overrides_caller_params_only_when_claimed() {
+        let mut command = prepared("tenant_id", "other");
+        TenantContext::new("t1").bind(&mut command);
+        assert_eq!(
+            params(&command),
+            [("tenant_id", &serde_json::json!("other"))]
+        );
+
+        let claimed = TenantContext {
+            claimed: true,
+            ..TenantContext::new("t1")
+        };
+        claimed.bind(&mut command);
+        assert_eq!(params(&command), [("tenant_id", &serde_json::json!("t1"))]);
+    }
+
+    #[test]
+    fn claimed_context_refuses_other_tenants() {
+        let claimed = TenantContext {
+            claimed: true,
+            ..TenantContext::new("t1")
+        };
+        let policy = claimed.policy();
+        assert!(claimed
+            .check_claimed(&policy, &prepared("tenant_id", "t1"))
+            .is_ok());
+        assert!(matches!(
+            claimed.check_claimed(&policy, &prepared("tenant_id", "t2")),
+            Err(ModuleKitError::TenantMismatch { requested, .. }) if requested == "t2"
+        ));
+        assert!(TenantContext::new("t1")
+            .check_claimed(&policy, &prepared("tenant_id", "t2"))
+            .is_ok());
+
+        let schema = DbTenantPolicy {
+            schema: Some("t2".into()),
+            ..claimed.clone().schema("t1").policy()
+        };
+        assert!(claimed
+            .check_claimed(&schema, &DbConnectorCommand::Capabilities)
+            .is_err());
+    }
+
+    #[test]
+    fn entered_context_is_restored_on_drop() {
+        assert_eq!(TenantContext::current(), None);
+        TenantContext::new("outer").scope(|| {
+            {
+                let _inner = TenantContext::new("inner").enter();
+                assert_eq!(TenantContext::current().unwrap().tenant_id, "inner");
+            }
+            assert_eq!(TenantContext::current().unwrap().tenant_id, "outer");
+        });
+        assert_eq!(TenantContext::current(), None);
+    }
+}
@@ -1,65 +1,99 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
 use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc, Mutex,
+    atomic::{AtomicBool, AtomicU32, Ordering},
+    mpsc, Arc, Mutex,
 };
 use std::thread;
-use std::time::Duration as StdDuration;
+use std::time::{Duration as StdDuration, Instant};
 
-use crate::control_plane::ControlPlaneClient;
-use crate::error::ModuleKitError;
-use crate::tokens::{ModuleTokenExchangeRequest, ModuleTokenExchangeResponse};
+use crate::control_plane::ControlPlane;
+use crate::error::{FailureDomain, ModuleKitError};
+#[cfg(feature = "jwt")]
+use crate::jwt::TokenClaims;
+use crate::secrets::SecretString;
+use crate::token_source::TokenSource;
+use crate::tokens::{
+    ModuleTokenExchangeRequest, ModuleTokenExchangeResponse, ScopeGrantFailure, ScopeGrantReport,
+};
 use time::Duration;
 use time::OffsetDateTime;
 
-const TOKEN_REFRESH_LEAD_SECS: i64 = 60;
+const TOKEN_REFRESH_LEAD_SECS: u64 = 60;
 const AUTO_REFRESH_MIN_SLEEP_SECS: i64 = 5;
 const AUTO_REFRESH_FALLBACK_SLEEP_SECS: i64 = 300;
 const AUTO_REFRESH_RETRY_SECS: u64 = 5;
+const AUTO_REFRESH_MAX_RETRY_SECS: u64 = 300;
 const AUTO_REFRESH_REASON: &str = "service_token_refresh";
+/// Scoped tokens are dropped this long before the control plane expires them.
+const SCOPED_TOKEN_SAFETY_SECS: u64 = 5;
 
 #[derive(Debug, Clone)]
 pub struct ServiceTokenLease {
-    pub token: String,
+    pub token: SecretString,
     pub issued_at: Option<OffsetDateTime>,
     pub expires_at: Option<OffsetDateTime>,
     pub ttl_seconds: Option<u64>,
+    #[cfg(feature = "jwt")]
+    pub claims: Option<TokenClaims>,
     captured_at: OffsetDateTime,
 }
 
 impl ServiceTokenLease {
     pub fn new(
-        token: impl Into<String>,
+        token: impl Into<SecretString>,
         issued_at: Option<OffsetDateTime>,
         expires_at: Option<OffsetDateTime>,
         ttl_seconds: Option<u64>,
     ) -> Self {
-        Self {
+        let lease = Self {
             token: token.into(),
             issued_at,
             expires_at,
             ttl_seconds,
+            #[cfg(feature = "jwt")]
+            claims: None,
             captured_at: OffsetDateTime::now_utc(),
-        }
+        };
+        lease.with_token_claims()
     }
 
     pub fn from_exchange(response: ModuleTokenExchangeResponse) -> Self {
         let now = OffsetDateTime::now_utc();
-        let expires_at = now + Duration::seconds(response.expires_in_seconds as i64);
-        Self {
+        let lease = Self {
             token: response.token,
             issued_at: Some(now),
-            expires_at: Some(expires_at),
+            expires_at: expiry_after(now, response.expires_in_seconds),
             ttl_seconds: Some(response.expires_in_seconds),
+            #[cfg(feature = "jwt")]
+            claims: None,
             captured_at: now,
+        };
+        lease.with_token_claims()
+    }
+
+    /// Fills missing timestamps from the token's own JWT claims, when it has any.
+    #[cfg(feature = "jwt")]
+    fn with_token_claims(mut self) -> Self {
+        if let Ok(claims) = TokenClaims::decode_unverified(self.token.expose()) {
+            self.issued_at = self.issued_at.or_else(|| claims.issued_at());
+            self.expires_at = self.expires_at.or_else(|| claims.expires_at());
+            self.claims = Some(claims);
         }
+        self
+    }
+
+    #[cfg(not(feature = "jwt"))]
+    fn with_token_claims(self) -> Self {
+        self
     }
 
-    fn effective_expires_at(&self) -> Option<OffsetDateTime> {
+    pub fn effective_expires_at(&self) -> Option<OffsetDateTime> {
         if let Some(expires_at) = self.expires_at {
             return Some(expires_at);
         }
         self.ttl_seconds
-            .map(|ttl| self.captured_at + Duration::seconds(ttl as i64))
+            .and_then(|ttl| expiry_after(self.captured_at, ttl))
     }
 
     fn remaining_duration(&self) -> Option<Duration> {
@@ -75,33 +109,204 @@ impl ServiceTokenLease {
     }
 }
 
+/// Outcome of the most recent attempt to refresh the service token.
+#[derive(Debug, Clone)]
+pub struct TokenRefreshResult {
+    pub attempted_at: OffsetDateTime,
+    pub error: Option<String>,
+    pub failure_domain: Option<FailureDomain>,
+}
+
+impl TokenRefreshResult {
+    fn from_result(result: &Result<(), ModuleKitError>) -> Self {
+        let err = result.as_ref().err();
+        Self {
+            attempted_at: OffsetDateTime::now_utc(),
+            error: err.map(|err| err.to_string()),
+            failure_domain: err.map(ModuleKitError::failure_domain),
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Delivered to refresh listeners after every refresh attempt.
+#[derive(Debug, Clone)]
+pub struct TokenRefreshEvent {
+    pub lease: ServiceTokenLease,
+    pub result: TokenRefreshResult,
+}
+
+/// Returns `false` once the listener is gone and should be dropped.
+type RefreshListener = Arc<dyn Fn(&TokenRefreshEvent) -> bool + Send + Sync>;
+
+#[derive(Default)]
+struct RefreshState {
+    last: Mutex<Option<TokenRefreshResult>>,
+    consecutive_failures: AtomicU32,
+    listeners: Mutex<Vec<RefreshListener>>,
+}
+
+/// Timing of service token refreshes.
+#[derive(Debug, Clone)]
+pub struct TokenRefreshConfig {
+    /// How long before expiry the token is refreshed.
+    pub lead: StdDuration,
+    /// Initial delay before retrying a failed background refresh; doubled
+    /// (with jitter) after each consecutive failure.
+    pub retry_interval: StdDuration,
+    /// Upper bound for the retry delay.
+    pub max_retry_interval: StdDuration,
+    /// Whether a background thread refreshes the token; when disabled tokens
+    /// are only refreshed on demand by `current_token`.
+    pub auto_refresh: bool,
+}
+
+impl Default for TokenRefreshConfig {
+    fn default() -> Self {
+        Self {
+            lead: StdDuration::from_secs(TOKEN_REFRESH_LEAD_SECS),
+            retry_interval: StdDuration::from_secs(AUTO_REFRESH_RETRY_SECS),
+            max_retry_interval: StdDuration::from_secs(AUTO_REFRESH_MAX_RETRY_SECS),
+            auto_refresh: true,
+        }
+    }
+}
+
+impl TokenRefreshConfig {
+    fn lead_duration(&self) -> Duration {
+        Duration::try_from(self.lead).unwrap_or(Duration::MAX)
+    }
+
+    /// Delay before the next retry after `failures` consecutive failures,
+    /// picked uniformly from the upper half of the capped exponential delay.
+    fn retry_delay(&self, failures: u32) -> StdDuration {
+        let exponent = failures.saturating_sub(1).min(16);
+        let capped = self
+            .retry_interval
+            .saturating_mul(1 << exponent)
+            .min(self.max_retry_interval);
+        let half = capped / 2;
+        let jitter_range = (capped - half).as_millis() as u64;
+        if jitter_range == 0 {
+            return capped;
+        }
+        let jitter = RandomState::new().build_hasher().finish() % (jitter_range + 1);
+        half + StdDuration::from_millis(jitter)
+    }
+}
+
 pub struct ServiceTokenProvider {
     lease: Arc<Mutex<ServiceTokenLease>>,
-    control_plane: Option<Arc<ControlPlaneClient>>,
+    refresh_state: Arc<RefreshState>,
+    source: Option<Arc<dyn TokenSource>>,
+    control_plane: Option<Arc<dyn ControlPlane>>,
     refresh_lead: Duration,
-    _auto_refresh: Option<AutoRefreshHandle>,
+    auto_refresh: Mutex<Option<AutoRefreshHandle>>,
 }
 
-impl ServiceTokenProvider {
-    pub(crate) fn new(
-        initial: ServiceTokenLease,
-        control_plane: Option<ControlPlaneClient>,
-    ) -> Self {
-        let lease = Arc::new(Mutex::new(initial));
-        let control_plane = control_plane.map(|client| Arc::new(client));
-        let refresh_lead = Duration::seconds(TOKEN_REFRESH_LEAD_SECS);
+pub struct ServiceTokenProviderBuilder {
+    initial: ServiceTokenLease,
+    source: Option<Arc<dyn TokenSource>>,
+    control_plane: Option<Arc<dyn ControlPlane>>,
+    config: TokenRefreshConfig,
+}
+
+impl ServiceTokenProviderBuilder {
+    fn new(initial: ServiceTokenLease) -> Self {
+        Self {
+            initial,
+            source: None,
+            control_plane: None,
+            config: TokenRefreshConfig::default(),
+        }
+    }
+
+    pub fn source(mut self, source: impl TokenSource + 'static) -> Self {
+        self.source = Some(Arc::new(source));
+        self
+    }
+
+    pub fn control_plane(mut self, client: impl ControlPlane + 'static) -> Self {
+        self.control_plane = Some(Arc::new(client));
+        self
+    }
+
+    pub fn config(mut self, config: TokenRefreshConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn refresh_lead(mut self, value: StdDuration) -> Self {
+        self.config.lead = value;
+        self
+    }
+
+    pub fn retry_interval(mut self, value: StdDuration) -> Self {
+        self.config.retry_interval = value;
+        self
+    }
+
+    pub fn max_retry_interval(mut self, value: StdDuration) -> Self {
+        self.config.max_retry_interval = value;
+        self
+    }
+
+    pub fn auto_refresh(mut self, enabled: bool) -> Self {
+        self.config.auto_refresh = enabled;
+        self
+    }
+
+    pub fn build(self) -> ServiceTokenProvider {
+        let lease = Arc::new(Mutex::new(self.initial));
+        let refresh_state = Arc::new(RefreshState::default());
+        let control_plane = self.control_plane;
         let auto_refresh = control_plane
             .as_ref()
-            .map(|client| AutoRefreshHandle::start(Arc::clone(&lease), Arc::clone(client), refresh_lead));
-        Self {
+            .filter(|_| self.config.auto_refresh)
+            .map(|client| {
+                AutoRefreshHandle::start(
+                    Arc::clone(&lease),
+                    Arc::clone(&refresh_state),
+                    Arc::clone(client),
+                    self.config.clone(),
+                )
+            });
+        ServiceTokenProvider {
             lease,
+            refresh_state,
+            source: self.source,
             control_plane,
-            refresh_lead,
-            _auto_refresh: auto_refresh,
+            refresh_lead: self.config.lead_duration(),
+            auto_refresh: Mutex::new(auto_refresh),
         }
     }
+}
+
+impl ServiceTokenProvider {
+    pub fn builder(initial: ServiceTokenLease) -> ServiceTokenProviderBuilder {
+        ServiceTokenProviderBuilder::new(initial)
+    }
+
+    pub fn from_static_token(token: impl Into<String>) -> Self {
+        Self::builder(ServiceTokenLease::new(token.into(), None, None, None)).build()
+    }
+
+    pub fn from_exchange_response(response: ModuleTokenExchangeResponse) -> Self {
+        Self::builder(ServiceTokenLease::from_exchange(response)).build()
+    }
 
-    pub fn current_token(&self) -> Result<String, ModuleKitError> {
+    pub fn from_source(source: impl TokenSource + 'static) -> Result<Self, ModuleKitError> {
+        let initial = source.load()?;
+        Ok(Self::builder(initial).source(source).build())
+    }
+
+    pub fn current_token(&self) -> Result<SecretString, ModuleKitError> {
+        if let Some(source) = &self.source {
+            self.reload_from_source(source.as_ref())?;
+        }
         if self.control_plane.is_none() {
             return Ok(self.lease.lock().unwrap().token.clone());
         }
@@ -119,6 +324,48 @@ impl ServiceTokenProvider {
         Ok(self.lease.lock().unwrap().token.clone())
     }
 
+    /// Refreshes the service token immediately, e.g. after a downstream 401.
+    pub fn refresh_now(&self) -> Result<(), ModuleKitError> {
+        if self.source.is_none() && self.control_plane.is_none() {
+            return Err(ModuleKitError::ControlPlaneMissing);
+        }
+        let result = self.force_refresh();
+        record_refresh(&self.refresh_state, &self.lease, &result);
+        result
+    }
+
+    pub fn last_refresh_result(&self) -> Option<TokenRefreshResult> {
+        self.refresh_state.last.lock().unwrap().clone()
+    }
+
+    /// Number of refresh attempts that have failed since the last success.
+    pub fn consecutive_refresh_failures(&self) -> u32 {
+        self.refresh_state.consecutive_failures.load(Ordering::SeqCst)
+    }
+
+    /// Registers a callback invoked after every automatic or manual refresh attempt.
+    pub fn on_refresh(&self, callback: impl Fn(&TokenRefreshEvent) + Send + Sync + 'static) {
+        self.add_listener(Arc::new(move |event| {
+            callback(event);
+            true
+        }));
+    }
+
+    /// Channel of refresh events; the sender is dropped once the receiver is.
+    pub fn subscribe(&self) -> mpsc::Receiver<TokenRefreshEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.add_listener(Arc::new(move |event| sender.send(event.clone()).is_ok()));
+        receiver
+    }
+
+    fn add_listener(&self, listener: RefreshListener) {
+        self.refresh_state.listeners.lock().unwrap().push(listener);
+    }
+
+    pub fn lease_snapshot(&self) -> ServiceTokenLease {
+        self.lease.lock().unwrap().clone()
+    }
+
     pub fn issue_scoped_token(
         &self,
         request: ModuleTokenExchangeRequest,
@@ -128,15 +375,76 @@ impl ServiceTokenProvider {
             .control_plane
             .as_ref()
             .ok_or(ModuleKitError::ControlPlaneMissing)?;
-        client.exchange_token(&bearer, request)
+        client.exchange_token(bearer.expose(), request)
     }
 
-    fn refresh_default_token(&self, bearer: String) -> Result<(), ModuleKitError> {
+    /// Stops the background refresh thread; later calls to `current_token`
+    /// still refresh on demand.
+    pub fn stop_auto_refresh(&self) {
+        let handle = self.auto_refresh.lock().unwrap().take();
+        drop(handle);
+    }
+
+    /// Exchanges each scope on its own so a missing grant is reported per scope.
+    pub fn preflight_scopes<I>(&self, scopes: I) -> ScopeGrantReport
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let mut report = ScopeGrantReport::default();
+        for scope in scopes {
+            let scope = scope.into();
+            let request = ModuleTokenExchangeRequest {
+                scopes: vec![scope.clone()],
+                reason: Some("startup_preflight".to_string()),
+            };
+            match self.issue_scoped_token(request) {
+                Ok(response) if response.scopes.contains(&scope) => report.granted.push(scope),
+                Ok(_) => report.denied.push(ScopeGrantFailure {
+                    scope,
+                    reason: "not included in issued token".to_string(),
+                }),
+                Err(err) => report.denied.push(ScopeGrantFailure {
+                    scope,
+                    reason: err.to_string(),
+                }),
+            }
+        }
+        report
+    }
+
+    fn force_refresh(&self) -> Result<(), ModuleKitError> {
+        if let Some(source) = &self.source {
+            let lease = source.load()?;
+            *self.lease.lock().unwrap() = lease;
+        }
+        if let Some(client) = &self.control_plane {
+            let bearer = self.lease.lock().unwrap().token.clone();
+            exchange_default_token(&self.lease, client, bearer)?;
+        }
+        Ok(())
+    }
+
+    fn reload_from_source(&self, source: &dyn TokenSource) -> Result<(), ModuleKitError> {
+        let stale = self.lease.lock().unwrap().should_refresh(self.refresh_lead);
+        if stale || source.has_changed() {
+            let result = source
+                .load()
+                .map(|lease| *self.lease.lock().unwrap() = lease);
+            record_refresh(&self.refresh_state, &self.lease, &result);
+            result?;
+        }
+        Ok(())
+    }
+
+    fn refresh_default_token(&self, bearer: SecretString) -> Result<(), ModuleKitError> {
         let client = self
             .control_plane
             .as_ref()
             .ok_or(ModuleKitError::ControlPlaneMissing)?;
-        exchange_default_token(&self.lease, client, bearer)
+        let result = exchange_default_token(&self.lease, client, bearer);
+        record_refresh(&self.refresh_state, &self.lease, &result);
+        result
     }
 }
 
@@ -148,13 +456,14 @@ struct AutoRefreshHandle {
 impl AutoRefreshHandle {
     fn start(
         lease: Arc<Mutex<ServiceTokenLease>>,
-        client: Arc<ControlPlaneClient>,
-        refresh_lead: Duration,
+        refresh_state: Arc<RefreshState>,
+        client: Arc<dyn ControlPlane>,
+        config: TokenRefreshConfig,
     ) -> Self {
         let shutdown = Arc::new(AtomicBool::new(false));
         let thread_shutdown = Arc::clone(&shutdown);
         let handle = thread::spawn(move || {
-            run_auto_refresh_loop(lease, client, refresh_lead, thread_shutdown);
+            run_auto_refresh_loop(lease, refresh_state, client, config, thread_shutdown);
         });
         Self {
             shutdown,
@@ -175,10 +484,12 @@ impl Drop for AutoRefreshHandle {
 
 fn run_auto_refresh_loop(
     lease: Arc<Mutex<ServiceTokenLease>>,
-    client: Arc<ControlPlaneClient>,
-    refresh_lead: Duration,
+    refresh_state: Arc<RefreshState>,
+    client: Arc<dyn ControlPlane>,
+    config: TokenRefreshConfig,
     shutdown: Arc<AtomicBool>,
 ) {
+    let refresh_lead = config.lead_duration();
     loop {
         if shutdown.load(Ordering::SeqCst) {
             break;
@@ -192,21 +503,23 @@ fn run_auto_refresh_loop(
         if shutdown.load(Ordering::SeqCst) {
             break;
         }
-        let bearer = { lease.lock().unwrap().token.clone() };
-        match client.exchange_token(
-            &bearer,
-            ModuleTokenExchangeRequest {
-                scopes: Vec::new(),
-                reason: Some(AUTO_REFRESH_REASON.to_string()),
-            },
-        ) {
-            Ok(response) => {
-                let mut guard = lease.lock().unwrap();
-                *guard = ServiceTokenLease::from_exchange(response);
-            }
-            Err(_) => {
-                thread::sleep(StdDuration::from_secs(AUTO_REFRESH_RETRY_SECS));
-            }
+        let bearer = lease.lock().unwrap().token.clone();
+        let result = exchange_default_token(&lease, &client, bearer);
+        record_refresh(&refresh_state, &lease, &result);
+        #[cfg(feature = "tracing")]
+        match &result {
+            Ok(_) => tracing::debug!("service token refreshed"),
+            Err(err) => tracing::warn!(
+                error = %err,
+                failure_domain = %err.failure_domain(),
+                consecutive_failures =
+                    refresh_state.consecutive_failures.load(Ordering::SeqCst),
+                "service token refresh failed"
+            ),
+        }
+        if result.is_err() {
+            let failures = refresh_state.consecutive_failures.load(Ordering::SeqCst);
+            thread::park_timeout(config.retry_delay(failures));
         }
     }
 }
@@ -237,13 +550,19 @@ fn duration_to_std(duration: Duration) -> StdDuration {
     }
 }
 
+/// `start + seconds`, or `None` when that is not representable.
+fn expiry_after(start: OffsetDateTime, seconds: u64) -> Option<OffsetDateTime> {
+    let seconds = i64::try_from(seconds).ok()?;
+    start.checked_add(Duration::seconds(seconds))
+}
+
 fn exchange_default_token(
     lease: &Arc<Mutex<ServiceTokenLease>>,
-    client: &Arc<ControlPlaneClient>,
-    bearer: String,
+    client: &dyn ControlPlane,
+    bearer: SecretString,
 ) -> Result<(), ModuleKitError> {
     let response = client.exchange_token(
-        &bearer,
+        bearer.expose(),
         ModuleTokenExchangeRequest {
             scopes: Vec::new(),
             reason: Some(AUTO_REFRESH_REASON.to_string()),
@@ -253,3 +572,118 @@ fn exchange_default_token(
     *guard = ServiceTokenLease::from_exchange(response);
     Ok(())
 }
+
+fn record_refresh(
+    state: &RefreshState,
+    lease: &Mutex<ServiceTokenLease>,
+    result: &Result<(), ModuleKitError>,
+) {
+    if result.is_ok() {
+        state.consecutive_failures.store(0, Ordering::SeqCst);
+    } else {
+        state.consecutive_failures.fetch_add(1, Ordering::SeqCst);
+    }
+    let result = TokenRefreshResult::from_result(result);
+    *state.last.lock().unwrap() = Some(result.clone());
+    // Listeners run without the lock held so they may call back into the
+    // provider or register further listeners.
+    let listeners = state.listeners.lock().unwrap().clone();
+    if listeners.is_empty() {
+        return;
+    }
+    let event = TokenRefreshEvent {
+        lease: lease.lock().unwrap().clone(),
+        result,
+    };
+    let closed: Vec<RefreshListener> = listeners
+        .into_iter()
+        .filter(|listener| !listener(&event))
+        .collect();
+    if !closed.is_empty() {
+        state
+            .listeners
+            .lock()
+            .unwrap()
+            .retain(|listener| !closed.iter().any(|gone| Arc::ptr_eq(gone, listener)));
+    }
+}
+
+/// Scoped token reused across requests until shortly before it expires.
+pub(crate) struct ScopedTokenCache {
+    request: fn() -> ModuleTokenExchangeRequest,
+    /// Token and its expiry; `None` when the TTL is too large to represent.
+    cached: Mutex<Option<(SecretString, Option<Instant>)>>,
+}
+
+impl ScopedTokenCache {
+    pub(crate) fn new(request: fn() -> ModuleTokenExchangeRequest) -> Self {
+        Self {
+            request,
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// Cached token, if it is still valid.
+    pub(crate) fn cached(&self) -> Option<SecretString> {
+        self.cached
+            .lock()
+            .unwrap()
+            .as_ref()
+            .filter(|(_, expires_at)| expires_at.is_none_or(|at| at > Instant::now()))
+            .map(|(token, _)| token.clone())
+    }
+
+    /// Cached token, exchanging a new one through `tokens` when needed.
+    pub(crate) fn get(
+        &self,
+        tokens: &ServiceTokenProvider,
+    ) -> Result<SecretString, ModuleKitError> {
+        match self.cached() {
+            Some(token) => Ok(token),
+            None => self.refresh(tokens),
+        }
+    }
+
+    pub(crate) fn refresh(
+        &self,
+        tokens: &ServiceTokenProvider,
+    ) -> Result<SecretString, ModuleKitError> {
+        let response = tokens.issue_scoped_token((self.request)())?;
+        let ttl = response
+            .expires_in_seconds
+            .saturating_sub(SCOPED_TOKEN_SAFETY_SECS)
+            .max(SCOPED_TOKEN_SAFETY_SECS);
+        *self.cached.lock().unwrap() = Some((
+            response.token.clone(),
+            Instant::now().checked_add(StdDuration::from_secs(ttl)),
+        ));
+        Ok(response.token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct StaticSource;
+
+    impl TokenSource for StaticSource {
+        fn load(&self) -> Result<ServiceTokenLease, ModuleKitError> {
+            Ok(ServiceTokenLease::new("token", None, None, None))
+        }
+    }
+
+    #[test]
+    fn listeners_run_without_the_lock_and_closed_subscribers_are_pruned() {
+        let provider = Arc::new(ServiceTokenProvider::from_source(StaticSource).unwrap());
+        let nested = Arc::clone(&provider);
+        provider.on_refresh(move |_| nested.on_refresh(|_| {}));
+        drop(provider.subscribe());
+        let open = provider.subscribe();
+
+        provider.refresh_now().unwrap();
+        assert!(open.try_recv().is_ok());
+        // on_refresh + open subscriber + the listener added during the callback.
+        assert_eq!(provider.refresh_state.listeners.lock().unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,124 @@
+use std::env::VarError;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::env::{EnvSource, ProcessEnv};
+use crate::error::ModuleKitError;
+use crate::token_provider::ServiceTokenLease;
+
+/// Supplies the base service token for a `ServiceTokenProvider`.
+pub trait TokenSource: Send + Sync {
+    fn load(&self) -> Result<ServiceTokenLease, ModuleKitError>;
+
+    /// Whether the source holds a newer token than the one last returned by `load`.
+    fn has_changed(&self) -> bool {
+        false
+    }
+}
+
+pub struct EnvTokenSource {
+    name: &'static str,
+    vars: Box<dyn EnvSource + Send + Sync>,
+}
+
+impl EnvTokenSource {
+    pub fn new(name: &'static str) -> Self {
+        Self::new_with(name, ProcessEnv)
+    }
+
+    /// Reads `name` from `vars` instead of the process environment.
+    pub fn new_with(name: &'static str, vars: impl EnvSource + Send + Sync + 'static) -> Self {
+        Self {
+            name,
+            vars: Box::new(vars),
+        }
+    }
+}
+
+impl TokenSource for EnvTokenSource {
+    fn load(&self) -> Result<ServiceTokenLease, ModuleKitError> {
+        match self.vars.var(self.name) {
+            Ok(token) => Ok(ServiceTokenLease::new(token.trim(), None, None, None)),
+            Err(VarError::NotPresent) => Err(ModuleKitError::MissingEnv(self.name)),
+            Err(err) => Err(ModuleKitError::invalid_env(self.name, err)),
+        }
+    }
+}
+
+pub struct FileTokenSource {
+    path: PathBuf,
+    loaded_mtime: Mutex<Option<SystemTime>>,
+}
+
+impl FileTokenSource {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            loaded_mtime: Mutex::new(None),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn modified(&self) -> Option<SystemTime> {
+        fs::metadata(&self.path)
+            .and_then(|meta| meta.modified())
+            .ok()
+    }
+}
+
+impl TokenSource for FileTokenSource {
+    fn load(&self) -> Result<ServiceTokenLease, ModuleKitError> {
+        let mtime = self.modified();
+        let contents = fs::read_to_string(&self.path).map_err(|err| {
+            ModuleKitError::TokenSource(format!(
+                "failed to read token file {}: {err}",
+                self.path.display()
+            ))
+        })?;
+        let token = contents.trim();
+        if token.is_empty() {
+            return Err(ModuleKitError::TokenSource(format!(
+                "token file {} is empty",
+                self.path.display()
+            )));
+        }
+        *self.loaded_mtime.lock().unwrap() = mtime;
+        Ok(ServiceTokenLease::new(token, None, None, None))
+    }
+
+    fn has_changed(&self) -> bool {
+        let loaded = *self.loaded_mtime.lock().unwrap();
+        match (loaded, self.modified()) {
+            (Some(loaded), Some(current)) => current != loaded,
+            (None, Some(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+pub struct CallbackTokenSource<F> {
+    callback: F,
+}
+
+impl<F> CallbackTokenSource<F>
+where
+    F: Fn() -> Result<ServiceTokenLease, ModuleKitError> + Send + Sync,
+{
+    pub fn new(callback: F) -> Self {
+        Self { callback }
+    }
+}
+
+impl<F> TokenSource for CallbackTokenSource<F>
+where
+    F: Fn() -> Result<ServiceTokenLease, ModuleKitError> + Send + Sync,
+{
+    fn load(&self) -> Result<ServiceTokenLease, ModuleKitError> {
+        (self.callback)()
+    }
+}
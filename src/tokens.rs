@@ -1,5 +1,9 @@
+use std::fmt;
+
 use serde::{Deserialize, Serialize};
 
+use crate::secrets::SecretString;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModuleTokenExchangeRequest {
     pub scopes: Vec<String>,
@@ -14,11 +18,76 @@ impl ModuleTokenExchangeRequest {
             reason: Some("db_connector".to_string()),
         }
     }
+
+    pub fn db_admin() -> Self {
+        Self {
+            scopes: vec!["db:admin".to_string()],
+            reason: Some("db_connector_admin".to_string()),
+        }
+    }
+
+    pub fn cache_write() -> Self {
+        Self {
+            scopes: vec!["cache:write".to_string()],
+            reason: Some("kv_connector".to_string()),
+        }
+    }
+
+    pub fn bus_publish() -> Self {
+        Self {
+            scopes: vec!["bus:publish".to_string()],
+            reason: Some("bus_connector".to_string()),
+        }
+    }
+
+    pub fn bus_consume() -> Self {
+        Self {
+            scopes: vec!["bus:consume".to_string()],
+            reason: Some("bus_connector".to_string()),
+        }
+    }
+
+    pub fn blob_write() -> Self {
+        Self {
+            scopes: vec!["blob:write".to_string()],
+            reason: Some("blob_connector".to_string()),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModuleTokenExchangeResponse {
-    pub token: String,
+    pub token: SecretString,
     pub scopes: Vec<String>,
     pub expires_in_seconds: u64,
 }
+
+/// Outcome of exchanging each required scope individually at startup.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScopeGrantReport {
+    pub granted: Vec<String>,
+    pub denied: Vec<ScopeGrantFailure>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopeGrantFailure {
+    pub scope: String,
+    pub reason: String,
+}
+
+impl ScopeGrantReport {
+    pub fn is_complete(&self) -> bool {
+        self.denied.is_empty()
+    }
+}
+
+impl fmt::Display for ScopeGrantReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let items: Vec<String> = self
+            .denied
+            .iter()
+            .map(|failure| format!("{}: {}", failure.scope, failure.reason))
+            .collect();
+        write!(f, "{}", items.join("; "))
+    }
+}
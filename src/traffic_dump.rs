@@ -0,0 +1,214 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::connector_request::DbConnectorRequest;
+use crate::error::ModuleKitError;
+use crate::wire_format::decode_reply;
+
+const DEFAULT_MAX_FILE_BYTES: u64 = 8 * 1024 * 1024;
+const DEFAULT_MAX_FILES: usize = 4;
+const DUMP_FILE_PREFIX: &str = "connector-traffic";
+const REDACTED: &str = "[redacted]";
+
+/// Where and how much connector traffic to capture.
+///
+/// Deserializable so it can be embedded in the module's runtime configuration
+/// and toggled through `ModuleConfigClient::watch`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrafficDumpConfig {
+    pub dir: PathBuf,
+    /// Size at which the current file is rotated.
+    #[serde(default = "default_max_file_bytes")]
+    pub max_file_bytes: u64,
+    /// Number of files kept, including the one being written.
+    #[serde(default = "default_max_files")]
+    pub max_files: usize,
+}
+
+impl TrafficDumpConfig {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            max_file_bytes: DEFAULT_MAX_FILE_BYTES,
+            max_files: DEFAULT_MAX_FILES,
+        }
+    }
+}
+
+fn default_max_file_bytes() -> u64 {
+    DEFAULT_MAX_FILE_BYTES
+}
+
+fn default_max_files() -> usize {
+    DEFAULT_MAX_FILES
+}
+
+/// Writes one JSON line per connector round trip to
+/// `connector-traffic.jsonl`, shifting older files to `connector-traffic.N.jsonl`.
+///
+/// Tokens, parameter values and result cells are replaced with `[redacted]`;
+/// statements, column names and response shapes are kept.
+pub(crate) struct TrafficDump {
+    config: TrafficDumpConfig,
+    file: Option<File>,
+    written: u64,
+}
+
+impl TrafficDump {
+    pub(crate) fn new(config: TrafficDumpConfig) -> Self {
+        Self {
+            config,
+            file: None,
+            written: 0,
+        }
+    }
+
+    pub(crate) fn config(&self) -> &TrafficDumpConfig {
+        &self.config
+    }
+
+    pub(crate) fn record(
+        &mut self,
+        request: &DbConnectorRequest,
+        response: &Result<Vec<u8>, ModuleKitError>,
+        elapsed: Duration,
+    ) -> io::Result<()> {
+        let timestamp = OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .unwrap_or_default();
+        let response = match response {
+            Ok(bytes) => match decode_reply::<JsonValue>(bytes) {
+                Ok(mut value) => {
+                    redact_response(&mut value);
+                    value
+                }
+                Err(_) => json!({ "unparsed_bytes": bytes.len() }),
+            },
+            Err(err) => json!({ "transport_error": err.to_string() }),
+        };
+        let entry = json!({
+            "timestamp": timestamp,
+            "elapsed_ms": elapsed.as_millis() as u64,
+            "request": redact_request(request)?,
+            "response": response,
+        });
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        if self.file.is_none() || self.written + line.len() as u64 > self.config.max_file_bytes {
+            self.rotate()?;
+        }
+        if let Some(file) = self.file.as_mut() {
+            file.write_all(&line)?;
+            self.written += line.len() as u64;
+        }
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file = None;
+        fs::create_dir_all(&self.config.dir)?;
+        let keep = self.config.max_files.max(1);
+        for index in (1..keep).rev() {
+            let from = self.path(index - 1);
+            if from.exists() {
+                fs::rename(from, self.path(index))?;
+            }
+        }
+        self.file = Some(File::create(self.path(0))?);
+        self.written = 0;
+        Ok(())
+    }
+
+    fn path(&self, index: usize) -> PathBuf {
+        let name = if index == 0 {
+            format!("{DUMP_FILE_PREFIX}.jsonl")
+        } else {
+            format!("{DUMP_FILE_PREFIX}.{index}.jsonl")
+        };
+        self.config.dir.join(name)
+    }
+}
+
+fn redact_request(request: &DbConnectorRequest) -> io::Result<JsonValue> {
+    let mut value = serde_json::to_value(request)?;
+    for field in ["token", "session"] {
+        if let Some(secret) = value.get_mut(field) {
+            *secret = json!(REDACTED);
+        }
+    }
+    let params = value
+        .get_mut("command")
+        .and_then(|command| command.get_mut("params"))
+        .and_then(JsonValue::as_array_mut);
+    if let Some(params) = params {
+        for param in params.iter_mut().filter_map(JsonValue::as_object_mut) {
+            param.insert("value".into(), json!(REDACTED));
+        }
+    }
+    Ok(value)
+}
+
+/// Blanks result cells, session ids and error text, which can echo
+/// statement values.
+fn redact_response(value: &mut JsonValue) {
+    for field in ["session_id", "error"] {
+        if let Some(secret) = value.get_mut(field).filter(|secret| !secret.is_null()) {
+            *secret = json!(REDACTED);
+        }
+    }
+    if let Some(detail) = value
+        .get_mut("error_info")
+        .and_then(|info| info.get_mut("detail"))
+        .filter(|detail| !detail.is_null())
+    {
+        *detail = json!(REDACTED);
+    }
+    let Some(results) = value.get_mut("results").and_then(JsonValue::as_array_mut) else {
+        return;
+    };
+    for result in results {
+        if let Some(rows) = result.get_mut("rows").and_then(JsonValue::as_array_mut) {
+            for cell in rows
+                .iter_mut()
+                .filter_map(JsonValue::as_array_mut)
+                .flatten()
+            {
+                *cell = json!(REDACTED);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_sessions_errors_and_rows() {
+        let mut value = json!({
+            "ok": false,
+            "session_id": "sess-123",
+            "error": "duplicate key 'alice@example.com'",
+            "error_info": {"code": "23505", "detail": "Key (email)=(alice@example.com)"},
+            "results": [{"columns": ["email"], "rows": [["alice@example.com"]]}],
+        });
+        redact_response(&mut value);
+        assert_eq!(
+            value,
+            json!({
+                "ok": false,
+                "session_id": REDACTED,
+                "error": REDACTED,
+                "error_info": {"code": "23505", "detail": REDACTED},
+                "results": [{"columns": ["email"], "rows": [[REDACTED]]}],
+            })
+        );
+    }
+}
@@ -0,0 +1,401 @@
+use std::sync::OnceLock;
+
+#[cfg(feature = "rust_decimal")]
+use std::str::FromStr;
+
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+#[cfg(feature = "rust_decimal")]
+use rust_decimal::Decimal;
+use serde_json::Value as JsonValue;
+use time::format_description::well_known::Rfc3339;
+use time::{OffsetDateTime, UtcOffset};
+#[cfg(feature = "uuid")]
+use uuid::Uuid;
+
+use crate::error::ModuleKitError;
+
+/// Typed value for a prepared statement parameter.
+///
+/// Converted to JSON on the wire; exact types such as decimals are encoded as
+/// strings so no precision is lost through `f64`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DbParamValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+    Json(JsonValue),
+    /// Sent as an RFC 3339 string normalized to UTC.
+    Timestamp(OffsetDateTime),
+    #[cfg(feature = "rust_decimal")]
+    Decimal(Decimal),
+    #[cfg(feature = "uuid")]
+    Uuid(Uuid),
+}
+
+impl From<DbParamValue> for JsonValue {
+    fn from(value: DbParamValue) -> Self {
+        match value {
+            DbParamValue::Null => JsonValue::Null,
+            DbParamValue::Bool(value) => JsonValue::Bool(value),
+            DbParamValue::Int(value) => JsonValue::from(value),
+            DbParamValue::Float(value) => JsonValue::from(value),
+            DbParamValue::Text(value) => JsonValue::String(value),
+            DbParamValue::Json(value) => value,
+            DbParamValue::Timestamp(value) => {
+                let utc = value.to_offset(UtcOffset::UTC);
+                JsonValue::String(utc.format(&Rfc3339).unwrap_or_else(|_| utc.to_string()))
+            }
+            #[cfg(feature = "rust_decimal")]
+            DbParamValue::Decimal(value) => JsonValue::String(value.to_string()),
+            #[cfg(feature = "uuid")]
+            DbParamValue::Uuid(value) => JsonValue::String(value.hyphenated().to_string()),
+        }
+    }
+}
+
+impl From<bool> for DbParamValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<i32> for DbParamValue {
+    fn from(value: i32) -> Self {
+        Self::Int(value as i64)
+    }
+}
+
+impl From<i64> for DbParamValue {
+    fn from(value: i64) -> Self {
+        Self::Int(value)
+    }
+}
+
+impl From<f64> for DbParamValue {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<String> for DbParamValue {
+    fn from(value: String) -> Self {
+        Self::Text(value)
+    }
+}
+
+impl From<&str> for DbParamValue {
+    fn from(value: &str) -> Self {
+        Self::Text(value.to_string())
+    }
+}
+
+impl From<JsonValue> for DbParamValue {
+    fn from(value: JsonValue) -> Self {
+        Self::Json(value)
+    }
+}
+
+impl From<OffsetDateTime> for DbParamValue {
+    fn from(value: OffsetDateTime) -> Self {
+        Self::Timestamp(value)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<DateTime<Utc>> for DbParamValue {
+    fn from(value: DateTime<Utc>) -> Self {
+        let nanos = i128::from(value.timestamp()) * 1_000_000_000
+            + i128::from(value.timestamp_subsec_nanos());
+        match OffsetDateTime::from_unix_timestamp_nanos(nanos) {
+            Ok(timestamp) => Self::Timestamp(timestamp),
+            Err(_) => Self::Text(value.to_rfc3339()),
+        }
+    }
+}
+
+#[cfg(feature = "rust_decimal")]
+impl From<Decimal> for DbParamValue {
+    fn from(value: Decimal) -> Self {
+        Self::Decimal(value)
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl From<Uuid> for DbParamValue {
+    fn from(value: Uuid) -> Self {
+        Self::Uuid(value)
+    }
+}
+
+impl<T> From<Option<T>> for DbParamValue
+where
+    T: Into<DbParamValue>,
+{
+    fn from(value: Option<T>) -> Self {
+        value.map(Into::into).unwrap_or(Self::Null)
+    }
+}
+
+/// Textual NULL and boolean conventions used by a connector engine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellCoercion {
+    pub true_values: Vec<String>,
+    pub false_values: Vec<String>,
+    pub null_values: Vec<String>,
+}
+
+impl CellCoercion {
+    pub fn new<I, S>(true_values: I, false_values: I, null_values: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            true_values: true_values.into_iter().map(Into::into).collect(),
+            false_values: false_values.into_iter().map(Into::into).collect(),
+            null_values: null_values.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    pub fn standard() -> Self {
+        Self::new(vec!["true"], vec!["false"], vec![])
+    }
+
+    pub fn postgres() -> Self {
+        Self::new(vec!["t", "true"], vec!["f", "false"], vec![""])
+    }
+
+    pub fn mysql() -> Self {
+        Self::new(vec!["1", "true"], vec!["0", "false"], vec!["", "NULL"])
+    }
+
+    pub fn sqlite() -> Self {
+        Self::new(vec!["1", "true"], vec!["0", "false"], vec![""])
+    }
+
+    /// Built-in profile for a known engine name, falling back to `standard()`.
+    pub fn for_engine(engine: &str) -> Self {
+        match engine.trim().to_ascii_lowercase().as_str() {
+            "postgres" | "postgresql" | "cockroach" | "cockroachdb" => Self::postgres(),
+            "mysql" | "mariadb" => Self::mysql(),
+            "sqlite" | "sqlite3" => Self::sqlite(),
+            _ => Self::standard(),
+        }
+    }
+
+    pub fn is_null(&self, cell: &str) -> bool {
+        contains_ignore_case(&self.null_values, cell)
+    }
+
+    pub fn parse_bool(&self, cell: &str) -> Option<bool> {
+        let cell = cell.trim();
+        if contains_ignore_case(&self.true_values, cell) {
+            Some(true)
+        } else if contains_ignore_case(&self.false_values, cell) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for CellCoercion {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+fn contains_ignore_case(values: &[String], cell: &str) -> bool {
+    values.iter().any(|value| value.eq_ignore_ascii_case(cell))
+}
+
+pub(crate) fn standard_coercion() -> &'static CellCoercion {
+    static STANDARD: OnceLock<CellCoercion> = OnceLock::new();
+    STANDARD.get_or_init(CellCoercion::standard)
+}
+
+/// Conversion from the textual cell representation returned by the connector.
+pub trait FromDbCell: Sized {
+    fn from_cell(cell: &str) -> Result<Self, String>;
+
+    /// Conversion honoring an engine's NULL and boolean conventions.
+    fn from_cell_with(cell: &str, coercion: &CellCoercion) -> Result<Self, String> {
+        let _ = coercion;
+        Self::from_cell(cell)
+    }
+}
+
+impl<T: FromDbCell> FromDbCell for Option<T> {
+    fn from_cell(cell: &str) -> Result<Self, String> {
+        Self::from_cell_with(cell, standard_coercion())
+    }
+
+    fn from_cell_with(cell: &str, coercion: &CellCoercion) -> Result<Self, String> {
+        if coercion.is_null(cell) {
+            return Ok(None);
+        }
+        T::from_cell_with(cell, coercion).map(Some)
+    }
+}
+
+impl FromDbCell for String {
+    fn from_cell(cell: &str) -> Result<Self, String> {
+        Ok(cell.to_string())
+    }
+}
+
+impl FromDbCell for i64 {
+    fn from_cell(cell: &str) -> Result<Self, String> {
+        cell.trim()
+            .parse()
+            .map_err(|_| format!("expected integer, got '{cell}'"))
+    }
+}
+
+impl FromDbCell for f64 {
+    fn from_cell(cell: &str) -> Result<Self, String> {
+        cell.trim()
+            .parse()
+            .map_err(|_| format!("expected float, got '{cell}'"))
+    }
+}
+
+impl FromDbCell for bool {
+    fn from_cell(cell: &str) -> Result<Self, String> {
+        Self::from_cell_with(cell, standard_coercion())
+    }
+
+    fn from_cell_with(cell: &str, coercion: &CellCoercion) -> Result<Self, String> {
+        coercion
+            .parse_bool(cell)
+            .ok_or_else(|| format!("expected boolean, got '{cell}'"))
+    }
+}
+
+#[cfg(feature = "rust_decimal")]
+impl FromDbCell for Decimal {
+    fn from_cell(cell: &str) -> Result<Self, String> {
+        Decimal::from_str(cell.trim())
+            .map_err(|err| format!("expected decimal, got '{cell}': {err}"))
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl FromDbCell for Uuid {
+    fn from_cell(cell: &str) -> Result<Self, String> {
+        Uuid::parse_str(cell.trim()).map_err(|err| format!("expected uuid, got '{cell}': {err}"))
+    }
+}
+
+/// Parses timestamps in the formats commonly produced by database engines.
+///
+/// Accepts RFC 3339, a space instead of `T`, short offsets such as `+00` or
+/// `+0530`, and bare dates. Values without an offset are interpreted as UTC.
+pub fn parse_db_timestamp(cell: &str) -> Result<OffsetDateTime, String> {
+    let trimmed = cell.trim();
+    if let Ok(parsed) = OffsetDateTime::parse(trimmed, &Rfc3339) {
+        return Ok(parsed);
+    }
+    OffsetDateTime::parse(&normalize_timestamp(trimmed), &Rfc3339)
+        .map_err(|err| format!("expected timestamp, got '{cell}': {err}"))
+}
+
+fn normalize_timestamp(value: &str) -> String {
+    if !value.is_ascii() {
+        return value.to_string();
+    }
+    if value.len() == 10 {
+        return format!("{value}T00:00:00Z");
+    }
+    let mut value = value.to_string();
+    if value.len() > 10 && value.as_bytes()[10] == b' ' {
+        value.replace_range(10..11, "T");
+    }
+    let (head, tail) = value.split_at(value.len().min(19));
+    let (fraction, offset) = match tail.find(['+', '-', 'Z', 'z']) {
+        Some(pos) => tail.split_at(pos),
+        None => (tail, ""),
+    };
+    let offset = match offset.len() {
+        0 => "Z".to_string(),
+        3 => format!("{offset}:00"),
+        5 => format!("{}:{}", &offset[..3], &offset[3..]),
+        _ => offset.to_string(),
+    };
+    format!("{head}{fraction}{offset}")
+}
+
+impl FromDbCell for OffsetDateTime {
+    fn from_cell(cell: &str) -> Result<Self, String> {
+        parse_db_timestamp(cell)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl FromDbCell for DateTime<Utc> {
+    fn from_cell(cell: &str) -> Result<Self, String> {
+        let parsed = parse_db_timestamp(cell)?;
+        DateTime::from_timestamp(parsed.unix_timestamp(), parsed.nanosecond())
+            .ok_or_else(|| format!("timestamp out of range: '{cell}'"))
+    }
+}
+
+/// Borrowed view of a single row in a result set.
+///
+/// `S` is the cell and column name type: `String` for
+/// `DbConnectorResultView` rows, `Arc<str>` for `DbResultSet` rows.
+#[derive(Debug)]
+pub struct DbRow<'a, S = String> {
+    columns: &'a [S],
+    values: &'a [S],
+    coercion: &'a CellCoercion,
+}
+
+impl<S> Clone for DbRow<'_, S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<S> Copy for DbRow<'_, S> {}
+
+impl<'a, S: AsRef<str>> DbRow<'a, S> {
+    pub(crate) fn with_coercion(
+        columns: &'a [S],
+        values: &'a [S],
+        coercion: &'a CellCoercion,
+    ) -> Self {
+        Self {
+            columns,
+            values,
+            coercion,
+        }
+    }
+
+    pub fn columns(&self) -> &'a [S] {
+        self.columns
+    }
+
+    pub fn values(&self) -> &'a [S] {
+        self.values
+    }
+
+    pub fn raw(&self, column: &str) -> Option<&'a str> {
+        let index = self
+            .columns
+            .iter()
+            .position(|name| name.as_ref() == column)?;
+        self.values.get(index).map(AsRef::as_ref)
+    }
+
+    pub fn get<T: FromDbCell>(&self, column: &str) -> Result<T, ModuleKitError> {
+        let cell = self
+            .raw(column)
+            .ok_or_else(|| ModuleKitError::invalid_cell(column, "column not present".into()))?;
+        T::from_cell_with(cell, self.coercion)
+            .map_err(|message| ModuleKitError::invalid_cell(column, message))
+    }
+}
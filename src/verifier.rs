@@ -0,0 +1,270 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use jsonwebtoken::jwk::{Jwk, JwkSet};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+
+use crate::control_plane::{parse_json_response, ControlPlaneClient};
+use crate::error::ModuleKitError;
+use crate::http_transport::HttpRequest;
+use crate::jwt::TokenClaims;
+
+const JWKS_PATH: &str = "modules/runtime/jwks";
+const DEFAULT_JWKS_TTL: Duration = Duration::from_secs(600);
+/// Minimum time between key set fetches caused by unknown `kid`s.
+const DEFAULT_JWKS_REFETCH_INTERVAL: Duration = Duration::from_secs(30);
+const DEFAULT_LEEWAY_SECS: u64 = 30;
+
+/// Claims of a caller token whose signature, expiry and audience were checked.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VerifiedClaims {
+    pub sub: Option<String>,
+    pub tenant: Option<String>,
+    pub scopes: Vec<String>,
+    pub roles: Vec<String>,
+    pub aud: Vec<String>,
+    pub exp: Option<i64>,
+    pub iat: Option<i64>,
+    /// Remaining claims not mapped to a field above.
+    pub extra: BTreeMap<String, JsonValue>,
+}
+
+impl VerifiedClaims {
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+}
+
+#[derive(Deserialize)]
+struct RawClaims {
+    #[serde(flatten)]
+    base: TokenClaims,
+    /// Space-separated, as in OAuth 2.0 access tokens.
+    #[serde(default)]
+    scope: Option<String>,
+    #[serde(default, alias = "scp")]
+    scopes: Vec<String>,
+    #[serde(default)]
+    roles: Vec<String>,
+    #[serde(flatten)]
+    extra: BTreeMap<String, JsonValue>,
+}
+
+impl From<RawClaims> for VerifiedClaims {
+    fn from(raw: RawClaims) -> Self {
+        let mut scopes = raw.scopes;
+        if let Some(scope) = raw.scope {
+            scopes.extend(scope.split_whitespace().map(str::to_string));
+        }
+        Self {
+            sub: raw.base.sub,
+            tenant: raw.base.tenant,
+            scopes,
+            roles: raw.roles,
+            aud: raw.base.aud,
+            exp: raw.base.exp,
+            iat: raw.base.iat,
+            extra: raw.extra,
+        }
+    }
+}
+
+/// Verifies incoming bearer tokens against the control plane's published keys.
+///
+/// The key set is cached for `jwks_ttl` and re-fetched early when a token
+/// names an unknown `kid`, at most once per `jwks_refetch_interval`, so key
+/// rotation does not need a restart and forged tokens cannot flood the
+/// control plane.
+///
+/// The signature algorithm comes from the key's `alg`, never from the token
+/// header alone; keys without one need `algorithms` to be configured.
+/// Tokens must list the verifier's audience, usually the module's service id,
+/// in `aud`.
+pub struct TokenVerifier {
+    control_plane: ControlPlaneClient,
+    jwks_path: String,
+    audience: String,
+    algorithms: Vec<Algorithm>,
+    leeway: u64,
+    jwks_ttl: Duration,
+    jwks_refetch_interval: Duration,
+    jwks: Mutex<Option<(Instant, JwkSet)>>,
+}
+
+impl TokenVerifier {
+    /// Accepts only tokens whose `aud` includes `audience`.
+    pub fn new(control_plane: ControlPlaneClient, audience: impl Into<String>) -> Self {
+        Self {
+            control_plane,
+            jwks_path: JWKS_PATH.to_string(),
+            audience: audience.into(),
+            algorithms: Vec::new(),
+            leeway: DEFAULT_LEEWAY_SECS,
+            jwks_ttl: DEFAULT_JWKS_TTL,
+            jwks_refetch_interval: DEFAULT_JWKS_REFETCH_INTERVAL,
+            jwks: Mutex::new(None),
+        }
+    }
+
+    /// Signature algorithms accepted. Keys that name their own `alg` must
+    /// use one of these; keys that do not are checked with the token's
+    /// algorithm if it is listed. Unset accepts whatever each key names.
+    pub fn algorithms(mut self, value: impl IntoIterator<Item = Algorithm>) -> Self {
+        self.algorithms = value.into_iter().collect();
+        self
+    }
+
+    pub fn leeway(mut self, value: Duration) -> Self {
+        self.leeway = value.as_secs();
+        self
+    }
+
+    pub fn jwks_ttl(mut self, value: Duration) -> Self {
+        self.jwks_ttl = value;
+        self
+    }
+
+    pub fn jwks_refetch_interval(mut self, value: Duration) -> Self {
+        self.jwks_refetch_interval = value;
+        self
+    }
+
+    pub fn jwks_path(mut self, value: impl Into<String>) -> Self {
+        self.jwks_path = value.into();
+        self
+    }
+
+    pub fn verify(&self, token: &str) -> Result<VerifiedClaims, ModuleKitError> {
+        let token = token.trim().trim_start_matches("Bearer ").trim();
+        let header = decode_header(token).map_err(invalid_token)?;
+        let kid = header
+            .kid
+            .ok_or_else(|| ModuleKitError::InvalidToken("token header has no kid".into()))?;
+        let jwk = self.signing_key(&kid)?;
+        let algorithm = self.pinned_algorithm(&jwk, header.alg)?;
+        let key = DecodingKey::from_jwk(&jwk).map_err(invalid_token)?;
+        let mut validation = Validation::new(algorithm);
+        validation.leeway = self.leeway;
+        validation.set_audience(&[&self.audience]);
+        let data = decode::<RawClaims>(token, &key, &validation).map_err(invalid_token)?;
+        Ok(data.claims.into())
+    }
+
+    /// Drops the cached key set so the next verification re-fetches it.
+    pub fn invalidate_keys(&self) {
+        *self.jwks.lock().unwrap() = None;
+    }
+
+    /// The algorithm the token must be signed with: the key's own `alg`,
+    /// or for keys without one the token's, provided it is configured.
+    fn pinned_algorithm(
+        &self,
+        jwk: &Jwk,
+        requested: Algorithm,
+    ) -> Result<Algorithm, ModuleKitError> {
+        let invalid = |message: String| Err(ModuleKitError::InvalidToken(message));
+        let pinned = match jwk.common.key_algorithm {
+            Some(key_algorithm) => match key_algorithm.to_string().parse::<Algorithm>() {
+                Ok(algorithm)
+                    if self.algorithms.is_empty() || self.algorithms.contains(&algorithm) =>
+                {
+                    algorithm
+                }
+                Ok(algorithm) => {
+                    return invalid(format!("key algorithm {algorithm:?} is not allowed"))
+                }
+                Err(_) => return invalid(format!("key algorithm {key_algorithm} cannot sign")),
+            },
+            None if self.algorithms.contains(&requested) => requested,
+            None => return invalid("signing key names no algorithm and none is configured".into()),
+        };
+        if pinned != requested {
+            return invalid(format!(
+                "token uses {requested:?} but its key requires {pinned:?}"
+            ));
+        }
+        Ok(pinned)
+    }
+
+    fn signing_key(&self, kid: &str) -> Result<Jwk, ModuleKitError> {
+        let unknown = || ModuleKitError::InvalidToken(format!("unknown signing key '{kid}'"));
+        {
+            let cached = self.jwks.lock().unwrap();
+            if let Some((fetched_at, jwks)) = cached.as_ref() {
+                let age = fetched_at.elapsed();
+                if age < self.jwks_ttl {
+                    if let Some(jwk) = jwks.find(kid) {
+                        return Ok(jwk.clone());
+                    }
+                    if age < self.jwks_refetch_interval {
+                        return Err(unknown());
+                    }
+                }
+            }
+        }
+        let jwks = self.fetch_jwks()?;
+        let jwk = jwks.find(kid).cloned().ok_or_else(unknown);
+        *self.jwks.lock().unwrap() = Some((Instant::now(), jwks));
+        jwk
+    }
+
+    fn fetch_jwks(&self) -> Result<JwkSet, ModuleKitError> {
+        let url = self.control_plane.endpoint(&self.jwks_path)?;
+        parse_json_response(self.control_plane.send(&HttpRequest::get(url))?)
+    }
+}
+
+fn invalid_token(err: jsonwebtoken::errors::Error) -> ModuleKitError {
+    ModuleKitError::InvalidToken(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env::ControlPlaneEnvironment;
+
+    fn verifier() -> TokenVerifier {
+        let env = ControlPlaneEnvironment {
+            url: Some(url::Url::parse("https://cp.internal/").unwrap()),
+            ..ControlPlaneEnvironment::default()
+        };
+        TokenVerifier::new(ControlPlaneClient::new(&env).unwrap(), "service")
+    }
+
+    fn rsa_key(alg: Option<&str>) -> Jwk {
+        let mut key = serde_json::json!({"kty": "RSA", "kid": "k1", "n": "AQAB", "e": "AQAB"});
+        if let Some(alg) = alg {
+            key["alg"] = alg.into();
+        }
+        serde_json::from_value(key).unwrap()
+    }
+
+    #[test]
+    fn algorithm_is_pinned_by_the_key() {
+        let verifier = verifier();
+        let key = rsa_key(Some("RS256"));
+        assert_eq!(
+            verifier.pinned_algorithm(&key, Algorithm::RS256).unwrap(),
+            Algorithm::RS256
+        );
+        assert!(verifier.pinned_algorithm(&key, Algorithm::HS256).is_err());
+        let restricted = verifier.algorithms([Algorithm::ES256]);
+        assert!(restricted.pinned_algorithm(&key, Algorithm::RS256).is_err());
+    }
+
+    #[test]
+    fn keys_without_alg_need_configured_algorithms() {
+        let key = rsa_key(None);
+        assert!(verifier().pinned_algorithm(&key, Algorithm::RS256).is_err());
+        let configured = verifier().algorithms([Algorithm::RS256]);
+        assert!(configured.pinned_algorithm(&key, Algorithm::RS256).is_ok());
+        assert!(configured.pinned_algorithm(&key, Algorithm::HS256).is_err());
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::connector_endpoint::ConnectionHandle;
+use crate::shutdown::ShutdownSignal;
+
+const STATEMENT_PREVIEW_CHARS: usize = 256;
+
+#[derive(Debug, Clone)]
+pub struct QueryWatchdogConfig {
+    /// Requests running longer than this are reported once.
+    pub slow_threshold: Duration,
+    /// Requests running longer than this have their connection aborted.
+    pub kill_after: Option<Duration>,
+    pub check_interval: Duration,
+}
+
+impl Default for QueryWatchdogConfig {
+    fn default() -> Self {
+        Self {
+            slow_threshold: Duration::from_secs(5),
+            kill_after: None,
+            check_interval: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Structured report of a slow or aborted connector request.
+#[derive(Debug, Clone)]
+pub struct SlowQueryEvent {
+    pub request_id: u64,
+    pub engine: Option<String>,
+    /// Statement text, truncated to 256 characters.
+    pub statement: String,
+    pub elapsed: Duration,
+    pub killed: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConnectorStats {
+    pub requests: u64,
+    pub in_flight: usize,
+    pub slow_requests: u64,
+    pub killed_requests: u64,
+}
+
+struct InFlightRequest {
+    started: Instant,
+    engine: Option<String>,
+    statement: String,
+    connection: Option<ConnectionHandle>,
+    reported_slow: bool,
+    killed: bool,
+}
+
+#[derive(Default)]
+pub(crate) struct InFlightTracker {
+    next_id: AtomicU64,
+    requests: Mutex<HashMap<u64, InFlightRequest>>,
+    total: AtomicU64,
+    slow: AtomicU64,
+    killed: AtomicU64,
+}
+
+impl InFlightTracker {
+    pub(crate) fn begin(&self, engine: Option<String>, statement: &str) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.total.fetch_add(1, Ordering::Relaxed);
+        self.requests.lock().unwrap().insert(
+            id,
+            InFlightRequest {
+                started: Instant::now(),
+                engine,
+                statement: statement.chars().take(STATEMENT_PREVIEW_CHARS).collect(),
+                connection: None,
+                reported_slow: false,
+                killed: false,
+            },
+        );
+        id
+    }
+
+    pub(crate) fn attach(&self, id: u64, connection: ConnectionHandle) {
+        if let Some(request) = self.requests.lock().unwrap().get_mut(&id) {
+            request.connection = Some(connection);
+        }
+    }
+
+    /// Removes the request; returns its runtime when the watchdog aborted it.
+    pub(crate) fn finish(&self, id: u64) -> Option<Duration> {
+        let request = self.requests.lock().unwrap().remove(&id)?;
+        request.killed.then(|| request.started.elapsed())
+    }
+
+    pub(crate) fn stats(&self) -> ConnectorStats {
+        ConnectorStats {
+            requests: self.total.load(Ordering::Relaxed),
+            in_flight: self.requests.lock().unwrap().len(),
+            slow_requests: self.slow.load(Ordering::Relaxed),
+            killed_requests: self.killed.load(Ordering::Relaxed),
+        }
+    }
+
+    fn scan(&self, config: &QueryWatchdogConfig) -> Vec<SlowQueryEvent> {
+        let mut events = Vec::new();
+        for (id, request) in self.requests.lock().unwrap().iter_mut() {
+            let elapsed = request.started.elapsed();
+            let kill = !request.killed && config.kill_after.is_some_and(|limit| elapsed >= limit);
+            let slow = !request.reported_slow && elapsed >= config.slow_threshold;
+            if kill {
+                if let Some(connection) = &request.connection {
+                    connection.abort();
+                }
+                request.killed = true;
+                self.killed.fetch_add(1, Ordering::Relaxed);
+            }
+            if slow {
+                request.reported_slow = true;
+                self.slow.fetch_add(1, Ordering::Relaxed);
+            }
+            if slow || kill {
+                events.push(SlowQueryEvent {
+                    request_id: *id,
+                    engine: request.engine.clone(),
+                    statement: request.statement.clone(),
+                    elapsed,
+                    killed: kill,
+                });
+            }
+        }
+        events
+    }
+}
+
+pub(crate) struct WatchdogHandle {
+    signal: ShutdownSignal,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl WatchdogHandle {
+    pub(crate) fn start(
+        tracker: Arc<InFlightTracker>,
+        config: QueryWatchdogConfig,
+        on_slow: impl Fn(&SlowQueryEvent) + Send + 'static,
+    ) -> Self {
+        let signal = ShutdownSignal::new();
+        let thread_signal = signal.clone();
+        let handle = thread::spawn(move || {
+            while !thread_signal.wait_timeout(config.check_interval) {
+                for event in tracker.scan(&config) {
+                    on_slow(&event);
+                }
+            }
+        });
+        Self {
+            signal,
+            thread: Some(handle),
+        }
+    }
+}
+
+impl Drop for WatchdogHandle {
+    fn drop(&mut self) {
+        self.signal.trigger();
+        if let Some(handle) = self.thread.take() {
+            let _ = handle.join();
+        }
+    }
+}
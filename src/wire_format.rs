@@ -0,0 +1,181 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::compression::{CompressionAlgorithm, CompressionConfig};
+use crate::error::ModuleKitError;
+
+/// Serialization of connector requests, from `FENRIR_DB_CONNECTOR_FORMAT`.
+///
+/// Binary formats are only used once the connector acknowledges them in a
+/// `NegotiateFormat` handshake; until then, and against connectors that do
+/// not, requests are sent as JSON.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WireFormat {
+    #[default]
+    Json,
+    /// Needs the `cbor` feature.
+    Cbor,
+    /// Needs the `msgpack` feature.
+    #[serde(rename = "msgpack")]
+    MsgPack,
+}
+
+impl WireFormat {
+    /// Parses `json`, `cbor` or `msgpack`, rejecting formats this build
+    /// cannot encode.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let format = match value.trim().to_ascii_lowercase().as_str() {
+            "" | "json" => WireFormat::Json,
+            "cbor" => WireFormat::Cbor,
+            "msgpack" | "messagepack" => WireFormat::MsgPack,
+            other => {
+                return Err(format!(
+                    "unknown wire format '{other}' (expected json, cbor or msgpack)"
+                ))
+            }
+        };
+        if !format.is_available() {
+            return Err(format!(
+                "wire format '{}' needs the `{}` feature",
+                format.as_str(),
+                format.as_str()
+            ));
+        }
+        Ok(format)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WireFormat::Json => "json",
+            WireFormat::Cbor => "cbor",
+            WireFormat::MsgPack => "msgpack",
+        }
+    }
+
+    /// Whether this build was compiled with support for the format.
+    pub fn is_available(&self) -> bool {
+        match self {
+            WireFormat::Json => true,
+            WireFormat::Cbor => cfg!(feature = "cbor"),
+            WireFormat::MsgPack => cfg!(feature = "msgpack"),
+        }
+    }
+
+    pub(crate) fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, ModuleKitError> {
+        match self {
+            WireFormat::Json => Ok(serde_json::to_vec(value)?),
+            #[cfg(feature = "cbor")]
+            WireFormat::Cbor => {
+                let mut bytes = Vec::new();
+                ciborium::into_writer(value, &mut bytes)
+                    .map_err(|err| ModuleKitError::WireFormat(err.to_string()))?;
+                Ok(bytes)
+            }
+            // Maps rather than arrays, so optional fields can be left out.
+            #[cfg(feature = "msgpack")]
+            WireFormat::MsgPack => rmp_serde::to_vec_named(value)
+                .map_err(|err| ModuleKitError::WireFormat(err.to_string())),
+            #[allow(unreachable_patterns)]
+            other => Err(ModuleKitError::WireFormat(format!(
+                "{} support is not compiled in",
+                other.as_str()
+            ))),
+        }
+    }
+
+    /// Format of a reply, told apart by its first byte: every reply is a
+    /// map, which starts with `{` in JSON and with distinct markers in CBOR
+    /// and MessagePack.
+    fn sniff(bytes: &[u8]) -> Self {
+        match bytes.first() {
+            Some(0xa0..=0xbf) => WireFormat::Cbor,
+            Some(0x80..=0x8f | 0xde | 0xdf) => WireFormat::MsgPack,
+            _ => WireFormat::Json,
+        }
+    }
+}
+
+/// Decodes a connector reply in whichever format it was sent.
+pub(crate) fn decode_reply<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, ModuleKitError> {
+    match WireFormat::sniff(bytes) {
+        #[cfg(feature = "cbor")]
+        WireFormat::Cbor => {
+            ciborium::from_reader(bytes).map_err(|err| ModuleKitError::WireFormat(err.to_string()))
+        }
+        #[cfg(feature = "msgpack")]
+        WireFormat::MsgPack => {
+            rmp_serde::from_slice(bytes).map_err(|err| ModuleKitError::WireFormat(err.to_string()))
+        }
+        // Without the feature, let the JSON parser report the bad input.
+        _ => Ok(serde_json::from_slice(bytes)?),
+    }
+}
+
+/// Encoding agreed with the connector in the `NegotiateFormat` handshake.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct PayloadEncoding {
+    pub(crate) format: WireFormat,
+    pub(crate) compression: Option<CompressionConfig>,
+}
+
+impl PayloadEncoding {
+    pub(crate) fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, ModuleKitError> {
+        let payload = self.format.encode(value)?;
+        match &self.compression {
+            Some(compression) => compression.compress(payload),
+            None => Ok(payload),
+        }
+    }
+}
+
+/// Negotiation state of one client; see `DbConnectorClient::set_wire_format`
+/// and `set_compression`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct FormatNegotiation {
+    requested: PayloadEncoding,
+    agreed: Option<PayloadEncoding>,
+}
+
+impl FormatNegotiation {
+    pub(crate) fn new(requested: PayloadEncoding) -> Self {
+        Self {
+            requested,
+            agreed: (requested == PayloadEncoding::default()).then_some(requested),
+        }
+    }
+
+    pub(crate) fn requested(&self) -> PayloadEncoding {
+        self.requested
+    }
+
+    /// `None` while a handshake is still needed.
+    pub(crate) fn agreed(&self) -> Option<PayloadEncoding> {
+        self.agreed
+    }
+
+    /// Records the connector's answer. The format and compression fall back
+    /// to JSON and none independently unless the connector acknowledged
+    /// exactly what was requested.
+    pub(crate) fn settle(
+        &mut self,
+        format: Option<WireFormat>,
+        compression: Option<CompressionAlgorithm>,
+    ) {
+        let requested = self.requested;
+        self.agreed = Some(PayloadEncoding {
+            format: match format {
+                Some(format) if format == requested.format => format,
+                _ => WireFormat::Json,
+            },
+            compression: requested
+                .compression
+                .filter(|config| compression == Some(config.algorithm)),
+        });
+    }
+
+    /// Asks again on the next request, e.g. after the connector moved.
+    pub(crate) fn reset(&mut self) {
+        *self = Self::new(self.requested);
+    }
+}
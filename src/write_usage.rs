@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+
+use crate::cancellation::unique_request_id;
+use crate::connector_request::{DbConnectorCommand, DbTenantBindingMode, DbTenantPolicy};
+use crate::connector_response::{DbConnectorResponse, DbConnectorResultView};
+use crate::control_plane::ControlPlaneClient;
+use crate::error::ModuleKitError;
+use crate::http_transport::{HttpRequest, IDEMPOTENCY_KEY_HEADER};
+use crate::shutdown::TaskSupervisor;
+use crate::token_provider::ServiceTokenProvider;
+
+const USAGE_METRICS_PATH: &str = "modules/runtime/metrics/tenant-usage";
+
+/// Write volume accumulated for one tenant since the last report.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct TenantWriteUsage {
+    pub writes: u64,
+    pub rows: u64,
+    /// Size of statement text and parameter values sent to the connector.
+    pub bytes: u64,
+}
+
+impl TenantWriteUsage {
+    fn add(&mut self, other: TenantWriteUsage) {
+        self.writes += other.writes;
+        self.rows += other.rows;
+        self.bytes += other.bytes;
+    }
+}
+
+#[derive(Serialize)]
+struct UsageReport<'a> {
+    module_id: &'a str,
+    tenants: &'a HashMap<String, TenantWriteUsage>,
+}
+
+/// In-memory per-tenant write counters, fed by `DbConnectorClient` once
+/// attached with `set_write_usage_meter`.
+///
+/// Accounting is soft: counts only cover writes made through this process and
+/// are lost if it exits between reports.
+#[derive(Debug, Default)]
+pub struct WriteUsageMeter {
+    tenants: Mutex<HashMap<String, TenantWriteUsage>>,
+}
+
+impl WriteUsageMeter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, tenant: &str, usage: TenantWriteUsage) {
+        self.tenants
+            .lock()
+            .unwrap()
+            .entry(tenant.to_string())
+            .or_default()
+            .add(usage);
+    }
+
+    /// Counts accumulated since the last `drain`.
+    pub fn snapshot(&self) -> HashMap<String, TenantWriteUsage> {
+        self.tenants.lock().unwrap().clone()
+    }
+
+    /// Takes the accumulated counts, resetting them to zero.
+    pub fn drain(&self) -> HashMap<String, TenantWriteUsage> {
+        std::mem::take(&mut *self.tenants.lock().unwrap())
+    }
+
+    /// Posts drained counts to the control plane every `interval` under
+    /// `supervisor`.
+    ///
+    /// Each batch carries an idempotency key. A failed batch is resent
+    /// unchanged with the same key, so the control plane can drop a copy it
+    /// already counted; newer counts wait for the next batch.
+    pub fn report_supervised(
+        self: &Arc<Self>,
+        supervisor: &TaskSupervisor,
+        control_plane: ControlPlaneClient,
+        tokens: Arc<ServiceTokenProvider>,
+        module_id: impl Into<String>,
+        interval: Duration,
+    ) {
+        let meter = Arc::clone(self);
+        let module_id = module_id.into();
+        let mut pending: Option<(String, HashMap<String, TenantWriteUsage>)> = None;
+        supervisor.spawn("fenrir-write-usage", move |signal| loop {
+            let stopping = signal.wait_timeout(interval);
+            if pending.is_none() {
+                let tenants = meter.drain();
+                if !tenants.is_empty() {
+                    pending = Some((unique_request_id(), tenants));
+                }
+            }
+            if let Some((key, tenants)) = &pending {
+                let report = UsageReport {
+                    module_id: &module_id,
+                    tenants,
+                };
+                if post_report(&control_plane, &tokens, key, &report).is_ok() {
+                    pending = None;
+                }
+            }
+            if stopping {
+                // Keep unsent counts visible through `snapshot`.
+                if let Some((_, tenants)) = pending.take() {
+                    for (tenant, usage) in tenants {
+                        meter.record(&tenant, usage);
+                    }
+                }
+                break;
+            }
+        });
+    }
+
+    /// Attributes a successful write to the tenant bound by `tenant`'s
+    /// parameter, or to its schema in `SchemaSwitch` mode.
+    pub(crate) fn observe(
+        &self,
+        command: &DbConnectorCommand,
+        tenant: &DbTenantPolicy,
+        response: &DbConnectorResponse,
+    ) {
+        let DbConnectorCommand::Prepared { statement, params } = command else {
+            return;
+        };
+        let tenant_id = match tenant.mode {
+            DbTenantBindingMode::SchemaSwitch => tenant.schema.clone(),
+            _ => params
+                .iter()
+                .find(|param| param.name == tenant.param)
+                .map(|param| match &param.value {
+                    JsonValue::String(value) => value.clone(),
+                    other => other.to_string(),
+                }),
+        };
+        let Some(tenant_id) = tenant_id else {
+            return;
+        };
+        let rows = response
+            .results
+            .iter()
+            .flatten()
+            .map(|result| match result {
+                DbConnectorResultView::AffectedRows { count } => *count,
+                _ => 0,
+            })
+            .sum();
+        let bytes = statement.len()
+            + params
+                .iter()
+                .map(|param| param.value.to_string().len())
+                .sum::<usize>();
+        self.record(
+            &tenant_id,
+            TenantWriteUsage {
+                writes: 1,
+                rows,
+                bytes: bytes as u64,
+            },
+        );
+    }
+}
+
+fn post_report(
+    control_plane: &ControlPlaneClient,
+    tokens: &ServiceTokenProvider,
+    key: &str,
+    report: &UsageReport<'_>,
+) -> Result<(), ModuleKitError> {
+    let bearer = tokens.current_token()?;
+    let request = HttpRequest::post_json(control_plane.endpoint(USAGE_METRICS_PATH)?, report)?
+        .bearer_auth(bearer.expose())
+        .header(IDEMPOTENCY_KEY_HEADER, key);
+    let response = control_plane.send(&request)?;
+    if response.is_success() {
+        return Ok(());
+    }
+    Err(ModuleKitError::ControlPlaneStatus {
+        status: response.status,
+        body: response.text(),
+    })
+}